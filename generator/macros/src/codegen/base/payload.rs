@@ -45,12 +45,51 @@ impl Base for Payload {
                     crate::Payload::INITIAL_PACKET_BUFFER_CAPACITY;
             }
         };
+        // Compression is applied to the bincode body before encryption; decompression
+        // is limited by MAX_PAYLOAD_LEN to reject oversized (bomb) frames early.
+        let (compress, decompress) = if let Some(compression) = self.attrs.compress() {
+            let enabled = match compression {
+                PayloadCompression::Zstd => cfg!(feature = "compress-zstd"),
+                PayloadCompression::Lz4 => cfg!(feature = "compress-lz4"),
+            };
+            if !enabled {
+                // Without the feature brec has no compression API; report the missing
+                // feature instead of unresolved paths
+                return Err(E::AttrRequires(
+                    format!("compress = \"{compression}\""),
+                    format!("the compress-{compression} feature of brec"),
+                ));
+            }
+            let compression = match (compression, self.attrs.level()) {
+                (PayloadCompression::Zstd, Some(level)) => {
+                    quote! { brec::Compression::Zstd(#level) }
+                }
+                (PayloadCompression::Zstd, None) => quote! { brec::Compression::zstd() },
+                (PayloadCompression::Lz4, _) => quote! { brec::Compression::Lz4 },
+            };
+            (
+                quote! {
+                    let payload_body = #compression.compress(&payload_body)?;
+                },
+                quote! {
+                    let payload_body = brec::compress::decompress(
+                        &payload_body,
+                        <#payload_name as brec::ProtocolSchema>::MAX_PAYLOAD_LEN as u64,
+                    )
+                    .map_err(std::io::Error::from)?;
+                },
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
         let bincode_impl = if self.attrs.is_bincode() && !self.attrs.is_crypt() {
             quote! {
                 impl brec::PayloadEncode for #payload_name {
                     fn encode(&self, _ctx: &mut Self::Context<'_>) -> std::io::Result<Vec<u8>> {
-                        brec::bincode::serde::encode_to_vec(self, brec::bincode::config::standard())
-                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+                        let payload_body = brec::bincode::serde::encode_to_vec(self, brec::bincode::config::standard())
+                            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+                        #compress
+                        Ok(payload_body)
                     }
                 }
 
@@ -62,7 +101,9 @@ impl Base for Payload {
 
                 impl brec::PayloadDecode<#payload_name> for #payload_name {
                     fn decode(buf: &[u8], _ctx: &mut Self::Context<'_>) -> std::io::Result<#payload_name> {
-                        brec::bincode::serde::decode_from_slice(buf, brec::bincode::config::standard())
+                        let payload_body = buf;
+                        #decompress
+                        brec::bincode::serde::decode_from_slice(&payload_body, brec::bincode::config::standard())
                             .map(|(value, _)| value)
                             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
                     }
@@ -77,6 +118,7 @@ impl Base for Payload {
                     fn encode(&self, ctx: &mut Self::Context<'_>) -> std::io::Result<Vec<u8>> {
                         let payload_body = brec::bincode::serde::encode_to_vec(self, brec::bincode::config::standard())
                             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+                        #compress
                         let encrypt_options = match ctx {
                             crate::ProtocolContext::Encrypt(opt) => opt,
                             _ => {
@@ -115,6 +157,7 @@ impl Base for Payload {
                         };
                         let payload_body = brec::CryptCodec::decrypt(buf, decrypt_options)
                             .map_err(std::io::Error::from)?;
+                        #decompress
                         brec::bincode::serde::decode_from_slice(&payload_body, brec::bincode::config::standard())
                             .map(|(value, _)| value)
                             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
@@ -76,16 +76,24 @@ pub fn generate(
 
 pub fn gen_referred(blocks: &[&Block]) -> Result<TokenStream, E> {
     let mut variants = Vec::new();
+    let mut names = Vec::new();
     for blk in blocks.iter() {
         let fullname = blk.fullname()?;
         let referred_name = blk.referred_name();
         variants.push(quote! {#fullname(#referred_name<'a>)});
+        names.push(quote! {Self::#fullname(..) => stringify!(#fullname)});
     }
     Ok(quote! {
         pub enum BlockReferred<'a> {
             #(#variants,)*
         }
 
-        impl<'a> brec::BlockReferredDef<Block> for BlockReferred<'a> {}
+        impl<'a> brec::BlockReferredDef<Block> for BlockReferred<'a> {
+            fn block_name(&self) -> &'static str {
+                match *self {
+                    #(#names,)*
+                }
+            }
+        }
     })
 }
@@ -8,11 +8,14 @@ pub fn generate() -> Result<TokenStream, E> {
         quote! {
             #[allow(dead_code, type_alias_bounds)]
             pub type FileStorage = brec::FileWriterDef<Block, Payload, Payload, ()>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type FileReader = brec::FileReaderDef<Block, BlockReferred<'static>, Payload, Payload>;
         }
     } else {
         quote! {}
     };
-    let observer = if cfg!(feature = "observer") {
+    let observer_common = if cfg!(feature = "observer") || cfg!(feature = "observer-sync") {
         quote! {
             #[allow(dead_code)]
             pub type SubscriptionUpdate = brec::SubscriptionUpdate;
@@ -24,16 +27,31 @@ pub fn generate() -> Result<TokenStream, E> {
             pub trait Subscription: Send + 'static {
                 fn on_update(&mut self, total: usize, added: usize) -> SubscriptionUpdate;
 
+                fn on_cursor(&mut self, cursor: usize) {
+                    let _ = cursor;
+                }
+
                 fn on_packet(&mut self, packet: Packet) {
                     let _ = packet;
                 }
 
-                fn on_error(&mut self, err: &brec::Error) -> SubscriptionErrorAction {
+                fn on_packets(&mut self, packets: Vec<Packet>) -> Result<(), brec::Error> {
+                    for packet in packets {
+                        self.on_packet(packet);
+                    }
+                    Ok(())
+                }
+
+                fn on_error(&mut self, err: &brec::ObserverError) -> SubscriptionErrorAction {
                     let _ = err;
                     SubscriptionErrorAction::Continue
                 }
 
-                fn on_stopped(&mut self, reason: Option<brec::Error>) {
+                fn on_truncated(&mut self) {}
+
+                fn on_replaced(&mut self) {}
+
+                fn on_stopped(&mut self, reason: Option<brec::ObserverError>) {
                     let _ = reason;
                 }
 
@@ -52,15 +70,31 @@ pub fn generate() -> Result<TokenStream, E> {
                     self.0.on_update(total, added)
                 }
 
+                fn on_cursor(&mut self, cursor: usize) {
+                    self.0.on_cursor(cursor)
+                }
+
                 fn on_packet(&mut self, packet: Packet) {
                     self.0.on_packet(packet)
                 }
 
-                fn on_error(&mut self, err: &brec::Error) -> brec::SubscriptionErrorAction {
+                fn on_packets(&mut self, packets: Vec<Packet>) -> Result<(), brec::Error> {
+                    self.0.on_packets(packets)
+                }
+
+                fn on_error(&mut self, err: &brec::ObserverError) -> brec::SubscriptionErrorAction {
                     self.0.on_error(err)
                 }
 
-                fn on_stopped(&mut self, reason: Option<brec::Error>) {
+                fn on_truncated(&mut self) {
+                    self.0.on_truncated()
+                }
+
+                fn on_replaced(&mut self) {
+                    self.0.on_replaced()
+                }
+
+                fn on_stopped(&mut self, reason: Option<brec::ObserverError>) {
                     self.0.on_stopped(reason)
                 }
 
@@ -98,8 +132,43 @@ pub fn generate() -> Result<TokenStream, E> {
                     self.inner = self.inner.subscribe(SubscriptionWrapper(subscription));
                     self
                 }
+
+                pub fn with_rule(mut self, rule: Rule<'static>) -> Self {
+                    self.inner = self.inner.with_rule(rule);
+                    self
+                }
+
+                pub fn start_at(mut self, position: brec::StartPosition) -> Self {
+                    self.inner = self.inner.start_at(position);
+                    self
+                }
+
+                pub fn with_max_batch(mut self, max_batch: usize) -> Self {
+                    self.inner = self.inner.with_max_batch(max_batch);
+                    self
+                }
+
+                pub fn reopen(mut self, reopen: bool) -> Self {
+                    self.inner = self.inner.reopen(reopen);
+                    self
+                }
+
+                pub fn with_sensor(mut self, mode: brec::SensorMode) -> Self {
+                    self.inner = self.inner.with_sensor(mode);
+                    self
+                }
             }
 
+            #[allow(dead_code, type_alias_bounds)]
+            pub type FileObserverStreamOptions =
+                brec::FileObserverStreamOptions<Block, BlockReferred<'static>, Payload, Payload>;
+
+        }
+    } else {
+        quote! {}
+    };
+    let observer = if cfg!(feature = "observer") {
+        quote! {
             #[allow(dead_code)]
             pub struct FileObserver(
                 brec::FileObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>,
@@ -113,6 +182,10 @@ pub fn generate() -> Result<TokenStream, E> {
                     brec::FileObserverDef::new(options.inner).map(Self)
                 }
 
+                pub fn cursor(&self) -> usize {
+                    self.0.cursor()
+                }
+
                 pub async fn shutdown(&mut self) {
                     self.0.shutdown().await
                 }
@@ -121,6 +194,77 @@ pub fn generate() -> Result<TokenStream, E> {
             #[allow(dead_code, type_alias_bounds)]
             pub type FileObserverStream =
                 brec::FileObserverStreamDef<Block, BlockReferred<'static>, Payload, Payload, ()>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type StreamFileObserver =
+                brec::StreamFileObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type StreamFileObserverOptions =
+                brec::StreamFileObserverOptions<Block, BlockReferred<'static>, Payload, Payload>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type ObserverHub =
+                brec::ObserverHubDef<Block, BlockReferred<'static>, Payload, Payload, ()>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type HubSubscriberOptions =
+                brec::HubSubscriberOptions<Block, BlockReferred<'static>, Payload, Payload>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type HubStream = brec::HubStreamDef<Block, Payload, Payload>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type HubEvent = brec::HubEvent<Block, Payload, Payload>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type SharedPacket = brec::SharedPacketDef<Block, Payload, Payload>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type DirectoryObserver =
+                brec::DirectoryObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type DirectoryObserverOptions =
+                brec::DirectoryObserverOptions<Block, BlockReferred<'static>, Payload, Payload>;
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type DirectoryObserverEvent = brec::DirectoryObserverEvent<Block, Payload, Payload>;
+        }
+    } else {
+        quote! {}
+    };
+    let observer_sync = if cfg!(feature = "observer-sync") {
+        quote! {
+            #[allow(dead_code)]
+            pub struct SyncFileObserver(
+                brec::SyncFileObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>,
+            );
+
+            impl SyncFileObserver {
+                pub fn new<S>(options: FileObserverOptions<S>) -> Result<Self, brec::Error>
+                where
+                    S: Subscription,
+                {
+                    brec::SyncFileObserverDef::new(options.inner).map(Self)
+                }
+
+                pub fn cursor(&self) -> usize {
+                    self.0.cursor()
+                }
+
+                pub fn is_finished(&self) -> bool {
+                    self.0.is_finished()
+                }
+
+                pub fn shutdown(&mut self) {
+                    self.0.shutdown()
+                }
+            }
+
+            #[allow(dead_code, type_alias_bounds)]
+            pub type SyncFileObserverIter =
+                brec::SyncFileObserverIterDef<Block, BlockReferred<'static>, Payload, Payload, ()>;
         }
     } else {
         quote! {}
@@ -173,7 +317,9 @@ pub fn generate() -> Result<TokenStream, E> {
         #[allow(dead_code, type_alias_bounds)]
         pub type Writer<S> = brec::WriterDef<S, Block, Payload, Payload>;
 
+        #observer_common
         #observer
+        #observer_sync
 
         #locked_storage
     })
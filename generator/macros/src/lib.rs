@@ -379,14 +379,34 @@ pub fn derive_csharp(input: TokenStream) -> TokenStream {
 /// | `FileObserverOptions<S>` | Local wrapper over `brec::FileObserverOptions<..., SubscriptionWrapper<S>>`                 |
 /// | `FileObserver`           | Local wrapper over `FileObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>`  |
 /// | `FileObserverStream`     | `brec::FileObserverStreamDef<Block, BlockReferred<'static>, Payload, Payload, ()>`         |
-///
-/// `Subscription` uses `on_*` callbacks: `on_update`, `on_packet`, `on_error`, `on_stopped`, `on_aborted`.
+/// | `StreamFileObserver`     | `brec::StreamFileObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>`         |
+/// | `StreamFileObserverOptions` | `brec::StreamFileObserverOptions<Block, BlockReferred<'static>, Payload, Payload>`   |
+/// | `FileObserverStreamOptions` | `brec::FileObserverStreamOptions<Block, BlockReferred<'static>, Payload, Payload>`   |
+/// | `ObserverHub`            | `brec::ObserverHubDef<Block, BlockReferred<'static>, Payload, Payload, ()>`                |
+/// | `HubSubscriberOptions`   | `brec::HubSubscriberOptions<Block, BlockReferred<'static>, Payload, Payload>`               |
+/// | `HubStream`              | `brec::HubStreamDef<Block, Payload, Payload>`                                               |
+/// | `HubEvent`               | `brec::HubEvent<Block, Payload, Payload>`                                                   |
+/// | `SharedPacket`           | `brec::SharedPacketDef<Block, Payload, Payload>` (`Arc<Packet>`)                            |
+/// | `DirectoryObserver`      | `brec::DirectoryObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>`          |
+/// | `DirectoryObserverOptions` | `brec::DirectoryObserverOptions<Block, BlockReferred<'static>, Payload, Payload>`       |
+/// | `DirectoryObserverEvent` | `brec::DirectoryObserverEvent<Block, Payload, Payload>`                                     |
+///
+/// `Subscription` uses `on_*` callbacks: `on_update`, `on_cursor`, `on_packet`, `on_packets`, `on_error`, `on_truncated`, `on_replaced`, `on_stopped`, `on_aborted`.
+///
+/// When `brec` is built with the `observer-sync` feature, `SubscriptionUpdate`, `SubscriptionErrorAction`,
+/// `Subscription`, `FileObserverOptions<S>` and `FileObserverStreamOptions` are generated as well, plus:
+///
+/// | Alias                  | Expanded to                                                                                    |
+/// |------------------------|------------------------------------------------------------------------------------------------|
+/// | `SyncFileObserver`     | Local wrapper over `SyncFileObserverDef<Block, BlockReferred<'static>, Payload, Payload, ()>` |
+/// | `SyncFileObserverIter` | `brec::SyncFileObserverIterDef<Block, BlockReferred<'static>, Payload, Payload, ()>`          |
 ///
 /// When `brec` is built with the `locked_storage` feature, the macro also generates:
 ///
-/// | Alias         | Expanded to                                      |
-/// |---------------|--------------------------------------------------|
-/// | `FileStorage` | `brec::FileWriterDef<Block, Payload, Payload, ()>` |
+/// | Alias         | Expanded to                                                              |
+/// |---------------|--------------------------------------------------------------------------|
+/// | `FileStorage` | `brec::FileWriterDef<Block, Payload, Payload, ()>`                       |
+/// | `FileReader`  | `brec::FileReaderDef<Block, BlockReferred<'static>, Payload, Payload>`   |
 ///
 /// ---
 ///
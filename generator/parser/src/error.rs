@@ -25,6 +25,10 @@ pub enum E {
 
     #[error("Attribute isn't supported")]
     UnsupportedAttr,
+    #[error("Unknown compression algorithm \"{0}\"; expected \"zstd\" or \"lz4\"")]
+    UnknownCompression(String),
+    #[error("Attribute {0} requires {1}")]
+    AttrRequires(String, String),
 
     #[error("Fail to access to collector")]
     NoAccessToCollector,
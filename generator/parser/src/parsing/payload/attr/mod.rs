@@ -26,6 +26,28 @@ impl Parse for PayloadAttrs {
                         .to_string();
                     if key == PayloadAttrId::Path.to_string() {
                         attrs.push(PayloadAttr::Path(ModulePath::try_from(&*assign.right)?));
+                    } else if key == PayloadAttrId::Compress.to_string() {
+                        let Expr::Lit(expr_lit) = assign.right.as_ref() else {
+                            return Err(syn::Error::new_spanned(assign.right, E::UnsupportedAttr));
+                        };
+                        let syn::Lit::Str(lit_str) = &expr_lit.lit else {
+                            return Err(syn::Error::new_spanned(expr_lit, E::UnsupportedAttr));
+                        };
+                        attrs.push(PayloadAttr::Compress(
+                            PayloadCompression::try_from(lit_str.value().as_str())
+                                .map_err(|err| syn::Error::new_spanned(lit_str, err))?,
+                        ));
+                    } else if key == PayloadAttrId::Level.to_string() {
+                        let level = if let Expr::Lit(expr_lit) = assign.right.as_ref()
+                            && let syn::Lit::Int(lit_int) = &expr_lit.lit
+                        {
+                            lit_int
+                                .base10_parse::<i32>()
+                                .map_err(|e| syn::Error::new_spanned(expr_lit, e.to_string()))?
+                        } else {
+                            return Err(syn::Error::new_spanned(assign.right, E::UnsupportedAttr));
+                        };
+                        attrs.push(PayloadAttr::Level(level));
                     } else {
                         return Err(syn::Error::new_spanned(assign, E::UnsupportedAttr));
                     }
@@ -57,6 +79,19 @@ impl Parse for PayloadAttrs {
                 }
             }
         }
-        Ok(PayloadAttrs(attrs))
+        let attrs = PayloadAttrs(attrs);
+        if attrs.compress().is_some() && !attrs.is_bincode() {
+            return Err(input.error(E::AttrRequires(
+                PayloadAttrId::Compress.to_string(),
+                PayloadAttrId::Bincode.to_string(),
+            )));
+        }
+        if attrs.level().is_some() && attrs.compress() != Some(&PayloadCompression::Zstd) {
+            return Err(input.error(E::AttrRequires(
+                PayloadAttrId::Level.to_string(),
+                "compress = \"zstd\"".to_owned(),
+            )));
+        }
+        Ok(attrs)
     }
 }
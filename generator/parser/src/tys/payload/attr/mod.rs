@@ -59,6 +59,24 @@ impl PayloadAttrs {
     pub fn is_crypt(&self) -> bool {
         self.0.iter().any(|attr| matches!(attr, PayloadAttr::Crypt))
     }
+    pub fn compress(&self) -> Option<&PayloadCompression> {
+        self.0.iter().find_map(|attr| {
+            if let PayloadAttr::Compress(compression) = attr {
+                Some(compression)
+            } else {
+                None
+            }
+        })
+    }
+    pub fn level(&self) -> Option<i32> {
+        self.0.iter().find_map(|attr| {
+            if let PayloadAttr::Level(level) = attr {
+                Some(*level)
+            } else {
+                None
+            }
+        })
+    }
     pub fn is_include(&self) -> bool {
         self.0
             .iter()
@@ -76,6 +94,38 @@ pub enum PayloadAttr {
     NoCrc,
     Bincode,
     Crypt,
+    Compress(PayloadCompression),
+    Level(i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadCompression {
+    Zstd,
+    Lz4,
+}
+
+impl TryFrom<&str> for PayloadCompression {
+    type Error = E;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "zstd" => Ok(Self::Zstd),
+            "lz4" => Ok(Self::Lz4),
+            unknown => Err(E::UnknownCompression(unknown.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for PayloadCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Zstd => "zstd",
+                Self::Lz4 => "lz4",
+            }
+        )
+    }
 }
 
 impl fmt::Display for PayloadAttr {
@@ -92,6 +142,8 @@ impl fmt::Display for PayloadAttr {
                 Self::NoCrc => PayloadAttrId::NoCrc.to_string(),
                 Self::Bincode => PayloadAttrId::Bincode.to_string(),
                 Self::Crypt => PayloadAttrId::Crypt.to_string(),
+                Self::Compress(compression) => format!("{}({compression})", self.id()),
+                Self::Level(level) => format!("{}({level})", self.id()),
             }
         )
     }
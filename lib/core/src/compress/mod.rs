@@ -0,0 +1,185 @@
+use crate::*;
+
+/// Identifier of zstd compression in a compression frame.
+pub const COMPRESSION_ZSTD_ID: u8 = 1;
+
+/// Identifier of lz4 (block format) compression in a compression frame.
+pub const COMPRESSION_LZ4_ID: u8 = 2;
+
+/// Length of the compression frame prefix (algorithm identifier and original length).
+pub const COMPRESSION_FRAME_HEADER_LEN: usize = 1 + std::mem::size_of::<u64>();
+
+/// Default zstd compression level.
+#[cfg(feature = "compress-zstd")]
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Compression algorithm used to pack packets or payload bodies.
+///
+/// Compressed data is stored as a frame:
+/// - Algorithm identifier: `u8`
+/// - Length of the original data: `u64` (little-endian)
+/// - Compressed bytes
+///
+/// The original length is checked against a caller-provided limit before anything is
+/// decompressed, so a malformed or hostile frame can't force a huge allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// zstd with the given compression level.
+    #[cfg(feature = "compress-zstd")]
+    Zstd(i32),
+    /// lz4 block format.
+    #[cfg(feature = "compress-lz4")]
+    Lz4,
+}
+
+impl Compression {
+    /// zstd with [`DEFAULT_ZSTD_LEVEL`].
+    #[cfg(feature = "compress-zstd")]
+    pub fn zstd() -> Self {
+        Self::Zstd(DEFAULT_ZSTD_LEVEL)
+    }
+
+    /// Returns the algorithm identifier stored in compression frames.
+    pub fn id(&self) -> u8 {
+        match self {
+            #[cfg(feature = "compress-zstd")]
+            Self::Zstd(_) => COMPRESSION_ZSTD_ID,
+            #[cfg(feature = "compress-lz4")]
+            Self::Lz4 => COMPRESSION_LZ4_ID,
+        }
+    }
+
+    /// Returns the algorithm of the given frame identifier, if it's enabled by features.
+    ///
+    /// zstd is restored with [`DEFAULT_ZSTD_LEVEL`], since frames don't record the level.
+    #[cfg(feature = "crypt")]
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            #[cfg(feature = "compress-zstd")]
+            COMPRESSION_ZSTD_ID => Some(Self::zstd()),
+            #[cfg(feature = "compress-lz4")]
+            COMPRESSION_LZ4_ID => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Compresses bytes into a compression frame.
+    pub fn compress(&self, raw: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut frame = Vec::with_capacity(COMPRESSION_FRAME_HEADER_LEN + raw.len() / 2);
+        frame.push(self.id());
+        frame.extend_from_slice(&(raw.len() as u64).to_le_bytes());
+        match self {
+            #[cfg(feature = "compress-zstd")]
+            Self::Zstd(level) => {
+                frame.extend_from_slice(&zstd::bulk::compress(raw, *level)?);
+            }
+            #[cfg(feature = "compress-lz4")]
+            Self::Lz4 => {
+                frame.extend_from_slice(&lz4_flex::block::compress(raw));
+            }
+        }
+        Ok(frame)
+    }
+}
+
+/// Restores original bytes from a compression frame.
+///
+/// # Arguments
+/// * `frame` - Compression frame produced by [`Compression::compress`].
+/// * `max_len` - Maximum allowed length of the restored data.
+///
+/// # Errors
+/// - `Error::NotEnoughData` if the frame prefix is incomplete.
+/// - `Error::InvalidLength` if the declared original length exceeds `max_len`.
+/// - `Error::UnsupportedCompression` if the algorithm isn't known or not enabled.
+/// - `Error::Decompression` if the compressed bytes are damaged.
+pub fn decompress(frame: &[u8], max_len: u64) -> Result<Vec<u8>, Error> {
+    if frame.len() < COMPRESSION_FRAME_HEADER_LEN {
+        return Err(Error::NotEnoughData(
+            COMPRESSION_FRAME_HEADER_LEN - frame.len(),
+        ));
+    }
+    let id = frame[0];
+    let raw_len = u64::from_le_bytes(frame[1..COMPRESSION_FRAME_HEADER_LEN].try_into()?);
+    if raw_len > max_len {
+        return Err(Error::InvalidLength);
+    }
+    let data = &frame[COMPRESSION_FRAME_HEADER_LEN..];
+    let raw = match id {
+        #[cfg(feature = "compress-zstd")]
+        COMPRESSION_ZSTD_ID => zstd::bulk::decompress(data, raw_len as usize)
+            .map_err(|err| Error::Decompression(err.to_string()))?,
+        #[cfg(feature = "compress-lz4")]
+        COMPRESSION_LZ4_ID => lz4_flex::block::decompress(data, raw_len as usize)
+            .map_err(|err| Error::Decompression(err.to_string()))?,
+        id => return Err(Error::UnsupportedCompression(id)),
+    };
+    if raw.len() as u64 != raw_len {
+        return Err(Error::Decompression(format!(
+            "restored {} bytes; expected {raw_len}",
+            raw.len()
+        )));
+    }
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        b"2026-01-01 INFO service started; listening on 0.0.0.0:8080\n".repeat(64)
+    }
+
+    fn algorithms() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "compress-zstd")]
+            Compression::zstd(),
+            #[cfg(feature = "compress-lz4")]
+            Compression::Lz4,
+        ]
+    }
+
+    #[test]
+    fn compress_decompress_roundtrip() {
+        let raw = sample();
+        for compression in algorithms() {
+            let frame = compression.compress(&raw).expect("compress");
+            assert_eq!(frame[0], compression.id());
+            assert!(frame.len() < raw.len());
+            let restored = decompress(&frame, raw.len() as u64).expect("decompress");
+            assert_eq!(restored, raw);
+        }
+    }
+
+    #[test]
+    fn decompress_rejects_oversized_frames() {
+        let raw = sample();
+        for compression in algorithms() {
+            let frame = compression.compress(&raw).expect("compress");
+            assert!(matches!(
+                decompress(&frame, raw.len() as u64 - 1),
+                Err(Error::InvalidLength)
+            ));
+        }
+    }
+
+    #[test]
+    fn decompress_rejects_unknown_and_damaged_frames() {
+        let mut frame = vec![0xFF];
+        frame.extend_from_slice(&4u64.to_le_bytes());
+        frame.extend_from_slice(&[1, 2, 3, 4]);
+        assert!(matches!(
+            decompress(&frame, 16),
+            Err(Error::UnsupportedCompression(0xFF))
+        ));
+        assert!(matches!(
+            decompress(&frame[..3], 16),
+            Err(Error::NotEnoughData(_))
+        ));
+        for compression in algorithms() {
+            frame[0] = compression.id();
+            assert!(decompress(&frame, 16).is_err());
+        }
+    }
+}
@@ -1,12 +1,38 @@
 use crate::crypt::error::CryptError;
 use serde::{Deserialize, Serialize};
 
-/// Supported crypto configuration identifiers embedded into wrapper bytes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Supported payload encryption (AEAD) identifiers embedded into wrapper bytes.
+///
+/// The identifier is stored in every envelope, so a reader decrypts each payload with the
+/// algorithm it was encrypted with, and streams which mix algorithms stay readable.
+///
+/// The algorithm doesn't define how the session key is wrapped: that depends on the key of
+/// each recipient (see [`CryptKeyWrap`]), so any algorithm can be combined with any key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 #[serde(try_from = "u8", into = "u8")]
 pub enum CryptAlgorithm {
+    /// `ChaCha20Poly1305` payload encryption.
+    ///
+    /// The name comes from the time RSA-OAEP-SHA256 was the only way to wrap session keys;
+    /// it's kept for compatibility and doesn't restrict the key wrap.
+    #[default]
     ChaCha20Poly1305RsaOaepSha256 = 1,
+    /// `AES-256-GCM` payload encryption (hardware accelerated with AES-NI).
+    Aes256Gcm = 2,
+    /// `XChaCha20Poly1305` payload encryption with 192-bit random nonces, safe for long-lived
+    /// sessions.
+    XChaCha20Poly1305 = 3,
+}
+
+impl CryptAlgorithm {
+    /// Returns the length of the AEAD nonce used by the algorithm.
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            Self::ChaCha20Poly1305RsaOaepSha256 | Self::Aes256Gcm => 12,
+            Self::XChaCha20Poly1305 => 24,
+        }
+    }
 }
 
 impl TryFrom<u8> for CryptAlgorithm {
@@ -15,6 +41,8 @@ impl TryFrom<u8> for CryptAlgorithm {
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
             1 => Ok(Self::ChaCha20Poly1305RsaOaepSha256),
+            2 => Ok(Self::Aes256Gcm),
+            3 => Ok(Self::XChaCha20Poly1305),
             _ => Err(CryptError::UnsupportedAlgorithmId(value)),
         }
     }
@@ -25,3 +53,21 @@ impl From<CryptAlgorithm> for u8 {
         value as u8
     }
 }
+
+/// The way a session key is made available to a recipient of an envelope.
+///
+/// It's defined by the kind of the recipient's key rather than recorded in the envelope;
+/// see [`EncryptOptions::key_wrap`] and [`DecryptKey::key_wrap`].
+///
+/// [`EncryptOptions::key_wrap`]: crate::crypt::EncryptOptions::key_wrap
+/// [`DecryptKey::key_wrap`]: crate::crypt::DecryptKey::key_wrap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CryptKeyWrap {
+    /// The session key is wrapped with an RSA public key (RSA-OAEP-SHA256).
+    RsaOaepSha256,
+    /// The session key is agreed between an ephemeral key and the recipient's X25519 key;
+    /// the wrapped key is the ephemeral public key.
+    X25519,
+    /// The session key is derived from a pre-shared key; there is no wrapped key.
+    Symmetric,
+}
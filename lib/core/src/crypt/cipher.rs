@@ -0,0 +1,99 @@
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::{
+    ChaCha20Poly1305, XChaCha20Poly1305,
+    aead::{Aead, KeyInit},
+};
+use hkdf::Hkdf;
+use rsa::sha2::Sha256;
+
+use crate::crypt::{
+    algorithm::CryptAlgorithm,
+    consts,
+    error::{CryptError, CryptResult},
+};
+
+/// Encrypts payload bytes with the AEAD cipher of the given algorithm.
+pub(crate) fn seal(
+    algorithm: CryptAlgorithm,
+    session_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
+    nonce: &[u8],
+    plaintext: &[u8],
+) -> CryptResult<Vec<u8>> {
+    fn seal_with<C: Aead + KeyInit>(
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+    ) -> CryptResult<Vec<u8>> {
+        C::new_from_slice(key)
+            .map_err(|_| CryptError::InitCipher)?
+            .encrypt(nonce.into(), plaintext)
+            .map_err(|_| CryptError::EncryptPayloadBody)
+    }
+
+    check_nonce(algorithm, nonce)?;
+    match algorithm {
+        CryptAlgorithm::ChaCha20Poly1305RsaOaepSha256 => {
+            seal_with::<ChaCha20Poly1305>(session_key, nonce, plaintext)
+        }
+        CryptAlgorithm::Aes256Gcm => seal_with::<Aes256Gcm>(session_key, nonce, plaintext),
+        CryptAlgorithm::XChaCha20Poly1305 => {
+            seal_with::<XChaCha20Poly1305>(session_key, nonce, plaintext)
+        }
+    }
+}
+
+/// Decrypts and authenticates payload bytes with the AEAD cipher of the given algorithm.
+pub(crate) fn open(
+    algorithm: CryptAlgorithm,
+    session_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
+    nonce: &[u8],
+    ciphertext: &[u8],
+) -> CryptResult<Vec<u8>> {
+    fn open_with<C: Aead + KeyInit>(
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> CryptResult<Vec<u8>> {
+        C::new_from_slice(key)
+            .map_err(|_| CryptError::InitCipher)?
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| CryptError::DecryptPayloadBody)
+    }
+
+    check_nonce(algorithm, nonce)?;
+    match algorithm {
+        CryptAlgorithm::ChaCha20Poly1305RsaOaepSha256 => {
+            open_with::<ChaCha20Poly1305>(session_key, nonce, ciphertext)
+        }
+        CryptAlgorithm::Aes256Gcm => open_with::<Aes256Gcm>(session_key, nonce, ciphertext),
+        CryptAlgorithm::XChaCha20Poly1305 => {
+            open_with::<XChaCha20Poly1305>(session_key, nonce, ciphertext)
+        }
+    }
+}
+
+/// Derives the session key of a symmetric (pre-shared key) session.
+///
+/// Each session gets its own key, so AEAD nonces are never reused across sessions, even
+/// though all of them share the same pre-shared key.
+pub(crate) fn derive_session_key(
+    shared_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
+    session_id: u64,
+) -> CryptResult<[u8; consts::ENVELOPE_SESSION_KEY_LEN]> {
+    let mut session_key = [0u8; consts::ENVELOPE_SESSION_KEY_LEN];
+    Hkdf::<Sha256>::new(Some(&session_id.to_le_bytes()), shared_key)
+        .expand(consts::SYMMETRIC_SESSION_KEY_INFO, &mut session_key)
+        .map_err(|_| CryptError::DeriveSessionKey)?;
+    Ok(session_key)
+}
+
+/// The cipher panics on a nonce of unexpected length, so it's checked upfront.
+fn check_nonce(algorithm: CryptAlgorithm, nonce: &[u8]) -> CryptResult<()> {
+    if nonce.len() != algorithm.nonce_len() {
+        return Err(CryptError::InvalidNonceLength {
+            algorithm,
+            actual: nonce.len(),
+        });
+    }
+    Ok(())
+}
@@ -1,16 +1,12 @@
-use chacha20poly1305::{
-    ChaCha20Poly1305, Nonce,
-    aead::{Aead, KeyInit},
-};
 use rsa::rand_core::{OsRng, RngCore};
 use zeroize::Zeroize;
 
 use crate::crypt::{
-    algorithm::CryptAlgorithm,
-    consts,
+    cipher, consts,
     error::{CryptError, CryptResult},
+    meta::CryptEnvelopeMeta,
     options::{DecryptOptions, EncryptOptions},
-    record::CryptEnvelopeRecord,
+    record::{CryptEnvelopeRecord, CryptRecipient},
 };
 use crate::{PayloadDecode, PayloadEncode};
 
@@ -20,106 +16,157 @@ pub struct CryptCodec;
 impl CryptCodec {
     /// Encrypts payload bytes and serializes envelope.
     pub fn encrypt(payload_body: &[u8], options: &mut EncryptOptions) -> CryptResult<Vec<u8>> {
-        let (session_id, wrapped_key, mut session_key) = match options.current_session() {
-            Some((session_id, session_key, wrapped_key)) => {
-                (session_id, wrapped_key.to_vec(), *session_key)
+        let (session_id, recipients, mut session_key) = match options.current_session() {
+            Some((session_id, session_key, recipients)) => {
+                (session_id, recipients.to_vec(), *session_key)
             }
             _ => options.create_session()?,
         };
 
-        let mut nonce = [0u8; consts::ENVELOPE_NONCE_LEN];
+        let algorithm = options.algorithm();
+        let mut nonce = vec![0u8; algorithm.nonce_len()];
         OsRng.fill_bytes(&mut nonce);
 
-        let cipher =
-            ChaCha20Poly1305::new_from_slice(&session_key).map_err(|_| CryptError::InitCipher)?;
+        let ciphertext = cipher::seal(algorithm, &session_key, &nonce, payload_body);
 
         session_key.zeroize();
 
-        let ciphertext = cipher
-            .encrypt(Nonce::from_slice(&nonce), payload_body)
-            .map_err(|_| CryptError::EncryptPayloadBody)?;
-
-        CryptEnvelopeRecord::new(
+        CryptEnvelopeRecord {
+            version: consts::ENVELOPE_VERSION,
+            algorithm,
             session_id,
-            wrapped_key,
+            recipients,
             nonce,
-            ciphertext,
-            options.key_id().map(Vec::from),
-        )
+            payload: ciphertext?,
+        }
         .encode()
     }
 
     /// Decrypts envelope bytes and returns plaintext payload bytes.
+    ///
+    /// The payload is decrypted with the algorithm recorded in the envelope, regardless of
+    /// the algorithm configured for encryption. The recipient entry and the private key are
+    /// selected by `key_id` (see [`DecryptKeyring`](crate::crypt::DecryptKeyring)); the key
+    /// which decrypted the payload is reported by [`DecryptOptions::last_key`].
     pub fn decrypt(
         encrypted_payload_body: &[u8],
         options: &mut DecryptOptions,
     ) -> CryptResult<Vec<u8>> {
+        let envelope = CryptEnvelopeRecord::decode(encrypted_payload_body)?;
+        let (mut session_key, decrypted) = Self::open(&envelope, options)?;
+        session_key.zeroize();
+        Ok(decrypted)
+    }
+
+    /// Decrypts an already parsed envelope and returns the unwrapped session key together
+    /// with plaintext payload bytes. The caller is responsible for zeroizing the key.
+    pub(crate) fn open(
+        envelope: &CryptEnvelopeRecord,
+        options: &mut DecryptOptions,
+    ) -> CryptResult<([u8; consts::ENVELOPE_SESSION_KEY_LEN], Vec<u8>)> {
         fn decrypt_with_session_key(
             envelope: &CryptEnvelopeRecord,
             session_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
         ) -> CryptResult<Vec<u8>> {
-            let cipher = ChaCha20Poly1305::new_from_slice(session_key)
-                .map_err(|_| CryptError::InitCipher)?;
-            cipher
-                .decrypt(
-                    Nonce::from_slice(&envelope.nonce),
-                    envelope.payload.as_ref(),
-                )
-                .map_err(|_| CryptError::DecryptPayloadBody)
+            cipher::open(
+                envelope.algorithm,
+                session_key,
+                &envelope.nonce,
+                &envelope.payload,
+            )
         }
 
-        let envelope = CryptEnvelopeRecord::decode(encrypted_payload_body)?;
-        if envelope.version != consts::ENVELOPE_VERSION {
+        if !(consts::ENVELOPE_VERSION_V1..=consts::ENVELOPE_VERSION).contains(&envelope.version) {
             return Err(CryptError::UnsupportedEnvelopeVersion {
                 actual: envelope.version,
                 expected: consts::ENVELOPE_VERSION,
             });
         }
 
-        if envelope.algorithm != CryptAlgorithm::ChaCha20Poly1305RsaOaepSha256 {
-            return Err(CryptError::UnsupportedAlgorithm(envelope.algorithm));
-        }
-
-        if let Some(expected_key_id) = options.expected_key_id() {
-            let key_id = envelope.key_id.as_deref().ok_or(CryptError::MissingKeyId)?;
-            if key_id != expected_key_id {
-                return Err(CryptError::KeyIdMismatch);
+        let recipients: Vec<&CryptRecipient> = match options.expected_key_id() {
+            Some(expected_key_id) => {
+                if envelope.key_ids().next().is_none() {
+                    return Err(CryptError::MissingKeyId);
+                }
+                let recipients: Vec<&CryptRecipient> = envelope
+                    .recipients
+                    .iter()
+                    .filter(|recipient| recipient.key_id.as_deref() == Some(expected_key_id))
+                    .collect();
+                if recipients.is_empty() {
+                    return Err(CryptError::KeyIdMismatch);
+                }
+                recipients
             }
-        }
+            None => envelope.recipients.iter().collect(),
+        };
 
-        let wrapped_key_hash = envelope.wrapped_key_hash();
-        if let Some((idx, session_key)) = options.cached_session_key(
-            envelope.session_id,
-            envelope.key_id.as_deref(),
-            wrapped_key_hash,
-        ) {
-            match decrypt_with_session_key(&envelope, session_key) {
+        for recipient in recipients.iter() {
+            let Some((idx, key_idx, session_key)) = options.cached_session_key(
+                envelope.session_id,
+                recipient.key_id.as_deref(),
+                recipient.wrapped_key_hash(),
+            ) else {
+                continue;
+            };
+            let mut session_key = *session_key;
+            match decrypt_with_session_key(envelope, &session_key) {
                 Ok(decrypted) => {
-                    return Ok(decrypted);
+                    options.set_last_key(key_idx);
+                    return Ok((session_key, decrypted));
                 }
                 Err(CryptError::DecryptPayloadBody) => {
                     // Fall back to envelope unwrap in case cache state is stale or collided.
+                    session_key.zeroize();
+                    break;
                 }
                 Err(err) => {
+                    session_key.zeroize();
                     options.remove_cache_session_by_idx(idx);
                     return Err(err);
                 }
             }
         }
 
-        let mut session_key = options.unwrap_session_key(&envelope.wrapped_key)?;
-
-        let decrypted = decrypt_with_session_key(&envelope, &session_key)
-            .inspect_err(|_| session_key.zeroize())?;
-
-        options.cache_session(
-            envelope.session_id,
-            envelope.key_id.clone(),
-            wrapped_key_hash,
-            session_key,
-        );
-        session_key.zeroize();
-        Ok(decrypted)
+        // With a single candidate key the error is reported as is; otherwise the error of the
+        // last tried key is reported once none of the keys fits.
+        let key_ids: Vec<Option<&[u8]>> = recipients
+            .iter()
+            .map(|recipient| recipient.key_id.as_deref())
+            .collect();
+        let mut failure = CryptError::UnwrapSessionKey;
+        for (recipient_idx, key_idx) in options.candidate_keys(&key_ids)? {
+            let recipient = recipients[recipient_idx];
+            let mut session_key = match options.unwrap_session_key(
+                key_idx,
+                envelope.session_id,
+                &recipient.wrapped_key,
+            ) {
+                Ok(session_key) => session_key,
+                Err(err) => {
+                    failure = err;
+                    continue;
+                }
+            };
+            match decrypt_with_session_key(envelope, &session_key) {
+                Ok(decrypted) => {
+                    options.cache_session(
+                        envelope.session_id,
+                        recipient.key_id.clone(),
+                        recipient.wrapped_key_hash(),
+                        session_key,
+                        key_idx,
+                    );
+                    options.set_last_key(key_idx);
+                    return Ok((session_key, decrypted));
+                }
+                Err(err) => {
+                    session_key.zeroize();
+                    failure = err;
+                }
+            }
+        }
+        Err(failure)
     }
 
     /// Parses envelope bytes without decrypting payload.
@@ -127,6 +174,12 @@ impl CryptCodec {
         CryptEnvelopeRecord::decode(encrypted_payload_body)
     }
 
+    /// Reads envelope metadata (version, algorithm, session and key identifiers) without
+    /// decrypting or copying the payload.
+    pub fn metadata(encrypted_payload_body: &[u8]) -> CryptResult<CryptEnvelopeMeta<'_>> {
+        CryptEnvelopeMeta::parse(encrypted_payload_body)
+    }
+
     /// Serializes already built envelope record.
     pub fn format(record: &CryptEnvelopeRecord) -> CryptResult<Vec<u8>> {
         record.encode()
@@ -159,6 +212,7 @@ impl CryptCodec {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypt::{algorithm::CryptAlgorithm, options::DecryptKeyring};
     use rsa::RsaPrivateKey;
 
     const TEST_KEY_ID: &[u8] = b"k1";
@@ -202,13 +256,176 @@ mod tests {
         let record = CryptEnvelopeRecord::new(
             7,
             vec![1, 2, 3],
-            [9; consts::ENVELOPE_NONCE_LEN],
+            vec![9; 12],
             vec![4, 5, 6, 7],
             Some(vec![8, 8]),
         );
         let encoded = record.encode().expect("encode");
         let decoded = CryptEnvelopeRecord::decode(&encoded).expect("decode");
         assert_eq!(decoded, record);
+
+        let record = record.with_algorithm(CryptAlgorithm::XChaCha20Poly1305);
+        assert!(matches!(
+            record.encode(),
+            Err(CryptError::InvalidNonceLength { actual: 12, .. })
+        ));
+    }
+
+    #[test]
+    fn envelope_record_of_first_version_keeps_its_layout() {
+        let mut record =
+            CryptEnvelopeRecord::new(7, vec![1, 2, 3], vec![9; 12], vec![4, 5, 6, 7], None);
+        record.version = consts::ENVELOPE_VERSION_V1;
+        let encoded = record.encode().expect("encode");
+        // The first version stores a single recipient and the nonce without length prefixes
+        let current = CryptEnvelopeRecord {
+            version: consts::ENVELOPE_VERSION,
+            ..record.clone()
+        };
+        assert_eq!(encoded.len() + 2, current.encode().expect("encode").len());
+
+        let decoded = CryptEnvelopeRecord::decode(&encoded).expect("decode");
+        assert_eq!(decoded, record);
+    }
+
+    #[test]
+    fn envelope_record_requires_recipients() {
+        let mut record =
+            CryptEnvelopeRecord::new(7, vec![1, 2, 3], vec![9; 12], vec![4, 5, 6, 7], None);
+        record.recipients.clear();
+        assert!(matches!(record.encode(), Err(CryptError::NoRecipients)));
+    }
+
+    #[test]
+    fn crypt_codec_roundtrip_for_each_algorithm() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let public_key = private_key.to_public_key();
+        let mut decrypt_options = DecryptOptions::new(private_key);
+
+        let mut encrypted = Vec::new();
+        for algorithm in [
+            CryptAlgorithm::ChaCha20Poly1305RsaOaepSha256,
+            CryptAlgorithm::Aes256Gcm,
+            CryptAlgorithm::XChaCha20Poly1305,
+        ] {
+            let mut encrypt_options =
+                EncryptOptions::new(public_key.clone()).with_algorithm(algorithm);
+            let bytes = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+            let envelope = CryptCodec::parse(&bytes).expect("parse");
+            assert_eq!(envelope.algorithm, algorithm);
+            assert_eq!(envelope.nonce.len(), algorithm.nonce_len());
+            encrypted.push(bytes);
+        }
+        // The same options read a stream which mixes algorithms
+        for bytes in encrypted.iter() {
+            let decrypted = CryptCodec::decrypt(bytes, &mut decrypt_options).expect("decrypt");
+            assert_eq!(decrypted, TEST_PAYLOAD);
+        }
+    }
+
+    #[test]
+    fn crypt_codec_roundtrip_for_each_recipient() {
+        let mut rng = OsRng;
+        let ops_key = RsaPrivateKey::new(&mut rng, 1024).expect("ops key");
+        let security_key = RsaPrivateKey::new(&mut rng, 1024).expect("security key");
+
+        let mut encrypt_options = EncryptOptions::new(ops_key.to_public_key()).with_key_id("ops");
+        encrypt_options.add_recipient("security", security_key.to_public_key());
+        assert_eq!(encrypt_options.recipients_len(), 2);
+
+        let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        let envelope = CryptCodec::parse(&encrypted).expect("parse");
+        assert_eq!(envelope.version, consts::ENVELOPE_VERSION);
+        assert_eq!(
+            envelope.key_ids().collect::<Vec<_>>(),
+            vec![b"ops".as_slice(), b"security".as_slice()]
+        );
+
+        let mut ops = DecryptOptions::new(ops_key).with_expected_key_id("ops");
+        let mut security = DecryptOptions::from_keyring(
+            DecryptKeyring::new().with_key("security", security_key.clone()),
+        )
+        .expect("keyring");
+        assert_eq!(
+            CryptCodec::decrypt(&encrypted, &mut ops).expect("ops decrypt"),
+            TEST_PAYLOAD
+        );
+        assert_eq!(
+            CryptCodec::decrypt(&encrypted, &mut security).expect("security decrypt"),
+            TEST_PAYLOAD
+        );
+        assert_eq!(
+            security.last_key().and_then(|key| key.key_id()),
+            Some(b"security".as_slice())
+        );
+        // The cached session key of the matching entry is used for the next envelope
+        let next = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        assert_eq!(
+            CryptCodec::parse(&next).expect("parse").session_id,
+            envelope.session_id
+        );
+        assert_eq!(
+            CryptCodec::decrypt(&next, &mut security).expect("security decrypt"),
+            TEST_PAYLOAD
+        );
+
+        let mut pinned = DecryptOptions::new(security_key).with_expected_key_id("audit");
+        assert!(matches!(
+            CryptCodec::decrypt(&encrypted, &mut pinned),
+            Err(CryptError::KeyIdMismatch)
+        ));
+        let mut stranger = DecryptOptions::from_keyring(
+            DecryptKeyring::new().with_key("audit", RsaPrivateKey::new(&mut rng, 1024).unwrap()),
+        )
+        .expect("keyring");
+        assert!(matches!(
+            CryptCodec::decrypt(&encrypted, &mut stranger),
+            Err(CryptError::UnknownKeyId)
+        ));
+    }
+
+    #[test]
+    fn crypt_codec_adds_rsa_recipient_to_other_key_kinds() {
+        let mut rng = OsRng;
+        let rsa_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let public_key = x25519_dalek::PublicKey::from(&secret).to_bytes();
+
+        for mut encrypt_options in [
+            EncryptOptions::from_x25519_public(public_key).with_key_id("x25519"),
+            EncryptOptions::symmetric("psk", [3u8; 32]),
+        ] {
+            encrypt_options.add_recipient(Vec::new(), rsa_key.to_public_key());
+            let encrypted =
+                CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+            let envelope = CryptCodec::parse(&encrypted).expect("parse");
+            assert_eq!(envelope.recipients.len(), 2);
+            assert!(envelope.recipients[1].key_id.is_none());
+
+            let mut decrypt_options = DecryptOptions::new(rsa_key.clone());
+            assert_eq!(
+                CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt"),
+                TEST_PAYLOAD
+            );
+        }
+    }
+
+    #[test]
+    fn crypt_codec_decrypts_envelope_of_first_version() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let public_key = private_key.to_public_key();
+        let mut encrypt_options = EncryptOptions::new(public_key);
+        let mut decrypt_options = DecryptOptions::new(private_key);
+
+        let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        let mut envelope = CryptCodec::parse(&encrypted).expect("parse");
+        envelope.version = consts::ENVELOPE_VERSION_V1;
+        let legacy = CryptCodec::format(&envelope).expect("format");
+
+        let decrypted = CryptCodec::decrypt(&legacy, &mut decrypt_options).expect("decrypt");
+        assert_eq!(decrypted, TEST_PAYLOAD);
     }
 
     #[derive(Debug, PartialEq)]
@@ -326,7 +543,10 @@ mod tests {
         .expect("parse second");
 
         assert_eq!(first.session_id, second.session_id);
-        assert_eq!(first.wrapped_key, second.wrapped_key);
+        assert_eq!(
+            first.recipients[0].wrapped_key,
+            second.recipients[0].wrapped_key
+        );
 
         let mut last = second;
         for _ in 2..reuse_limit {
@@ -342,7 +562,10 @@ mod tests {
         .expect("parse rotated");
 
         assert_ne!(last.session_id, rotated.session_id);
-        assert_ne!(last.wrapped_key, rotated.wrapped_key);
+        assert_ne!(
+            last.recipients[0].wrapped_key,
+            rotated.recipients[0].wrapped_key
+        );
     }
 
     #[test]
@@ -390,12 +613,204 @@ mod tests {
 
         decrypt_options.cache_session(
             envelope.session_id,
-            envelope.key_id.clone(),
-            envelope.wrapped_key_hash(),
+            envelope.recipients[0].key_id.clone(),
+            envelope.recipients[0].wrapped_key_hash(),
             [7u8; consts::ENVELOPE_SESSION_KEY_LEN],
+            0,
         );
 
         let decrypted = CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt");
         assert_eq!(decrypted, TEST_PAYLOAD);
     }
+
+    #[test]
+    fn crypt_codec_selects_keyring_key_by_key_id() {
+        let mut rng = OsRng;
+        let first_key = RsaPrivateKey::new(&mut rng, 1024).expect("first key");
+        let second_key = RsaPrivateKey::new(&mut rng, 1024).expect("second key");
+        let mut first_options =
+            EncryptOptions::new(first_key.to_public_key()).with_key_id(b"kid-1".to_vec());
+        let mut second_options =
+            EncryptOptions::new(second_key.to_public_key()).with_key_id(b"kid-2".to_vec());
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-1".to_vec(), first_key)
+            .with_key(b"kid-2".to_vec(), second_key);
+        let mut decrypt_options = DecryptOptions::from_keyring(keyring).expect("options");
+        assert!(decrypt_options.last_key().is_none());
+
+        for key_id in [b"kid-2".as_slice(), b"kid-1", b"kid-2"] {
+            let options = if key_id == b"kid-1" {
+                &mut first_options
+            } else {
+                &mut second_options
+            };
+            let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, options).expect("encrypt");
+            let decrypted = CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt");
+            assert_eq!(decrypted, TEST_PAYLOAD);
+            let used = decrypt_options.last_key().expect("used key");
+            assert_eq!(used.key_id(), Some(key_id));
+        }
+    }
+
+    #[test]
+    fn crypt_codec_tries_keyring_keys_for_envelope_without_key_id() {
+        let mut rng = OsRng;
+        let first_key = RsaPrivateKey::new(&mut rng, 1024).expect("first key");
+        let second_key = RsaPrivateKey::new(&mut rng, 1024).expect("second key");
+        let mut encrypt_options = EncryptOptions::new(second_key.to_public_key());
+        let mut decrypt_options =
+            DecryptOptions::new(first_key).with_key(b"kid-2".to_vec(), second_key);
+
+        let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        let decrypted = CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt");
+        assert_eq!(decrypted, TEST_PAYLOAD);
+        assert_eq!(
+            decrypt_options.last_key().and_then(|key| key.key_id()),
+            Some(b"kid-2".as_slice())
+        );
+    }
+
+    #[test]
+    fn crypt_codec_rejects_unknown_key_id() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let mut encrypt_options =
+            EncryptOptions::new(private_key.to_public_key()).with_key_id(b"kid-3".to_vec());
+        let keyring = DecryptKeyring::new().with_key(b"kid-1".to_vec(), private_key);
+        let mut decrypt_options = DecryptOptions::from_keyring(keyring).expect("options");
+
+        let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        let result = CryptCodec::decrypt(&encrypted, &mut decrypt_options);
+        assert!(matches!(result, Err(CryptError::UnknownKeyId)));
+        assert!(matches!(
+            DecryptOptions::from_keyring(DecryptKeyring::new()),
+            Err(CryptError::EmptyKeyring)
+        ));
+    }
+
+    #[test]
+    fn crypt_codec_symmetric_roundtrip() {
+        let mut encrypt_options = EncryptOptions::symmetric(TEST_KEY_ID.to_vec(), [3u8; 32])
+            .with_algorithm(CryptAlgorithm::XChaCha20Poly1305);
+        let mut decrypt_options = DecryptOptions::symmetric(TEST_KEY_ID.to_vec(), [3u8; 32]);
+
+        for _ in 0..3 {
+            let encrypted =
+                CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+            let envelope = CryptCodec::parse(&encrypted).expect("parse");
+            assert!(envelope.recipients[0].wrapped_key.is_empty());
+            assert_eq!(envelope.recipients[0].key_id.as_deref(), Some(TEST_KEY_ID));
+
+            let decrypted = CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt");
+            assert_eq!(decrypted, TEST_PAYLOAD);
+            decrypt_options.clear_session_cache();
+        }
+    }
+
+    #[test]
+    fn crypt_codec_symmetric_and_rsa_keys_share_keyring() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let mut rsa_options =
+            EncryptOptions::new(private_key.to_public_key()).with_key_id(b"kid-rsa".to_vec());
+        let mut symmetric_options = EncryptOptions::symmetric(b"kid-psk".to_vec(), [5u8; 32]);
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-rsa".to_vec(), private_key)
+            .with_symmetric_key(b"kid-psk".to_vec(), [5u8; 32]);
+        let mut decrypt_options = DecryptOptions::from_keyring(keyring).expect("options");
+
+        for options in [&mut symmetric_options, &mut rsa_options] {
+            let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, options).expect("encrypt");
+            let decrypted = CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt");
+            assert_eq!(decrypted, TEST_PAYLOAD);
+            assert_eq!(
+                decrypt_options.last_key().and_then(|key| key.key_id()),
+                options.key_id()
+            );
+        }
+    }
+
+    #[test]
+    fn crypt_codec_symmetric_wrong_key() {
+        let mut encrypt_options = EncryptOptions::symmetric(TEST_KEY_ID.to_vec(), [3u8; 32]);
+        let mut decrypt_options = DecryptOptions::symmetric(TEST_KEY_ID.to_vec(), [4u8; 32]);
+
+        let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        let result = CryptCodec::decrypt(&encrypted, &mut decrypt_options);
+        assert!(matches!(result, Err(CryptError::DecryptPayloadBody)));
+    }
+
+    #[test]
+    fn crypt_codec_x25519_roundtrip() {
+        let secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let mut decrypt_options = DecryptOptions::from_x25519_private(secret.to_bytes());
+        let public_key = decrypt_options
+            .keyring()
+            .keys()
+            .next()
+            .and_then(|key| key.x25519_public_key())
+            .expect("public key");
+        let mut encrypt_options =
+            EncryptOptions::from_x25519_public(public_key).with_key_id(TEST_KEY_ID.to_vec());
+
+        let encrypted: Vec<Vec<u8>> = (0..3)
+            .map(|_| CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt"))
+            .collect();
+        let first = CryptCodec::parse(&encrypted[0]).expect("parse");
+        let last = CryptCodec::parse(&encrypted[2]).expect("parse");
+        assert_eq!(
+            first.recipients[0].wrapped_key.len(),
+            consts::X25519_KEY_LEN
+        );
+        assert_eq!(first.session_id, last.session_id);
+        assert_eq!(
+            first.recipients[0].wrapped_key,
+            last.recipients[0].wrapped_key
+        );
+
+        for encrypted in encrypted.iter() {
+            decrypt_options.clear_session_cache();
+            let decrypted = CryptCodec::decrypt(encrypted, &mut decrypt_options).expect("decrypt");
+            assert_eq!(decrypted, TEST_PAYLOAD);
+        }
+    }
+
+    #[test]
+    fn crypt_codec_x25519_and_rsa_keys_without_key_id() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let mut rsa_options = EncryptOptions::new(private_key.to_public_key());
+        let mut x25519_options =
+            EncryptOptions::from_x25519_public(x25519_dalek::PublicKey::from(&secret).to_bytes());
+        let keyring = DecryptKeyring::new()
+            .with_key(Vec::new(), private_key)
+            .with_x25519_key(Vec::new(), secret.to_bytes());
+        let mut decrypt_options = DecryptOptions::from_keyring(keyring).expect("options");
+
+        for options in [&mut x25519_options, &mut rsa_options] {
+            let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, options).expect("encrypt");
+            let decrypted = CryptCodec::decrypt(&encrypted, &mut decrypt_options).expect("decrypt");
+            assert_eq!(decrypted, TEST_PAYLOAD);
+            let used = decrypt_options.last_key().expect("used key");
+            assert_eq!(
+                used.x25519_public_key(),
+                options.x25519_public_key(),
+                "decrypted with key of another kind"
+            );
+        }
+    }
+
+    #[test]
+    fn crypt_codec_x25519_wrong_private_key() {
+        let secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let another_secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let mut encrypt_options =
+            EncryptOptions::from_x25519_public(x25519_dalek::PublicKey::from(&secret).to_bytes());
+        let mut decrypt_options = DecryptOptions::from_x25519_private(another_secret.to_bytes());
+
+        let encrypted = CryptCodec::encrypt(TEST_PAYLOAD, &mut encrypt_options).expect("encrypt");
+        let result = CryptCodec::decrypt(&encrypted, &mut decrypt_options);
+        assert!(matches!(result, Err(CryptError::DecryptPayloadBody)));
+    }
 }
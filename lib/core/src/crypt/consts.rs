@@ -1,3 +1,10 @@
-pub(crate) const ENVELOPE_VERSION: u8 = 1;
-pub(crate) const ENVELOPE_NONCE_LEN: usize = 12;
+pub(crate) const ENVELOPE_VERSION: u8 = 2;
+/// First envelope version: it used a fixed 12-byte nonce and supported ChaCha20Poly1305 only.
+pub(crate) const ENVELOPE_VERSION_V1: u8 = 1;
+pub(crate) const ENVELOPE_V1_NONCE_LEN: usize = 12;
 pub(crate) const ENVELOPE_SESSION_KEY_LEN: usize = 32;
+/// HKDF `info` of session keys derived from a pre-shared key.
+pub(crate) const SYMMETRIC_SESSION_KEY_INFO: &[u8] = b"brec crypt symmetric session key";
+pub(crate) const X25519_KEY_LEN: usize = 32;
+/// HKDF `info` prefix of session keys agreed with X25519.
+pub(crate) const X25519_SESSION_KEY_INFO: &[u8] = b"brec crypt x25519 session key";
@@ -23,12 +23,34 @@ pub enum CryptError {
     ExportPublicKeyPem,
     #[error("Invalid RSA private key PEM")]
     InvalidRsaPrivateKeyPem,
+    #[error("Invalid X25519 public key PEM")]
+    InvalidX25519PublicKeyPem,
+    #[error("Invalid X25519 private key PEM")]
+    InvalidX25519PrivateKeyPem,
+    #[error("Invalid encrypted private key PEM")]
+    InvalidEncryptedPrivateKeyPem,
+    #[error("Failed to decrypt private key: wrong password or unsupported encryption scheme")]
+    DecryptPrivateKey,
+    #[error("Private key is encrypted, a password is required")]
+    PasswordRequired,
+    #[error("Key provider error: {0}")]
+    KeyProvider(String),
+    #[error("Expected {expected} PEM block, got {actual}")]
+    UnexpectedPemBlock { expected: String, actual: String },
+    #[error("Crypto envelope has no recipients")]
+    NoRecipients,
+    #[error("Crypto envelope of version {version} carries a single recipient, got {actual}")]
+    SingleRecipientEnvelope { version: u8, actual: usize },
     #[error("Crypto envelope has empty key_id")]
     EmptyKeyId,
     #[error("Missing key_id in crypto envelope")]
     MissingKeyId,
     #[error("key_id mismatch in crypto envelope")]
     KeyIdMismatch,
+    #[error("No private key for key_id in crypto envelope")]
+    UnknownKeyId,
+    #[error("Decryption keyring is empty")]
+    EmptyKeyring,
     #[error("Failed to encrypt payload body")]
     EncryptPayloadBody,
     #[error("Failed to decrypt payload body")]
@@ -45,14 +67,23 @@ pub enum CryptError {
     DecodeEnvelope,
     #[error("Malformed crypto envelope: decoded {decoded} bytes from {total}")]
     MalformedEnvelope { decoded: usize, total: usize },
-    #[error("Failed to initialize chacha20poly1305 cipher")]
+    #[error("Failed to initialize payload cipher")]
     InitCipher,
+    #[error("Invalid nonce length for {algorithm:?}: {actual} bytes")]
+    InvalidNonceLength {
+        algorithm: crate::crypt::CryptAlgorithm,
+        actual: usize,
+    },
     #[error("Failed to wrap session key")]
     WrapSessionKey,
     #[error("Failed to unwrap session key")]
     UnwrapSessionKey,
     #[error("Invalid unwrapped session key length")]
     InvalidUnwrappedSessionKeyLength,
+    #[error("Failed to derive session key from pre-shared key")]
+    DeriveSessionKey,
+    #[error("Operation requires an RSA key, but a key of another kind is configured")]
+    NotAnRsaKey,
     #[error("One of sections is too large for envelope format: {0} bytes")]
     OversizedSection(usize),
     #[error("I/O error: {0}")]
@@ -67,6 +98,39 @@ impl CryptError {
     pub fn invalid_data(message: impl Into<String>) -> Self {
         Self::InvalidData(message.into())
     }
+
+    pub fn key_provider(message: impl Into<String>) -> Self {
+        Self::KeyProvider(message.into())
+    }
+
+    /// Returns `true` if the error is caused by the encrypted data itself: no key for the
+    /// envelope, wrong key, tampered or malformed envelope.
+    ///
+    /// Errors of key configuration, key providers and encryption return `false`; they are
+    /// never treated as an undecryptable payload (see `UndecryptablePolicy`).
+    pub(crate) fn is_undecryptable(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidData(_)
+                | Self::NoRecipients
+                | Self::SingleRecipientEnvelope { .. }
+                | Self::EmptyKeyId
+                | Self::MissingKeyId
+                | Self::KeyIdMismatch
+                | Self::UnknownKeyId
+                | Self::DecryptPayloadBody
+                | Self::UnsupportedAlgorithmId(_)
+                | Self::UnsupportedAlgorithm(_)
+                | Self::UnsupportedEnvelopeVersion { .. }
+                | Self::DecodeEnvelope
+                | Self::MalformedEnvelope { .. }
+                | Self::InvalidNonceLength { .. }
+                | Self::UnwrapSessionKey
+                | Self::InvalidUnwrappedSessionKeyLength
+                | Self::DeriveSessionKey
+                | Self::OversizedSection(_)
+        )
+    }
 }
 
 impl From<CryptError> for std::io::Error {
@@ -84,12 +148,23 @@ impl From<CryptError> for std::io::Error {
             | CryptError::InvalidX509Certificate
             | CryptError::CertificateNoRsaPublicKey
             | CryptError::ExportPublicKeyPem
-            | CryptError::InvalidRsaPrivateKeyPem => {
+            | CryptError::InvalidRsaPrivateKeyPem
+            | CryptError::InvalidX25519PublicKeyPem
+            | CryptError::InvalidX25519PrivateKeyPem
+            | CryptError::InvalidEncryptedPrivateKeyPem
+            | CryptError::DecryptPrivateKey
+            | CryptError::PasswordRequired
+            | CryptError::UnexpectedPemBlock { .. }
+            | CryptError::EmptyKeyring
+            | CryptError::NotAnRsaKey => {
                 std::io::Error::new(std::io::ErrorKind::InvalidInput, value.to_string())
             }
-            CryptError::EmptyKeyId
+            CryptError::NoRecipients
+            | CryptError::SingleRecipientEnvelope { .. }
+            | CryptError::EmptyKeyId
             | CryptError::MissingKeyId
             | CryptError::KeyIdMismatch
+            | CryptError::UnknownKeyId
             | CryptError::EncryptPayloadBody
             | CryptError::DecryptPayloadBody
             | CryptError::UnsupportedAlgorithmId(_)
@@ -99,12 +174,16 @@ impl From<CryptError> for std::io::Error {
             | CryptError::DecodeEnvelope
             | CryptError::MalformedEnvelope { .. }
             | CryptError::InitCipher
+            | CryptError::InvalidNonceLength { .. }
             | CryptError::WrapSessionKey
             | CryptError::UnwrapSessionKey
             | CryptError::InvalidUnwrappedSessionKeyLength
+            | CryptError::DeriveSessionKey
             | CryptError::OversizedSection { .. } => {
-                std::io::Error::new(std::io::ErrorKind::InvalidData, value.to_string())
+                // Kept as the source, so readers can tell undecryptable payloads apart
+                std::io::Error::new(std::io::ErrorKind::InvalidData, value)
             }
+            CryptError::KeyProvider(message) => std::io::Error::other(message),
             CryptError::Io(err) => err,
         }
     }
@@ -0,0 +1,268 @@
+use crate::PayloadFilterCallback;
+use crate::RuleFnDef;
+use crate::crypt::{
+    algorithm::CryptAlgorithm,
+    consts,
+    error::{CryptError, CryptResult},
+};
+
+/// Metadata of a crypto envelope read without decrypting (and without copying) its payload.
+///
+/// Readers can look at the metadata of raw payload bytes in a `FilterPayload` rule, e.g. to
+/// skip packets encrypted for other recipients instead of trying to decrypt them (see
+/// [`CryptEnvelopeMeta::filter`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CryptEnvelopeMeta<'a> {
+    /// Envelope format version.
+    pub version: u8,
+    /// Encryption algorithm used for this envelope.
+    pub algorithm: CryptAlgorithm,
+    /// Session identifier used to correlate wrapped keys.
+    pub session_id: u64,
+    /// Key identifiers of the recipients, in order; `None` for recipients without one.
+    pub recipients: Vec<Option<&'a [u8]>>,
+}
+
+impl<'a> CryptEnvelopeMeta<'a> {
+    /// Reads envelope metadata from encrypted payload bytes.
+    ///
+    /// The same layouts as [`CryptEnvelopeRecord::decode`] are accepted, but the wrapped keys,
+    /// the nonce and the ciphertext are only skipped. Other version bytes are reported as
+    /// [`CryptError::UnsupportedEnvelopeVersion`].
+    ///
+    /// [`CryptEnvelopeRecord::decode`]: crate::crypt::CryptEnvelopeRecord::decode
+    pub fn parse(buf: &'a [u8]) -> CryptResult<Self> {
+        fn decode_as<'a, T: serde::Deserialize<'a>>(buf: &'a [u8]) -> CryptResult<T> {
+            let (meta, read): (T, usize) =
+                bincode::serde::borrow_decode_from_slice(buf, bincode::config::standard())
+                    .map_err(|_| CryptError::DecodeEnvelope)?;
+            if read != buf.len() {
+                return Err(CryptError::MalformedEnvelope {
+                    decoded: read,
+                    total: buf.len(),
+                });
+            }
+            Ok(meta)
+        }
+
+        let (meta, nonce_len) = match buf.first() {
+            Some(&consts::ENVELOPE_VERSION_V1) => {
+                let layout = decode_as::<EnvelopeMetaV1>(buf)?;
+                (
+                    Self {
+                        version: layout.version,
+                        algorithm: layout.algorithm,
+                        session_id: layout.session_id,
+                        recipients: vec![layout.key_id.filter(|key_id| !key_id.is_empty())],
+                    },
+                    consts::ENVELOPE_V1_NONCE_LEN,
+                )
+            }
+            Some(&consts::ENVELOPE_VERSION) => {
+                let layout = decode_as::<EnvelopeMeta>(buf)?;
+                (
+                    Self {
+                        version: layout.version,
+                        algorithm: layout.algorithm,
+                        session_id: layout.session_id,
+                        recipients: layout
+                            .recipients
+                            .into_iter()
+                            .map(|recipient| recipient.key_id)
+                            .collect(),
+                    },
+                    layout.nonce.len(),
+                )
+            }
+            Some(&actual) => {
+                return Err(CryptError::UnsupportedEnvelopeVersion {
+                    actual,
+                    expected: consts::ENVELOPE_VERSION,
+                });
+            }
+            None => return Err(CryptError::DecodeEnvelope),
+        };
+        if nonce_len != meta.algorithm.nonce_len() {
+            return Err(CryptError::InvalidNonceLength {
+                algorithm: meta.algorithm,
+                actual: nonce_len,
+            });
+        }
+        if meta.recipients.is_empty() {
+            return Err(CryptError::NoRecipients);
+        }
+        Ok(meta)
+    }
+
+    /// Returns key identifiers of all recipients which have one.
+    pub fn key_ids(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        self.recipients.iter().filter_map(|key_id| *key_id)
+    }
+
+    /// Returns `true` if one of the recipients has the given key identifier.
+    pub fn has_key_id(&self, key_id: &[u8]) -> bool {
+        self.key_ids().any(|known| known == key_id)
+    }
+
+    /// Builds a callback for `RuleDef::FilterPayload` which decides by envelope metadata.
+    ///
+    /// Payloads which aren't crypto envelopes (e.g. plain payloads) are kept; for encrypted
+    /// payloads the packet is kept if `filter` returns `true`.
+    ///
+    /// ```ignore
+    /// reader.add_rule(RuleDef::FilterPayload(CryptEnvelopeMeta::filter(|meta| {
+    ///     meta.has_key_id(b"tenant-a")
+    /// })))?;
+    /// ```
+    pub fn filter(
+        filter: impl Fn(&CryptEnvelopeMeta<'_>) -> bool + Send + 'static,
+    ) -> PayloadFilterCallback {
+        RuleFnDef::Dynamic(Box::new(move |buf| match CryptEnvelopeMeta::parse(buf) {
+            Ok(meta) => filter(&meta),
+            Err(_) => true,
+        }))
+    }
+}
+
+/// Borrowed view of the current envelope layout.
+#[derive(serde::Deserialize)]
+struct EnvelopeMeta<'a> {
+    version: u8,
+    algorithm: CryptAlgorithm,
+    session_id: u64,
+    #[serde(borrow)]
+    recipients: Vec<RecipientMeta<'a>>,
+    nonce: &'a [u8],
+    _payload: &'a [u8],
+}
+
+/// Borrowed view of a recipient entry.
+#[derive(serde::Deserialize)]
+struct RecipientMeta<'a> {
+    #[serde(borrow)]
+    key_id: Option<&'a [u8]>,
+    _wrapped_key: &'a [u8],
+}
+
+/// Borrowed view of the first envelope layout.
+#[derive(serde::Deserialize)]
+struct EnvelopeMetaV1<'a> {
+    version: u8,
+    algorithm: CryptAlgorithm,
+    session_id: u64,
+    _wrapped_key: &'a [u8],
+    _nonce: [u8; consts::ENVELOPE_V1_NONCE_LEN],
+    _payload: &'a [u8],
+    #[serde(borrow)]
+    key_id: Option<&'a [u8]>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::{CryptCodec, CryptEnvelopeRecord, CryptRecipient, EncryptOptions};
+
+    fn envelope(version: u8, recipients: Vec<CryptRecipient>) -> CryptEnvelopeRecord {
+        let mut record = CryptEnvelopeRecord::new(
+            42,
+            Vec::new(),
+            vec![1u8; consts::ENVELOPE_V1_NONCE_LEN],
+            vec![2u8; 64],
+            None,
+        );
+        record.version = version;
+        record.recipients = recipients;
+        record
+    }
+
+    #[test]
+    fn envelope_meta_matches_record_of_each_version() {
+        let single = vec![CryptRecipient::new(Some(b"ops".to_vec()), vec![3u8; 32])];
+        for version in [consts::ENVELOPE_VERSION_V1, consts::ENVELOPE_VERSION] {
+            let bytes = envelope(version, single.clone()).encode().expect("encode");
+            let meta = CryptEnvelopeMeta::parse(&bytes).expect("meta");
+            assert_eq!(meta.version, version);
+            assert_eq!(meta.algorithm, CryptAlgorithm::default());
+            assert_eq!(meta.session_id, 42);
+            assert_eq!(meta.recipients, vec![Some(b"ops".as_slice())]);
+        }
+
+        let bytes = envelope(
+            consts::ENVELOPE_VERSION,
+            vec![
+                CryptRecipient::new(None, vec![3u8; 32]),
+                CryptRecipient::new(Some(b"audit".to_vec()), vec![4u8; 32]),
+            ],
+        )
+        .encode()
+        .expect("encode");
+        let meta = CryptEnvelopeMeta::parse(&bytes).expect("meta");
+        assert_eq!(meta.recipients, vec![None, Some(b"audit".as_slice())]);
+        assert!(meta.has_key_id(b"audit"));
+        assert!(!meta.has_key_id(b"ops"));
+        assert_eq!(meta.key_ids().collect::<Vec<_>>(), vec![b"audit"]);
+    }
+
+    #[test]
+    fn envelope_meta_rejects_other_bytes() {
+        let bytes = envelope(
+            consts::ENVELOPE_VERSION,
+            vec![CryptRecipient::new(None, Vec::new())],
+        )
+        .encode()
+        .expect("encode");
+        assert!(matches!(
+            CryptEnvelopeMeta::parse(&bytes[..bytes.len() - 1]),
+            Err(CryptError::DecodeEnvelope)
+        ));
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(matches!(
+            CryptEnvelopeMeta::parse(&extended),
+            Err(CryptError::MalformedEnvelope { .. })
+        ));
+        assert!(CryptEnvelopeMeta::parse(b"plain payload").is_err());
+        assert!(matches!(
+            CryptEnvelopeMeta::parse(&[]),
+            Err(CryptError::DecodeEnvelope)
+        ));
+    }
+
+    #[test]
+    fn envelope_meta_rejects_unknown_versions() {
+        let bytes = envelope(
+            consts::ENVELOPE_VERSION,
+            vec![CryptRecipient::new(None, Vec::new())],
+        )
+        .encode()
+        .expect("encode");
+        for version in [0, consts::ENVELOPE_VERSION + 1] {
+            let mut unknown = bytes.clone();
+            unknown[0] = version;
+            assert!(matches!(
+                CryptEnvelopeMeta::parse(&unknown),
+                Err(CryptError::UnsupportedEnvelopeVersion {
+                    actual,
+                    expected: consts::ENVELOPE_VERSION,
+                }) if actual == version
+            ));
+        }
+    }
+
+    #[test]
+    fn envelope_meta_filter_keeps_plain_payloads() {
+        let mut options = EncryptOptions::symmetric("tenant-a", [5u8; 32]);
+        let own = CryptCodec::encrypt(b"own", &mut options).expect("encrypt");
+        let mut options = EncryptOptions::symmetric("tenant-b", [6u8; 32]);
+        let other = CryptCodec::encrypt(b"other", &mut options).expect("encrypt");
+
+        let RuleFnDef::Dynamic(filter) =
+            CryptEnvelopeMeta::filter(|meta| meta.has_key_id(b"tenant-a"))
+        else {
+            panic!("filter must be dynamic");
+        };
+        assert!(filter(&own));
+        assert!(!filter(&other));
+        assert!(filter(b"plain payload"));
+    }
+}
@@ -1,12 +1,22 @@
 mod algorithm;
+mod cipher;
 mod codec;
 mod consts;
 pub mod error;
+mod meta;
 pub mod options;
 mod record;
+mod rewrap;
+mod x25519;
 
-pub use algorithm::CryptAlgorithm;
+pub use algorithm::{CryptAlgorithm, CryptKeyWrap};
 pub use codec::CryptCodec;
 pub use error::{CryptError, CryptResult};
-pub use options::{CryptPolicy, DecryptOptions, EncryptOptions};
-pub use record::CryptEnvelopeRecord;
+pub use meta::CryptEnvelopeMeta;
+pub use options::{
+    CryptPolicy, DecryptKey, DecryptKeyring, DecryptOptions, EncryptOptions, FileKeyProvider,
+    KeyProvider,
+};
+pub use record::{CryptEnvelopeRecord, CryptRecipient};
+pub use rewrap::{RewrapMode, RewrapReport, Rewrapper};
+pub use secrecy::SecretString;
@@ -1,25 +1,25 @@
 use std::collections::VecDeque;
 use std::path::Path;
 
-use rsa::{Oaep, RsaPrivateKey, pkcs1::DecodeRsaPrivateKey, pkcs8::DecodePrivateKey, sha2::Sha256};
-use secrecy::{ExposeSecret, SecretBox};
-use zeroize::Zeroize;
+use rsa::{RsaPrivateKey, pkcs1::DecodeRsaPrivateKey, pkcs8::DecodePrivateKey};
+use secrecy::{ExposeSecret, SecretBox, SecretString};
 
 use crate::crypt::{
     consts,
     error::{CryptError, CryptResult},
-    options::CryptPolicy,
+    options::{CryptPolicy, DecryptKey, DecryptKeyring, KeyProvider},
 };
 
 /// Decryption settings for `CryptCodec`.
 ///
-/// This object is designed to be reused and keeps already-parsed key material.
+/// This object is designed to be reused and keeps already-parsed key material. Keys are
+/// kept in [`DecryptKeyring`]; the key used for an envelope is selected by its `key_id`.
 pub struct DecryptOptions {
-    private_key: RsaPrivateKey,
-    _private_key_source_pem: Option<SecretBox<String>>,
+    keyring: DecryptKeyring,
     expected_key_id: Option<Vec<u8>>,
     policy: CryptPolicy,
     session_cache: VecDeque<DecryptSessionCache>,
+    last_key: Option<usize>,
 }
 
 struct DecryptSessionCache {
@@ -27,20 +27,65 @@ struct DecryptSessionCache {
     key_id: Option<Vec<u8>>,
     wrapped_key_hash: [u8; 32],
     session_key: SecretBox<[u8; 32]>,
+    key_idx: usize,
 }
 
 impl DecryptOptions {
     /// Creates options from an already parsed RSA private key.
     pub fn new(private_key: RsaPrivateKey) -> Self {
+        Self::with_keyring(DecryptKeyring::new().with_key(Vec::new(), private_key))
+    }
+
+    /// Creates options of symmetric mode with a pre-shared 256-bit key.
+    ///
+    /// Counterpart of [`EncryptOptions::symmetric`]; more keys (symmetric or RSA) can be
+    /// registered with [`DecryptKeyring`].
+    ///
+    /// [`EncryptOptions::symmetric`]: crate::crypt::EncryptOptions::symmetric
+    pub fn symmetric(key_id: impl Into<Vec<u8>>, key: [u8; 32]) -> Self {
+        Self::with_keyring(DecryptKeyring::new().with_symmetric_key(key_id, key))
+    }
+
+    /// Creates options from a keyring with one or more RSA private keys.
+    ///
+    /// Returns [`CryptError::EmptyKeyring`] if the keyring has no keys.
+    pub fn from_keyring(keyring: DecryptKeyring) -> CryptResult<Self> {
+        if keyring.is_empty() {
+            return Err(CryptError::EmptyKeyring);
+        }
+        Ok(Self::with_keyring(keyring))
+    }
+
+    fn with_keyring(keyring: DecryptKeyring) -> Self {
         Self {
-            private_key,
-            _private_key_source_pem: None,
+            keyring,
             expected_key_id: None,
             policy: CryptPolicy::default(),
             session_cache: VecDeque::new(),
+            last_key: None,
         }
     }
 
+    /// Registers one more RSA private key under the given key identifier.
+    ///
+    /// See [`DecryptKeyring::with_key`].
+    pub fn with_key(mut self, key_id: impl Into<Vec<u8>>, private_key: RsaPrivateKey) -> Self {
+        self.keyring = self.keyring.with_key(key_id, private_key);
+        self
+    }
+
+    /// Registers a [`KeyProvider`] under the given key identifier.
+    ///
+    /// See [`DecryptKeyring::with_provider`].
+    pub fn with_provider(
+        mut self,
+        key_id: impl Into<Vec<u8>>,
+        provider: impl KeyProvider + 'static,
+    ) -> Self {
+        self.keyring = self.keyring.with_provider(key_id, provider);
+        self
+    }
+
     /// Replaces crypto runtime policy for this options instance.
     pub fn with_policy(mut self, policy: CryptPolicy) -> Self {
         self.policy = policy;
@@ -67,9 +112,32 @@ impl DecryptOptions {
         self.expected_key_id.as_deref()
     }
 
-    /// Returns parsed RSA private key.
+    /// Returns parsed RSA private key registered first.
+    ///
+    /// # Panics
+    /// Panics if the keyring holds no RSA private key (e.g. options of X25519 or symmetric
+    /// mode).
+    #[deprecated(
+        note = "use `DecryptOptions::rsa_private_key`, which returns `None` without RSA keys"
+    )]
     pub fn private_key(&self) -> &RsaPrivateKey {
-        &self.private_key
+        self.rsa_private_key()
+            .expect("DecryptOptions::private_key called on options without RSA private key")
+    }
+
+    /// Returns parsed RSA private key registered first; `None` if there are no RSA keys.
+    pub fn rsa_private_key(&self) -> Option<&RsaPrivateKey> {
+        self.keyring.keys().find_map(DecryptKey::private_key)
+    }
+
+    /// Returns keyring with registered private keys.
+    pub fn keyring(&self) -> &DecryptKeyring {
+        &self.keyring
+    }
+
+    /// Returns the key which decrypted the last payload, if any was decrypted.
+    pub fn last_key(&self) -> Option<&DecryptKey> {
+        self.last_key.and_then(|idx| self.keyring.key(idx))
     }
 
     /// Returns active runtime crypto policy.
@@ -77,12 +145,24 @@ impl DecryptOptions {
         self.policy
     }
 
+    pub(crate) fn set_last_key(&mut self, key_idx: usize) {
+        self.last_key = Some(key_idx);
+    }
+
+    /// Returns indexes of keys which should be tried for envelope with the given `key_id`.
+    pub(crate) fn candidate_keys(
+        &self,
+        key_ids: &[Option<&[u8]>],
+    ) -> CryptResult<Vec<(usize, usize)>> {
+        self.keyring.recipient_candidates(key_ids)
+    }
+
     pub(crate) fn cached_session_key(
         &self,
         session_id: u64,
         key_id: Option<&[u8]>,
         wrapped_key_hash: [u8; 32],
-    ) -> Option<(usize, &[u8; 32])> {
+    ) -> Option<(usize, usize, &[u8; 32])> {
         self.session_cache
             .iter()
             .enumerate()
@@ -91,7 +171,7 @@ impl DecryptOptions {
                     && entry.key_id.as_deref() == key_id
                     && entry.wrapped_key_hash == wrapped_key_hash
             })
-            .map(|(idx, entry)| (idx, entry.session_key.expose_secret()))
+            .map(|(idx, entry)| (idx, entry.key_idx, entry.session_key.expose_secret()))
     }
 
     pub(crate) fn remove_cache_session_by_idx(&mut self, idx: usize) {
@@ -104,6 +184,7 @@ impl DecryptOptions {
         key_id: Option<Vec<u8>>,
         wrapped_key_hash: [u8; 32],
         session_key: [u8; 32],
+        key_idx: usize,
     ) {
         self.session_cache.retain(|entry| {
             !(entry.session_id == session_id
@@ -115,6 +196,7 @@ impl DecryptOptions {
             key_id,
             wrapped_key_hash,
             session_key: SecretBox::new(Box::new(session_key)),
+            key_idx,
         });
         while self.session_cache.len() > self.policy.decrypt_cache_limit {
             self.session_cache.pop_back();
@@ -123,22 +205,14 @@ impl DecryptOptions {
 
     pub(crate) fn unwrap_session_key(
         &self,
+        key_idx: usize,
+        session_id: u64,
         wrapped_key: &[u8],
     ) -> CryptResult<[u8; consts::ENVELOPE_SESSION_KEY_LEN]> {
-        let mut session_key_raw = self
-            .private_key()
-            .decrypt(Oaep::new::<Sha256>(), wrapped_key)
-            .map_err(|_| CryptError::UnwrapSessionKey)?;
-
-        if session_key_raw.len() != consts::ENVELOPE_SESSION_KEY_LEN {
-            session_key_raw.zeroize();
-            return Err(CryptError::InvalidUnwrappedSessionKeyLength);
-        }
-
-        let mut session_key = [0u8; consts::ENVELOPE_SESSION_KEY_LEN];
-        session_key.copy_from_slice(&session_key_raw);
-        session_key_raw.zeroize();
-        Ok(session_key)
+        self.keyring
+            .key(key_idx)
+            .ok_or(CryptError::UnknownKeyId)?
+            .session_key(session_id, wrapped_key)
     }
 
     #[cfg(test)]
@@ -148,14 +222,9 @@ impl DecryptOptions {
 
     /// Builds options from PEM that contains an RSA private key.
     pub fn from_private_key_pem(pem: &str) -> CryptResult<Self> {
-        let private_key = parse_private_key_pem(pem)?;
-        Ok(Self {
-            private_key,
-            _private_key_source_pem: Some(SecretBox::new(Box::new(pem.to_owned()))),
-            expected_key_id: None,
-            policy: CryptPolicy::default(),
-            session_cache: VecDeque::new(),
-        })
+        Ok(Self::with_keyring(
+            DecryptKeyring::new().with_private_key_pem(Vec::new(), pem)?,
+        ))
     }
 
     /// Builds options from PEM file that contains an RSA private key.
@@ -164,6 +233,50 @@ impl DecryptOptions {
         Self::from_private_key_pem(&pem)
     }
 
+    /// Builds options from password-protected PKCS#8 PEM (`ENCRYPTED PRIVATE KEY`) that
+    /// contains an RSA or X25519 private key.
+    pub fn from_encrypted_private_key_pem(pem: &str, password: &SecretString) -> CryptResult<Self> {
+        Ok(Self::with_keyring(
+            DecryptKeyring::new().with_encrypted_private_key_pem(Vec::new(), pem, password)?,
+        ))
+    }
+
+    /// Builds options from password-protected PKCS#8 PEM file.
+    pub fn from_encrypted_private_key_pem_file(
+        path: impl AsRef<Path>,
+        password: &SecretString,
+    ) -> CryptResult<Self> {
+        let pem = read_text_file(path)?;
+        Self::from_encrypted_private_key_pem(&pem, password)
+    }
+
+    /// Creates options which delegate unwrapping of session keys to a [`KeyProvider`].
+    pub fn from_provider(provider: impl KeyProvider + 'static) -> Self {
+        Self::with_keyring(DecryptKeyring::new().with_provider(Vec::new(), provider))
+    }
+
+    /// Creates options from a raw X25519 private key.
+    ///
+    /// Counterpart of [`EncryptOptions::from_x25519_public`].
+    ///
+    /// [`EncryptOptions::from_x25519_public`]: crate::crypt::EncryptOptions::from_x25519_public
+    pub fn from_x25519_private(private_key: [u8; 32]) -> Self {
+        Self::with_keyring(DecryptKeyring::new().with_x25519_key(Vec::new(), private_key))
+    }
+
+    /// Builds options from PKCS#8 PEM (`PRIVATE KEY`) that contains an X25519 private key.
+    pub fn from_x25519_private_pem(pem: &str) -> CryptResult<Self> {
+        Ok(Self::with_keyring(
+            DecryptKeyring::new().with_x25519_private_key_pem(Vec::new(), pem)?,
+        ))
+    }
+
+    /// Builds options from PEM file that contains an X25519 private key.
+    pub fn from_x25519_private_pem_file(path: impl AsRef<Path>) -> CryptResult<Self> {
+        let pem = read_text_file(path)?;
+        Self::from_x25519_private_pem(&pem)
+    }
+
     /// Alias for [`from_private_key_pem`].
     pub fn from_pem(pem: &str) -> CryptResult<Self> {
         Self::from_private_key_pem(pem)
@@ -175,7 +288,7 @@ impl DecryptOptions {
     }
 }
 
-fn parse_private_key_pem(pem: &str) -> CryptResult<RsaPrivateKey> {
+pub(super) fn parse_private_key_pem(pem: &str) -> CryptResult<RsaPrivateKey> {
     if let Ok(private_key) = RsaPrivateKey::from_pkcs8_pem(pem) {
         return Ok(private_key);
     }
@@ -183,7 +296,7 @@ fn parse_private_key_pem(pem: &str) -> CryptResult<RsaPrivateKey> {
     RsaPrivateKey::from_pkcs1_pem(pem).map_err(|_| CryptError::InvalidRsaPrivateKeyPem)
 }
 
-fn read_text_file(path: impl AsRef<Path>) -> CryptResult<String> {
+pub(super) fn read_text_file(path: impl AsRef<Path>) -> CryptResult<String> {
     std::fs::read_to_string(path).map_err(CryptError::from)
 }
 
@@ -236,11 +349,39 @@ mod tests {
         assert!(from_file.expected_key_id().is_none());
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn decrypt_options_private_key_accessors() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let options = DecryptOptions::new(private_key.clone());
+        assert_eq!(options.private_key(), &private_key);
+        assert_eq!(options.rsa_private_key(), Some(&private_key));
+
+        let options = DecryptOptions::symmetric(b"kid-1".to_vec(), [7u8; 32]);
+        assert!(options.rsa_private_key().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "without RSA private key")]
+    #[allow(deprecated)]
+    fn decrypt_options_private_key_panics_without_rsa_key() {
+        DecryptOptions::symmetric(b"kid-1".to_vec(), [7u8; 32]).private_key();
+    }
+
     #[test]
     fn decrypt_options_from_pem_aliases_work() {
         let pem = make_private_key_pem();
         let options = DecryptOptions::from_pem(&pem).expect("from pem alias");
-        assert!(options.private_key().to_public_key().n().bits() >= 1024);
+        assert!(
+            options
+                .rsa_private_key()
+                .expect("RSA key")
+                .to_public_key()
+                .n()
+                .bits()
+                >= 1024
+        );
     }
 
     #[test]
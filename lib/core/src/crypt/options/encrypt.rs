@@ -8,39 +8,102 @@ use rsa::{
 use secrecy::{ExposeSecret, SecretBox};
 use std::path::Path;
 use x509_parser::prelude::parse_x509_certificate;
+use x25519_dalek::PublicKey as X25519PublicKey;
 use zeroize::Zeroize;
 
 use crate::crypt::{
-    consts,
+    algorithm::{CryptAlgorithm, CryptKeyWrap},
+    cipher, consts,
     error::{CryptError, CryptResult},
     options::CryptPolicy,
+    record::CryptRecipient,
+    x25519,
 };
 
 const PEM_CERTIFICATE_TAG: &str = "CERTIFICATE";
 
 /// Encryption settings for `CryptCodec`.
 ///
-/// This object is designed to be reused and keeps already-parsed key material.
+/// This object is designed to be reused and keeps already-parsed key material. Session keys
+/// are wrapped with an RSA public key by default, agreed with an X25519 public key (see
+/// [`EncryptOptions::from_x25519_public`]), or derived from a pre-shared key in symmetric
+/// mode (see [`EncryptOptions::symmetric`]).
+///
+/// More RSA key holders can be added with [`EncryptOptions::add_recipient`]: the session key
+/// is wrapped for each of them, so every recipient can decrypt the same envelope.
 pub struct EncryptOptions {
-    public_key: RsaPublicKey,
+    key: EncryptKey,
     key_id: Option<Vec<u8>>,
+    recipients: Vec<EncryptRecipient>,
+    algorithm: CryptAlgorithm,
     policy: CryptPolicy,
     session_cache: Option<EncryptSessionCache>,
 }
 
+enum EncryptKey {
+    Rsa(RsaPublicKey),
+    X25519(X25519PublicKey),
+    Symmetric(SecretBox<[u8; 32]>),
+}
+
+struct EncryptRecipient {
+    key_id: Option<Vec<u8>>,
+    public_key: RsaPublicKey,
+}
+
 pub(crate) struct EncryptSessionCache {
     session_id: u64,
     remaining_uses: u32,
     session_key: SecretBox<[u8; 32]>,
-    wrapped_key: Vec<u8>,
+    recipients: Vec<CryptRecipient>,
 }
 
 impl EncryptOptions {
     /// Creates options from an already parsed RSA public key.
     pub fn new(public_key: RsaPublicKey) -> Self {
+        Self::with_key(EncryptKey::Rsa(public_key))
+    }
+
+    /// Creates options of symmetric mode with a pre-shared 256-bit key.
+    ///
+    /// No RSA is involved: session keys are derived from the pre-shared key, and envelopes
+    /// carry no wrapped session key. The key identifier is embedded into every envelope,
+    /// so the reader can pick the same key with [`DecryptOptions::symmetric`] or
+    /// [`DecryptKeyring::with_symmetric_key`].
+    ///
+    /// [`DecryptOptions::symmetric`]: crate::crypt::DecryptOptions::symmetric
+    /// [`DecryptKeyring::with_symmetric_key`]: crate::crypt::DecryptKeyring::with_symmetric_key
+    pub fn symmetric(key_id: impl Into<Vec<u8>>, key: [u8; 32]) -> Self {
+        Self::with_key(EncryptKey::Symmetric(SecretBox::new(Box::new(key)))).with_key_id(key_id)
+    }
+
+    /// Creates options from a raw X25519 public key of the recipient.
+    ///
+    /// Each session key is agreed between an ephemeral sender key and the recipient's key
+    /// (ECIES); the envelope carries the 32-byte ephemeral public key instead of an RSA
+    /// wrapped session key.
+    pub fn from_x25519_public(public_key: [u8; 32]) -> Self {
+        Self::with_key(EncryptKey::X25519(X25519PublicKey::from(public_key)))
+    }
+
+    /// Builds options from SPKI PEM (`PUBLIC KEY`) that contains an X25519 public key.
+    pub fn from_x25519_public_pem(pem: &str) -> CryptResult<Self> {
+        let public_key = x25519::parse_public_key_pem(pem)?;
+        Ok(Self::with_key(EncryptKey::X25519(public_key)))
+    }
+
+    /// Builds options from PEM file that contains an X25519 public key.
+    pub fn from_x25519_public_pem_file(path: impl AsRef<Path>) -> CryptResult<Self> {
+        let pem = read_text_file(path)?;
+        Self::from_x25519_public_pem(&pem)
+    }
+
+    fn with_key(key: EncryptKey) -> Self {
         Self {
-            public_key,
+            key,
             key_id: None,
+            recipients: Vec::new(),
+            algorithm: CryptAlgorithm::default(),
             policy: CryptPolicy::default(),
             session_cache: None,
         }
@@ -52,6 +115,15 @@ impl EncryptOptions {
         self
     }
 
+    /// Sets the algorithm used to encrypt payloads.
+    ///
+    /// `ChaCha20Poly1305RsaOaepSha256` is used by default. The algorithm is recorded in
+    /// every envelope, so readers don't need to be configured for it.
+    pub fn with_algorithm(mut self, algorithm: CryptAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
     /// Sets optional key identifier embedded into the crypto envelope.
     pub fn with_key_id(mut self, key_id: impl Into<Vec<u8>>) -> Self {
         self.key_id = match key_id.into() {
@@ -67,14 +139,89 @@ impl EncryptOptions {
         self
     }
 
+    /// Adds one more recipient: the session key is also wrapped with the given RSA public key.
+    ///
+    /// The recipient's key identifier (ignored if empty) is stored next to its wrapped key,
+    /// so the reader picks the matching entry. The recipient can be added whatever kind of
+    /// key the options are created with; the current session is dropped.
+    pub fn add_recipient(&mut self, key_id: impl Into<Vec<u8>>, public_key: RsaPublicKey) {
+        let key_id = key_id.into();
+        self.recipients.push(EncryptRecipient {
+            key_id: (!key_id.is_empty()).then_some(key_id),
+            public_key,
+        });
+        self.session_cache = None;
+    }
+
+    /// Adds one more recipient from PEM that may contain either an RSA public key or an X509
+    /// certificate. See [`EncryptOptions::add_recipient`].
+    pub fn add_recipient_pem(&mut self, key_id: impl Into<Vec<u8>>, pem: &str) -> CryptResult<()> {
+        let public_key = match parse_public_key_pem(pem) {
+            Ok(public_key) => public_key,
+            Err(public_key_err) => parse_certificate_public_key(pem).map_err(|_| public_key_err)?,
+        };
+        self.add_recipient(key_id, public_key);
+        Ok(())
+    }
+
+    /// Returns the number of recipients, including the key the options are created with.
+    pub fn recipients_len(&self) -> usize {
+        self.recipients.len() + 1
+    }
+
     /// Returns configured key identifier, if set.
     pub fn key_id(&self) -> Option<&[u8]> {
         self.key_id.as_deref()
     }
 
+    /// Returns the algorithm used to encrypt payloads.
+    pub fn algorithm(&self) -> CryptAlgorithm {
+        self.algorithm
+    }
+
     /// Returns parsed RSA public key.
+    ///
+    /// # Panics
+    /// Panics if options use another kind of key (X25519 or symmetric).
+    #[deprecated(
+        note = "use `EncryptOptions::rsa_public_key`, which returns `None` for other keys"
+    )]
     pub fn public_key(&self) -> &RsaPublicKey {
-        &self.public_key
+        self.rsa_public_key()
+            .expect("EncryptOptions::public_key called on options without RSA public key")
+    }
+
+    /// Returns parsed RSA public key; `None` if options use another kind of key.
+    pub fn rsa_public_key(&self) -> Option<&RsaPublicKey> {
+        match &self.key {
+            EncryptKey::Rsa(public_key) => Some(public_key),
+            EncryptKey::X25519(_) | EncryptKey::Symmetric(_) => None,
+        }
+    }
+
+    /// Returns raw X25519 public key of the recipient, if options use X25519.
+    pub fn x25519_public_key(&self) -> Option<[u8; 32]> {
+        match &self.key {
+            EncryptKey::X25519(public_key) => Some(public_key.to_bytes()),
+            EncryptKey::Rsa(_) | EncryptKey::Symmetric(_) => None,
+        }
+    }
+
+    /// Returns `true` if options are of symmetric (pre-shared key) mode.
+    pub fn is_symmetric(&self) -> bool {
+        matches!(self.key, EncryptKey::Symmetric(_))
+    }
+
+    /// Returns how the session key is wrapped for the key the options are created with.
+    ///
+    /// Recipients added with [`EncryptOptions::add_recipient`] always use
+    /// [`CryptKeyWrap::RsaOaepSha256`].
+    pub fn key_wrap(&self) -> CryptKeyWrap {
+        match &self.key {
+            EncryptKey::Rsa(_) => CryptKeyWrap::RsaOaepSha256,
+            EncryptKey::X25519(_) => CryptKeyWrap::X25519,
+            EncryptKey::Symmetric(_) => CryptKeyWrap::Symmetric,
+        }
     }
 
     /// Returns active runtime crypto policy.
@@ -82,30 +229,70 @@ impl EncryptOptions {
         self.policy
     }
 
-    pub(crate) fn create_session(&mut self) -> CryptResult<(u64, Vec<u8>, [u8; 32])> {
+    pub(crate) fn create_session(&mut self) -> CryptResult<(u64, Vec<CryptRecipient>, [u8; 32])> {
         let mut session_id = OsRng.next_u64();
         if session_id == 0 {
             session_id = 1;
         }
-        let mut session_key = [0u8; consts::ENVELOPE_SESSION_KEY_LEN];
-        OsRng.fill_bytes(&mut session_key);
-        let wrapped_key = self
-            .public_key()
-            .encrypt(&mut OsRng, Oaep::new::<Sha256>(), &session_key)
-            .map_err(|_| {
-                session_key.zeroize();
-                CryptError::WrapSessionKey
-            })?;
+        let (mut session_key, wrapped_key) = match &self.key {
+            EncryptKey::Rsa(public_key) => {
+                let mut session_key = [0u8; consts::ENVELOPE_SESSION_KEY_LEN];
+                OsRng.fill_bytes(&mut session_key);
+                let wrapped_key = wrap_session_key(public_key, &session_key).inspect_err(|_| {
+                    session_key.zeroize();
+                })?;
+                (session_key, wrapped_key)
+            }
+            EncryptKey::X25519(public_key) => x25519::wrap_session_key(public_key)?,
+            EncryptKey::Symmetric(shared_key) => (
+                cipher::derive_session_key(shared_key.expose_secret(), session_id)?,
+                Vec::new(),
+            ),
+        };
+        let mut recipients = Vec::with_capacity(self.recipients_len());
+        recipients.push(CryptRecipient::new(self.key_id.clone(), wrapped_key));
+        for recipient in self.recipients.iter() {
+            let wrapped_key =
+                wrap_session_key(&recipient.public_key, &session_key).inspect_err(|_| {
+                    session_key.zeroize();
+                })?;
+            recipients.push(CryptRecipient::new(recipient.key_id.clone(), wrapped_key));
+        }
         self.cache_session(
             session_id,
             self.policy().session_reuse_limit.saturating_sub(1),
             session_key,
-            wrapped_key.clone(),
+            recipients.clone(),
         );
-        Ok((session_id, wrapped_key, session_key))
+        Ok((session_id, recipients, session_key))
+    }
+
+    /// Wraps an already existing session key for the primary key and every added recipient.
+    ///
+    /// Returns `None` if the primary key cannot carry an arbitrary session key: X25519 and
+    /// symmetric session keys are derived rather than wrapped.
+    pub(crate) fn wrap_existing_session(
+        &self,
+        session_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
+    ) -> CryptResult<Option<Vec<CryptRecipient>>> {
+        let EncryptKey::Rsa(public_key) = &self.key else {
+            return Ok(None);
+        };
+        let mut recipients = Vec::with_capacity(self.recipients_len());
+        recipients.push(CryptRecipient::new(
+            self.key_id.clone(),
+            wrap_session_key(public_key, session_key)?,
+        ));
+        for recipient in self.recipients.iter() {
+            recipients.push(CryptRecipient::new(
+                recipient.key_id.clone(),
+                wrap_session_key(&recipient.public_key, session_key)?,
+            ));
+        }
+        Ok(Some(recipients))
     }
 
-    pub(crate) fn current_session(&mut self) -> Option<(u64, &[u8; 32], &[u8])> {
+    pub(crate) fn current_session(&mut self) -> Option<(u64, &[u8; 32], &[CryptRecipient])> {
         self.session_cache.as_mut().and_then(|session| {
             if session.remaining_uses == 0 {
                 None
@@ -114,7 +301,7 @@ impl EncryptOptions {
                 Some((
                     session.session_id,
                     session.session_key.expose_secret(),
-                    session.wrapped_key.as_slice(),
+                    session.recipients.as_slice(),
                 ))
             }
         })
@@ -125,13 +312,13 @@ impl EncryptOptions {
         session_id: u64,
         remaining_uses: u32,
         session_key: [u8; 32],
-        wrapped_key: Vec<u8>,
+        recipients: Vec<CryptRecipient>,
     ) {
         self.session_cache = Some(EncryptSessionCache {
             session_id,
             remaining_uses,
             session_key: SecretBox::new(Box::new(session_key)),
-            wrapped_key,
+            recipients,
         });
     }
 
@@ -178,13 +365,25 @@ impl EncryptOptions {
     }
 
     /// Exports configured public key as SPKI PEM.
+    ///
+    /// Returns [`CryptError::NotAnRsaKey`] if options use another kind of key.
     pub fn to_public_key_pem(&self) -> CryptResult<String> {
-        self.public_key
+        self.rsa_public_key()
+            .ok_or(CryptError::NotAnRsaKey)?
             .to_public_key_pem(Default::default())
             .map_err(|_| CryptError::ExportPublicKeyPem)
     }
 }
 
+fn wrap_session_key(
+    public_key: &RsaPublicKey,
+    session_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
+) -> CryptResult<Vec<u8>> {
+    public_key
+        .encrypt(&mut OsRng, Oaep::new::<Sha256>(), session_key)
+        .map_err(|_| CryptError::WrapSessionKey)
+}
+
 fn parse_public_key_pem(pem: &str) -> CryptResult<RsaPublicKey> {
     if let Ok(public_key) = RsaPublicKey::from_public_key_pem(pem) {
         return Ok(public_key);
@@ -257,6 +456,7 @@ mod tests {
 
         let from_pem = EncryptOptions::from_public_key_pem(&pem).expect("from pem");
         assert!(from_pem.key_id().is_none());
+        assert_eq!(from_pem.key_wrap(), CryptKeyWrap::RsaOaepSha256);
 
         let mut file = NamedTempFile::new().expect("temp file");
         file.write_all(pem.as_bytes()).expect("write pem");
@@ -282,6 +482,20 @@ mod tests {
         assert!(matches!(err, CryptError::InvalidRsaPublicKeyPem));
     }
 
+    #[test]
+    fn encrypt_options_algorithm_mutator() {
+        let mut rng = OsRng;
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).expect("private key");
+        let public_key = private_key.to_public_key();
+        let options = EncryptOptions::new(public_key);
+        assert_eq!(
+            options.algorithm(),
+            CryptAlgorithm::ChaCha20Poly1305RsaOaepSha256
+        );
+        let options = options.with_algorithm(CryptAlgorithm::Aes256Gcm);
+        assert_eq!(options.algorithm(), CryptAlgorithm::Aes256Gcm);
+    }
+
     #[test]
     fn encrypt_options_policy_mutator() {
         let mut rng = OsRng;
@@ -294,4 +508,44 @@ mod tests {
         let options = EncryptOptions::new(public_key).with_policy(policy);
         assert_eq!(options.policy(), policy);
     }
+
+    #[test]
+    fn encrypt_options_symmetric_mode() {
+        let mut options = EncryptOptions::symmetric(TEST_KEY_ID.to_vec(), [7u8; 32]);
+        assert!(options.is_symmetric());
+        assert_eq!(options.key_wrap(), CryptKeyWrap::Symmetric);
+        assert!(options.rsa_public_key().is_none());
+        assert_eq!(options.key_id(), Some(TEST_KEY_ID));
+        assert!(matches!(
+            options.to_public_key_pem(),
+            Err(CryptError::NotAnRsaKey)
+        ));
+
+        let (session_id, recipients, session_key) = options.create_session().expect("session");
+        assert_eq!(recipients.len(), 1);
+        assert!(recipients[0].wrapped_key.is_empty());
+        assert_ne!(session_key, [7u8; 32]);
+        assert_eq!(
+            cipher::derive_session_key(&[7u8; 32], session_id).expect("derive"),
+            session_key
+        );
+    }
+
+    #[test]
+    fn encrypt_options_from_x25519_public() {
+        let secret = x25519_dalek::StaticSecret::random_from_rng(OsRng);
+        let public_key = X25519PublicKey::from(&secret).to_bytes();
+        let mut options = EncryptOptions::from_x25519_public(public_key);
+        assert_eq!(options.x25519_public_key(), Some(public_key));
+        assert_eq!(options.key_wrap(), CryptKeyWrap::X25519);
+        assert!(options.rsa_public_key().is_none());
+        assert!(!options.is_symmetric());
+
+        let (_, recipients, session_key) = options.create_session().expect("session");
+        assert_eq!(recipients[0].wrapped_key.len(), consts::X25519_KEY_LEN);
+        assert_eq!(
+            x25519::unwrap_session_key(&secret, &recipients[0].wrapped_key).expect("unwrap"),
+            session_key
+        );
+    }
 }
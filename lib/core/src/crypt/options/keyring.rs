@@ -0,0 +1,518 @@
+use std::path::Path;
+
+use rsa::{Oaep, RsaPrivateKey, sha2::Sha256};
+use secrecy::{ExposeSecret, SecretBox, SecretString};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+use zeroize::Zeroize;
+
+use crate::crypt::{
+    algorithm::CryptKeyWrap,
+    cipher, consts,
+    error::{CryptError, CryptResult},
+    options::{
+        KeyProvider,
+        decrypt::{parse_private_key_pem, read_text_file},
+        provider::parse_encrypted_private_key_pem,
+    },
+    x25519,
+};
+
+/// Private key registered in [`DecryptKeyring`].
+pub struct DecryptKey {
+    key_id: Option<Vec<u8>>,
+    material: DecryptKeyMaterial,
+    _private_key_source_pem: Option<SecretBox<String>>,
+}
+
+pub(super) enum DecryptKeyMaterial {
+    Rsa(Box<RsaPrivateKey>),
+    X25519(StaticSecret),
+    Symmetric(SecretBox<[u8; 32]>),
+    Provider(Box<dyn KeyProvider>),
+}
+
+impl DecryptKey {
+    /// Returns key identifier, if the key was registered with one.
+    pub fn key_id(&self) -> Option<&[u8]> {
+        self.key_id.as_deref()
+    }
+
+    /// Returns parsed RSA private key; `None` for a key of another kind.
+    pub fn private_key(&self) -> Option<&RsaPrivateKey> {
+        match &self.material {
+            DecryptKeyMaterial::Rsa(private_key) => Some(private_key),
+            DecryptKeyMaterial::X25519(_)
+            | DecryptKeyMaterial::Symmetric(_)
+            | DecryptKeyMaterial::Provider(_) => None,
+        }
+    }
+
+    /// Returns raw X25519 public key matching the private key, if it's an X25519 key.
+    ///
+    /// This is the key to pass to [`EncryptOptions::from_x25519_public`] on the writer side.
+    ///
+    /// [`EncryptOptions::from_x25519_public`]: crate::crypt::EncryptOptions::from_x25519_public
+    pub fn x25519_public_key(&self) -> Option<[u8; 32]> {
+        match &self.material {
+            DecryptKeyMaterial::X25519(secret) => Some(X25519PublicKey::from(secret).to_bytes()),
+            DecryptKeyMaterial::Rsa(_)
+            | DecryptKeyMaterial::Symmetric(_)
+            | DecryptKeyMaterial::Provider(_) => None,
+        }
+    }
+
+    /// Returns `true` if the key is a pre-shared symmetric key.
+    pub fn is_symmetric(&self) -> bool {
+        matches!(self.material, DecryptKeyMaterial::Symmetric(_))
+    }
+
+    /// Returns `true` if session keys are unwrapped by a [`KeyProvider`].
+    pub fn is_provided(&self) -> bool {
+        matches!(self.material, DecryptKeyMaterial::Provider(_))
+    }
+
+    /// Returns how session keys are wrapped for this key; `None` for a [`KeyProvider`], which
+    /// keeps its key elsewhere.
+    pub fn key_wrap(&self) -> Option<CryptKeyWrap> {
+        match &self.material {
+            DecryptKeyMaterial::Rsa(_) => Some(CryptKeyWrap::RsaOaepSha256),
+            DecryptKeyMaterial::X25519(_) => Some(CryptKeyWrap::X25519),
+            DecryptKeyMaterial::Symmetric(_) => Some(CryptKeyWrap::Symmetric),
+            DecryptKeyMaterial::Provider(_) => None,
+        }
+    }
+
+    /// Recovers the session key of an envelope.
+    pub(crate) fn session_key(
+        &self,
+        session_id: u64,
+        wrapped_key: &[u8],
+    ) -> CryptResult<[u8; consts::ENVELOPE_SESSION_KEY_LEN]> {
+        self.material.session_key(session_id, wrapped_key)
+    }
+}
+
+impl DecryptKeyMaterial {
+    /// Recovers the session key of an envelope.
+    ///
+    /// RSA keys unwrap `wrapped_key`, X25519 keys agree the session key with the ephemeral
+    /// public key stored in `wrapped_key`; symmetric keys derive the session key from
+    /// `session_id` and expect no wrapped key. Providers get both values as is.
+    pub(super) fn session_key(
+        &self,
+        session_id: u64,
+        wrapped_key: &[u8],
+    ) -> CryptResult<[u8; consts::ENVELOPE_SESSION_KEY_LEN]> {
+        let private_key = match self {
+            DecryptKeyMaterial::Symmetric(shared_key) => {
+                if !wrapped_key.is_empty() {
+                    return Err(CryptError::UnwrapSessionKey);
+                }
+                return cipher::derive_session_key(shared_key.expose_secret(), session_id);
+            }
+            DecryptKeyMaterial::X25519(secret) => {
+                return x25519::unwrap_session_key(secret, wrapped_key);
+            }
+            DecryptKeyMaterial::Provider(provider) => {
+                return provider.unwrap_session_key(session_id, wrapped_key);
+            }
+            DecryptKeyMaterial::Rsa(private_key) => private_key,
+        };
+        if wrapped_key.is_empty() {
+            return Err(CryptError::UnwrapSessionKey);
+        }
+        let mut session_key_raw = private_key
+            .decrypt(Oaep::new::<Sha256>(), wrapped_key)
+            .map_err(|_| CryptError::UnwrapSessionKey)?;
+
+        if session_key_raw.len() != consts::ENVELOPE_SESSION_KEY_LEN {
+            session_key_raw.zeroize();
+            return Err(CryptError::InvalidUnwrappedSessionKeyLength);
+        }
+
+        let mut session_key = [0u8; consts::ENVELOPE_SESSION_KEY_LEN];
+        session_key.copy_from_slice(&session_key_raw);
+        session_key_raw.zeroize();
+        Ok(session_key)
+    }
+}
+
+/// Set of keys used for decryption, addressed by key identifier.
+///
+/// A keyring may hold RSA and X25519 private keys, pre-shared symmetric keys and
+/// [`KeyProvider`]s which keep their keys elsewhere.
+///
+/// The key is selected by `key_id` stored in the crypto envelope. Envelopes without
+/// `key_id` (or with `key_id` unknown to the keyring) are tried against keys registered
+/// without identifier, in order of registration.
+#[derive(Default)]
+pub struct DecryptKeyring {
+    keys: Vec<DecryptKey>,
+}
+
+impl DecryptKeyring {
+    /// Creates an empty keyring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an RSA private key under the given key identifier.
+    ///
+    /// An empty identifier registers the key without identifier. A key registered under
+    /// an already known identifier replaces the previous one.
+    pub fn with_key(mut self, key_id: impl Into<Vec<u8>>, private_key: RsaPrivateKey) -> Self {
+        self.insert(
+            key_id.into(),
+            DecryptKeyMaterial::Rsa(Box::new(private_key)),
+            None,
+        );
+        self
+    }
+
+    /// Registers a raw X25519 private key under the given key identifier.
+    pub fn with_x25519_key(mut self, key_id: impl Into<Vec<u8>>, private_key: [u8; 32]) -> Self {
+        self.insert(
+            key_id.into(),
+            DecryptKeyMaterial::X25519(StaticSecret::from(private_key)),
+            None,
+        );
+        self
+    }
+
+    /// Registers an X25519 private key from PKCS#8 PEM (`PRIVATE KEY`) under the given key
+    /// identifier.
+    pub fn with_x25519_private_key_pem(
+        mut self,
+        key_id: impl Into<Vec<u8>>,
+        pem: &str,
+    ) -> CryptResult<Self> {
+        let secret = x25519::parse_private_key_pem(pem)?;
+        self.insert(
+            key_id.into(),
+            DecryptKeyMaterial::X25519(secret),
+            Some(SecretBox::new(Box::new(pem.to_owned()))),
+        );
+        Ok(self)
+    }
+
+    /// Registers an X25519 private key from PEM file under the given key identifier.
+    pub fn with_x25519_private_key_pem_file(
+        self,
+        key_id: impl Into<Vec<u8>>,
+        path: impl AsRef<Path>,
+    ) -> CryptResult<Self> {
+        let pem = read_text_file(path)?;
+        self.with_x25519_private_key_pem(key_id, &pem)
+    }
+
+    /// Registers a pre-shared 256-bit key (see [`EncryptOptions::symmetric`]) under the
+    /// given key identifier.
+    ///
+    /// [`EncryptOptions::symmetric`]: crate::crypt::EncryptOptions::symmetric
+    pub fn with_symmetric_key(mut self, key_id: impl Into<Vec<u8>>, key: [u8; 32]) -> Self {
+        self.insert(
+            key_id.into(),
+            DecryptKeyMaterial::Symmetric(SecretBox::new(Box::new(key))),
+            None,
+        );
+        self
+    }
+
+    /// Registers an RSA private key from PEM under the given key identifier.
+    pub fn with_private_key_pem(
+        mut self,
+        key_id: impl Into<Vec<u8>>,
+        pem: &str,
+    ) -> CryptResult<Self> {
+        let private_key = parse_private_key_pem(pem)?;
+        self.insert(
+            key_id.into(),
+            DecryptKeyMaterial::Rsa(Box::new(private_key)),
+            Some(SecretBox::new(Box::new(pem.to_owned()))),
+        );
+        Ok(self)
+    }
+
+    /// Registers an RSA or X25519 private key from password-protected PKCS#8 PEM
+    /// (`ENCRYPTED PRIVATE KEY`) under the given key identifier.
+    pub fn with_encrypted_private_key_pem(
+        mut self,
+        key_id: impl Into<Vec<u8>>,
+        pem: &str,
+        password: &SecretString,
+    ) -> CryptResult<Self> {
+        let material = parse_encrypted_private_key_pem(pem, password)?;
+        self.insert(key_id.into(), material, None);
+        Ok(self)
+    }
+
+    /// Registers a private key from password-protected PKCS#8 PEM file under the given key
+    /// identifier.
+    pub fn with_encrypted_private_key_pem_file(
+        self,
+        key_id: impl Into<Vec<u8>>,
+        path: impl AsRef<Path>,
+        password: &SecretString,
+    ) -> CryptResult<Self> {
+        let pem = read_text_file(path)?;
+        self.with_encrypted_private_key_pem(key_id, &pem, password)
+    }
+
+    /// Registers a [`KeyProvider`] under the given key identifier.
+    ///
+    /// Session keys of envelopes addressed to this identifier are unwrapped by the provider.
+    pub fn with_provider(
+        mut self,
+        key_id: impl Into<Vec<u8>>,
+        provider: impl KeyProvider + 'static,
+    ) -> Self {
+        self.insert(
+            key_id.into(),
+            DecryptKeyMaterial::Provider(Box::new(provider)),
+            None,
+        );
+        self
+    }
+
+    /// Registers an RSA private key from PEM file under the given key identifier.
+    pub fn with_private_key_pem_file(
+        self,
+        key_id: impl Into<Vec<u8>>,
+        path: impl AsRef<Path>,
+    ) -> CryptResult<Self> {
+        let pem = read_text_file(path)?;
+        self.with_private_key_pem(key_id, &pem)
+    }
+
+    /// Returns key registered under the given identifier.
+    pub fn get(&self, key_id: &[u8]) -> Option<&DecryptKey> {
+        self.position(key_id).map(|idx| &self.keys[idx])
+    }
+
+    /// Returns registered keys in order of registration.
+    pub fn keys(&self) -> impl Iterator<Item = &DecryptKey> {
+        self.keys.iter()
+    }
+
+    /// Returns number of registered keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if no keys are registered.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub(crate) fn key(&self, idx: usize) -> Option<&DecryptKey> {
+        self.keys.get(idx)
+    }
+
+    /// Returns indexes of keys which should be tried for envelope with the given `key_id`.
+    pub(crate) fn candidates(&self, key_id: Option<&[u8]>) -> CryptResult<Vec<usize>> {
+        if let Some(idx) = key_id.and_then(|key_id| self.position(key_id)) {
+            return Ok(vec![idx]);
+        }
+        let candidates: Vec<usize> = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| key_id.is_none() || key.key_id.is_none())
+            .map(|(idx, _)| idx)
+            .collect();
+        if candidates.is_empty() {
+            return Err(CryptError::UnknownKeyId);
+        }
+        Ok(candidates)
+    }
+
+    /// Returns `(recipient, key)` index pairs which should be tried for envelope with
+    /// recipients of the given key identifiers.
+    ///
+    /// Recipients whose `key_id` is registered are tried with that key only; other keys are
+    /// tried only if no recipient is addressed to a registered key.
+    pub(crate) fn recipient_candidates(
+        &self,
+        key_ids: &[Option<&[u8]>],
+    ) -> CryptResult<Vec<(usize, usize)>> {
+        let exact: Vec<(usize, usize)> = key_ids
+            .iter()
+            .enumerate()
+            .filter_map(|(recipient, key_id)| {
+                key_id
+                    .and_then(|key_id| self.position(key_id))
+                    .map(|idx| (recipient, idx))
+            })
+            .collect();
+        if !exact.is_empty() {
+            return Ok(exact);
+        }
+        let candidates: Vec<(usize, usize)> = key_ids
+            .iter()
+            .enumerate()
+            .flat_map(|(recipient, key_id)| {
+                self.candidates(*key_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |idx| (recipient, idx))
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Err(CryptError::UnknownKeyId);
+        }
+        Ok(candidates)
+    }
+
+    fn position(&self, key_id: &[u8]) -> Option<usize> {
+        self.keys
+            .iter()
+            .position(|key| key.key_id.as_deref() == Some(key_id))
+    }
+
+    fn insert(
+        &mut self,
+        key_id: Vec<u8>,
+        material: DecryptKeyMaterial,
+        source_pem: Option<SecretBox<String>>,
+    ) {
+        let key = DecryptKey {
+            key_id: (!key_id.is_empty()).then_some(key_id),
+            material,
+            _private_key_source_pem: source_pem,
+        };
+        match key
+            .key_id
+            .as_deref()
+            .and_then(|key_id| self.position(key_id))
+        {
+            Some(idx) => self.keys[idx] = key,
+            None => self.keys.push(key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::{pkcs8::EncodePrivateKey, rand_core::OsRng};
+
+    fn make_private_key() -> RsaPrivateKey {
+        let mut rng = OsRng;
+        RsaPrivateKey::new(&mut rng, 1024).expect("private key")
+    }
+
+    #[test]
+    fn decrypt_keyring_selects_key_by_key_id() {
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-1".to_vec(), make_private_key())
+            .with_key(b"kid-2".to_vec(), make_private_key());
+        assert_eq!(keyring.len(), 2);
+        assert_eq!(
+            keyring.candidates(Some(b"kid-2")).expect("candidates"),
+            vec![1]
+        );
+        assert_eq!(keyring.candidates(None).expect("candidates"), vec![0, 1]);
+        assert!(matches!(
+            keyring.candidates(Some(b"kid-3")),
+            Err(CryptError::UnknownKeyId)
+        ));
+    }
+
+    #[test]
+    fn decrypt_keyring_falls_back_to_keys_without_key_id() {
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-1".to_vec(), make_private_key())
+            .with_key(Vec::new(), make_private_key());
+        assert!(keyring.keys().nth(1).expect("key").key_id().is_none());
+        assert_eq!(
+            keyring.candidates(Some(b"kid-3")).expect("candidates"),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn decrypt_keyring_prefers_recipient_with_known_key_id() {
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-1".to_vec(), make_private_key())
+            .with_key(Vec::new(), make_private_key());
+        assert_eq!(
+            keyring
+                .recipient_candidates(&[Some(b"kid-3"), Some(b"kid-1")])
+                .expect("candidates"),
+            vec![(1, 0)]
+        );
+        assert_eq!(
+            keyring
+                .recipient_candidates(&[Some(b"kid-3"), None])
+                .expect("candidates"),
+            vec![(0, 1), (1, 0), (1, 1)]
+        );
+        assert!(matches!(
+            DecryptKeyring::new()
+                .with_key(b"kid-1".to_vec(), make_private_key())
+                .recipient_candidates(&[Some(b"kid-2"), Some(b"kid-3")]),
+            Err(CryptError::UnknownKeyId)
+        ));
+    }
+
+    #[test]
+    fn decrypt_keyring_replaces_key_with_same_key_id() {
+        let replacement = make_private_key();
+        let pem = replacement
+            .to_pkcs8_pem(Default::default())
+            .expect("private key pem")
+            .to_string();
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-1".to_vec(), make_private_key())
+            .with_private_key_pem(b"kid-1".to_vec(), &pem)
+            .expect("from pem");
+        assert_eq!(keyring.len(), 1);
+        assert_eq!(
+            keyring.get(b"kid-1").and_then(DecryptKey::private_key),
+            Some(&replacement)
+        );
+    }
+
+    #[test]
+    fn decrypt_keyring_holds_symmetric_keys() {
+        let keyring = DecryptKeyring::new()
+            .with_key(b"kid-1".to_vec(), make_private_key())
+            .with_symmetric_key(b"kid-2".to_vec(), [7u8; 32]);
+        let key = keyring.get(b"kid-2").expect("key");
+        assert!(key.is_symmetric());
+        assert_eq!(key.key_wrap(), Some(CryptKeyWrap::Symmetric));
+        assert!(key.private_key().is_none());
+        assert_eq!(
+            key.session_key(5, &[]).expect("session key"),
+            cipher::derive_session_key(&[7u8; 32], 5).expect("derive")
+        );
+        assert!(matches!(
+            key.session_key(5, &[1, 2, 3]),
+            Err(CryptError::UnwrapSessionKey)
+        ));
+    }
+
+    #[test]
+    fn decrypt_keyring_holds_x25519_keys() {
+        let secret = StaticSecret::random_from_rng(OsRng);
+        let keyring = DecryptKeyring::new().with_x25519_key(b"kid-1".to_vec(), secret.to_bytes());
+        let key = keyring.get(b"kid-1").expect("key");
+        assert!(key.private_key().is_none());
+        assert_eq!(key.key_wrap(), Some(CryptKeyWrap::X25519));
+        assert_eq!(
+            key.x25519_public_key(),
+            Some(X25519PublicKey::from(&secret).to_bytes())
+        );
+
+        let (session_key, wrapped_key) =
+            x25519::wrap_session_key(&X25519PublicKey::from(&secret)).expect("wrap");
+        assert_eq!(
+            key.session_key(1, &wrapped_key).expect("session key"),
+            session_key
+        );
+        assert!(matches!(
+            key.session_key(1, &[]),
+            Err(CryptError::UnwrapSessionKey)
+        ));
+    }
+}
@@ -1,7 +1,11 @@
 mod decrypt;
 mod encrypt;
+mod keyring;
 mod policy;
+mod provider;
 
 pub use decrypt::DecryptOptions;
 pub use encrypt::EncryptOptions;
+pub use keyring::{DecryptKey, DecryptKeyring};
 pub use policy::CryptPolicy;
+pub use provider::{FileKeyProvider, KeyProvider};
@@ -15,65 +15,121 @@ pub struct CryptEnvelopeRecord {
     pub algorithm: CryptAlgorithm,
     /// Session identifier used to correlate wrapped keys.
     pub session_id: u64,
-    /// Wrapped symmetric session key bytes.
-    pub wrapped_key: Vec<u8>,
-    /// AEAD nonce used for payload encryption.
-    pub nonce: [u8; consts::ENVELOPE_NONCE_LEN],
+    /// Recipients of the envelope; each of them can recover the session key.
+    pub recipients: Vec<CryptRecipient>,
+    /// AEAD nonce used for payload encryption; its length depends on the algorithm.
+    pub nonce: Vec<u8>,
     /// Encrypted payload bytes (ciphertext + tag).
     pub payload: Vec<u8>,
-    /// Optional key identifier carried with the envelope.
+}
+
+/// Session key of an envelope wrapped for one recipient.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CryptRecipient {
+    /// Optional identifier of the recipient's key.
     pub key_id: Option<Vec<u8>>,
+    /// Wrapped symmetric session key bytes.
+    pub wrapped_key: Vec<u8>,
+}
+
+impl CryptRecipient {
+    /// Creates a recipient entry; an empty key identifier is normalized to `None`.
+    pub fn new(key_id: Option<Vec<u8>>, wrapped_key: Vec<u8>) -> Self {
+        Self {
+            key_id: key_id.filter(|key_id| !key_id.is_empty()),
+            wrapped_key,
+        }
+    }
+
+    pub(crate) fn wrapped_key_hash(&self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&Sha256::digest(&self.wrapped_key));
+        hash
+    }
 }
 
 impl CryptEnvelopeRecord {
-    /// Creates a new envelope record with crate defaults for version and algorithm.
+    /// Creates a new envelope record of a single recipient with crate defaults for version
+    /// and algorithm.
     pub fn new(
         session_id: u64,
         wrapped_key: Vec<u8>,
-        nonce: [u8; consts::ENVELOPE_NONCE_LEN],
+        nonce: Vec<u8>,
         payload: Vec<u8>,
         key_id: Option<Vec<u8>>,
     ) -> Self {
-        let key_id = key_id.filter(|key_id| !key_id.is_empty());
         Self {
             version: consts::ENVELOPE_VERSION,
-            algorithm: CryptAlgorithm::ChaCha20Poly1305RsaOaepSha256,
+            algorithm: CryptAlgorithm::default(),
             session_id,
-            wrapped_key,
+            recipients: vec![CryptRecipient::new(key_id, wrapped_key)],
             nonce,
             payload,
-            key_id,
         }
     }
 
+    /// Sets the algorithm the payload is encrypted with.
+    pub fn with_algorithm(mut self, algorithm: CryptAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Adds one more recipient of the session key.
+    pub fn with_recipient(mut self, recipient: CryptRecipient) -> Self {
+        self.recipients.push(recipient);
+        self
+    }
+
+    /// Returns key identifiers of all recipients which have one.
+    pub fn key_ids(&self) -> impl Iterator<Item = &[u8]> {
+        self.recipients
+            .iter()
+            .filter_map(|recipient| recipient.key_id.as_deref())
+    }
+
     /// Serializes envelope record to bytes with bincode.
+    ///
+    /// A record of the first envelope version is written in the layout of that version, which
+    /// carries exactly one recipient.
     pub fn encode(&self) -> CryptResult<Vec<u8>> {
         self.validate()?;
-        bincode::serde::encode_to_vec(self, bincode::config::standard())
-            .map_err(|_| CryptError::EncodeEnvelope)
+        let encoded = match self.version {
+            consts::ENVELOPE_VERSION_V1 => bincode::serde::encode_to_vec(
+                CryptEnvelopeRecordV1::try_from(self)?,
+                bincode::config::standard(),
+            ),
+            _ => bincode::serde::encode_to_vec(self, bincode::config::standard()),
+        };
+        encoded.map_err(|_| CryptError::EncodeEnvelope)
     }
 
     /// Parses envelope record from bincode bytes.
+    ///
+    /// Envelopes of the first version (single recipient, fixed 12-byte nonce) are supported
+    /// as well.
     pub fn decode(buf: &[u8]) -> CryptResult<Self> {
-        let (record, read): (Self, usize) =
-            bincode::serde::decode_from_slice(buf, bincode::config::standard())
-                .map_err(|_| CryptError::DecodeEnvelope)?;
-        if read != buf.len() {
-            return Err(CryptError::MalformedEnvelope {
-                decoded: read,
-                total: buf.len(),
-            });
+        fn decode_as<T: serde::de::DeserializeOwned>(buf: &[u8]) -> CryptResult<T> {
+            let (record, read): (T, usize) =
+                bincode::serde::decode_from_slice(buf, bincode::config::standard())
+                    .map_err(|_| CryptError::DecodeEnvelope)?;
+            if read != buf.len() {
+                return Err(CryptError::MalformedEnvelope {
+                    decoded: read,
+                    total: buf.len(),
+                });
+            }
+            Ok(record)
         }
+
+        // The version is the first field, and `u8` is encoded as a single byte
+        let record = match buf.first() {
+            Some(&consts::ENVELOPE_VERSION_V1) => decode_as::<CryptEnvelopeRecordV1>(buf)?.into(),
+            _ => decode_as::<Self>(buf)?,
+        };
         record.validate()?;
         Ok(record)
     }
 
-    pub(crate) fn wrapped_key_hash(&self) -> [u8; 32] {
-        let mut hash = [0u8; 32];
-        hash.copy_from_slice(&Sha256::digest(&self.wrapped_key));
-        hash
-    }
-
     fn validate(&self) -> CryptResult<()> {
         let u32_max = |len: usize| -> CryptResult<()> {
             u32::try_from(len).map_err(|_| CryptError::OversizedSection(len))?;
@@ -83,20 +139,86 @@ impl CryptEnvelopeRecord {
             u16::try_from(len).map_err(|_| CryptError::OversizedSection(len))?;
             Ok(())
         };
-        u16_max(self.wrapped_key.len())?;
+        if self.nonce.len() != self.algorithm.nonce_len() {
+            return Err(CryptError::InvalidNonceLength {
+                algorithm: self.algorithm,
+                actual: self.nonce.len(),
+            });
+        }
+        if self.recipients.is_empty() {
+            return Err(CryptError::NoRecipients);
+        }
+        u16_max(self.recipients.len())?;
+        for recipient in self.recipients.iter() {
+            u16_max(recipient.wrapped_key.len())?;
+            u16_max(recipient.key_id.as_deref().map(|v| v.len()).unwrap_or(0))?;
+            if recipient.key_id.as_deref().is_some_and(|v| v.is_empty()) {
+                return Err(CryptError::EmptyKeyId);
+            }
+        }
         u16_max(self.nonce.len())?;
-        u16_max(self.key_id.as_deref().map(|v| v.len()).unwrap_or(0))?;
         u32_max(self.payload.len())?;
-        self.key_id
-            .as_deref()
-            .map(|v| {
-                if v.is_empty() {
-                    Err(CryptError::EmptyKeyId)
-                } else {
-                    Ok(())
-                }
-            })
-            .unwrap_or(Ok(()))?;
         Ok(())
     }
+
+    /// Returns the only recipient of an envelope written in the layout of the first version.
+    fn single_recipient(&self) -> CryptResult<&CryptRecipient> {
+        match self.recipients.as_slice() {
+            [recipient] => Ok(recipient),
+            recipients => Err(CryptError::SingleRecipientEnvelope {
+                version: self.version,
+                actual: recipients.len(),
+            }),
+        }
+    }
+}
+
+/// Layout of the first envelope version.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CryptEnvelopeRecordV1 {
+    version: u8,
+    algorithm: CryptAlgorithm,
+    session_id: u64,
+    wrapped_key: Vec<u8>,
+    nonce: [u8; consts::ENVELOPE_V1_NONCE_LEN],
+    payload: Vec<u8>,
+    key_id: Option<Vec<u8>>,
+}
+
+impl From<CryptEnvelopeRecordV1> for CryptEnvelopeRecord {
+    fn from(record: CryptEnvelopeRecordV1) -> Self {
+        Self {
+            version: record.version,
+            algorithm: record.algorithm,
+            session_id: record.session_id,
+            recipients: vec![CryptRecipient {
+                key_id: record.key_id,
+                wrapped_key: record.wrapped_key,
+            }],
+            nonce: record.nonce.to_vec(),
+            payload: record.payload,
+        }
+    }
+}
+
+impl TryFrom<&CryptEnvelopeRecord> for CryptEnvelopeRecordV1 {
+    type Error = CryptError;
+
+    fn try_from(record: &CryptEnvelopeRecord) -> Result<Self, Self::Error> {
+        let recipient = record.single_recipient()?;
+        Ok(Self {
+            version: record.version,
+            algorithm: record.algorithm,
+            session_id: record.session_id,
+            wrapped_key: recipient.wrapped_key.clone(),
+            nonce: record.nonce.as_slice().try_into().map_err(|_| {
+                CryptError::InvalidNonceLength {
+                    algorithm: record.algorithm,
+                    actual: record.nonce.len(),
+                }
+            })?,
+            payload: record.payload.clone(),
+            key_id: recipient.key_id.clone(),
+        })
+    }
 }
@@ -0,0 +1,818 @@
+use secrecy::{ExposeSecret, SecretBox};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek, SeekFrom, Write};
+use zeroize::Zeroize;
+
+use crate::crypt::{
+    codec::CryptCodec,
+    consts,
+    error::CryptResult,
+    options::{DecryptOptions, EncryptOptions},
+    record::{CryptEnvelopeRecord, CryptRecipient},
+};
+use crate::*;
+
+/// Size of chunks read from the source by [`Rewrapper::rewrap_stream`].
+const STREAM_CHUNK_LEN: usize = 64 * 1024;
+
+/// The way [`Rewrapper`] moves encrypted payloads to the new key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RewrapMode {
+    /// Only session keys are unwrapped and wrapped again for the new recipients; nonces and
+    /// ciphertexts are kept as is.
+    ///
+    /// Session keys can be wrapped with RSA keys only. If the new [`EncryptOptions`] use an
+    /// X25519 or a pre-shared key, payloads are re-encrypted instead.
+    ///
+    /// This mode gives access to the new recipients, but it doesn't protect the data from the
+    /// old key: payloads stay encrypted with the same session keys, and anyone holding the old
+    /// private key (or a copy of the old file) can still recover them. Never use it to rotate
+    /// a compromised key.
+    SessionKeys,
+    /// Each payload is decrypted and encrypted again with the new [`EncryptOptions`], using
+    /// fresh session keys, so the old key can't decrypt the result.
+    #[default]
+    Reencrypt,
+}
+
+/// Summary of a rewrap run collected by [`Rewrapper`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewrapReport {
+    /// Number of packets read from the source.
+    pub packets: usize,
+    /// Number of encrypted payloads with session keys wrapped for the new recipients.
+    pub rewrapped: usize,
+    /// Number of encrypted payloads decrypted and encrypted again.
+    pub reencrypted: usize,
+    /// Number of packets copied as is: packets without payload or with a plain payload.
+    pub untouched: usize,
+    /// Number of signed packets with an encrypted payload copied as is, because no signer
+    /// is set to sign them again.
+    pub kept_signed: usize,
+    /// Number of moved payloads per identifier of the key which decrypted them. If the key
+    /// has no identifier, the first key identifier of the envelope is used (`None` if there
+    /// are no identifiers at all).
+    pub key_ids: BTreeMap<Option<Vec<u8>>, usize>,
+}
+
+/// Moves encrypted payloads of storage files and streams to a new key.
+///
+/// Packets are handled as bytes, so no protocol types are required: a payload body which
+/// parses as a crypto envelope (see [`CryptEnvelopeRecord`]) is decrypted with the given
+/// [`DecryptOptions`] and written for the recipients of the given [`EncryptOptions`]. Blocks
+/// and plain payloads are copied as is.
+///
+/// Compressed packets with a changed payload are compressed again, since the packet bytes
+/// change in both modes. Compression frames record the algorithm but not the zstd level, so
+/// such packets are compressed with the algorithm of the source frame and zstd with the default
+/// level, unless a compression is set with [`Rewrapper::with_compression`]. Compressed packets
+/// without encrypted payloads keep their original bytes.
+///
+/// Packets are read with the default protocol limits (see [`ProtocolSchema`]).
+///
+/// Changed packets get a new payload CRC; payloads with a CRC other than the default CRC32
+/// can't be moved and are reported as [`Error::CrcDismatch`].
+pub struct Rewrapper {
+    decrypt: DecryptOptions,
+    encrypt: EncryptOptions,
+    mode: RewrapMode,
+    sessions: HashMap<u64, RewrappedSession>,
+    #[cfg(feature = "sign")]
+    signer: Option<crate::sign::PacketSigner>,
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    compression: Option<crate::compress::Compression>,
+    report: RewrapReport,
+}
+
+/// Session key of a source session together with its entries wrapped for the new recipients.
+struct RewrappedSession {
+    session_key: SecretBox<[u8; consts::ENVELOPE_SESSION_KEY_LEN]>,
+    recipients: Vec<CryptRecipient>,
+}
+
+/// Result of moving a single packet.
+enum Outcome {
+    Untouched,
+    KeptSigned,
+    Rewritten(Vec<u8>),
+}
+
+impl Rewrapper {
+    /// Creates a rewrapper which reads payloads with `decrypt` and writes them with `encrypt`.
+    pub fn new(decrypt: DecryptOptions, encrypt: EncryptOptions) -> Self {
+        Self {
+            decrypt,
+            encrypt,
+            mode: RewrapMode::default(),
+            sessions: HashMap::new(),
+            #[cfg(feature = "sign")]
+            signer: None,
+            #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+            compression: None,
+            report: RewrapReport::default(),
+        }
+    }
+
+    /// Sets the way payloads are moved to the new key.
+    pub fn with_mode(mut self, mode: RewrapMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the signer for signed packets.
+    ///
+    /// The signature of a signed packet doesn't match once its payload is changed, so such
+    /// packets are signed again with this signer. Without a signer, signed packets with an
+    /// encrypted payload are copied as is (see [`RewrapReport::kept_signed`]).
+    #[cfg(feature = "sign")]
+    pub fn with_signer(mut self, signer: crate::sign::PacketSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Sets the compression of changed compressed packets.
+    ///
+    /// By default such packets are compressed with the algorithm of the source frame, and zstd
+    /// with [`crate::compress::DEFAULT_ZSTD_LEVEL`]; set a compression to keep another zstd
+    /// level. Packets which weren't compressed in the source stay uncompressed.
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    pub fn with_compression(mut self, compression: crate::compress::Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Returns the way payloads are moved to the new key.
+    pub fn mode(&self) -> RewrapMode {
+        self.mode
+    }
+
+    /// Returns the summary of everything handled so far.
+    pub fn report(&self) -> &RewrapReport {
+        &self.report
+    }
+
+    /// Returns the summary of everything handled so far and drops the rewrapper.
+    pub fn into_report(self) -> RewrapReport {
+        self.report
+    }
+
+    /// Reads a storage file and writes all its packets into a new storage.
+    ///
+    /// Packets are written in the order they're stored; free entries of the source slots
+    /// aren't kept. `dest` is expected to be empty.
+    ///
+    /// # Errors
+    /// - `Error::DamagedSlot` if a slot of the source is damaged.
+    /// - Any error of [`Rewrapper::rewrap_packet`].
+    pub fn rewrap_storage<R: Read + Seek, W: Write + Seek>(
+        &mut self,
+        src: &mut R,
+        dest: &mut W,
+    ) -> Result<(), Error> {
+        let mut slots = Vec::new();
+        let mut locator = FreeSlotLocator::default();
+        for_each_stored_packet(src, |packet| {
+            let packet = self.rewrap_packet(packet)?;
+            let compressed =
+                <PacketHeader as ReadBlockFromSlice>::read_from_slice(&packet, false)?.compressed;
+            write_to_slot(dest, &mut slots, &mut locator, &packet, compressed)
+        })
+    }
+
+    /// Reads a stream of packets and writes it into `dest`.
+    ///
+    /// Bytes between packets (e.g. text log lines) and incomplete packets at the end of the
+    /// stream are copied as is.
+    ///
+    /// # Errors
+    /// Any error of [`Rewrapper::rewrap_packet`] for a packet with a valid header.
+    pub fn rewrap_stream<R: Read, W: Write>(
+        &mut self,
+        src: &mut R,
+        dest: &mut W,
+    ) -> Result<(), Error> {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut eof = false;
+        loop {
+            // Bytes which cannot start a packet are copied right away; a tail which may be
+            // the beginning of a signature is kept until more data is read.
+            let pos = match buffer
+                .windows(PACKET_SIG.len())
+                .position(|window| window == PACKET_SIG)
+            {
+                Some(pos) => pos,
+                None if eof => buffer.len(),
+                None => buffer.len().saturating_sub(PACKET_SIG.len() - 1),
+            };
+            dest.write_all(&buffer[..pos])?;
+            buffer.drain(..pos);
+            if eof && buffer.is_empty() {
+                break;
+            }
+            if buffer.starts_with(&PACKET_SIG) {
+                match <PacketHeader as ReadBlockFromSlice>::read_from_slice(&buffer, false) {
+                    Ok(header) if header.size <= <() as ProtocolSchema>::MAX_PACKET_LEN => {
+                        let len = (PacketHeader::SIZE + header.size) as usize;
+                        if buffer.len() >= len {
+                            let packet = self.rewrap_packet(&buffer[..len])?;
+                            dest.write_all(&packet)?;
+                            buffer.drain(..len);
+                            continue;
+                        } else if eof {
+                            dest.write_all(&buffer)?;
+                            buffer.clear();
+                            continue;
+                        }
+                    }
+                    Err(Error::NotEnoughData(_)) if !eof => {}
+                    _ => {
+                        // Not a packet: skip the signature byte and look for the next one
+                        dest.write_all(&buffer[..1])?;
+                        buffer.drain(..1);
+                        continue;
+                    }
+                }
+            }
+            let start = buffer.len();
+            buffer.resize(start + STREAM_CHUNK_LEN, 0);
+            let read = src.read(&mut buffer[start..])?;
+            buffer.truncate(start + read);
+            eof = read == 0;
+        }
+        dest.flush()?;
+        Ok(())
+    }
+
+    /// Moves encrypted payloads of a complete serialized packet (header, blocks and payload).
+    ///
+    /// Returns the packet to write; packets without encrypted payloads are returned as is.
+    ///
+    /// # Errors
+    /// - `Error::Crypt` if a payload cannot be decrypted or encrypted.
+    /// - `Error::CrcDismatch` if a changed payload doesn't use the default CRC32.
+    /// - `Error::InvalidLength` if the packet is malformed or a changed payload exceeds the
+    ///   default payload limit.
+    /// - `Error::UnsupportedCompression` if the packet is compressed with an algorithm which
+    ///   isn't enabled by features.
+    pub fn rewrap_packet(&mut self, packet: &[u8]) -> Result<Vec<u8>, Error> {
+        self.report.packets += 1;
+        match self.packet(packet)? {
+            Outcome::Untouched => {
+                self.report.untouched += 1;
+                Ok(packet.to_vec())
+            }
+            Outcome::KeptSigned => {
+                self.report.kept_signed += 1;
+                Ok(packet.to_vec())
+            }
+            Outcome::Rewritten(packet) => Ok(packet),
+        }
+    }
+
+    /// Moves a payload body to the new key.
+    ///
+    /// Returns `None` if the body isn't a crypto envelope.
+    pub fn rewrap_payload(&mut self, body: &[u8]) -> CryptResult<Option<Vec<u8>>> {
+        let Ok(envelope) = CryptEnvelopeRecord::decode(body) else {
+            return Ok(None);
+        };
+        let (mut session_key, decrypted) = CryptCodec::open(&envelope, &mut self.decrypt)?;
+        let key_id = self
+            .decrypt
+            .last_key()
+            .and_then(|key| key.key_id())
+            .or_else(|| envelope.key_ids().next())
+            .map(<[u8]>::to_vec);
+        let rewrapped = match self.mode {
+            RewrapMode::SessionKeys => self.rewrap_session(&envelope, &session_key),
+            RewrapMode::Reencrypt => Ok(None),
+        };
+        session_key.zeroize();
+        let body = match rewrapped? {
+            Some(body) => {
+                self.report.rewrapped += 1;
+                body
+            }
+            None => {
+                let body = CryptCodec::encrypt(&decrypted, &mut self.encrypt)?;
+                self.report.reencrypted += 1;
+                body
+            }
+        };
+        *self.report.key_ids.entry(key_id).or_default() += 1;
+        Ok(Some(body))
+    }
+
+    /// Builds an envelope with the same ciphertext and the session key wrapped for the new
+    /// recipients. Returns `None` if the new options cannot wrap the session key.
+    fn rewrap_session(
+        &mut self,
+        envelope: &CryptEnvelopeRecord,
+        session_key: &[u8; consts::ENVELOPE_SESSION_KEY_LEN],
+    ) -> CryptResult<Option<Vec<u8>>> {
+        let recipients = match self.sessions.get(&envelope.session_id) {
+            Some(session) if session.session_key.expose_secret() == session_key => {
+                session.recipients.clone()
+            }
+            _ => {
+                let Some(recipients) = self.encrypt.wrap_existing_session(session_key)? else {
+                    return Ok(None);
+                };
+                if self.sessions.len() >= self.decrypt.policy().decrypt_cache_limit {
+                    self.sessions.clear();
+                }
+                self.sessions.insert(
+                    envelope.session_id,
+                    RewrappedSession {
+                        session_key: SecretBox::new(Box::new(*session_key)),
+                        recipients: recipients.clone(),
+                    },
+                );
+                recipients
+            }
+        };
+        CryptEnvelopeRecord {
+            version: consts::ENVELOPE_VERSION,
+            algorithm: envelope.algorithm,
+            session_id: envelope.session_id,
+            recipients,
+            nonce: envelope.nonce.clone(),
+            payload: envelope.payload.clone(),
+        }
+        .encode()
+        .map(Some)
+    }
+
+    /// Compresses a changed packet again with the configured compression or the algorithm of
+    /// the source compression frame.
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    fn pack(&self, id: u8, packet: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let compression = match self.compression {
+            Some(compression) => compression,
+            None => crate::compress::Compression::from_id(id)
+                .ok_or(Error::UnsupportedCompression(id))?,
+        };
+        Ok(PacketHeader::pack(&packet, &compression)?.unwrap_or(packet))
+    }
+
+    #[cfg(not(any(feature = "compress-zstd", feature = "compress-lz4")))]
+    fn pack(&self, id: u8, _packet: Vec<u8>) -> Result<Vec<u8>, Error> {
+        Err(Error::UnsupportedCompression(id))
+    }
+
+    fn packet(&mut self, packet: &[u8]) -> Result<Outcome, Error> {
+        let header = <PacketHeader as ReadBlockFromSlice>::read_from_slice(packet, false)?;
+        if PacketHeader::SIZE + header.size != packet.len() as u64 {
+            return Err(Error::InvalidLength);
+        }
+        let body = &packet[PacketHeader::SIZE as usize..];
+        if header.compressed {
+            let (_, inner) = header.unpack::<()>(body)?;
+            return Ok(match self.packet(&inner)? {
+                Outcome::Rewritten(inner) => Outcome::Rewritten(self.pack(body[0], inner)?),
+                outcome => outcome,
+            });
+        }
+        if header.signed {
+            let signed = header.unsign::<()>(body)?;
+            #[cfg(feature = "sign")]
+            if let Some(signer) = self.signer.take() {
+                let outcome = self.packet(signed.packet());
+                let outcome = match outcome {
+                    Ok(Outcome::Rewritten(inner)) => signer
+                        .sign(&inner)
+                        .map(Outcome::Rewritten)
+                        .map_err(|err| Error::from(std::io::Error::from(err))),
+                    outcome => outcome,
+                };
+                self.signer = Some(signer);
+                return outcome;
+            }
+            let (_, payload) = split_packet(signed.packet())?;
+            return Ok(match payload {
+                Some((_, body)) if CryptEnvelopeRecord::decode(body).is_ok() => Outcome::KeptSigned,
+                _ => Outcome::Untouched,
+            });
+        }
+        let (blocks, payload) = split_packet(packet)?;
+        let Some((payload_header, origin)) = payload else {
+            return Ok(Outcome::Untouched);
+        };
+        let Some(body) = self.rewrap_payload(origin)? else {
+            return Ok(Outcome::Untouched);
+        };
+        if body.len() > <() as ProtocolSchema>::MAX_PAYLOAD_LEN as usize {
+            return Err(Error::InvalidLength);
+        }
+        let crc = crc32fast::hash(&body).to_le_bytes();
+        let payload_header = PayloadHeader {
+            sig: payload_header.sig,
+            crc: match payload_header.crc {
+                ByteBlock::Len4(old) if old == crc32fast::hash(origin).to_le_bytes() => {
+                    ByteBlock::Len4(crc)
+                }
+                _ => return Err(Error::CrcDismatch),
+            },
+            len: body.len() as u32,
+        }
+        .as_vec();
+        let header = PacketHeader::from_lengths(
+            blocks.len() as u64,
+            (payload_header.len() + body.len()) as u64,
+            true,
+        );
+        let mut rewritten = Vec::with_capacity(
+            PacketHeader::SIZE as usize + blocks.len() + payload_header.len() + body.len(),
+        );
+        header.write_all(&mut rewritten)?;
+        rewritten.extend_from_slice(blocks);
+        rewritten.extend_from_slice(&payload_header);
+        rewritten.extend_from_slice(&body);
+        Ok(Outcome::Rewritten(rewritten))
+    }
+}
+
+/// Blocks and payload (header and body) of a plain packet.
+type PacketParts<'a> = (&'a [u8], Option<(PayloadHeader, &'a [u8])>);
+
+/// Splits a complete plain packet into its blocks and payload.
+fn split_packet(packet: &[u8]) -> Result<PacketParts<'_>, Error> {
+    let header = <PacketHeader as ReadBlockFromSlice>::read_from_slice(packet, false)?;
+    let body = &packet[PacketHeader::SIZE as usize..];
+    if body.len() as u64 != header.size || header.compressed || header.signed {
+        return Err(Error::InvalidLength);
+    }
+    let (blocks, rest) = body.split_at(header.blocks_len as usize);
+    if !header.payload {
+        return Ok((blocks, None));
+    }
+    let payload_header =
+        <PayloadHeader as ReadFrom>::read::<_, ()>(&mut std::io::Cursor::new(rest))?;
+    header.validate_payload(&payload_header)?;
+    let body = &rest[payload_header.size()..];
+    if body.len() != payload_header.payload_len() {
+        return Err(Error::InvalidLength);
+    }
+    Ok((blocks, Some((payload_header, body))))
+}
+
+/// Calls `handler` for each packet of a storage in the order packets are stored.
+fn for_each_stored_packet<R: Read + Seek>(
+    src: &mut R,
+    mut handler: impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut offset = 0;
+    let mut buffer = Vec::new();
+    loop {
+        src.seek(SeekFrom::Start(offset))?;
+        let slot = match <Slot as TryReadFrom>::try_read::<_, ()>(src) {
+            Ok(ReadStatus::Success(slot)) => slot,
+            Ok(ReadStatus::NotEnoughData(_)) => break,
+            Err(err @ (Error::CrcDismatch | Error::SignatureDismatch(_))) => {
+                return Err(Error::DamagedSlot(Box::new(err)));
+            }
+            Err(err) => return Err(err),
+        };
+        let mut packet_offset = offset + slot.size();
+        offset += slot.size() + slot.width();
+        for len in (0..slot.lenghts.len())
+            .map(|nth| slot.len_of(nth))
+            .filter(|len| *len > 0)
+        {
+            // A corrupted entry must not cause a huge allocation
+            if len > PacketHeader::ssize() + <() as ProtocolSchema>::MAX_PACKET_LEN {
+                return Err(Error::DamagedSlot(Box::new(Error::InvalidLength)));
+            }
+            src.seek(SeekFrom::Start(packet_offset))?;
+            packet_offset += len;
+            buffer.resize(len as usize, 0);
+            src.read_exact(&mut buffer)?;
+            handler(&buffer)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::DecryptKeyring;
+    use rsa::{RsaPrivateKey, rand_core::OsRng};
+
+    const BLOCKS: &[u8] = b"raw blocks bytes";
+
+    fn packet_bytes(body: &[u8]) -> Vec<u8> {
+        packet_with_blocks(BLOCKS, body)
+    }
+
+    fn packet_with_blocks(blocks: &[u8], body: &[u8]) -> Vec<u8> {
+        let payload_header = PayloadHeader {
+            sig: ByteBlock::Len4(*b"TEST"),
+            crc: ByteBlock::Len4(crc32fast::hash(body).to_le_bytes()),
+            len: body.len() as u32,
+        }
+        .as_vec();
+        let mut packet = Vec::new();
+        PacketHeader::from_lengths(
+            blocks.len() as u64,
+            (payload_header.len() + body.len()) as u64,
+            true,
+        )
+        .write_all(&mut packet)
+        .expect("header");
+        packet.extend_from_slice(blocks);
+        packet.extend_from_slice(&payload_header);
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    fn payload_of(packet: &[u8]) -> Vec<u8> {
+        let (blocks, payload) = split_packet(packet).expect("plain packet");
+        assert_eq!(blocks, BLOCKS);
+        payload.expect("payload").1.to_vec()
+    }
+
+    fn rsa_keys() -> (RsaPrivateKey, RsaPrivateKey) {
+        let mut rng = OsRng;
+        (
+            RsaPrivateKey::new(&mut rng, 1024).expect("old key"),
+            RsaPrivateKey::new(&mut rng, 1024).expect("new key"),
+        )
+    }
+
+    #[test]
+    fn rewrapper_wraps_session_keys_for_new_key() {
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key()).with_key_id("old");
+        let envelopes: Vec<Vec<u8>> = (0..3u8)
+            .map(|n| CryptCodec::encrypt(&[n; 16], &mut old_options).expect("encrypt"))
+            .collect();
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key.clone()),
+            EncryptOptions::new(new_key.to_public_key()).with_key_id("new"),
+        )
+        .with_mode(RewrapMode::SessionKeys);
+        for (n, envelope) in envelopes.iter().enumerate() {
+            let packet = rewrapper
+                .rewrap_packet(&packet_bytes(envelope))
+                .expect("rewrap");
+            let body = payload_of(&packet);
+            let (origin, rewrapped) = (
+                CryptCodec::parse(envelope).expect("origin"),
+                CryptCodec::parse(&body).expect("rewrapped"),
+            );
+            assert_eq!(rewrapped.session_id, origin.session_id);
+            assert_eq!(rewrapped.nonce, origin.nonce);
+            assert_eq!(rewrapped.payload, origin.payload);
+            assert_eq!(rewrapped.key_ids().collect::<Vec<_>>(), vec![b"new"]);
+            let mut new_options = DecryptOptions::new(new_key.clone());
+            assert_eq!(
+                CryptCodec::decrypt(&body, &mut new_options).expect("decrypt"),
+                vec![n as u8; 16]
+            );
+            let mut old_options = DecryptOptions::new(old_key.clone());
+            assert!(CryptCodec::decrypt(&body, &mut old_options).is_err());
+        }
+        let report = rewrapper.into_report();
+        assert_eq!(report.packets, 3);
+        assert_eq!(report.rewrapped, 3);
+        assert_eq!(report.reencrypted, 0);
+        assert_eq!(report.key_ids.get(&Some(b"old".to_vec())), Some(&3));
+    }
+
+    #[test]
+    fn rewrapper_reencrypts_for_symmetric_key() {
+        let (old_key, _) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key());
+        let envelope = CryptCodec::encrypt(b"payload", &mut old_options).expect("encrypt");
+        for mode in [RewrapMode::SessionKeys, RewrapMode::Reencrypt] {
+            let mut rewrapper = Rewrapper::new(
+                DecryptOptions::new(old_key.clone()),
+                EncryptOptions::symmetric("shared", [9u8; 32]),
+            )
+            .with_mode(mode);
+            let packet = rewrapper
+                .rewrap_packet(&packet_bytes(&envelope))
+                .expect("rewrap");
+            let mut options = DecryptOptions::symmetric("shared", [9u8; 32]);
+            assert_eq!(
+                CryptCodec::decrypt(&payload_of(&packet), &mut options).expect("decrypt"),
+                b"payload"
+            );
+            let report = rewrapper.report();
+            assert_eq!((report.rewrapped, report.reencrypted), (0, 1));
+            assert_eq!(report.key_ids.get(&None), Some(&1));
+        }
+    }
+
+    #[test]
+    fn rewrapper_keeps_plain_packets_and_other_bytes_of_stream() {
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key()).with_key_id("old");
+        let envelope = CryptCodec::encrypt(b"secret", &mut old_options).expect("encrypt");
+        let plain = packet_bytes(b"plain payload");
+        let mut stream = b"text line\n".to_vec();
+        stream.extend_from_slice(&plain);
+        stream.extend_from_slice(&PACKET_SIG);
+        stream.extend_from_slice(&packet_bytes(&envelope));
+        stream.extend_from_slice(b"tail");
+
+        let keyring = DecryptKeyring::new()
+            .with_key("old", old_key)
+            .with_key("other", new_key.clone());
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::from_keyring(keyring).expect("keyring"),
+            EncryptOptions::new(new_key.to_public_key()),
+        )
+        .with_mode(RewrapMode::SessionKeys);
+        let mut output = Vec::new();
+        rewrapper
+            .rewrap_stream(&mut std::io::Cursor::new(&stream), &mut output)
+            .expect("rewrap stream");
+
+        let prefix = b"text line\n".len() + plain.len() + PACKET_SIG.len();
+        assert_eq!(output[..prefix], stream[..prefix]);
+        assert!(output.ends_with(b"tail"));
+        let packet = &output[prefix..output.len() - 4];
+        let mut options = DecryptOptions::new(new_key);
+        assert_eq!(
+            CryptCodec::decrypt(&payload_of(packet), &mut options).expect("decrypt"),
+            b"secret"
+        );
+        let report = rewrapper.report();
+        assert_eq!(report.packets, 2);
+        assert_eq!(report.untouched, 1);
+        assert_eq!(report.rewrapped, 1);
+    }
+
+    #[test]
+    fn rewrapper_copies_storage() {
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key());
+        let mut packets = Vec::new();
+        for n in 0..(DEFAULT_SLOT_CAPACITY + 5) {
+            packets.push(if n % 2 == 0 {
+                packet_bytes(&CryptCodec::encrypt(&[n as u8; 8], &mut old_options).unwrap())
+            } else {
+                packet_bytes(&[n as u8; 8])
+            });
+        }
+        let mut src = std::io::Cursor::new(Vec::new());
+        let (mut slots, mut locator) = (Vec::new(), FreeSlotLocator::default());
+        for packet in packets.iter() {
+            write_to_slot(&mut src, &mut slots, &mut locator, packet, false).expect("write");
+        }
+
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key),
+            EncryptOptions::new(new_key.to_public_key()),
+        )
+        .with_mode(RewrapMode::SessionKeys);
+        let mut dest = std::io::Cursor::new(Vec::new());
+        rewrapper
+            .rewrap_storage(&mut src, &mut dest)
+            .expect("rewrap storage");
+
+        let mut options = DecryptOptions::new(new_key);
+        let mut n = 0;
+        for_each_stored_packet(&mut dest, |packet| {
+            let body = payload_of(packet);
+            if n % 2 == 0 {
+                assert_eq!(
+                    CryptCodec::decrypt(&body, &mut options).expect("decrypt"),
+                    vec![n as u8; 8]
+                );
+            } else {
+                assert_eq!(packet, packets[n].as_slice());
+            }
+            n += 1;
+            Ok(())
+        })
+        .expect("read storage");
+        assert_eq!(n, packets.len());
+        let report = rewrapper.report();
+        assert_eq!(report.rewrapped, packets.len().div_ceil(2));
+        assert_eq!(report.untouched, packets.len() / 2);
+    }
+
+    #[test]
+    fn rewrapper_rejects_payloads_with_custom_crc() {
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key());
+        let envelope = CryptCodec::encrypt(b"secret", &mut old_options).expect("encrypt");
+        let mut packet = packet_bytes(&envelope);
+        // CRC of the payload follows the blocks, the signature and both length bytes
+        let crc_pos = PacketHeader::SIZE as usize + BLOCKS.len() + 6;
+        packet[crc_pos] ^= 0xFF;
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key),
+            EncryptOptions::new(new_key.to_public_key()),
+        );
+        assert!(matches!(
+            rewrapper.rewrap_packet(&packet),
+            Err(Error::CrcDismatch)
+        ));
+    }
+
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    #[test]
+    fn rewrapper_compresses_packets_again() {
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key());
+        let envelope = CryptCodec::encrypt(b"secret", &mut old_options).expect("encrypt");
+        let packet = packet_with_blocks(&[0u8; 1024], &envelope);
+        #[cfg(feature = "compress-zstd")]
+        let compression = crate::compress::Compression::zstd();
+        #[cfg(not(feature = "compress-zstd"))]
+        let compression = crate::compress::Compression::Lz4;
+        let compressed = PacketHeader::pack(&packet, &compression)
+            .expect("pack")
+            .expect("smaller");
+
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key),
+            EncryptOptions::new(new_key.to_public_key()),
+        );
+        let rewrapped = rewrapper.rewrap_packet(&compressed).expect("rewrap");
+        let header =
+            <PacketHeader as ReadBlockFromSlice>::read_from_slice(&rewrapped, false).unwrap();
+        assert!(header.compressed);
+        assert_eq!(
+            rewrapped[PacketHeader::SIZE as usize],
+            compressed[PacketHeader::SIZE as usize]
+        );
+        let (_, inner) = header
+            .unpack::<()>(&rewrapped[PacketHeader::SIZE as usize..])
+            .expect("unpack");
+        let (_, payload) = split_packet(&inner).expect("plain packet");
+        let mut options = DecryptOptions::new(new_key);
+        assert_eq!(
+            CryptCodec::decrypt(payload.expect("payload").1, &mut options).expect("decrypt"),
+            b"secret"
+        );
+    }
+
+    #[cfg(all(feature = "compress-zstd", feature = "compress-lz4"))]
+    #[test]
+    fn rewrapper_uses_given_compression() {
+        use crate::compress::{COMPRESSION_LZ4_ID, Compression};
+
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key());
+        let envelope = CryptCodec::encrypt(b"secret", &mut old_options).expect("encrypt");
+        let compressed = PacketHeader::pack(
+            &packet_with_blocks(&[0u8; 1024], &envelope),
+            &Compression::Zstd(19),
+        )
+        .expect("pack")
+        .expect("smaller");
+
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key),
+            EncryptOptions::new(new_key.to_public_key()),
+        )
+        .with_compression(Compression::Lz4);
+        let rewrapped = rewrapper.rewrap_packet(&compressed).expect("rewrap");
+        assert_eq!(rewrapped[PacketHeader::SIZE as usize], COMPRESSION_LZ4_ID);
+    }
+
+    #[cfg(feature = "sign")]
+    #[test]
+    fn rewrapper_signs_packets_again() {
+        use crate::sign::{PacketSigner, PacketVerifier};
+
+        let (old_key, new_key) = rsa_keys();
+        let mut old_options = EncryptOptions::new(old_key.to_public_key());
+        let envelope = CryptCodec::encrypt(b"secret", &mut old_options).expect("encrypt");
+        let signer = PacketSigner::new("signer", [3u8; 32]).expect("signer");
+        let signed = signer.sign(&packet_bytes(&envelope)).expect("sign");
+
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key.clone()),
+            EncryptOptions::new(new_key.to_public_key()),
+        );
+        assert_eq!(rewrapper.rewrap_packet(&signed).expect("keep"), signed);
+        assert_eq!(rewrapper.report().kept_signed, 1);
+
+        let mut rewrapper = Rewrapper::new(
+            DecryptOptions::new(old_key),
+            EncryptOptions::new(new_key.to_public_key()),
+        )
+        .with_signer(signer.clone());
+        let rewrapped = rewrapper.rewrap_packet(&signed).expect("rewrap");
+        let header =
+            <PacketHeader as ReadBlockFromSlice>::read_from_slice(&rewrapped, false).unwrap();
+        let unsigned = header
+            .unsign::<()>(&rewrapped[PacketHeader::SIZE as usize..])
+            .expect("unsign");
+        let verifier = PacketVerifier::new()
+            .with_key("signer", signer.public_key())
+            .expect("verifier");
+        assert!(verifier.verify(&unsigned).is_verified());
+        let mut options = DecryptOptions::new(new_key);
+        assert_eq!(
+            CryptCodec::decrypt(&payload_of(unsigned.packet()), &mut options).expect("decrypt"),
+            b"secret"
+        );
+    }
+}
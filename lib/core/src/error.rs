@@ -7,7 +7,7 @@ use crate::crypt::CryptError;
 use crate::csharp_feat::CSharpError;
 #[cfg(feature = "java")]
 use crate::java_feat::JavaError;
-#[cfg(feature = "observer")]
+#[cfg(any(feature = "observer", feature = "observer-sync"))]
 use crate::storage::SensorError;
 #[cfg(feature = "wasm")]
 use crate::wasm_feat::WasmError;
@@ -240,18 +240,36 @@ pub enum Error {
     /// The source contained no readable data.
     #[error("Empty source")]
     EmptySource,
+    /// Compressed data uses an algorithm which is unknown or not enabled by features.
+    #[error("Unsupported compression algorithm: {0}")]
+    UnsupportedCompression(u8),
+    /// Compressed data could not be restored.
+    #[error("Decompression error: {0}")]
+    Decompression(String),
     #[cfg(feature = "crypt")]
     /// Wrapper over `CryptError` when the `crypt` feature is enabled.
     #[error("Crypt: {0}")]
     Crypt(#[from] CryptError),
-    #[cfg(feature = "observer")]
+    #[cfg(any(feature = "observer", feature = "observer-sync"))]
     /// Wrapper over storage observer sensor errors.
     #[error("Sensor: {0}")]
     Sensor(SensorError),
-    #[cfg(feature = "observer")]
+    #[cfg(any(feature = "observer", feature = "observer-sync"))]
     /// Observer setup was attempted without a subscription.
     #[error("No subscription")]
     NoSubscription,
+    #[cfg(any(feature = "observer", feature = "observer-sync"))]
+    /// Observer stream channel is full and configured to stop on overflow.
+    #[error("Observer stream overflow: channel capacity {0} is exceeded")]
+    ObserverOverflow(usize),
+    #[cfg(feature = "observer")]
+    /// A subscriber was attached to an observer hub which has already stopped.
+    #[error("Observer hub is stopped")]
+    HubStopped,
+    #[cfg(feature = "observer")]
+    /// File name pattern of a directory observer cannot be parsed.
+    #[error("Invalid file pattern: {0}")]
+    InvalidPattern(String),
     #[cfg(feature = "napi")]
     /// Wrapper over `NapiError` when the `napi` feature is enabled.
     #[error("Napi: {0}")]
@@ -294,7 +312,7 @@ impl From<Error> for std::io::Error {
     }
 }
 
-#[cfg(feature = "observer")]
+#[cfg(any(feature = "observer", feature = "observer-sync"))]
 impl From<SensorError> for Error {
     fn from(value: SensorError) -> Self {
         Error::Sensor(value)
@@ -6,10 +6,14 @@ pub mod tests;
 #[cfg(test)]
 use tempfile as _;
 
+#[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+pub mod compress;
 #[cfg(feature = "crypt")]
 pub mod crypt;
 #[cfg(feature = "bincode")]
 pub use bincode;
+#[cfg(feature = "sign")]
+pub mod sign;
 
 extern crate brec_macros;
 
@@ -55,13 +59,19 @@ pub use brec_node_lib as napi_feat;
 pub use brec_wasm_gen_macro::Wasm;
 #[cfg(feature = "wasm")]
 pub use brec_wasm_lib as wasm_feat;
+#[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+pub use compress::Compression;
 pub use crc32fast;
 #[cfg(feature = "crypt")]
-pub use crypt::{CryptAlgorithm, CryptCodec, CryptEnvelopeRecord};
+pub use crypt::{
+    CryptAlgorithm, CryptCodec, CryptEnvelopeMeta, CryptEnvelopeRecord, CryptRecipient,
+};
 pub use payload::{
     DefaultProtocolContext, PayloadDecode, PayloadEncode, PayloadEncodeReferred, PayloadHeader,
     PayloadHooks, ProtocolSchema, default_payload_context,
 };
+#[cfg(feature = "sign")]
+pub use sign::{PacketSigner, PacketVerifier};
 pub use storage::*;
 
 pub use crate::error::*;
@@ -9,12 +9,32 @@ use crate::*;
 /// It serves as a unique byte pattern that helps to recognize and validate the start of a packet.
 pub const PACKET_SIG: [u8; 8] = [236u8, 37u8, 94u8, 136u8, 236u8, 37u8, 94u8, 136u8];
 
+/// Bit of the packet flags byte which marks the presence of a payload.
+pub const PACKET_FLAG_PAYLOAD: u8 = 0b0000_0001;
+
+/// Bit of the packet flags byte which marks a compressed packet.
+///
+/// The body of a compressed packet is a compression frame carrying a complete
+/// serialized packet (header, blocks and payload).
+pub const PACKET_FLAG_COMPRESSED: u8 = 0b0000_0010;
+
+/// Bit of the packet flags byte which marks a signed packet.
+///
+/// The body of a signed packet carries the identifier of the signing key, the signature and
+/// a complete serialized packet (header, blocks and payload) covered by the signature.
+pub const PACKET_FLAG_SIGNED: u8 = 0b0000_0100;
+
 /// Packet header structure.
 ///
 /// The packet header precedes the actual content of a packet in the binary stream.
 /// A complete packet consists of:
 /// - A sequence of blocks (from 0 to 255), stored consecutively.
 /// - An optional payload, appended after the blocks.
+///
+/// A compressed packet has no blocks and no payload of its own; its body is a compression
+/// frame with the original packet inside (see [`PACKET_FLAG_COMPRESSED`]). In the same way a
+/// signed packet wraps the original packet together with its signature (see
+/// [`PACKET_FLAG_SIGNED`]).
 #[derive(Debug)]
 pub struct PacketHeader {
     /// Total size of the packet, excluding the `PacketHeader` itself (in bytes).
@@ -26,6 +46,12 @@ pub struct PacketHeader {
     /// Indicates whether the packet includes a payload.
     pub payload: bool,
 
+    /// Indicates whether the packet body is a compressed packet.
+    pub compressed: bool,
+
+    /// Indicates whether the packet body is a signed packet.
+    pub signed: bool,
+
     /// CRC checksum of the header itself (not the full packet).
     pub crc: u32,
 }
@@ -37,7 +63,7 @@ impl PacketHeader {
     /// - Signature (`PACKET_SIG`)
     /// - Total packet size (`u64`)
     /// - Blocks length (`u64`)
-    /// - Payload presence flag (`u8`)
+    /// - Packet flags (`u8`)
     /// - Header CRC (`u32`)
     pub const SIZE: u64 = (
         // Signature
@@ -46,7 +72,7 @@ impl PacketHeader {
         + std::mem::size_of::<u64>()
         // Length of blocks
         + std::mem::size_of::<u64>()
-        // Payload existing flag
+        // Packet flags
         + 1
         // Crc
         + std::mem::size_of::<u32>()
@@ -71,19 +97,94 @@ impl PacketHeader {
     }
 
     pub(crate) fn from_lengths(blocks_len: u64, payload_len: u64, has_payload: bool) -> Self {
-        let size = blocks_len + payload_len;
-        let mut hasher = crc32fast::Hasher::new();
-        hasher.update(&size.to_le_bytes());
-        hasher.update(&blocks_len.to_le_bytes());
-        hasher.update(if has_payload { &[1] } else { &[0] });
-        let crc = hasher.finalize();
-
-        Self {
-            size,
+        let mut header = Self {
+            size: blocks_len + payload_len,
             blocks_len,
             payload: has_payload,
-            crc,
+            compressed: false,
+            signed: false,
+            crc: 0,
+        };
+        header.crc = u32::from_le_bytes(header.crc());
+        header
+    }
+
+    /// Creates a header of a compressed packet with a body of the given length.
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    pub(crate) fn compressed(body_len: u64) -> Self {
+        let mut header = Self {
+            size: body_len,
+            blocks_len: 0,
+            payload: false,
+            compressed: true,
+            signed: false,
+            crc: 0,
+        };
+        header.crc = u32::from_le_bytes(header.crc());
+        header
+    }
+
+    /// Returns the packet flags byte as it's stored in the serialized header.
+    pub fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.payload {
+            flags |= PACKET_FLAG_PAYLOAD;
+        }
+        if self.compressed {
+            flags |= PACKET_FLAG_COMPRESSED;
         }
+        if self.signed {
+            flags |= PACKET_FLAG_SIGNED;
+        }
+        flags
+    }
+
+    /// Restores the packet carried by the body of a compressed packet.
+    ///
+    /// Returns the header of the inner packet together with the complete serialized inner
+    /// packet (header, blocks and payload). The inner packet must be a plain (not compressed)
+    /// packet which exactly fills the restored data.
+    ///
+    /// # Errors
+    /// - `Error::InvalidLength` if the compressed packet or the restored packet is malformed
+    ///   or exceeds `S::MAX_PACKET_LEN`.
+    /// - `Error::UnsupportedCompression` if the algorithm isn't enabled by features.
+    /// - `Error::Decompression` if the compressed bytes are damaged.
+    pub(crate) fn unpack<S: ProtocolSchema>(
+        &self,
+        body: &[u8],
+    ) -> Result<(PacketHeader, Vec<u8>), Error> {
+        if !self.compressed || self.signed || self.payload || self.blocks_len != 0 {
+            return Err(Error::InvalidLength);
+        }
+        let packet = decompress(body, PacketHeader::SIZE + S::MAX_PACKET_LEN)?;
+        let inner = PacketHeader::read_from_slice(&packet, false)?;
+        if inner.compressed
+            || inner.size > S::MAX_PACKET_LEN
+            || PacketHeader::SIZE + inner.size != packet.len() as u64
+        {
+            return Err(Error::InvalidLength);
+        }
+        Ok((inner, packet))
+    }
+
+    /// Wraps a serialized packet into a compressed packet.
+    ///
+    /// Returns `None` if compression doesn't make the packet smaller. In this case the
+    /// original packet should be written as is, so readers don't spend time on decompression.
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    pub(crate) fn pack(
+        packet: &[u8],
+        compression: &crate::compress::Compression,
+    ) -> std::io::Result<Option<Vec<u8>>> {
+        let body = compression.compress(packet)?;
+        if PacketHeader::SIZE as usize + body.len() >= packet.len() {
+            return Ok(None);
+        }
+        let mut compressed = Vec::with_capacity(PacketHeader::SIZE as usize + body.len());
+        PacketHeader::compressed(body.len() as u64).write_all(&mut compressed)?;
+        compressed.extend_from_slice(&body);
+        Ok(Some(compressed))
     }
 
     /// Constructs a new `PacketHeader` from a list of blocks and an optional payload.
@@ -167,6 +268,18 @@ impl PacketHeader {
     }
 }
 
+#[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+fn decompress(body: &[u8], max_len: u64) -> Result<Vec<u8>, Error> {
+    crate::compress::decompress(body, max_len)
+}
+
+#[cfg(not(any(feature = "compress-zstd", feature = "compress-lz4")))]
+fn decompress(body: &[u8], _max_len: u64) -> Result<Vec<u8>, Error> {
+    Err(Error::UnsupportedCompression(
+        body.first().copied().unwrap_or_default(),
+    ))
+}
+
 impl StaticSize for PacketHeader {
     /// Returns the static size (in bytes) of the serialized `PacketHeader`.
     ///
@@ -182,7 +295,7 @@ impl CrcU32 for PacketHeader {
     /// The checksum is calculated over the following fields:
     /// - `size` (as little-endian bytes)
     /// - `blocks_len` (as little-endian bytes)
-    /// - `payload` flag (`1` if present, `0` otherwise)
+    /// - packet flags (`1` for a plain packet with payload, `0` for a plain packet without payload)
     ///
     /// # Returns
     /// A 4-byte CRC32 checksum in little-endian order.
@@ -190,7 +303,7 @@ impl CrcU32 for PacketHeader {
         let mut hasher = crc32fast::Hasher::new();
         hasher.update(&self.size.to_le_bytes());
         hasher.update(&self.blocks_len.to_le_bytes());
-        hasher.update(if self.payload { &[1] } else { &[0] });
+        hasher.update(&[self.flags()]);
         hasher.finalize().to_le_bytes()
     }
 }
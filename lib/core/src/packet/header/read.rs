@@ -7,7 +7,7 @@ impl ReadFrom for PacketHeader {
     /// - Signature: `[u8; 8]`
     /// - Size: `u64` (little-endian)
     /// - Blocks length: `u64` (little-endian)
-    /// - Payload flag: `u8` (0 or 1)
+    /// - Packet flags: `u8` (see `PACKET_FLAG_PAYLOAD`, `PACKET_FLAG_COMPRESSED` and `PACKET_FLAG_SIGNED`)
     /// - CRC: `u32` (little-endian)
     ///
     /// # Errors
@@ -33,9 +33,9 @@ impl ReadFrom for PacketHeader {
         buf.read_exact(&mut blocks_len)?;
         let blocks_len = u64::from_le_bytes(blocks_len);
 
-        let mut payload = [0u8; 1usize];
-        buf.read_exact(&mut payload)?;
-        let payload = payload[0] == 1;
+        let mut flags = [0u8; 1usize];
+        buf.read_exact(&mut flags)?;
+        let flags = flags[0];
 
         let mut crc = [0u8; 4usize];
         buf.read_exact(&mut crc)?;
@@ -44,7 +44,9 @@ impl ReadFrom for PacketHeader {
         let pkg = PacketHeader {
             blocks_len,
             size,
-            payload,
+            payload: flags & PACKET_FLAG_PAYLOAD != 0,
+            compressed: flags & PACKET_FLAG_COMPRESSED != 0,
+            signed: flags & PACKET_FLAG_SIGNED != 0,
             crc,
         };
 
@@ -90,14 +92,16 @@ impl ReadBlockFromSlice for PacketHeader {
         offset += 8;
         let blocks_len = u64::from_le_bytes(buf[offset..offset + 8].try_into()?);
         offset += 8;
-        let payload = buf[offset] == 1;
+        let flags = buf[offset];
         offset += 1;
         let crc = u32::from_le_bytes(buf[offset..offset + 4].try_into()?);
 
         let pkg = PacketHeader {
             blocks_len,
             size,
-            payload,
+            payload: flags & PACKET_FLAG_PAYLOAD != 0,
+            compressed: flags & PACKET_FLAG_COMPRESSED != 0,
+            signed: flags & PACKET_FLAG_SIGNED != 0,
             crc,
         };
 
@@ -173,6 +177,8 @@ mod tests {
             size: 123,
             blocks_len: 77,
             payload: true,
+            compressed: false,
+            signed: false,
             crc: 0,
         };
         header.crc = u32::from_le_bytes(header.crc());
@@ -6,7 +6,7 @@ use crate::*;
 /// - Signature: `[u8; 8]`
 /// - Size: `u64` (little-endian)
 /// - Blocks length: `u64` (little-endian)
-/// - Payload flag: `u8` (`1` if payload exists, `0` otherwise)
+/// - Packet flags: `u8` (see `PACKET_FLAG_PAYLOAD`, `PACKET_FLAG_COMPRESSED` and `PACKET_FLAG_SIGNED`)
 /// - CRC: `u32` (little-endian)
 ///
 /// # Arguments
@@ -21,7 +21,7 @@ fn fill_buf(header: &PacketHeader, buffer: &mut [u8]) {
     offset += 8usize;
     buffer[offset..offset + 8usize].copy_from_slice(&header.blocks_len.to_le_bytes());
     offset += 8usize;
-    buffer[offset..offset + 1usize].copy_from_slice(&[header.payload.into()]);
+    buffer[offset..offset + 1usize].copy_from_slice(&[header.flags()]);
     offset += 1;
     buffer[offset..offset + 4usize].copy_from_slice(&header.crc.to_le_bytes());
 }
@@ -63,6 +63,8 @@ mod tests {
             size: 500,
             blocks_len: 123,
             payload: false,
+            compressed: false,
+            signed: false,
             crc: 0,
         };
         header.crc = u32::from_le_bytes(header.crc());
@@ -3,12 +3,16 @@ mod read;
 mod reader;
 mod referred;
 mod rules;
+mod signed;
+mod undecryptable;
 mod write;
 
 pub use header::*;
 pub use reader::*;
 pub use referred::*;
 pub use rules::*;
+pub use signed::*;
+pub use undecryptable::*;
 
 use crate::*;
 
@@ -17,7 +21,12 @@ use std::marker::PhantomData;
 /// Defines a fully parsed block type.
 ///
 /// Required for reading, writing, size computation, and vectored I/O.
-pub trait BlockReferredDef<B: BlockDef>: ReadBlockFromSlice + Size + Sized + Into<B> {}
+pub trait BlockReferredDef<B: BlockDef>: ReadBlockFromSlice + Size + Sized + Into<B> {
+    /// Returns the name of the block type (the variant name for generated `BlockReferred`).
+    fn block_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
 
 /// Defines a block that refers to slices of existing memory (zero-copy).
 ///
@@ -85,6 +94,12 @@ pub struct PacketDef<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef>
     /// Optional decoded payload.
     pub payload: Option<Inner>,
 
+    /// Result of the signature check of a read packet.
+    signature: SignatureStatus,
+
+    /// Raw payload which couldn't be decrypted (kept instead of `payload`).
+    undecryptable: Option<Box<UndecryptablePayload>>,
+
     /// Internal marker for payload definition type.
     _pi: PhantomData<P>,
 }
@@ -101,10 +116,44 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P,
         Self {
             blocks,
             payload,
+            signature: SignatureStatus::Unsigned,
+            undecryptable: None,
             _pi: PhantomData,
         }
     }
 
+    /// Creates a packet from given blocks and a payload which couldn't be decrypted.
+    pub(crate) fn with_undecryptable(blocks: Vec<B>, payload: UndecryptablePayload) -> Self {
+        let mut packet = Self::new(blocks, None);
+        packet.undecryptable = Some(Box::new(payload));
+        packet
+    }
+
+    /// Returns the result of the signature check of the packet.
+    ///
+    /// Packets created by the caller are [`SignatureStatus::Unsigned`]; a packet read from a
+    /// stream or storage reports whether it was signed and, if a `RuleDef::Verify` rule was
+    /// applied, whether its signature was verified.
+    pub fn signature(&self) -> &SignatureStatus {
+        &self.signature
+    }
+
+    /// Sets the result of the signature check of a packet which was decoded separately from
+    /// its rules (see [`PacketDef::prematches`]).
+    #[cfg(feature = "observer")]
+    pub(crate) fn set_signature(&mut self, signature: SignatureStatus) {
+        self.signature = signature;
+    }
+
+    /// Returns the payload which couldn't be decrypted.
+    ///
+    /// It's set only for packets read with `RuleDef::Undecryptable(UndecryptablePolicy::Keep)`;
+    /// such packets have no decoded `payload`. Writing the packet writes this payload back
+    /// unchanged, so the packet can be forwarded or stored as it is.
+    pub fn undecryptable(&self) -> Option<&UndecryptablePayload> {
+        self.undecryptable.as_deref()
+    }
+
     /// Attempts to read and filter a packet from a stream using the provided rules.
     ///
     /// This function:
@@ -130,29 +179,86 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P,
         rules: &RulesDef<B, BR, P, Inner>,
         ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
     ) -> Result<LookInStatus<PacketDef<B, P, Inner>>, Error>
+    where
+        R: std::io::Read + std::io::Seek,
+        BR: BlockReferredDef<B>,
+        Self: Sized,
+    {
+        Self::filtered_with_signature(reader, rules, ctx, SignatureStatus::Unsigned)
+    }
+
+    /// Reads and filters a packet; `signature` is the status of the signed packet which
+    /// carries it (or `Unsigned`).
+    fn filtered_with_signature<R, BR>(
+        reader: &mut R,
+        rules: &RulesDef<B, BR, P, Inner>,
+        ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
+        signature: SignatureStatus,
+    ) -> Result<LookInStatus<PacketDef<B, P, Inner>>, Error>
     where
         R: std::io::Read + std::io::Seek,
         BR: BlockReferredDef<B>,
         Self: Sized,
     {
         let header = <PacketHeader as ReadFrom>::read::<_, Inner>(reader)?;
+        if header.compressed || header.signed {
+            let mut body = vec![0u8; header.size as usize];
+            reader.read_exact(&mut body)?;
+            let packet_size = header.size as usize;
+            let status = if header.compressed {
+                let (_, packet) = header.unpack::<Inner>(&body)?;
+                Self::filtered_with_signature(
+                    &mut std::io::Cursor::new(packet),
+                    rules,
+                    ctx,
+                    signature,
+                )?
+            } else {
+                let signed = header.unsign::<Inner>(&body)?;
+                let signature = rules.verify_signature(&signed);
+                if !rules.accepts_signature(&signature) {
+                    return Ok(LookInStatus::Denied(packet_size));
+                }
+                Self::filtered_with_signature(
+                    &mut std::io::Cursor::new(signed.packet()),
+                    rules,
+                    ctx,
+                    signature,
+                )?
+            };
+            return Ok(match status {
+                LookInStatus::Accepted(_, pkg) => LookInStatus::Accepted(packet_size, pkg),
+                LookInStatus::Denied(_) => LookInStatus::Denied(packet_size),
+                LookInStatus::NotEnoughData(needed) => LookInStatus::NotEnoughData(needed),
+            });
+        }
+        let packet_size = header.size as usize;
+        if !rules.accepts_signature(&signature) {
+            reader.seek(std::io::SeekFrom::Current(packet_size as i64))?;
+            return Ok(LookInStatus::Denied(packet_size));
+        }
         let mut read = 0usize;
-        let mut blocks = Vec::new();
         let blocks_len = header.blocks_len as usize;
+        // Referred blocks point into this buffer, so it has to outlive them
+        let mut blocks_buffer = vec![0; blocks_len];
+        let mut blocks = Vec::new();
         if blocks_len > 0 {
-            let mut blocks_buffer = vec![0; blocks_len];
             reader.read_exact(&mut blocks_buffer)?;
             loop {
                 let blk =
                     <BR as ReadBlockFromSlice>::read_from_slice(&blocks_buffer[read..], false)?;
-                read += blk.size() as usize;
+                let size = blk.size() as usize;
+                // A damaged block must neither stall the loop nor run past the blocks
+                if size == 0 || read + size > blocks_len {
+                    return Err(Error::InvalidLength);
+                }
+                read += size;
                 blocks.push(blk);
                 if read == blocks_len {
                     break;
                 }
             }
         }
-        let packet_size = header.size as usize;
         if !rules.prefilter(&blocks) {
             if header.payload {
                 let to_skip = packet_size.saturating_sub(blocks_len);
@@ -162,10 +268,11 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P,
             }
             return Ok(LookInStatus::Denied(packet_size));
         }
-        let pkg = if header.payload {
+        let mut pkg = if header.payload {
             let payload_header = <PayloadHeader as ReadFrom>::read::<_, Inner>(reader)?;
             header.validate_payload(&payload_header)?;
-            if rules.has_payload_filter() {
+            // The raw payload is kept for the payload filter and for undecryptable payloads
+            if rules.has_payload_filter() || rules.undecryptable() != UndecryptablePolicy::Fail {
                 let mut payload_buffer = vec![0; payload_header.payload_len()];
                 reader.read_exact(&mut payload_buffer)?;
                 if !rules.filter_payload(&payload_buffer) {
@@ -184,9 +291,17 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P,
                     Ok(ReadStatus::NotEnoughData(needed)) => {
                         return Err(Error::NotEnoughData(needed as usize));
                     }
-                    Err(err) => {
-                        return Err(err);
-                    }
+                    Err(err) => match rules.undecryptable().resolve(
+                        err,
+                        payload_header,
+                        payload_reader.into_inner(),
+                    )? {
+                        Some(payload) => PacketDef::with_undecryptable(
+                            blocks.into_iter().map(|blk| blk.into()).collect::<Vec<B>>(),
+                            payload,
+                        ),
+                        None => return Ok(LookInStatus::Denied(packet_size)),
+                    },
                 }
             } else {
                 match <P as TryExtractPayloadFrom<Inner>>::try_read(reader, &payload_header, ctx) {
@@ -208,6 +323,7 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P,
                 None,
             )
         };
+        pkg.signature = signature;
         if !rules.filter_packet(&pkg) {
             // PacketDef marked as ignored
             Ok(LookInStatus::Denied(packet_size))
@@ -215,6 +331,143 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P,
             Ok(LookInStatus::Accepted(packet_size, pkg))
         }
     }
+    /// Checks whether a packet from a stream passes the provided rules without building it.
+    ///
+    /// Only blocks are parsed (in zero-copy mode) for the prefilter, and the raw payload
+    /// buffer is read for the payload filter; the payload isn't decoded. If a `FilterPacket`
+    /// rule is configured, the packet has to be fully parsed, so this falls back to
+    /// [`PacketDef::filtered`].
+    ///
+    /// # Limitations
+    /// - Will fail if the entire packet is not already in the stream
+    ///
+    /// # Returns
+    /// - `Accepted(bytes, ())` - if all filters passed
+    /// - `Denied(bytes)` - if blocked by rules
+    /// - `NotEnoughData(bytes)` - if more input is needed
+    ///
+    /// # Errors
+    /// - Propagates parsing errors of headers and blocks (and payload errors in fallback mode)
+    pub fn matches<R, BR>(
+        reader: &mut R,
+        rules: &RulesDef<B, BR, P, Inner>,
+        ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
+    ) -> Result<LookInStatus<()>, Error>
+    where
+        R: std::io::Read + std::io::Seek,
+        BR: BlockReferredDef<B>,
+        Self: Sized,
+    {
+        if rules.has_packet_filter() {
+            return Ok(match Self::filtered(reader, rules, ctx)? {
+                LookInStatus::Accepted(size, _) => LookInStatus::Accepted(size, ()),
+                LookInStatus::Denied(size) => LookInStatus::Denied(size),
+                LookInStatus::NotEnoughData(needed) => LookInStatus::NotEnoughData(needed),
+            });
+        }
+        Ok(match Self::prematches(reader, rules)? {
+            LookInStatus::Accepted(size, _) => LookInStatus::Accepted(size, ()),
+            LookInStatus::Denied(size) => LookInStatus::Denied(size),
+            LookInStatus::NotEnoughData(needed) => LookInStatus::NotEnoughData(needed),
+        })
+    }
+
+    /// Checks whether a packet from a stream passes the `Prefilter` and `FilterPayload`
+    /// rules; a `FilterPacket` rule is not applied.
+    ///
+    /// Used when the packet is decoded separately (once for several rule sets) and
+    /// `RulesDef::filter_packet` is called on the decoded packet afterwards. An accepted packet
+    /// comes with its signature status checked by the `Verify` rule of `rules`.
+    pub(crate) fn prematches<R, BR>(
+        reader: &mut R,
+        rules: &RulesDef<B, BR, P, Inner>,
+    ) -> Result<LookInStatus<SignatureStatus>, Error>
+    where
+        R: std::io::Read + std::io::Seek,
+        BR: BlockReferredDef<B>,
+        Self: Sized,
+    {
+        Self::prematches_with_signature(reader, rules, &SignatureStatus::Unsigned)
+    }
+
+    fn prematches_with_signature<R, BR>(
+        reader: &mut R,
+        rules: &RulesDef<B, BR, P, Inner>,
+        signature: &SignatureStatus,
+    ) -> Result<LookInStatus<SignatureStatus>, Error>
+    where
+        R: std::io::Read + std::io::Seek,
+        BR: BlockReferredDef<B>,
+        Self: Sized,
+    {
+        let header = <PacketHeader as ReadFrom>::read::<_, Inner>(reader)?;
+        let packet_size = header.size as usize;
+        if header.compressed || header.signed {
+            let mut body = vec![0u8; packet_size];
+            reader.read_exact(&mut body)?;
+            let status = if header.compressed {
+                let (_, packet) = header.unpack::<Inner>(&body)?;
+                Self::prematches_with_signature(
+                    &mut std::io::Cursor::new(packet),
+                    rules,
+                    signature,
+                )?
+            } else {
+                let signed = header.unsign::<Inner>(&body)?;
+                Self::prematches_with_signature(
+                    &mut std::io::Cursor::new(signed.packet()),
+                    rules,
+                    &rules.verify_signature(&signed),
+                )?
+            };
+            return Ok(match status {
+                LookInStatus::Accepted(_, signature) => {
+                    LookInStatus::Accepted(packet_size, signature)
+                }
+                LookInStatus::Denied(_) => LookInStatus::Denied(packet_size),
+                LookInStatus::NotEnoughData(needed) => LookInStatus::NotEnoughData(needed),
+            });
+        }
+        if !rules.accepts_signature(signature) {
+            return Ok(LookInStatus::Denied(packet_size));
+        }
+        let blocks_len = header.blocks_len as usize;
+        if blocks_len > 0 {
+            let mut blocks_buffer = vec![0; blocks_len];
+            reader.read_exact(&mut blocks_buffer)?;
+            let mut read = 0usize;
+            let mut blocks: Vec<BR> = Vec::new();
+            loop {
+                let blk =
+                    <BR as ReadBlockFromSlice>::read_from_slice(&blocks_buffer[read..], false)?;
+                let size = blk.size() as usize;
+                // A damaged block must neither stall the loop nor run past the blocks
+                if size == 0 || read + size > blocks_len {
+                    return Err(Error::InvalidLength);
+                }
+                read += size;
+                blocks.push(blk);
+                if read == blocks_len {
+                    break;
+                }
+            }
+            if !rules.prefilter(&blocks) {
+                return Ok(LookInStatus::Denied(packet_size));
+            }
+        } else if !rules.prefilter(&[]) {
+            return Ok(LookInStatus::Denied(packet_size));
+        }
+        if header.payload && rules.has_payload_filter() {
+            let payload_header = <PayloadHeader as ReadFrom>::read::<_, Inner>(reader)?;
+            header.validate_payload(&payload_header)?;
+            let mut payload_buffer = vec![0; payload_header.payload_len()];
+            reader.read_exact(&mut payload_buffer)?;
+            if !rules.filter_payload(&payload_buffer) {
+                return Ok(LookInStatus::Denied(packet_size));
+            }
+        }
+        Ok(LookInStatus::Accepted(packet_size, signature.clone()))
+    }
 }
 
 impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> Default for PacketDef<B, P, Inner> {
@@ -223,6 +476,8 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> Default for Pack
         Self {
             blocks: Vec::new(),
             payload: None,
+            signature: SignatureStatus::Unsigned,
+            undecryptable: None,
             _pi: PhantomData,
         }
     }
@@ -239,6 +494,10 @@ mod tests {
         NotEnough(u64),
         ErrInvalidLength,
         ErrCrcDismatch,
+        #[cfg(feature = "crypt")]
+        ErrUndecryptable,
+        #[cfg(feature = "crypt")]
+        ErrKeyProvider,
     }
 
     #[derive(Clone, Copy)]
@@ -314,6 +573,14 @@ mod tests {
                 DecodeOutcome::NotEnough(needed) => Ok(ReadStatus::NotEnoughData(needed)),
                 DecodeOutcome::ErrInvalidLength => Err(Error::InvalidLength),
                 DecodeOutcome::ErrCrcDismatch => Err(Error::CrcDismatch),
+                #[cfg(feature = "crypt")]
+                DecodeOutcome::ErrUndecryptable => Err(Error::Io(
+                    crate::crypt::CryptError::DecryptPayloadBody.into(),
+                )),
+                #[cfg(feature = "crypt")]
+                DecodeOutcome::ErrKeyProvider => Err(Error::Crypt(
+                    crate::crypt::CryptError::key_provider("agent is unavailable"),
+                )),
             }
         }
     }
@@ -329,6 +596,14 @@ mod tests {
                 DecodeOutcome::NotEnough(needed) => Ok(ReadStatus::NotEnoughData(needed)),
                 DecodeOutcome::ErrInvalidLength => Err(Error::InvalidLength),
                 DecodeOutcome::ErrCrcDismatch => Err(Error::CrcDismatch),
+                #[cfg(feature = "crypt")]
+                DecodeOutcome::ErrUndecryptable => Err(Error::Io(
+                    crate::crypt::CryptError::DecryptPayloadBody.into(),
+                )),
+                #[cfg(feature = "crypt")]
+                DecodeOutcome::ErrKeyProvider => Err(Error::Crypt(
+                    crate::crypt::CryptError::key_provider("agent is unavailable"),
+                )),
             }
         }
     }
@@ -432,6 +707,32 @@ mod tests {
 
     impl BlockReferredDef<TestBlock> for TestBlockRef {}
 
+    /// Referred block which is always read successfully, but declares a zero size.
+    struct ZeroSizeBlockRef;
+
+    impl Size for ZeroSizeBlockRef {
+        fn size(&self) -> u64 {
+            0
+        }
+    }
+
+    impl ReadBlockFromSlice for ZeroSizeBlockRef {
+        fn read_from_slice<'a>(_: &'a [u8], _: bool) -> Result<Self, Error>
+        where
+            Self: 'a + Sized,
+        {
+            Ok(ZeroSizeBlockRef)
+        }
+    }
+
+    impl From<ZeroSizeBlockRef> for TestBlock {
+        fn from(_: ZeroSizeBlockRef) -> TestBlock {
+            TestBlock
+        }
+    }
+
+    impl BlockReferredDef<TestBlock> for ZeroSizeBlockRef {}
+
     fn packet_bytes_with_payload(payload: bool) -> Vec<u8> {
         let mut out = Vec::new();
         if payload {
@@ -566,6 +867,40 @@ mod tests {
         assert!(matches!(err, Error::InvalidLength));
     }
 
+    #[test]
+    fn zero_size_block_is_rejected() {
+        let mut bytes = Vec::new();
+        PacketHeader::from_lengths(8, 0, false)
+            .write_all(&mut bytes)
+            .expect("header");
+        bytes.extend_from_slice(&[0; 8]);
+        let mut rules: RulesDef<TestBlock, ZeroSizeBlockRef, TestPayload, TestPayload> =
+            RulesDef::default();
+        let mut ctx = DecodeCtx {
+            buffered: DecodeOutcome::Success,
+            stream: DecodeOutcome::Success,
+        };
+        assert!(matches!(
+            PacketDef::<TestBlock, TestPayload, TestPayload>::matches(
+                &mut std::io::Cursor::new(&bytes),
+                &rules,
+                &mut ctx
+            ),
+            Err(Error::InvalidLength)
+        ));
+        rules
+            .add_rule(RuleDef::FilterPacket(RuleFnDef::Static(|_| true)))
+            .expect("packet filter");
+        assert!(matches!(
+            PacketDef::<TestBlock, TestPayload, TestPayload>::matches(
+                &mut std::io::Cursor::new(&bytes),
+                &rules,
+                &mut ctx
+            ),
+            Err(Error::InvalidLength)
+        ));
+    }
+
     #[test]
     fn filtered_packet_filter_can_deny_packet() {
         let mut reader = Cursor::new(packet_bytes_with_payload(false));
@@ -585,4 +920,81 @@ mod tests {
         .expect("filtered");
         assert!(matches!(status, LookInStatus::Denied(_)));
     }
+
+    #[cfg(feature = "crypt")]
+    #[test]
+    fn filtered_applies_undecryptable_policy() {
+        let bytes = packet_bytes_with_payload(true);
+        let filtered = |outcome: DecodeOutcome, policy: Option<UndecryptablePolicy>| {
+            let mut rules =
+                RulesDef::<TestBlock, TestBlockRef, TestPayload, TestPayload>::default();
+            if let Some(policy) = policy {
+                rules
+                    .add_rule(RuleDef::Undecryptable(policy))
+                    .expect("undecryptable rule");
+            }
+            let mut ctx = DecodeCtx {
+                buffered: outcome,
+                stream: outcome,
+            };
+            PacketDef::<TestBlock, TestPayload, TestPayload>::filtered::<_, TestBlockRef>(
+                &mut Cursor::new(bytes.clone()),
+                &rules,
+                &mut ctx,
+            )
+        };
+
+        for policy in [None, Some(UndecryptablePolicy::Fail)] {
+            assert!(matches!(
+                filtered(DecodeOutcome::ErrUndecryptable, policy),
+                Err(Error::Io(_))
+            ));
+        }
+        assert!(matches!(
+            filtered(
+                DecodeOutcome::ErrUndecryptable,
+                Some(UndecryptablePolicy::Skip)
+            ),
+            Ok(LookInStatus::Denied(_))
+        ));
+        // Other errors aren't affected by the policy
+        assert!(matches!(
+            filtered(
+                DecodeOutcome::ErrInvalidLength,
+                Some(UndecryptablePolicy::Skip)
+            ),
+            Err(Error::InvalidLength)
+        ));
+        // Errors of key providers aren't errors of the data
+        for policy in [UndecryptablePolicy::Skip, UndecryptablePolicy::Keep] {
+            assert!(matches!(
+                filtered(DecodeOutcome::ErrKeyProvider, Some(policy)),
+                Err(Error::Crypt(crate::crypt::CryptError::KeyProvider(_)))
+            ));
+        }
+
+        let Ok(LookInStatus::Accepted(_, mut packet)) = filtered(
+            DecodeOutcome::ErrUndecryptable,
+            Some(UndecryptablePolicy::Keep),
+        ) else {
+            panic!("packet must be kept");
+        };
+        assert!(packet.payload.is_none());
+        let payload = packet.undecryptable().expect("undecryptable payload");
+        assert_eq!(payload.sig(), &ByteBlock::Len4(*b"TSTP"));
+        assert_eq!(payload.envelope(), &[1, 2, 3]);
+        assert!(matches!(
+            payload.error(),
+            Error::Crypt(crate::crypt::CryptError::DecryptPayloadBody)
+        ));
+
+        // The packet is written back unchanged
+        let mut ctx = DecodeCtx {
+            buffered: DecodeOutcome::Success,
+            stream: DecodeOutcome::Success,
+        };
+        let mut written = Vec::new();
+        packet.write_all(&mut written, &mut ctx).expect("write");
+        assert_eq!(written, bytes);
+    }
 }
@@ -27,6 +27,21 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> ReadPacketFrom
         Self: Sized,
     {
         let header = PacketHeader::read::<_, Inner>(buf)?;
+        if header.compressed {
+            let mut body = vec![0u8; header.size as usize];
+            buf.read_exact(&mut body)?;
+            let (_, packet) = header.unpack::<Inner>(&body)?;
+            return <Self as ReadPacketFrom>::read(&mut std::io::Cursor::new(packet), ctx);
+        }
+        if header.signed {
+            let mut body = vec![0u8; header.size as usize];
+            buf.read_exact(&mut body)?;
+            let signed = header.unsign::<Inner>(&body)?;
+            let mut pkg =
+                <Self as ReadPacketFrom>::read(&mut std::io::Cursor::new(signed.packet()), ctx)?;
+            pkg.signature = SignatureStatus::Unchecked(signed.key_id().to_vec());
+            return Ok(pkg);
+        }
         let mut pkg = PacketDef::default();
         let mut read = 0;
         if header.blocks_len > 0 {
@@ -105,6 +120,52 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> TryReadPacketFro
         if packet_size > available {
             return Ok(PacketReadStatus::NotEnoughData(packet_size - available));
         }
+        if packet_header.compressed {
+            let mut body = vec![0u8; packet_header.size as usize];
+            buf.read_exact(&mut body)?;
+            let status = packet_header
+                .unpack::<Inner>(&body)
+                .and_then(|(_, packet)| {
+                    <Self as TryReadPacketFrom>::try_read(&mut std::io::Cursor::new(packet), ctx)
+                });
+            return match status {
+                Ok(PacketReadStatus::NotEnoughData(needed)) => {
+                    buf.seek(std::io::SeekFrom::Start(start_pos))?;
+                    Err(Error::NotEnoughData(needed as usize))
+                }
+                Ok(status) => Ok(status),
+                Err(err) => {
+                    buf.seek(std::io::SeekFrom::Start(start_pos))?;
+                    Err(err)
+                }
+            };
+        }
+        if packet_header.signed {
+            let mut body = vec![0u8; packet_header.size as usize];
+            buf.read_exact(&mut body)?;
+            let status = packet_header.unsign::<Inner>(&body).and_then(|signed| {
+                let status = <Self as TryReadPacketFrom>::try_read(
+                    &mut std::io::Cursor::new(signed.packet()),
+                    ctx,
+                )?;
+                let key_id = signed.key_id().to_vec();
+                Ok(status.map(|mut pkg| {
+                    pkg.signature = SignatureStatus::Unchecked(key_id);
+                    pkg
+                }))
+            });
+            return match status {
+                Ok(PacketReadStatus::NotEnoughData(needed)) => {
+                    buf.seek(std::io::SeekFrom::Start(start_pos))?;
+                    Err(Error::NotEnoughData(needed as usize))
+                }
+                Ok(status) => Ok(status),
+                Err(err) => {
+                    buf.seek(std::io::SeekFrom::Start(start_pos))?;
+                    Err(err)
+                }
+            };
+        }
         let mut pkg = PacketDef::default();
         let mut read = 0;
         if packet_header.blocks_len > 0 {
@@ -262,6 +323,38 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> TryReadPacketFro
         if packet_size > available {
             return Ok(PacketReadStatus::NotEnoughData(packet_size - available));
         }
+        // Like `TryReadPacketFrom`, a wrapped packet is consumed only once it has been read, so
+        // on error the reader stays at the start of the packet
+        if packet_header.compressed {
+            let (_, packet) = packet_header
+                .unpack::<Inner>(&bytes[PacketHeader::ssize() as usize..packet_size as usize])?;
+            let status =
+                match <Self as TryReadPacketFromBuffered>::try_read(&mut packet.as_slice(), ctx)? {
+                    PacketReadStatus::NotEnoughData(needed) => {
+                        return Err(Error::NotEnoughData(needed as usize));
+                    }
+                    status => status,
+                };
+            reader.consume(packet_size as usize);
+            return Ok(status);
+        }
+        if packet_header.signed {
+            let signed = packet_header
+                .unsign::<Inner>(&bytes[PacketHeader::ssize() as usize..packet_size as usize])?;
+            let key_id = signed.key_id().to_vec();
+            let status =
+                match <Self as TryReadPacketFromBuffered>::try_read(&mut signed.packet(), ctx)? {
+                    PacketReadStatus::NotEnoughData(needed) => {
+                        return Err(Error::NotEnoughData(needed as usize));
+                    }
+                    status => status.map(|mut pkg| {
+                        pkg.signature = SignatureStatus::Unchecked(key_id);
+                        pkg
+                    }),
+                };
+            reader.consume(packet_size as usize);
+            return Ok(status);
+        }
         reader.consume(PacketHeader::ssize() as usize);
         #[cfg(feature = "resilient")]
         let mut unrecognized = Vec::new();
@@ -377,7 +470,7 @@ mod tests {
         TryReadPacketFromBuffered, WriteTo,
         tests::{TestBlock, TestPayload},
     };
-    use std::io::{BufReader, Cursor, Seek};
+    use std::io::{BufRead, BufReader, Cursor, Seek};
 
     fn empty_packet_bytes() -> Vec<u8> {
         let header = PacketHeader::from_lengths(0, 0, false);
@@ -442,6 +535,89 @@ mod tests {
         }
     }
 
+    fn compressed_packet_bytes(frame: &[u8]) -> Vec<u8> {
+        let mut header = PacketHeader::from_lengths(0, frame.len() as u64, false);
+        header.compressed = true;
+        header.crc = u32::from_le_bytes(crate::CrcU32::crc(&header));
+        let mut out = Vec::new();
+        header.write_all(&mut out).expect("packet header write");
+        out.extend_from_slice(frame);
+        out
+    }
+
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    #[test]
+    fn compressed_packet_is_restored_by_all_read_paths() {
+        #[cfg(feature = "compress-zstd")]
+        let compression = crate::Compression::zstd();
+        #[cfg(not(feature = "compress-zstd"))]
+        let compression = crate::Compression::Lz4;
+        let frame = compression
+            .compress(&empty_packet_bytes())
+            .expect("compress packet");
+        let bytes = compressed_packet_bytes(&frame);
+
+        let mut cursor = Cursor::new(bytes.clone());
+        let packet = <PacketDef<TestBlock, TestPayload, TestPayload> as ReadPacketFrom>::read(
+            &mut cursor,
+            &mut DefaultProtocolContext::default(),
+        )
+        .expect("read compressed packet");
+        assert!(packet.blocks.is_empty());
+        assert!(packet.payload.is_none());
+
+        let mut cursor = Cursor::new(bytes.clone());
+        assert!(matches!(
+            <PacketDef<TestBlock, TestPayload, TestPayload> as TryReadPacketFrom>::try_read(
+                &mut cursor,
+                &mut DefaultProtocolContext::default(),
+            ),
+            Ok(PacketReadStatus::Success(_))
+        ));
+        assert_eq!(
+            cursor.stream_position().expect("stream_position"),
+            bytes.len() as u64
+        );
+
+        let mut reader = BufReader::new(Cursor::new(bytes));
+        assert!(matches!(
+            <PacketDef<TestBlock, TestPayload, TestPayload> as TryReadPacketFromBuffered>::try_read(
+                &mut reader,
+                &mut DefaultProtocolContext::default(),
+            ),
+            Ok(PacketReadStatus::Success(_))
+        ));
+    }
+
+    #[test]
+    fn compressed_packet_with_unknown_algorithm_is_rejected() {
+        let mut frame = vec![0xEE];
+        frame.extend_from_slice(&(PacketHeader::SIZE).to_le_bytes());
+        frame.extend_from_slice(&[0_u8; 8]);
+        let bytes = compressed_packet_bytes(&frame);
+
+        let mut cursor = Cursor::new(bytes.clone());
+        assert!(matches!(
+            <PacketDef<TestBlock, TestPayload, TestPayload> as TryReadPacketFrom>::try_read(
+                &mut cursor,
+                &mut DefaultProtocolContext::default(),
+            ),
+            Err(Error::UnsupportedCompression(0xEE))
+        ));
+        assert_eq!(cursor.stream_position().expect("stream_position"), 0);
+
+        let mut reader = BufReader::new(Cursor::new(bytes.clone()));
+        assert!(matches!(
+            <PacketDef<TestBlock, TestPayload, TestPayload> as TryReadPacketFromBuffered>::try_read(
+                &mut reader,
+                &mut DefaultProtocolContext::default(),
+            ),
+            Err(Error::UnsupportedCompression(0xEE))
+        ));
+        // Both read paths leave the rejected packet in the source
+        assert_eq!(reader.fill_buf().expect("fill_buf"), bytes.as_slice());
+    }
+
     #[test]
     fn packet_try_read_and_buffered_report_not_enough_for_short_header() {
         let short = vec![1_u8, 2, 3];
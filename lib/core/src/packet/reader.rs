@@ -352,11 +352,38 @@ impl<
         self.rules.add_rule(rule)
     }
 
+    /// Replaces all rules with already validated ones.
+    #[cfg(feature = "observer")]
+    pub(crate) fn set_rules(&mut self, rules: RulesDef<B, BR, P, Inner, WorkflowCtx>) {
+        self.rules = rules;
+    }
+
+    /// Takes all rules out of the reader, leaving it without rules.
+    #[cfg(feature = "observer")]
+    pub(crate) fn take_rules(&mut self) -> RulesDef<B, BR, P, Inner, WorkflowCtx> {
+        std::mem::take(&mut self.rules)
+    }
+
     /// Removes a previously added rule. See `RuleDef` for more details.
     pub fn remove_rule(&mut self, rule: RuleDefId) {
         self.rules.remove_rule(rule);
     }
 
+    /// Returns the number of bytes which were taken from the source but aren't processed yet.
+    ///
+    /// These are buffered bytes, a partially received packet (including its header) or a tail
+    /// which may contain the beginning of a packet header. Subtracting this value from the number
+    /// of bytes read from the source gives the position in the source up to which all data has
+    /// been either returned as packets or reported as ignored.
+    pub fn pending(&self) -> usize {
+        let recent = match &self.recent {
+            HeaderReadState::Ready(Some(_)) => PacketHeader::ssize() as usize,
+            HeaderReadState::Refill(Some((buffer, _))) => buffer.len(),
+            _ => 0,
+        };
+        self.inner.buffer().len() + self.buffered.len() + recent
+    }
+
     /// Reads the current portion of data available in the internal `BufReader`.
     ///
     /// This method does **not** invoke `read` or otherwise fetch additional data into the internal buffer.
@@ -453,6 +480,42 @@ impl<
                 return Err(Error::InvalidPacketReaderLogic);
             }
         };
+        let unpacked;
+        let (packet_buffer, header) = if header.compressed {
+            match header.unpack::<Inner>(packet_buffer) {
+                Ok((inner, packet)) => {
+                    unpacked = packet;
+                    (&unpacked[PacketHeader::ssize() as usize..], inner)
+                }
+                Err(err) => {
+                    return self.drop_and_consume(consume, Err(err));
+                }
+            }
+        } else {
+            (packet_buffer, header)
+        };
+        let (packet_buffer, header, signature) = if header.signed {
+            match header.unsign::<Inner>(packet_buffer) {
+                Ok(signed) => {
+                    let signature = self.rules.verify_signature(&signed);
+                    let packet = signed.packet();
+                    (
+                        &packet[PacketHeader::ssize() as usize..],
+                        signed.into_header(),
+                        signature,
+                    )
+                }
+                Err(err) => {
+                    return self.drop_and_consume(consume, Err(err));
+                }
+            }
+        } else {
+            (packet_buffer, header, SignatureStatus::Unsigned)
+        };
+        if !self.rules.accepts_signature(&signature) {
+            // PacketDef rejected by signature check
+            return self.drop_and_consume(consume, Ok(NextPacket::Skipped));
+        }
         let blocks_len = header.blocks_len as usize;
         let blocks_buffer = &packet_buffer[..blocks_len];
         let mut blocks = Vec::new();
@@ -486,7 +549,7 @@ impl<
             return self.drop_and_consume(consume, Ok(NextPacket::Skipped));
         }
         // Loading payload if exists
-        let pkg = if header.payload {
+        let mut pkg = if header.payload {
             let mut payload_buffer = &packet_buffer[blocks_len..];
             match <PayloadHeader as TryReadFromBuffered>::try_read::<_, Inner>(&mut payload_buffer)
             {
@@ -499,16 +562,33 @@ impl<
                         // PacketDef marked as ignored
                         return self.drop_and_consume(consume, Ok(NextPacket::Skipped));
                     }
+                    let raw_payload =
+                        &payload_buffer[..payload_header.payload_len().min(payload_buffer.len())];
                     match <P as TryExtractPayloadFromBuffered<Inner>>::try_read(
                         &mut payload_buffer,
                         &payload_header,
                         ctx,
-                    )? {
-                        ReadStatus::Success(payload) => PacketDef::new(
+                    ) {
+                        Ok(ReadStatus::Success(payload)) => PacketDef::new(
                             blocks.into_iter().map(|blk| blk.into()).collect::<Vec<B>>(),
                             Some(payload),
                         ),
-                        ReadStatus::NotEnoughData(needed) => {
+                        Err(err) => match self.rules.undecryptable().resolve(
+                            err,
+                            payload_header,
+                            raw_payload.to_vec(),
+                        ) {
+                            Ok(Some(payload)) => PacketDef::with_undecryptable(
+                                blocks.into_iter().map(|blk| blk.into()).collect::<Vec<B>>(),
+                                payload,
+                            ),
+                            Ok(None) => {
+                                // PacketDef with undecryptable payload is skipped
+                                return self.drop_and_consume(consume, Ok(NextPacket::Skipped));
+                            }
+                            Err(err) => return self.drop_and_consume(consume, Err(err)),
+                        },
+                        Ok(ReadStatus::NotEnoughData(needed)) => {
                             // This is error, but not NextPacket::NotEnoughData because length of payload
                             // already has been check. If we are here - some data is invalid and
                             // it's an error
@@ -536,6 +616,7 @@ impl<
                 None,
             )
         };
+        pkg.signature = signature;
         if !self.rules.filter_packet(&pkg) {
             // PacketDef marked as ignored
             self.drop_and_consume(consume, Ok(NextPacket::Skipped))
@@ -1068,6 +1149,40 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn read_keeps_other_payload_errors_and_consumes_failed_packet() {
+        let body = [1u8, 2, 3];
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&body);
+        let payload_header = PayloadHeader {
+            sig: ByteBlock::Len4(*b"ABCD"),
+            crc: ByteBlock::Len4(hasher.finalize().to_le_bytes()),
+            len: body.len() as u32,
+        };
+        let packet_header =
+            PacketHeader::from_lengths(0, (payload_header.size() + body.len()) as u64, true);
+        let mut bytes = Vec::new();
+        packet_header
+            .write_all(&mut bytes)
+            .expect("packet header write");
+        bytes.extend_from_slice(&payload_header.as_vec());
+        bytes.extend_from_slice(&body);
+        bytes.extend_from_slice(&empty_packet_bytes());
+
+        let mut input = Cursor::new(bytes);
+        let mut reader = ReaderUnderTest::new(&mut input);
+        reader
+            .add_rule(RuleDef::Undecryptable(UndecryptablePolicy::Skip))
+            .expect("undecryptable rule");
+        // Only decryption errors are handled by the policy
+        assert!(matches!(reader.read(&mut ()), Err(Error::Test)));
+        // The failed packet is consumed
+        assert!(matches!(
+            reader.read(&mut ()).expect("next packet"),
+            NextPacket::Found(_)
+        ));
+    }
+
     #[test]
     fn add_rule_duplicate_then_remove_allows_readd() {
         let mut input = Cursor::new(Vec::<u8>::new());
@@ -1086,4 +1201,33 @@ mod tests {
             .add_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| true)))
             .expect("prefilter re-add after remove");
     }
+
+    #[test]
+    fn pending_counts_taken_but_unprocessed_bytes() {
+        let packet = empty_packet_bytes();
+        let half = packet.len() / 2;
+        let mut input = Cursor::new(packet[..half].to_vec());
+        let mut reader = ReaderUnderTest::new(&mut input);
+        assert_eq!(reader.pending(), 0);
+        assert!(matches!(
+            reader.read(&mut ()).expect("read partial header"),
+            NextPacket::NotEnoughData(_)
+        ));
+        assert_eq!(reader.pending(), half);
+
+        let mut bytes = packet.clone();
+        bytes.extend_from_slice(&packet);
+        let mut input = Cursor::new(bytes);
+        let mut reader = ReaderUnderTest::new(&mut input);
+        assert!(matches!(
+            reader.read(&mut ()).expect("read packet"),
+            NextPacket::Found(_)
+        ));
+        assert_eq!(reader.pending(), packet.len());
+        assert!(matches!(
+            reader.read(&mut ()).expect("read packet"),
+            NextPacket::Found(_)
+        ));
+        assert_eq!(reader.pending(), 0);
+    }
 }
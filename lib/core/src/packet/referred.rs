@@ -60,6 +60,8 @@ mod tests {
             size: 123,
             blocks_len: 45,
             payload: true,
+            compressed: false,
+            signed: false,
             crc: 7,
         };
         let packet = PacketReferred::<crate::tests::TestBlock, crate::tests::TestBlock>::new(
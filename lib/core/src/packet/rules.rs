@@ -251,6 +251,18 @@ pub enum RuleDef<
 
     /// Filters fully parsed packets.
     FilterPacket(FilterCallback<B, P, Inner>),
+
+    /// Checks signatures of signed packets.
+    ///
+    /// The result is exposed by `PacketDef::signature`. If the verifier is
+    /// [`SignatureVerifier::required`], unsigned packets and packets which fail the check
+    /// are rejected before their blocks are parsed.
+    Verify(Box<dyn SignatureVerifier>),
+
+    /// Defines what to do with packets whose payload cannot be decrypted.
+    ///
+    /// Without the rule reading fails (`UndecryptablePolicy::Fail`).
+    Undecryptable(UndecryptablePolicy),
 }
 
 /// Internal container for rule management used by `PacketBufReaderDef`.
@@ -346,6 +358,20 @@ impl<
                     return Err(Error::RuleDuplicate);
                 }
             }
+            RuleDef::Verify(..) => {
+                if self.rules.iter().any(|r| matches!(r, RuleDef::Verify(..))) {
+                    return Err(Error::RuleDuplicate);
+                }
+            }
+            RuleDef::Undecryptable(..) => {
+                if self
+                    .rules
+                    .iter()
+                    .any(|r| matches!(r, RuleDef::Undecryptable(..)))
+                {
+                    return Err(Error::RuleDuplicate);
+                }
+            }
         };
         self.rules.push(rule);
         Ok(())
@@ -438,6 +464,55 @@ impl<
             .any(|rule| matches!(rule, RuleDef::FilterPayload(..)))
     }
 
+    /// Returns `true` when a full packet filter rule is configured.
+    pub fn has_packet_filter(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| matches!(rule, RuleDef::FilterPacket(..)))
+    }
+
+    /// Checks the signature of a signed packet with the `Verify` rule (if defined).
+    ///
+    /// Without the rule the packet is reported as [`SignatureStatus::Unchecked`].
+    pub fn verify_signature(&self, packet: &SignedPacket<'_>) -> SignatureStatus {
+        match self.verifier() {
+            Some(verifier) => verifier.verify(packet),
+            None => SignatureStatus::Unchecked(packet.key_id().to_vec()),
+        }
+    }
+
+    /// Returns `false` if a packet with the given signature status must be rejected.
+    pub fn accepts_signature(&self, status: &SignatureStatus) -> bool {
+        match self.verifier() {
+            Some(verifier) if verifier.required() => status.is_verified(),
+            _ => true,
+        }
+    }
+
+    fn verifier(&self) -> Option<&dyn SignatureVerifier> {
+        self.rules.iter().find_map(|r| {
+            if let RuleDef::Verify(verifier) = r {
+                Some(verifier.as_ref())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the policy for packets whose payload cannot be decrypted.
+    pub fn undecryptable(&self) -> UndecryptablePolicy {
+        self.rules
+            .iter()
+            .find_map(|r| {
+                if let RuleDef::Undecryptable(policy) = r {
+                    Some(*policy)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
     /// Runs the full packet filter rule on a parsed packet.
     pub fn filter_packet(&self, packet: &PacketDef<B, P, Inner>) -> bool {
         let Some(cb) = self.rules.iter().find_map(|r| {
@@ -463,7 +538,8 @@ mod tests {
         NextPacket, PacketDef, PayloadDef, PayloadHeader, ProtocolSchema, ReadBlockFrom,
         ReadBlockFromSlice, ReadFrom, ReadStatus, RuleDef, RuleDefId, RuleFnDef, RulesDef,
         TryExtractPayloadFrom, TryExtractPayloadFromBuffered, TryReadFrom, TryReadFromBuffered,
-        WriteMutTo, WriteTo, WriteVectoredMutTo, WriteVectoredTo, packet::rules::PeekAs,
+        UndecryptablePolicy, WriteMutTo, WriteTo, WriteVectoredMutTo, WriteVectoredTo,
+        packet::rules::PeekAs,
     };
     use std::io::Cursor;
     use std::sync::{
@@ -730,6 +806,56 @@ mod tests {
         assert_eq!(collected, 2);
     }
 
+    struct RequiredVerifier;
+
+    impl crate::SignatureVerifier for RequiredVerifier {
+        fn verify(&self, packet: &crate::SignedPacket<'_>) -> crate::SignatureStatus {
+            crate::SignatureStatus::Invalid(packet.key_id().to_vec())
+        }
+
+        fn required(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn verify_rule_decides_signature_acceptance() {
+        use crate::SignatureStatus;
+
+        let mut rules = RulesDef::<RuleBlock, RuleBlock, RulePayload, RulePayload>::default();
+        assert!(rules.accepts_signature(&SignatureStatus::Unsigned));
+        rules
+            .add_rule(RuleDef::Verify(Box::new(RequiredVerifier)))
+            .expect("first verify");
+        assert!(matches!(
+            rules.add_rule(RuleDef::Verify(Box::new(RequiredVerifier))),
+            Err(Error::RuleDuplicate)
+        ));
+        assert!(!rules.accepts_signature(&SignatureStatus::Unsigned));
+        assert!(!rules.accepts_signature(&SignatureStatus::Invalid(b"key".to_vec())));
+        assert!(rules.accepts_signature(&SignatureStatus::Verified(b"key".to_vec())));
+        rules.remove_rule(RuleDefId::Verify);
+        assert!(rules.accepts_signature(&SignatureStatus::Unsigned));
+    }
+
+    #[test]
+    fn undecryptable_rule_sets_policy() {
+        let mut rules = RulesDef::<RuleBlock, RuleBlock, RulePayload, RulePayload>::default();
+        assert_eq!(rules.undecryptable(), UndecryptablePolicy::Fail);
+
+        rules
+            .add_rule(RuleDef::Undecryptable(UndecryptablePolicy::Keep))
+            .expect("undecryptable rule");
+        assert_eq!(rules.undecryptable(), UndecryptablePolicy::Keep);
+        assert!(matches!(
+            rules.add_rule(RuleDef::Undecryptable(UndecryptablePolicy::Skip)),
+            Err(Error::RuleDuplicate)
+        ));
+
+        rules.remove_rule(RuleDefId::Undecryptable);
+        assert_eq!(rules.undecryptable(), UndecryptablePolicy::Fail);
+    }
+
     #[test]
     fn rules_add_duplicate_and_remove_behaviour() {
         let mut rules = RulesDef::<RuleBlock, RuleBlock, RulePayload, RulePayload>::default();
@@ -848,6 +974,7 @@ mod tests {
             vec![],
             Some(RulePayload::new(1)),
         );
+        assert!(rules.has_packet_filter());
         assert!(rules.filter_packet(&packet_no_payload));
         assert!(!rules.filter_packet(&packet_with_payload));
     }
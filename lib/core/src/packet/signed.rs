@@ -0,0 +1,257 @@
+use crate::*;
+
+/// Length of a packet signature (Ed25519) in bytes.
+pub const PACKET_SIGNATURE_LEN: usize = 64;
+
+/// Maximum length of the identifier of a signing key.
+pub const MAX_SIGNATURE_KEY_ID_LEN: usize = u8::MAX as usize;
+
+/// Prefix of every signed message; keeps packet signatures apart from other uses of a key.
+const SIGNED_MESSAGE_CONTEXT: &[u8] = b"brec packet signature";
+
+/// Result of the signature check of a packet.
+///
+/// Packets read without a [`RuleDef::Verify`] rule (e.g. with `ReadPacketFrom` or storage
+/// `nth`/`iter`) report signed packets as [`SignatureStatus::Unchecked`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// The packet isn't signed.
+    #[default]
+    Unsigned,
+    /// The packet is signed with the given key, but the signature wasn't checked.
+    Unchecked(Vec<u8>),
+    /// The signature was made by the given key and matches the packet.
+    Verified(Vec<u8>),
+    /// The packet is signed with a key which isn't known to the verifier.
+    UnknownKey(Vec<u8>),
+    /// The signature doesn't match the packet.
+    Invalid(Vec<u8>),
+}
+
+impl SignatureStatus {
+    /// Returns the identifier of the signing key, if the packet is signed.
+    pub fn key_id(&self) -> Option<&[u8]> {
+        match self {
+            Self::Unsigned => None,
+            Self::Unchecked(key_id)
+            | Self::Verified(key_id)
+            | Self::UnknownKey(key_id)
+            | Self::Invalid(key_id) => Some(key_id),
+        }
+    }
+
+    /// Returns `true` if the packet is signed.
+    pub fn is_signed(&self) -> bool {
+        !matches!(self, Self::Unsigned)
+    }
+
+    /// Returns `true` if the signature was successfully verified.
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Self::Verified(..))
+    }
+}
+
+/// Signed packet unwrapped from the body of a packet marked with [`PACKET_FLAG_SIGNED`].
+///
+/// Body layout:
+/// - Length of the key identifier: `u8`
+/// - Key identifier
+/// - Signature: `[u8; PACKET_SIGNATURE_LEN]`
+/// - Complete serialized packet (header, blocks and payload)
+pub struct SignedPacket<'a> {
+    header: PacketHeader,
+    packet: &'a [u8],
+    key_id: &'a [u8],
+    signature: &'a [u8; PACKET_SIGNATURE_LEN],
+}
+
+impl<'a> SignedPacket<'a> {
+    /// Header of the signed (inner) packet.
+    pub fn header(&self) -> &PacketHeader {
+        &self.header
+    }
+
+    /// Complete serialized signed packet (header, blocks and payload).
+    pub fn packet(&self) -> &'a [u8] {
+        self.packet
+    }
+
+    /// Identifier of the signing key.
+    pub fn key_id(&self) -> &'a [u8] {
+        self.key_id
+    }
+
+    /// Signature of the packet.
+    pub fn signature(&self) -> &'a [u8; PACKET_SIGNATURE_LEN] {
+        self.signature
+    }
+
+    /// Returns the message covered by the signature.
+    pub fn message(&self) -> Vec<u8> {
+        signed_message(self.key_id, self.packet)
+    }
+
+    pub(crate) fn into_header(self) -> PacketHeader {
+        self.header
+    }
+}
+
+/// Checks signatures of packets for `RuleDef::Verify`.
+///
+/// With the `sign` feature enabled, `brec::sign::PacketVerifier` implements this trait for
+/// Ed25519 signatures.
+pub trait SignatureVerifier: Send {
+    /// Checks the signature of a packet.
+    fn verify(&self, packet: &SignedPacket<'_>) -> SignatureStatus;
+
+    /// Returns `true` if packets which aren't [`SignatureStatus::Verified`] must be rejected.
+    fn required(&self) -> bool {
+        false
+    }
+}
+
+/// Builds the message covered by a packet signature.
+pub(crate) fn signed_message(key_id: &[u8], packet: &[u8]) -> Vec<u8> {
+    let mut message =
+        Vec::with_capacity(SIGNED_MESSAGE_CONTEXT.len() + 1 + key_id.len() + packet.len());
+    message.extend_from_slice(SIGNED_MESSAGE_CONTEXT);
+    message.push(key_id.len() as u8);
+    message.extend_from_slice(key_id);
+    message.extend_from_slice(packet);
+    message
+}
+
+impl PacketHeader {
+    /// Unwraps the packet carried by the body of a signed packet.
+    ///
+    /// The inner packet must be a plain (not compressed and not signed) packet which exactly
+    /// fills the rest of the body. The signature isn't checked here.
+    ///
+    /// # Errors
+    /// - `Error::InvalidLength` if the signed packet or the inner packet is malformed or exceeds
+    ///   `S::MAX_PACKET_LEN`.
+    pub fn unsign<'a, S: ProtocolSchema>(&self, body: &'a [u8]) -> Result<SignedPacket<'a>, Error> {
+        if !self.signed || self.compressed || self.payload || self.blocks_len != 0 {
+            return Err(Error::InvalidLength);
+        }
+        let Some((key_id_len, rest)) = body.split_first() else {
+            return Err(Error::InvalidLength);
+        };
+        let key_id_len = *key_id_len as usize;
+        if rest.len() < key_id_len + PACKET_SIGNATURE_LEN {
+            return Err(Error::InvalidLength);
+        }
+        let (key_id, rest) = rest.split_at(key_id_len);
+        let (signature, packet) = rest.split_at(PACKET_SIGNATURE_LEN);
+        let inner = PacketHeader::read_from_slice(packet, false)?;
+        if inner.compressed
+            || inner.signed
+            || inner.size > S::MAX_PACKET_LEN
+            || PacketHeader::SIZE + inner.size != packet.len() as u64
+        {
+            return Err(Error::InvalidLength);
+        }
+        Ok(SignedPacket {
+            header: inner,
+            packet,
+            key_id,
+            signature: signature.try_into()?,
+        })
+    }
+
+    /// Wraps a serialized packet into a signed packet.
+    #[cfg(feature = "sign")]
+    pub(crate) fn sign(
+        packet: &[u8],
+        key_id: &[u8],
+        signature: &[u8; PACKET_SIGNATURE_LEN],
+    ) -> std::io::Result<Vec<u8>> {
+        let body_len = 1 + key_id.len() + PACKET_SIGNATURE_LEN + packet.len();
+        let mut header = Self {
+            size: body_len as u64,
+            blocks_len: 0,
+            payload: false,
+            compressed: false,
+            signed: true,
+            crc: 0,
+        };
+        header.crc = u32::from_le_bytes(header.crc());
+        let mut signed = Vec::with_capacity(PacketHeader::SIZE as usize + body_len);
+        header.write_all(&mut signed)?;
+        signed.push(key_id.len() as u8);
+        signed.extend_from_slice(key_id);
+        signed.extend_from_slice(signature);
+        signed.extend_from_slice(packet);
+        Ok(signed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TestPayload;
+
+    fn signed_packet_bytes(key_id: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut packet = Vec::new();
+        PacketHeader::from_lengths(0, 0, false)
+            .write_all(&mut packet)
+            .expect("inner header");
+        let body_len = 1 + key_id.len() + PACKET_SIGNATURE_LEN + packet.len();
+        let mut header = PacketHeader::from_lengths(0, body_len as u64, false);
+        header.signed = true;
+        header.crc = u32::from_le_bytes(header.crc());
+        let mut signed = Vec::new();
+        header.write_all(&mut signed).expect("outer header");
+        signed.push(key_id.len() as u8);
+        signed.extend_from_slice(key_id);
+        signed.extend_from_slice(&[7u8; PACKET_SIGNATURE_LEN]);
+        signed.extend_from_slice(&packet);
+        (signed, packet)
+    }
+
+    #[test]
+    fn signed_packet_is_unwrapped() {
+        let (bytes, packet) = signed_packet_bytes(b"signer");
+        let header = PacketHeader::read_from_slice(&bytes, false).expect("header");
+        assert!(header.signed);
+        let signed = header
+            .unsign::<TestPayload>(&bytes[PacketHeader::SIZE as usize..])
+            .expect("unsign");
+        assert_eq!(signed.key_id(), b"signer");
+        assert_eq!(signed.signature(), &[7u8; PACKET_SIGNATURE_LEN]);
+        assert_eq!(signed.packet(), packet.as_slice());
+        assert_eq!(signed.header().size, 0);
+        assert!(signed.message().ends_with(&packet));
+    }
+
+    #[test]
+    fn malformed_signed_packet_is_rejected() {
+        let (bytes, _) = signed_packet_bytes(b"signer");
+        let header = PacketHeader::read_from_slice(&bytes, false).expect("header");
+        let body = &bytes[PacketHeader::SIZE as usize..];
+        assert!(matches!(
+            header.unsign::<TestPayload>(&body[..body.len() - 1]),
+            Err(Error::NotEnoughData(_))
+        ));
+        assert!(matches!(
+            header.unsign::<TestPayload>(&body[..10]),
+            Err(Error::InvalidLength)
+        ));
+        assert!(matches!(
+            PacketHeader::from_lengths(0, body.len() as u64, false).unsign::<TestPayload>(body),
+            Err(Error::InvalidLength)
+        ));
+    }
+
+    #[test]
+    fn signature_status_accessors() {
+        assert_eq!(SignatureStatus::default(), SignatureStatus::Unsigned);
+        assert_eq!(SignatureStatus::Unsigned.key_id(), None);
+        assert!(!SignatureStatus::Unsigned.is_signed());
+        let status = SignatureStatus::Verified(b"signer".to_vec());
+        assert_eq!(status.key_id(), Some(b"signer".as_slice()));
+        assert!(status.is_signed());
+        assert!(status.is_verified());
+        assert!(!SignatureStatus::Invalid(b"signer".to_vec()).is_verified());
+    }
+}
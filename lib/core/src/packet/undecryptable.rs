@@ -0,0 +1,115 @@
+use crate::*;
+
+/// Defines what readers do with a packet whose payload cannot be decrypted.
+///
+/// Install it as `RuleDef::Undecryptable`. A payload is undecryptable if decoding fails
+/// with a crypto error of the data: no key for the envelope, wrong key or tampered
+/// ciphertext. Errors of key configuration or key providers, as well as errors after
+/// decryption (decompression, deserialization), always fail reading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UndecryptablePolicy {
+    /// Reading fails with the decryption error.
+    #[default]
+    Fail,
+    /// The packet is skipped, as if it was rejected by a filter.
+    Skip,
+    /// The packet is returned with its blocks, but without payload; the raw payload is
+    /// available via [`PacketDef::undecryptable`].
+    Keep,
+}
+
+impl UndecryptablePolicy {
+    /// Applies the policy to an error of payload decoding.
+    ///
+    /// # Returns
+    /// - `Ok(Some(payload))` - the packet is kept with the raw payload
+    /// - `Ok(None)` - the packet is skipped
+    /// - `Err(err)` - the error isn't a decryption error, or the policy is `Fail`
+    pub(crate) fn resolve(
+        self,
+        err: Error,
+        header: PayloadHeader,
+        body: Vec<u8>,
+    ) -> Result<Option<UndecryptablePayload>, Error> {
+        if self == Self::Fail {
+            return Err(err);
+        }
+        let error = decryption_error(err)?;
+        Ok(match self {
+            Self::Fail | Self::Skip => None,
+            Self::Keep => Some(UndecryptablePayload {
+                header,
+                body,
+                error,
+            }),
+        })
+    }
+}
+
+/// Payload of a packet which couldn't be decrypted (see [`UndecryptablePolicy::Keep`]).
+///
+/// Keeps the payload exactly as it was read, so the packet can still be forwarded or
+/// stored: writing the packet writes this payload back unchanged.
+pub struct UndecryptablePayload {
+    header: PayloadHeader,
+    body: Vec<u8>,
+    error: Error,
+}
+
+impl UndecryptablePayload {
+    /// Returns the payload header as it was read (signature, CRC and length).
+    pub fn header(&self) -> &PayloadHeader {
+        &self.header
+    }
+
+    /// Returns the signature of the payload type.
+    pub fn sig(&self) -> &ByteBlock {
+        &self.header.sig
+    }
+
+    /// Returns the raw payload body, i.e. the crypto envelope.
+    pub fn envelope(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Returns the error of decryption.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// Reads the metadata of the crypto envelope (e.g. key identifiers of its recipients).
+    #[cfg(feature = "crypt")]
+    pub fn metadata(&self) -> crate::crypt::CryptResult<crate::crypt::CryptEnvelopeMeta<'_>> {
+        crate::crypt::CryptEnvelopeMeta::parse(&self.body)
+    }
+}
+
+/// Returns the error as `Error::Crypt` if payload decoding failed because of the encrypted
+/// data (see [`crate::crypt::CryptError::is_undecryptable`]).
+///
+/// Generated payloads report decryption errors as `std::io::Error`, which keeps the
+/// `CryptError` as its source.
+#[cfg(feature = "crypt")]
+fn decryption_error(err: Error) -> Result<Error, Error> {
+    use crate::crypt::CryptError;
+
+    match err {
+        Error::Crypt(err) if err.is_undecryptable() => Ok(Error::Crypt(err)),
+        Error::Io(err)
+            if err
+                .get_ref()
+                .and_then(|err| err.downcast_ref::<CryptError>())
+                .is_some_and(CryptError::is_undecryptable) =>
+        {
+            err.downcast::<CryptError>()
+                .map(Error::Crypt)
+                .map_err(Error::Io)
+        }
+        err => Err(err),
+    }
+}
+
+#[cfg(not(feature = "crypt"))]
+fn decryption_error(err: Error) -> Result<Error, Error> {
+    Err(err)
+}
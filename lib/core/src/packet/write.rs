@@ -1,6 +1,26 @@
 use crate::payload::EncodedPayload;
 use crate::*;
 
+impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P, Inner> {
+    /// Encodes the payload with its header.
+    ///
+    /// A payload which couldn't be decrypted is taken as it was read.
+    fn prepared_payload(
+        &self,
+        ctx: &mut <Self as ProtocolSchema>::Context<'_>,
+    ) -> std::io::Result<Option<(PayloadHeader, EncodedPayload<'_>)>> {
+        if let Some(payload) = self.payload.as_ref() {
+            return Ok(Some(prepare_payload(payload, ctx)?));
+        }
+        Ok(self.undecryptable().map(|payload| {
+            (
+                payload.header().clone(),
+                EncodedPayload::Borrowed(payload.envelope()),
+            )
+        }))
+    }
+}
+
 /// Implements mutable stream writing for a full `PacketDef`, including header, blocks, and payload.
 ///
 /// This trait supports writing packets to a stream with partial write handling
@@ -28,11 +48,7 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> WriteMutTo
         buf: &mut T,
         ctx: &mut <Self as ProtocolSchema>::Context<'_>,
     ) -> std::io::Result<usize> {
-        let prepared_payload = if let Some(payload) = self.payload.as_ref() {
-            Some(prepare_payload(payload, ctx)?)
-        } else {
-            None
-        };
+        let prepared_payload = self.prepared_payload(ctx)?;
         let payload_len = prepared_payload
             .as_ref()
             .map(|(header, body)| (header.size() + body.len()) as u64)
@@ -80,11 +96,7 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> WriteMutTo
         buf: &mut T,
         ctx: &mut <Self as ProtocolSchema>::Context<'_>,
     ) -> std::io::Result<()> {
-        let prepared_payload = if let Some(payload) = self.payload.as_ref() {
-            Some(prepare_payload(payload, ctx)?)
-        } else {
-            None
-        };
+        let prepared_payload = self.prepared_payload(ctx)?;
         let payload_len = prepared_payload
             .as_ref()
             .map(|(header, body)| (header.size() + body.len()) as u64)
@@ -132,11 +144,7 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> WriteVectoredMut
         &mut self,
         ctx: &mut <Self as ProtocolSchema>::Context<'_>,
     ) -> std::io::Result<IoSlices<'_>> {
-        let prepared_payload = if let Some(payload) = self.payload.as_ref() {
-            Some(prepare_payload(payload, ctx)?)
-        } else {
-            None
-        };
+        let prepared_payload = self.prepared_payload(ctx)?;
         let payload_len = prepared_payload
             .as_ref()
             .map(|(header, body)| (header.size() + body.len()) as u64)
@@ -162,6 +170,61 @@ impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> WriteVectoredMut
     }
 }
 
+#[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P, Inner> {
+    /// Serializes the packet and compresses it into a compressed packet.
+    ///
+    /// The result is marked with `PACKET_FLAG_COMPRESSED` in its header, so `PacketBufReader`
+    /// and storage readers restore the original packet transparently. If compression doesn't
+    /// make the packet smaller, the plain packet is returned.
+    pub fn to_compressed(
+        &mut self,
+        compression: &crate::compress::Compression,
+        ctx: &mut <Self as ProtocolSchema>::Context<'_>,
+    ) -> std::io::Result<Vec<u8>> {
+        let mut packet = Vec::new();
+        self.write_all(&mut packet, ctx)?;
+        Ok(PacketHeader::pack(&packet, compression)?.unwrap_or(packet))
+    }
+
+    /// Writes the packet as a compressed packet (see [`PacketDef::to_compressed`]).
+    pub fn write_compressed<T: std::io::Write>(
+        &mut self,
+        buf: &mut T,
+        compression: &crate::compress::Compression,
+        ctx: &mut <Self as ProtocolSchema>::Context<'_>,
+    ) -> std::io::Result<()> {
+        buf.write_all(&self.to_compressed(compression, ctx)?)
+    }
+}
+
+#[cfg(feature = "sign")]
+impl<B: BlockDef, P: PayloadDef<Inner>, Inner: PayloadInnerDef> PacketDef<B, P, Inner> {
+    /// Serializes the packet and wraps it into a signed packet.
+    ///
+    /// The result is marked with `PACKET_FLAG_SIGNED` in its header; readers unwrap the
+    /// original packet transparently and check the signature with a `RuleDef::Verify` rule.
+    pub fn to_signed(
+        &mut self,
+        signer: &crate::sign::PacketSigner,
+        ctx: &mut <Self as ProtocolSchema>::Context<'_>,
+    ) -> std::io::Result<Vec<u8>> {
+        let mut packet = Vec::new();
+        self.write_all(&mut packet, ctx)?;
+        Ok(signer.sign(&packet)?)
+    }
+
+    /// Writes the packet as a signed packet (see [`PacketDef::to_signed`]).
+    pub fn write_signed<T: std::io::Write>(
+        &mut self,
+        buf: &mut T,
+        signer: &crate::sign::PacketSigner,
+        ctx: &mut <Self as ProtocolSchema>::Context<'_>,
+    ) -> std::io::Result<()> {
+        buf.write_all(&self.to_signed(signer, ctx)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
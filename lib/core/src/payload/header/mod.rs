@@ -24,6 +24,7 @@ pub use sreader::*;
 ///
 /// This flexible structure supports various signature/CRC sizes while keeping the layout
 /// parseable and consistent.
+#[derive(Clone)]
 pub struct PayloadHeader {
     /// Unique signature identifying the payload format.
     pub sig: ByteBlock,
@@ -6,8 +6,12 @@ pub use crate::payload::*;
 pub use crate::storage::*;
 pub use crate::traits::*;
 
+#[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+pub use crate::compress::*;
 #[cfg(feature = "crypt")]
 pub use crate::crypt::*;
+#[cfg(feature = "sign")]
+pub use crate::sign::{PacketSigner, PacketVerifier, SIGN_KEY_LEN, SignError, SignResult};
 #[cfg(feature = "bincode")]
 pub use bincode;
 pub use brec_macros::*;
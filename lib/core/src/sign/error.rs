@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+pub type SignResult<T> = Result<T, SignError>;
+
+/// Unified error type for `brec::sign` module.
+#[derive(Debug, Error)]
+pub enum SignError {
+    #[error("Invalid Ed25519 private key PEM")]
+    InvalidPrivateKeyPem,
+    #[error("Invalid Ed25519 public key PEM")]
+    InvalidPublicKeyPem,
+    #[error("Invalid Ed25519 public key")]
+    InvalidPublicKey,
+    #[error("Signing key_id is too long: {0} bytes")]
+    KeyIdTooLong(usize),
+    #[error("Only plain (not compressed and not signed) packets can be signed")]
+    NotAPlainPacket,
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<SignError> for std::io::Error {
+    fn from(value: SignError) -> Self {
+        match value {
+            SignError::InvalidPrivateKeyPem
+            | SignError::InvalidPublicKeyPem
+            | SignError::InvalidPublicKey
+            | SignError::KeyIdTooLong(_)
+            | SignError::NotAPlainPacket => {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, value.to_string())
+            }
+            SignError::Io(err) => err,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use ed25519_dalek::{Signer, SigningKey, pkcs8::DecodePrivateKey};
+
+use crate::sign::{SIGN_KEY_LEN, SignError, SignResult, check_key_id, read_text_file};
+use crate::*;
+
+/// Signs packets with an Ed25519 private key.
+///
+/// The identifier of the key is stored in every signed packet, so readers can pick the
+/// matching public key (see [`PacketVerifier`](crate::sign::PacketVerifier)).
+#[derive(Clone)]
+pub struct PacketSigner {
+    key_id: Vec<u8>,
+    key: SigningKey,
+}
+
+impl std::fmt::Debug for PacketSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PacketSigner")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PacketSigner {
+    /// Creates a signer from a raw Ed25519 private key (seed).
+    ///
+    /// # Errors
+    /// `SignError::KeyIdTooLong` if `key_id` exceeds `MAX_SIGNATURE_KEY_ID_LEN`.
+    pub fn new(key_id: impl Into<Vec<u8>>, private_key: [u8; SIGN_KEY_LEN]) -> SignResult<Self> {
+        Self::from_signing_key(key_id, SigningKey::from_bytes(&private_key))
+    }
+
+    /// Creates a signer from an `ed25519_dalek` signing key.
+    pub fn from_signing_key(key_id: impl Into<Vec<u8>>, key: SigningKey) -> SignResult<Self> {
+        let key_id = key_id.into();
+        check_key_id(&key_id)?;
+        Ok(Self { key_id, key })
+    }
+
+    /// Creates a signer from PKCS#8 PEM (`PRIVATE KEY`) with an Ed25519 private key.
+    pub fn from_private_key_pem(key_id: impl Into<Vec<u8>>, pem: &str) -> SignResult<Self> {
+        let key = SigningKey::from_pkcs8_pem(pem).map_err(|_| SignError::InvalidPrivateKeyPem)?;
+        Self::from_signing_key(key_id, key)
+    }
+
+    /// Creates a signer from a PEM file (see [`PacketSigner::from_private_key_pem`]).
+    pub fn from_private_key_pem_file(
+        key_id: impl Into<Vec<u8>>,
+        path: impl AsRef<std::path::Path>,
+    ) -> SignResult<Self> {
+        let pem = read_text_file(path)?;
+        Self::from_private_key_pem(key_id, &pem)
+    }
+
+    /// Returns the identifier of the signing key.
+    pub fn key_id(&self) -> &[u8] {
+        &self.key_id
+    }
+
+    /// Returns the raw Ed25519 public key, which verifies signatures of this signer.
+    pub fn public_key(&self) -> [u8; SIGN_KEY_LEN] {
+        self.key.verifying_key().to_bytes()
+    }
+
+    /// Wraps a serialized plain packet into a signed packet.
+    ///
+    /// The signature covers the complete packet (header, blocks and payload) and the key
+    /// identifier.
+    ///
+    /// # Errors
+    /// `SignError::NotAPlainPacket` if `packet` isn't a complete plain packet.
+    pub fn sign(&self, packet: &[u8]) -> SignResult<Vec<u8>> {
+        let header =
+            PacketHeader::read_from_slice(packet, false).map_err(|_| SignError::NotAPlainPacket)?;
+        if header.compressed
+            || header.signed
+            || PacketHeader::SIZE + header.size != packet.len() as u64
+        {
+            return Err(SignError::NotAPlainPacket);
+        }
+        let signature = self
+            .key
+            .sign(&signed_message(&self.key_id, packet))
+            .to_bytes();
+        Ok(PacketHeader::sign(packet, &self.key_id, &signature)?)
+    }
+}
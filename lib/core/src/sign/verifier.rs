@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use ed25519_dalek::{Signature, VerifyingKey, pkcs8::DecodePublicKey};
+
+use crate::sign::{SIGN_KEY_LEN, SignError, SignResult, check_key_id, read_text_file};
+use crate::*;
+
+/// Set of trusted Ed25519 public keys used to check packet signatures.
+///
+/// Install it as [`RuleDef::Verify`] to check signatures while reading; the result is
+/// exposed by `PacketDef::signature`. Keys are looked up by the key identifier stored in
+/// the signed packet.
+#[derive(Debug, Clone, Default)]
+pub struct PacketVerifier {
+    keys: HashMap<Vec<u8>, VerifyingKey>,
+    required: bool,
+}
+
+impl PacketVerifier {
+    /// Creates an empty verifier.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a raw Ed25519 public key. A key with the same identifier is replaced.
+    ///
+    /// # Errors
+    /// - `SignError::InvalidPublicKey` if the bytes aren't a valid Ed25519 public key.
+    /// - `SignError::KeyIdTooLong` if `key_id` exceeds `MAX_SIGNATURE_KEY_ID_LEN`.
+    pub fn with_key(
+        self,
+        key_id: impl Into<Vec<u8>>,
+        public_key: [u8; SIGN_KEY_LEN],
+    ) -> SignResult<Self> {
+        let key = VerifyingKey::from_bytes(&public_key).map_err(|_| SignError::InvalidPublicKey)?;
+        self.with_verifying_key(key_id, key)
+    }
+
+    /// Adds an `ed25519_dalek` verifying key. A key with the same identifier is replaced.
+    pub fn with_verifying_key(
+        mut self,
+        key_id: impl Into<Vec<u8>>,
+        key: VerifyingKey,
+    ) -> SignResult<Self> {
+        let key_id = key_id.into();
+        check_key_id(&key_id)?;
+        self.keys.insert(key_id, key);
+        Ok(self)
+    }
+
+    /// Adds a public key from SPKI PEM (`PUBLIC KEY`) with an Ed25519 public key.
+    pub fn with_public_key_pem(self, key_id: impl Into<Vec<u8>>, pem: &str) -> SignResult<Self> {
+        let key =
+            VerifyingKey::from_public_key_pem(pem).map_err(|_| SignError::InvalidPublicKeyPem)?;
+        self.with_verifying_key(key_id, key)
+    }
+
+    /// Adds a public key from a PEM file (see [`PacketVerifier::with_public_key_pem`]).
+    pub fn with_public_key_pem_file(
+        self,
+        key_id: impl Into<Vec<u8>>,
+        path: impl AsRef<std::path::Path>,
+    ) -> SignResult<Self> {
+        let pem = read_text_file(path)?;
+        self.with_public_key_pem(key_id, &pem)
+    }
+
+    /// Sets whether packets which aren't verified (unsigned, signed with an unknown key or
+    /// with an invalid signature) are rejected. Disabled by default.
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Returns the number of keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if there are no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl SignatureVerifier for PacketVerifier {
+    fn verify(&self, packet: &SignedPacket<'_>) -> SignatureStatus {
+        let key_id = packet.key_id().to_vec();
+        let Some(key) = self.keys.get(&key_id) else {
+            return SignatureStatus::UnknownKey(key_id);
+        };
+        let signature = Signature::from_bytes(packet.signature());
+        if key.verify_strict(&packet.message(), &signature).is_ok() {
+            SignatureStatus::Verified(key_id)
+        } else {
+            SignatureStatus::Invalid(key_id)
+        }
+    }
+
+    fn required(&self) -> bool {
+        self.required
+    }
+}
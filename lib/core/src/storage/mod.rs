@@ -3,10 +3,10 @@ mod reader;
 mod slot;
 mod writer;
 
-#[cfg(feature = "observer")]
+#[cfg(any(feature = "observer", feature = "observer-sync"))]
 mod observer;
 
-#[cfg(feature = "observer")]
+#[cfg(any(feature = "observer", feature = "observer-sync"))]
 pub use observer::*;
 pub use reader::*;
 pub use writer::*;
@@ -14,4 +14,4 @@ pub use writer::*;
 pub(crate) use locator::*;
 pub(crate) use slot::*;
 
-pub use slot::{DEFAULT_SLOT_CAPACITY, STORAGE_SLOT_SIG};
+pub use slot::{DEFAULT_SLOT_CAPACITY, SLOT_ENTRY_COMPRESSED, STORAGE_SLOT_SIG};
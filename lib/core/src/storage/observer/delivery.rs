@@ -0,0 +1,147 @@
+use std::{
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use crate::*;
+
+/// Observer state required to deliver packets to the subscription.
+///
+/// Shared by the Tokio-backed and the thread-based observers. The Tokio-backed observer runs
+/// delivery on the blocking thread pool, so the state is moved there and back on each update.
+pub(super) struct Delivery<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    O: Send + Sync + 'static,
+> {
+    pub(super) reader: ReaderDef<std::fs::File, B, BR, P, Inner>,
+    pub(super) subscription: S,
+    /// Index of the next packet to check.
+    pub(super) last: usize,
+    /// Cursor shared with the observer handle (e.g. [`FileObserverDef::cursor`]).
+    pub(super) cursor: Arc<AtomicUsize>,
+    pub(super) max_batch: usize,
+    pub(super) opt: O,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    O: Send + Sync + 'static,
+> Delivery<B, BR, P, Inner, S, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Delivers packets from `last` up to `count` (exclusive) which pass the reader's rules.
+    ///
+    /// Packets denied by rules are skipped without being delivered (and without decoding the
+    /// payload if the rules allow to make a decision earlier). `last` is advanced past every
+    /// checked packet, so denied packets aren't checked again on the next update. Accepted
+    /// packets are collected into batches of up to `max_batch` packets. The shared cursor is
+    /// updated (and reported via `on_cursor`) before each delivered batch and once the range
+    /// is processed.
+    ///
+    /// Returns `Err` with the error which made the subscription request a stop (or which was
+    /// returned by `on_packets`). In this case the cursor points to the first packet which
+    /// wasn't delivered.
+    pub(super) fn deliver(&mut self, count: usize) -> Result<(), ObserverError> {
+        let mut batch = Vec::new();
+        let mut first = self.last;
+        while self.last < count {
+            let item = self.reader.nth_filtered(self.last, &mut self.opt);
+            self.last += 1;
+            let err = match item {
+                Ok(Some(LookInStatus::Accepted(_, packet))) => {
+                    if batch.is_empty() {
+                        first = self.last - 1;
+                    }
+                    batch.push(packet);
+                    if batch.len() >= self.max_batch {
+                        self.flush(&mut batch, first, self.last)?;
+                    }
+                    continue;
+                }
+                Ok(Some(LookInStatus::Denied(_))) | Ok(None) => {
+                    continue;
+                }
+                Ok(Some(LookInStatus::NotEnoughData(needed))) => Error::NotEnoughData(needed),
+                Err(err) => err,
+            };
+            // Keep packets in order: everything before the failed packet goes first
+            self.flush(&mut batch, first, self.last - 1)?;
+            let nth = self.last - 1;
+            let mut err = ObserverError::new(err).at_packet(nth);
+            err.offset = self.reader.nth_offset(nth);
+            if matches!(
+                self.subscription.on_error(&err),
+                SubscriptionErrorAction::Stop
+            ) {
+                self.report(self.last - 1);
+                return Err(err);
+            }
+        }
+        self.flush(&mut batch, first, self.last)?;
+        self.report(self.last);
+        Ok(())
+    }
+
+    /// Passes collected packets to `on_packets`, reporting `position` as the cursor first.
+    ///
+    /// If the subscription fails to take the batch, the cursor is moved back to `first`,
+    /// the global index of the first packet in the batch.
+    fn flush(
+        &mut self,
+        batch: &mut Vec<PacketDef<B, P, Inner>>,
+        first: usize,
+        position: usize,
+    ) -> Result<(), ObserverError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.report(position);
+        if let Err(err) = self.subscription.on_packets(std::mem::take(batch)) {
+            self.last = first;
+            self.report(first);
+            return Err(ObserverError::new(err).at_packet(first));
+        }
+        Ok(())
+    }
+
+    /// Re-reads storage metadata.
+    ///
+    /// Returns the number of packets completely written to the file and the offset where
+    /// they end (see [`ReaderDef::written`]).
+    pub(super) fn reload(&mut self) -> Result<(usize, u64), Error> {
+        self.reader.reload()?;
+        self.reader.written()
+    }
+
+    /// Reopens the file at `path` and restarts delivery from its first packet.
+    ///
+    /// Returns the number of packets completely written to the reopened file.
+    pub(super) fn reopen(&mut self, path: &Path, sensor: &Sensor) -> Result<usize, Error> {
+        let file = std::fs::File::open(path)?;
+        self.reader.reopen(file.try_clone()?)?;
+        let (written, processed) = self.reader.written()?;
+        sensor.reset(&file, processed)?;
+        self.last = 0;
+        self.report(0);
+        Ok(written)
+    }
+
+    /// Updates the shared cursor and reports it to the subscription if it has changed.
+    pub(super) fn report(&mut self, position: usize) {
+        if self.cursor.swap(position, Ordering::SeqCst) != position {
+            self.subscription.on_cursor(position);
+        }
+    }
+}
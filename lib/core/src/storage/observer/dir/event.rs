@@ -0,0 +1,84 @@
+use std::{path::Path, sync::Arc};
+
+use crate::*;
+
+use super::super::stream::QueueMessage;
+
+/// Events emitted by [`DirectoryObserverDef`].
+///
+/// Events of the observed files are tagged with the path of their source file; the
+/// directory-level lifecycle is reported by the other variants.
+pub enum DirectoryObserverEvent<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> {
+    /// A file matching the pattern was found; its events follow.
+    Added(Arc<Path>),
+
+    /// An event of an observed file.
+    ///
+    /// Never contains [`FileObserverEvent::Lagged`] or [`FileObserverEvent::Aborted`]:
+    /// lagging is reported for the whole channel and aborting for the whole directory.
+    /// [`FileObserverEvent::Stopped`] means observation of this file has stopped (for
+    /// example, the file isn't a storage file); other files are still observed.
+    File(Arc<Path>, FileObserverEvent<B, P, Inner>),
+
+    /// The file hasn't changed for the configured time (see
+    /// [`DirectoryObserverOptions::complete_after`]) and isn't observed anymore.
+    Completed(Arc<Path>),
+
+    /// The file was removed and isn't observed anymore.
+    Removed(Arc<Path>),
+
+    /// The given number of packets was dropped because the stream consumer didn't keep up.
+    ///
+    /// Emitted only with a bounded channel and [`OverflowPolicy::DropOldest`].
+    Lagged(usize),
+
+    /// A non-terminal error of the directory observation (for example, a file which
+    /// cannot be inspected).
+    Error(ObserverError),
+
+    /// The observer stopped.
+    ///
+    /// `None` means a normal stop. `Some(error)` means the directory could not be observed
+    /// anymore (for example, it was removed).
+    Stopped(Option<ObserverError>),
+
+    /// The observer was explicitly aborted by shutdown.
+    Aborted,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> DirectoryObserverEvent<B, P, Inner>
+{
+    /// Returns the path of the file the event belongs to; `None` for directory-level events.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Added(path)
+            | Self::File(path, ..)
+            | Self::Completed(path)
+            | Self::Removed(path) => Some(path),
+            Self::Lagged(..) | Self::Error(..) | Self::Stopped(..) | Self::Aborted => None,
+        }
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> QueueMessage for DirectoryObserverEvent<B, P, Inner>
+{
+    fn is_packet(&self) -> bool {
+        matches!(self, Self::File(_, FileObserverEvent::Packet(..)))
+    }
+
+    fn lagged(dropped: usize) -> Self {
+        Self::Lagged(dropped)
+    }
+}
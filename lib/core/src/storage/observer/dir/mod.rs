@@ -0,0 +1,591 @@
+mod event;
+mod options;
+mod subscription;
+
+use glob::Pattern;
+use notify::{
+    Event, EventKind, RecursiveMode, Result as NotifyResult, Watcher, event::ModifyKind,
+    recommended_watcher,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::{
+    select,
+    sync::mpsc,
+    task::{self, JoinHandle},
+    time::{self, MissedTickBehavior},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::*;
+
+use self::subscription::FileSubscription;
+use super::stream::Queue;
+
+pub use event::DirectoryObserverEvent;
+pub use options::*;
+
+type EventQueue<B, P, Inner> = Arc<Queue<DirectoryObserverEvent<B, P, Inner>>>;
+
+/// Observer of a directory with segmented storage (for example, one storage file per
+/// session).
+///
+/// Every file matching the pattern is observed with its own [`FileObserverDef`] as soon as
+/// it appears in the directory. Events of all files are merged into a single Tokio stream of
+/// [`DirectoryObserverEvent`] values tagged with the path of the source file:
+/// - a new file is reported via [`DirectoryObserverEvent::Added`]
+/// - events of the file follow as [`DirectoryObserverEvent::File`]
+/// - a file which stopped changing (see [`DirectoryObserverOptions::complete_after`]) is
+///   reported via [`DirectoryObserverEvent::Completed`]
+/// - a removed file is reported via [`DirectoryObserverEvent::Removed`]
+///
+/// Events of a single file keep their order; events of different files are interleaved.
+/// Only the files directly in the directory are observed (no recursion).
+///
+/// Like [`FileObserverStreamDef`], the observer is driven by Tokio tasks while file access
+/// itself is blocking.
+pub struct DirectoryObserverDef<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> {
+    queue: EventQueue<B, P, Inner>,
+    handler: Option<JoinHandle<()>>,
+    sd: CancellationToken,
+    _phantom: PhantomData<(BR, O)>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> DirectoryObserverDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Creates an observer of the directory with explicit protocol context options.
+    ///
+    /// The context is cloned for each observed file.
+    pub fn with_options(
+        options: DirectoryObserverOptions<B, BR, P, Inner>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let pattern = options
+            .pattern
+            .as_deref()
+            .map(Pattern::new)
+            .transpose()
+            .map_err(|err| Error::InvalidPattern(err.to_string()))?;
+        if !fs::metadata(&options.dir)?.is_dir() {
+            return Err(Error::Sensor(SensorError::NotFile(
+                options.dir.to_string_lossy().to_string(),
+            )));
+        }
+        let (tx, mut rx) = mpsc::channel::<()>(1);
+        let (watcher, interval) = match options.sensor {
+            SensorMode::Watch => (Some(watch(&options.dir, tx.clone())?), None),
+            SensorMode::Poll(interval) => (None, Some(interval)),
+            SensorMode::Auto(interval) => match watch(&options.dir, tx.clone()) {
+                Ok(watcher) => (Some(watcher), None),
+                Err(err) => {
+                    warn!(
+                        "Cannot watch {}: {err}; falling back to polling",
+                        options.dir.display()
+                    );
+                    (None, Some(interval))
+                }
+            },
+        };
+        let queue = Arc::new(Queue::new(options.capacity));
+        let mut dir = Directory {
+            path: options.dir,
+            pattern,
+            rules: options.rules,
+            sensor: options.sensor,
+            complete_after: options.complete_after,
+            queue: queue.clone(),
+            opt,
+            files: HashMap::new(),
+            finished: HashSet::new(),
+        };
+        let existing = options.existing;
+        let polling = interval.is_some();
+        let idle = dir.complete_after.is_some();
+        let mut ticker = time::interval(interval.unwrap_or(DEFAULT_POLL_INTERVAL));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let sd = CancellationToken::new();
+        let shutdown = sd.clone();
+
+        let handler = task::spawn(async move {
+            // Keeps the directory watcher alive and the wake channel open while polling
+            let _watcher = watcher;
+            let _tx = tx;
+            let mut stop_reason = dir.scan(existing).await.err();
+            while stop_reason.is_none() {
+                select! {
+                    biased;
+                    _ = shutdown.cancelled() => {
+                        debug!("Cancel signal has been gotten");
+                        dir.finish(DirectoryObserverEvent::Aborted).await;
+                        return;
+                    }
+                    _ = rx.recv() => {
+                        stop_reason = dir.scan(StartPosition::Beginning).await.err();
+                    }
+                    _ = ticker.tick(), if polling || idle => {
+                        if polling {
+                            stop_reason = dir.scan(StartPosition::Beginning).await.err();
+                        }
+                        if stop_reason.is_none() && idle {
+                            dir.complete_idle().await;
+                        }
+                    }
+                }
+            }
+            debug!("directory observer loop is closed");
+            dir.finish(DirectoryObserverEvent::Stopped(
+                stop_reason.map(ObserverError::from),
+            ))
+            .await;
+        });
+        Ok(Self {
+            queue,
+            handler: Some(handler),
+            sd,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Creates an observer of the directory using default protocol context options.
+    pub fn new(options: DirectoryObserverOptions<B, BR, P, Inner>) -> Result<Self, Error>
+    where
+        O: Default,
+    {
+        Self::with_options(options, O::default())
+    }
+
+    /// Stops observers of all files and waits for them to finish.
+    ///
+    /// The stream yields [`DirectoryObserverEvent::Aborted`] and ends.
+    pub async fn shutdown(&mut self) {
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+        // Let file observers blocked by a full channel finish
+        self.queue.release();
+        self.sd.cancel();
+        let _ = handler.await;
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> Unpin for DirectoryObserverDef<B, BR, P, Inner, O>
+{
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> tokio_stream::Stream for DirectoryObserverDef<B, BR, P, Inner, O>
+{
+    type Item = DirectoryObserverEvent<B, P, Inner>;
+
+    /// Polls the next event of the directory or of one of the observed files.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().queue.poll_recv(cx)
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> Drop for DirectoryObserverDef<B, BR, P, Inner, O>
+{
+    /// Disconnects the queue, so file observers blocked by a full channel don't wait for a
+    /// consumer which is gone, and stops the observer task.
+    fn drop(&mut self) {
+        self.queue.disconnect();
+        self.sd.cancel();
+    }
+}
+
+/// Starts the platform watcher of the directory; any creation, removal or renaming of an
+/// entry sends a wake signal.
+fn watch(dir: &Path, tx: mpsc::Sender<()>) -> Result<notify::RecommendedWatcher, SensorError> {
+    let mut watcher = recommended_watcher(move |res: NotifyResult<Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        if matches!(
+            event.kind,
+            EventKind::Create(..) | EventKind::Remove(..) | EventKind::Modify(ModifyKind::Name(..))
+        ) {
+            // A full channel already has a pending wake
+            let _ = tx.try_send(());
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+/// Observed file.
+struct Tracked<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> {
+    path: Arc<Path>,
+    observer: FileObserverDef<B, BR, P, Inner, O>,
+    /// File size at the last check.
+    size: u64,
+    /// Time of the last detected change of the file size.
+    changed: Instant,
+}
+
+/// Directory state owned by the observer task.
+struct Directory<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> {
+    path: PathBuf,
+    pattern: Option<Pattern>,
+    rules: Option<RulesFactoryDef<B, BR, P, Inner>>,
+    sensor: SensorMode,
+    complete_after: Option<Duration>,
+    queue: EventQueue<B, P, Inner>,
+    opt: O,
+    files: HashMap<PathBuf, Tracked<B, BR, P, Inner, O>>,
+    /// Files which are still present, but aren't observed anymore (completed or failed).
+    finished: HashSet<PathBuf>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Clone + Send + Sync + 'static,
+> Directory<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Starts observing new matching files (in the order of names) and stops observing
+    /// removed ones.
+    ///
+    /// Returns `Err` only if the directory cannot be read.
+    async fn scan(&mut self, start: StartPosition) -> Result<(), Error> {
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let matches = match (&self.pattern, path.file_name()) {
+                (None, _) => true,
+                (Some(pattern), Some(name)) => pattern.matches(&name.to_string_lossy()),
+                (Some(..), None) => false,
+            };
+            if matches {
+                found.push(path);
+            }
+        }
+        found.sort();
+        let present = found.iter().cloned().collect::<HashSet<PathBuf>>();
+        self.finished.retain(|path| present.contains(path));
+        let removed = self
+            .files
+            .keys()
+            .filter(|path| !present.contains(*path))
+            .cloned()
+            .collect::<Vec<PathBuf>>();
+        for path in removed {
+            if let Some(tracked) = self.files.remove(&path) {
+                let path = tracked.path.clone();
+                Self::stop(tracked).await;
+                self.queue.send(DirectoryObserverEvent::Removed(path));
+            }
+        }
+        for path in found {
+            if !self.files.contains_key(&path) && !self.finished.contains(&path) {
+                self.add(path, start);
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts observing a file.
+    ///
+    /// A file which cannot be observed (for example, it isn't a storage file) is reported
+    /// via [`FileObserverEvent::Stopped`] and isn't checked again until it's removed.
+    fn add(&mut self, path: PathBuf, start: StartPosition) {
+        let tagged: Arc<Path> = Arc::from(path.as_path());
+        self.queue
+            .send(DirectoryObserverEvent::Added(tagged.clone()));
+        let rules = self
+            .rules
+            .as_ref()
+            .map(|factory| factory(&path))
+            .unwrap_or_default();
+        let options = rules.into_iter().fold(
+            FileObserverOptions::new(&path)
+                .subscribe(FileSubscription::<B, P, Inner, O>::new(
+                    tagged.clone(),
+                    self.queue.clone(),
+                ))
+                .start_at(start)
+                .with_sensor(self.sensor),
+            |options, rule| options.with_rule(rule),
+        );
+        let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        match FileObserverDef::with_opt(options, self.opt.clone()) {
+            Ok(observer) => {
+                self.files.insert(
+                    path,
+                    Tracked {
+                        path: tagged,
+                        observer,
+                        size,
+                        changed: Instant::now(),
+                    },
+                );
+            }
+            Err(err) => {
+                self.queue.send(DirectoryObserverEvent::File(
+                    tagged,
+                    FileObserverEvent::Stopped(Some(ObserverError::new(err))),
+                ));
+                self.finished.insert(path);
+            }
+        }
+    }
+
+    /// Stops observing files which haven't changed for the configured time.
+    async fn complete_idle(&mut self) {
+        let Some(idle) = self.complete_after else {
+            return;
+        };
+        let now = Instant::now();
+        let mut completed = Vec::new();
+        for (path, tracked) in self.files.iter_mut() {
+            let Ok(meta) = fs::metadata(path) else {
+                // Removed files are reported by the next scan
+                continue;
+            };
+            if meta.len() != tracked.size {
+                tracked.size = meta.len();
+                tracked.changed = now;
+            } else if now.duration_since(tracked.changed) >= idle {
+                completed.push(path.clone());
+            }
+        }
+        completed.sort();
+        for path in completed {
+            if let Some(tracked) = self.files.remove(&path) {
+                let tagged = tracked.path.clone();
+                Self::stop(tracked).await;
+                self.queue.send(DirectoryObserverEvent::Completed(tagged));
+                self.finished.insert(path);
+            }
+        }
+    }
+
+    /// Stops observers of all files, sends the final event and closes the queue.
+    async fn finish(&mut self, event: DirectoryObserverEvent<B, P, Inner>) {
+        for (_, tracked) in self.files.drain() {
+            Self::stop(tracked).await;
+        }
+        self.queue.send(event);
+        self.queue.close();
+    }
+
+    async fn stop(mut tracked: Tracked<B, BR, P, Inner, O>) {
+        tracked.observer.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DefaultProtocolContext, DirectoryObserverDef, DirectoryObserverEvent,
+        DirectoryObserverOptions, Error, FileObserverEvent, PacketDef, RuleDef, RuleFnDef,
+        SensorMode, WriterDef,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::{fs::File, path::Path, time::Duration};
+    use tempfile::tempdir;
+    use tokio::time::timeout;
+    use tokio_stream::StreamExt;
+
+    type Packet = PacketDef<TestBlock, TestPayload, TestPayload>;
+    type Observer = DirectoryObserverDef<
+        TestBlock,
+        TestBlock,
+        TestPayload,
+        TestPayload,
+        DefaultProtocolContext,
+    >;
+    type Options = DirectoryObserverOptions<TestBlock, TestBlock, TestPayload, TestPayload>;
+    type Event = DirectoryObserverEvent<TestBlock, TestPayload, TestPayload>;
+
+    fn write_packets(path: &Path, count: usize) {
+        let mut file = File::options()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .read(true)
+            .open(path)
+            .expect("file opened");
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(&mut file).expect("writer");
+        for _ in 0..count {
+            writer
+                .insert(Packet::default(), &mut ())
+                .expect("packet written");
+        }
+    }
+
+    async fn next_event(observer: &mut Observer) -> Option<Event> {
+        timeout(Duration::from_secs(5), observer.next())
+            .await
+            .expect("event in time")
+    }
+
+    /// Reads events until `count` packets of `path` are received; returns other events.
+    async fn read_packets(observer: &mut Observer, path: &Path, count: usize) -> Vec<Event> {
+        let mut packets = 0;
+        let mut events = Vec::new();
+        while packets < count {
+            let event = next_event(observer).await.expect("stream must not end");
+            match &event {
+                DirectoryObserverEvent::File(source, FileObserverEvent::Packet(..)) => {
+                    assert_eq!(&**source, path);
+                    packets += 1;
+                }
+                DirectoryObserverEvent::File(_, FileObserverEvent::Update { .. }) => {}
+                _ => events.push(event),
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn directory_observer_rejects_invalid_pattern() {
+        let dir = tempdir().expect("temp dir");
+        let result = Observer::new(Options::new(dir.path()).with_pattern("[*.bin"));
+        assert!(matches!(result, Err(Error::InvalidPattern(..))));
+    }
+
+    #[test]
+    fn directory_observer_rejects_missing_directory() {
+        let dir = tempdir().expect("temp dir");
+        let result = Observer::new(Options::new(dir.path().join("missing")));
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn directory_observer_tags_events_of_matching_files() {
+        let dir = tempdir().expect("temp dir");
+        let first = dir.path().join("session-1.bin");
+        write_packets(&first, 2);
+        write_packets(&dir.path().join("notes.txt"), 1);
+
+        let mut observer = Observer::new(
+            Options::new(dir.path())
+                .with_pattern("session-*.bin")
+                .with_rules(|_| vec![RuleDef::Prefilter(RuleFnDef::Static(|_| true))])
+                .with_sensor(SensorMode::Poll(Duration::from_millis(50))),
+        )
+        .expect("observer created");
+        let events = read_packets(&mut observer, &first, 2).await;
+        assert!(matches!(
+            events.as_slice(),
+            [DirectoryObserverEvent::Added(path)] if **path == *first
+        ));
+
+        let second = dir.path().join("session-2.bin");
+        write_packets(&second, 3);
+        let events = read_packets(&mut observer, &second, 3).await;
+        assert!(matches!(
+            events.as_slice(),
+            [DirectoryObserverEvent::Added(path)] if **path == *second
+        ));
+
+        std::fs::remove_file(&first).expect("file removed");
+        loop {
+            let event = next_event(&mut observer)
+                .await
+                .expect("stream must not end");
+            if let DirectoryObserverEvent::Removed(path) = &event {
+                assert_eq!(&**path, first.as_path());
+                break;
+            }
+            assert_eq!(event.path(), Some(first.as_path()));
+        }
+
+        observer.shutdown().await;
+        let rest = observer.collect::<Vec<_>>().await;
+        assert!(matches!(rest.last(), Some(DirectoryObserverEvent::Aborted)));
+    }
+
+    #[tokio::test]
+    async fn directory_observer_completes_idle_files() {
+        let dir = tempdir().expect("temp dir");
+        let path = dir.path().join("session.bin");
+        write_packets(&path, 1);
+
+        let mut observer =
+            Observer::new(Options::new(dir.path()).complete_after(Duration::from_millis(200)))
+                .expect("observer created");
+        read_packets(&mut observer, &path, 1).await;
+        loop {
+            match next_event(&mut observer)
+                .await
+                .expect("stream must not end")
+            {
+                DirectoryObserverEvent::Completed(completed) => {
+                    assert_eq!(&*completed, path.as_path());
+                    break;
+                }
+                event => assert_eq!(event.path(), Some(path.as_path())),
+            }
+        }
+
+        // A completed file isn't observed anymore
+        write_packets(&path, 1);
+        assert!(
+            timeout(Duration::from_millis(500), observer.next())
+                .await
+                .is_err()
+        );
+        observer.shutdown().await;
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::*;
+
+/// Produces rules for a file observed by [`DirectoryObserverDef`].
+///
+/// Rules cannot be cloned, so a directory observer creates them for each file it starts
+/// observing. The path of the file is passed to the factory.
+pub type RulesFactoryDef<B, BR, P, Inner> =
+    Arc<dyn Fn(&Path) -> Vec<RuleDef<B, BR, P, Inner>> + Send + Sync>;
+
+/// Builder options for creating [`DirectoryObserverDef`].
+pub struct DirectoryObserverOptions<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> {
+    /// Path to the observed directory.
+    pub dir: PathBuf,
+    /// Glob pattern (for example, `*.brec`) matched against file names; `None` matches
+    /// all files.
+    pub pattern: Option<String>,
+    /// Rules applied to each observed file.
+    pub rules: Option<RulesFactoryDef<B, BR, P, Inner>>,
+    /// Packet from which files found in the directory at startup are observed.
+    ///
+    /// Files which appear later are always observed from their beginning.
+    pub existing: StartPosition,
+    /// Capacity of the channel shared by all observed files.
+    pub capacity: ChannelCapacity,
+    /// A file which hasn't changed for this time is considered completed and isn't
+    /// observed anymore; `None` observes files until they are removed.
+    pub complete_after: Option<Duration>,
+    /// Defines how changes of the directory and of the observed files are detected.
+    pub sensor: SensorMode,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> DirectoryObserverOptions<B, BR, P, Inner>
+{
+    /// Creates options for a target directory.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            pattern: None,
+            rules: None,
+            existing: StartPosition::Beginning,
+            capacity: ChannelCapacity::Unbounded,
+            complete_after: None,
+            sensor: SensorMode::default(),
+        }
+    }
+
+    /// Observes only files whose names match the glob pattern (for example, `session-*.bin`).
+    ///
+    /// An invalid pattern makes creation of the observer fail with [`Error::InvalidPattern`].
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    /// Sets the factory of rules (`Prefilter`, `FilterPayload`, `FilterPacket`, etc.) applied
+    /// to each observed file.
+    pub fn with_rules<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(&Path) -> Vec<RuleDef<B, BR, P, Inner>> + Send + Sync + 'static,
+    {
+        self.rules = Some(Arc::new(factory));
+        self
+    }
+
+    /// Sets the packet from which files found in the directory at startup are observed.
+    ///
+    /// Use [`StartPosition::End`] to get only packets written from now on.
+    pub fn existing_from(mut self, position: StartPosition) -> Self {
+        self.existing = position;
+        self
+    }
+
+    /// Limits the number of pending packets. `0` is treated as `1`.
+    ///
+    /// The channel is shared by all observed files. With [`OverflowPolicy::StopWithError`]
+    /// the observer of the file which overflowed the channel is stopped.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.capacity = ChannelCapacity::Bounded(capacity.max(1), policy);
+        self
+    }
+
+    /// Considers a file completed once it hasn't changed for the given time.
+    ///
+    /// The completed file isn't observed anymore and is reported via
+    /// [`DirectoryObserverEvent::Completed`].
+    pub fn complete_after(mut self, idle: Duration) -> Self {
+        self.complete_after = Some(idle);
+        self
+    }
+
+    /// Sets how changes of the directory and of the observed files are detected.
+    ///
+    /// See [`FileObserverOptions::with_sensor`].
+    pub fn with_sensor(mut self, mode: SensorMode) -> Self {
+        self.sensor = mode;
+        self
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> std::fmt::Debug for DirectoryObserverOptions<B, BR, P, Inner>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DirectoryObserverOptions: {}", self.dir.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DirectoryObserverOptions;
+    use crate::{
+        ChannelCapacity, OverflowPolicy, RuleDef, RuleFnDef, SensorMode, StartPosition,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::{path::Path, time::Duration};
+
+    type Options = DirectoryObserverOptions<TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    #[test]
+    fn directory_options_defaults_observe_all_files() {
+        let opts = Options::new("abc/sessions");
+        assert!(opts.dir.ends_with("abc/sessions"));
+        assert!(opts.pattern.is_none());
+        assert!(opts.rules.is_none());
+        assert_eq!(opts.existing, StartPosition::Beginning);
+        assert_eq!(opts.capacity, ChannelCapacity::Unbounded);
+        assert!(opts.complete_after.is_none());
+        assert_eq!(opts.sensor, SensorMode::default());
+    }
+
+    #[test]
+    fn directory_options_builder_sets_fields() {
+        let opts = Options::new("sessions")
+            .with_pattern("*.bin")
+            .with_rules(|_| vec![RuleDef::Prefilter(RuleFnDef::Static(|_| true))])
+            .existing_from(StartPosition::End)
+            .bounded(0, OverflowPolicy::Block)
+            .complete_after(Duration::from_secs(1))
+            .with_sensor(SensorMode::Poll(Duration::from_millis(100)));
+        assert_eq!(opts.pattern.as_deref(), Some("*.bin"));
+        let rules = opts.rules.as_ref().expect("rules factory");
+        assert_eq!(rules(Path::new("a.bin")).len(), 1);
+        assert_eq!(opts.existing, StartPosition::End);
+        assert_eq!(
+            opts.capacity,
+            ChannelCapacity::Bounded(1, OverflowPolicy::Block)
+        );
+        assert_eq!(opts.complete_after, Some(Duration::from_secs(1)));
+        assert_eq!(opts.sensor, SensorMode::Poll(Duration::from_millis(100)));
+        assert!(format!("{opts:?}").contains("sessions"));
+    }
+}
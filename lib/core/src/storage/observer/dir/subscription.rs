@@ -0,0 +1,98 @@
+use std::{marker::PhantomData, path::Path, sync::Arc};
+
+use crate::*;
+
+use super::super::stream::Queue;
+
+/// Internal subscription adapter that tags events of a single observed file with its path
+/// and sends them into the queue shared by all files of the directory.
+pub(super) struct FileSubscription<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    path: Arc<Path>,
+    queue: Arc<Queue<DirectoryObserverEvent<B, P, Inner>>>,
+    _phantom: PhantomData<O>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> FileSubscription<B, P, Inner, O>
+{
+    pub(super) fn new(
+        path: Arc<Path>,
+        queue: Arc<Queue<DirectoryObserverEvent<B, P, Inner>>>,
+    ) -> Self {
+        Self {
+            path,
+            queue,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn send(&self, event: FileObserverEvent<B, P, Inner>) {
+        self.queue
+            .send(DirectoryObserverEvent::File(self.path.clone(), event));
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> SubscriptionDef<B, BR, P, Inner, O> for FileSubscription<B, P, Inner, O>
+{
+    fn on_update(&mut self, total: usize, added: usize) -> SubscriptionUpdate {
+        self.send(FileObserverEvent::Update { total, added });
+        SubscriptionUpdate::Read
+    }
+
+    fn on_packet(&mut self, packet: PacketDef<B, P, Inner>) {
+        let _ = self.queue.send_packet(DirectoryObserverEvent::File(
+            self.path.clone(),
+            FileObserverEvent::Packet(packet),
+        ));
+    }
+
+    /// Forwards parsed packets, applying the channel overflow policy.
+    ///
+    /// Fails with [`Error::ObserverOverflow`] if the channel is full and configured to stop;
+    /// only the observer of this file stops in this case.
+    fn on_packets(&mut self, packets: Vec<PacketDef<B, P, Inner>>) -> Result<(), Error> {
+        for packet in packets {
+            self.queue.send_packet(DirectoryObserverEvent::File(
+                self.path.clone(),
+                FileObserverEvent::Packet(packet),
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn on_truncated(&mut self) {
+        self.send(FileObserverEvent::Truncated);
+    }
+
+    fn on_replaced(&mut self) {
+        self.send(FileObserverEvent::Replaced);
+    }
+
+    fn on_error(&mut self, err: &ObserverError) -> SubscriptionErrorAction {
+        self.send(FileObserverEvent::Error(err.clone()));
+        SubscriptionErrorAction::Continue
+    }
+
+    fn on_stopped(&mut self, reason: Option<ObserverError>) {
+        self.send(FileObserverEvent::Stopped(reason));
+    }
+
+    /// File observers are aborted by the directory observer only; the directory reports
+    /// it by itself (completion, removal or shutdown).
+    fn on_aborted(&mut self) {}
+}
@@ -0,0 +1,193 @@
+use std::{fmt, io::ErrorKind, sync::Arc};
+
+use crate::*;
+
+/// Category of an error reported by an observer.
+///
+/// Lets consumers decide programmatically how to react (for example, skip a damaged packet
+/// but stop on an I/O failure) without parsing error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObserverErrorKind {
+    /// Reading the file failed.
+    Io,
+    /// The packet isn't complete yet (or the file was cut in the middle of it).
+    Incomplete,
+    /// The data is damaged: CRC mismatch, unknown signature or invalid layout.
+    Corrupted,
+    /// The packet is intact, but its payload or blocks cannot be decoded (including
+    /// decompression failures).
+    Decode,
+    /// The payload cannot be decrypted.
+    Crypt,
+    /// The file watcher failed.
+    Sensor,
+    /// The stream channel is full and configured to stop on overflow.
+    Overflow,
+    /// Any other error.
+    Other,
+}
+
+impl ObserverErrorKind {
+    /// Returns the category of the error.
+    pub fn of(err: &Error) -> Self {
+        match err {
+            Error::Io(err) => match err.kind() {
+                ErrorKind::UnexpectedEof => Self::Incomplete,
+                ErrorKind::InvalidData => Self::Decode,
+                _ => Self::Io,
+            },
+            Error::PathIsNotFile(..)
+            | Error::FileIsLocked(..)
+            | Error::TimeoutToWaitLockedFile(..)
+            | Error::FailToLockFile(..) => Self::Io,
+            Error::NotEnoughData(..) | Error::NotEnoughtSignatureData(..) => Self::Incomplete,
+            Error::CrcDismatch
+            | Error::SignatureDismatch(..)
+            | Error::DamagedSlot(..)
+            | Error::InvalidAlign(..)
+            | Error::InvalidLength
+            | Error::ZeroLengthBlock
+            | Error::MaxBlocksCount
+            | Error::MisalignedPointer
+            | Error::UnexpectedSliceLength
+            | Error::TryFromSliceError(..)
+            | Error::FailExtractByteBlock
+            | Error::FailToReadPayloadHeader
+            | Error::FailToReadPacketHeader
+            | Error::TooManyAttemptsToReadBlock(..)
+            | Error::OutOfBounds(..) => Self::Corrupted,
+            Error::FailedConverting(..)
+            | Error::UnsupportedCompression(..)
+            | Error::Decompression(..) => Self::Decode,
+            #[cfg(feature = "crypt")]
+            Error::Crypt(..) => Self::Crypt,
+            Error::Sensor(..) => Self::Sensor,
+            Error::ObserverOverflow(..) => Self::Overflow,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Error reported by an observer, with the location of the failure.
+///
+/// Cheap to clone: the original [`Error`] is shared, so the same error can be delivered to
+/// many consumers and still be inspected (e.g. matched against [`Error`] variants).
+#[derive(Debug, Clone)]
+pub struct ObserverError {
+    /// Category of the error.
+    pub kind: ObserverErrorKind,
+    /// Global index of the packet which caused the error, if the error relates to a packet.
+    pub packet: Option<usize>,
+    /// Byte offset in the observed file where the failed data starts, if known.
+    pub offset: Option<u64>,
+    /// The original error.
+    pub error: Arc<Error>,
+}
+
+impl ObserverError {
+    /// Wraps an error which isn't bound to a location in the file.
+    pub fn new(error: Error) -> Self {
+        Self {
+            kind: ObserverErrorKind::of(&error),
+            packet: None,
+            offset: None,
+            error: Arc::new(error),
+        }
+    }
+
+    /// Sets the global index of the packet which caused the error.
+    pub fn at_packet(mut self, packet: usize) -> Self {
+        self.packet = Some(packet);
+        self
+    }
+
+    /// Sets the byte offset where the failed data starts.
+    pub fn at_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+impl From<Error> for ObserverError {
+    fn from(error: Error) -> Self {
+        Self::new(error)
+    }
+}
+
+impl fmt::Display for ObserverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        match (self.packet, self.offset) {
+            (Some(packet), Some(offset)) => write!(f, " (packet {packet}, offset {offset})"),
+            (Some(packet), None) => write!(f, " (packet {packet})"),
+            (None, Some(offset)) => write!(f, " (offset {offset})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ObserverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ObserverError, ObserverErrorKind};
+    use crate::Error;
+
+    #[test]
+    fn kind_is_derived_from_error() {
+        assert_eq!(
+            ObserverErrorKind::of(&Error::CrcDismatch),
+            ObserverErrorKind::Corrupted
+        );
+        assert_eq!(
+            ObserverErrorKind::of(&Error::NotEnoughData(4)),
+            ObserverErrorKind::Incomplete
+        );
+        assert_eq!(
+            ObserverErrorKind::of(&Error::Io(std::io::Error::other("disk"))),
+            ObserverErrorKind::Io
+        );
+        assert_eq!(
+            ObserverErrorKind::of(&Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "bincode"
+            ))),
+            ObserverErrorKind::Decode
+        );
+        assert_eq!(
+            ObserverErrorKind::of(&Error::ObserverOverflow(1)),
+            ObserverErrorKind::Overflow
+        );
+        assert_eq!(
+            ObserverErrorKind::of(&Error::Test),
+            ObserverErrorKind::Other
+        );
+    }
+
+    #[test]
+    fn observer_error_keeps_location_and_source() {
+        let err = ObserverError::new(Error::CrcDismatch)
+            .at_packet(3)
+            .at_offset(128);
+        assert_eq!(err.kind, ObserverErrorKind::Corrupted);
+        assert_eq!(err.packet, Some(3));
+        assert_eq!(err.offset, Some(128));
+        assert!(matches!(*err.error, Error::CrcDismatch));
+        assert_eq!(
+            err.to_string(),
+            format!("{} (packet 3, offset 128)", Error::CrcDismatch)
+        );
+
+        let copy = err.clone();
+        assert!(std::sync::Arc::ptr_eq(&copy.error, &err.error));
+        assert!(std::error::Error::source(&copy).is_some());
+        assert_eq!(
+            ObserverError::from(Error::Test).to_string(),
+            Error::Test.to_string()
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    marker::PhantomData,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+use tokio::{
+    select,
+    task::{self, JoinHandle},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use crate::*;
+
+use super::delivery::Delivery;
+
+/// Asynchronous observer that tails a storage file and forwards packets to a subscription.
+pub struct FileObserverDef<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    handler: Option<JoinHandle<()>>,
+    sd: CancellationToken,
+    cursor: Arc<AtomicUsize>,
+    _phantom: PhantomData<(B, BR, P, Inner, O)>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> FileObserverDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Creates observer with explicit protocol context options.
+    pub fn with_opt<S>(
+        mut options: FileObserverOptions<B, BR, P, Inner, S, O>,
+        opt: O,
+    ) -> Result<Self, Error>
+    where
+        S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    {
+        let Some(subscription) = options.subscription.take() else {
+            return Err(Error::NoSubscription);
+        };
+        let sd = CancellationToken::new();
+        let shutdown = sd.clone();
+        let start = options.start;
+        let cursor = Arc::new(AtomicUsize::new(0));
+        let file = std::fs::File::open(&options.path)?;
+        let mut reader: ReaderDef<std::fs::File, B, BR, P, Inner> =
+            ReaderDef::new(file.try_clone()?)?;
+        for rule in options.rules.drain(..) {
+            reader.add_rule(rule)?;
+        }
+        let mut delivery = Delivery {
+            reader,
+            subscription,
+            last: 0,
+            cursor: cursor.clone(),
+            max_batch: options.max_batch.max(1),
+            opt,
+        };
+
+        let (sensor, mut wake_rx) = Sensor::with_mode(&options.path, options.sensor)?;
+        // Bind the sensor to the opened file and the packets already written to it
+        let (written, processed) = delivery.reader.written()?;
+        sensor.reset(&file, processed)?;
+        let path = options.path.clone();
+        let reopen = options.reopen;
+
+        let handler = task::spawn(async move {
+            let mut stop_reason: Option<ObserverError> = None;
+            let mut count = written;
+            delivery.last = start.resolve(count);
+            delivery.cursor.store(delivery.last, Ordering::SeqCst);
+            delivery.subscription.on_cursor(delivery.last);
+            if matches!(
+                delivery
+                    .subscription
+                    .on_update(count, count.saturating_sub(delivery.last)),
+                SubscriptionUpdate::Read
+            ) {
+                // Load first existed
+                let Some((done, result)) = delivery.deliver_blocking(count).await else {
+                    return;
+                };
+                delivery = done;
+                if let Err(err) = result {
+                    delivery.subscription.on_stopped(Some(err));
+                    return;
+                }
+            }
+            loop {
+                let wake = select! {
+                    biased;
+                    _ = shutdown.cancelled() => {
+                        debug!("Cancel signal has been gotten");
+                        delivery.subscription.on_aborted();
+                        return;
+                    }
+                    wake = wake_rx.recv() => {
+                        let Some(wake) = wake else {
+                            break;
+                        };
+                        wake
+                    }
+                };
+                if wake.change != FileChange::Grown {
+                    if wake.change == FileChange::Truncated {
+                        delivery.subscription.on_truncated();
+                    } else {
+                        delivery.subscription.on_replaced();
+                    }
+                    if !reopen {
+                        break;
+                    }
+                    count = match delivery.reopen(&path, &sensor) {
+                        Ok(count) => count,
+                        Err(err) => {
+                            let err = ObserverError::new(err);
+                            let _ = delivery.subscription.on_error(&err);
+                            stop_reason = Some(err);
+                            break;
+                        }
+                    };
+                    if !matches!(
+                        delivery.subscription.on_update(count, count),
+                        SubscriptionUpdate::Read
+                    ) {
+                        continue;
+                    }
+                    let Some((done, result)) = delivery.deliver_blocking(count).await else {
+                        return;
+                    };
+                    delivery = done;
+                    if let Err(err) = result {
+                        stop_reason = Some(err);
+                        break;
+                    }
+                    continue;
+                }
+                let (written, processed) = match delivery.reload() {
+                    Ok(written) => written,
+                    Err(Error::NotEnoughData(_)) => {
+                        continue;
+                    }
+                    Err(err) => {
+                        let err = ObserverError::new(err).at_offset(delivery.reader.get_offset());
+                        let _ = delivery.subscription.on_error(&err);
+                        stop_reason = Some(err);
+                        break;
+                    }
+                };
+                if let Err(err) = sensor.processed(processed) {
+                    let err = ObserverError::new(err.into());
+                    let _ = delivery.subscription.on_error(&err);
+                    stop_reason = Some(err);
+                    break;
+                }
+                if written <= count {
+                    continue;
+                }
+                let added = written - count;
+                count = written;
+                if !matches!(
+                    delivery.subscription.on_update(count, added),
+                    SubscriptionUpdate::Read
+                ) {
+                    continue;
+                }
+                let Some((done, result)) = delivery.deliver_blocking(count).await else {
+                    return;
+                };
+                delivery = done;
+                if let Err(err) = result {
+                    stop_reason = Some(err);
+                    break;
+                }
+            }
+            debug!("sensor loop is closed");
+            delivery.subscription.on_stopped(stop_reason);
+            drop(sensor);
+        });
+        Ok(Self {
+            handler: Some(handler),
+            sd,
+            cursor,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns the global index of the next packet the observer will check.
+    ///
+    /// All packets before the cursor have been delivered (or skipped by rules). The value
+    /// can be persisted and passed back via [`StartPosition::Index`] to resume observation
+    /// later. Note: the cursor moves right before `on_packet()` is called; use
+    /// [`SubscriptionDef::on_cursor`] to get it in sync with delivered packets.
+    pub fn cursor(&self) -> usize {
+        self.cursor.load(Ordering::SeqCst)
+    }
+
+    /// Creates observer using default protocol context options.
+    pub fn new<S>(options: FileObserverOptions<B, BR, P, Inner, S, O>) -> Result<Self, Error>
+    where
+        S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+        O: Default,
+    {
+        Self::with_opt(options, O::default())
+    }
+
+    /// Requests graceful shutdown and waits for observer task completion.
+    pub async fn shutdown(&mut self) {
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+        if !handler.is_finished() {
+            self.sd.cancel();
+        }
+        let _ = handler.await;
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    O: Send + Sync + 'static,
+> Delivery<B, BR, P, Inner, S, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Runs [`Delivery::deliver`] on the blocking thread pool.
+    ///
+    /// Reading packets is blocking I/O and a subscription is allowed to block in
+    /// `on_packets()` (for example, waiting for a bounded channel to get free space), so
+    /// neither should hold up the runtime. Returns `None` if delivery panicked.
+    async fn deliver_blocking(mut self, count: usize) -> Option<(Self, Result<(), ObserverError>)> {
+        match task::spawn_blocking(move || {
+            let result = self.deliver(count);
+            (self, result)
+        })
+        .await
+        {
+            Ok(done) => Some(done),
+            Err(err) => {
+                error!("Observer delivery has failed: {err}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DefaultProtocolContext, Error, FileObserverDef, FileObserverOptions, ObserverError,
+        PacketDef, SubscriptionDef, SubscriptionErrorAction, SubscriptionUpdate,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+    use tempfile::NamedTempFile;
+
+    struct CountingSubscription {
+        updates: Arc<AtomicUsize>,
+        stopped: Arc<AtomicUsize>,
+        aborted: Arc<AtomicUsize>,
+    }
+
+    impl SubscriptionDef<TestBlock, TestBlock, TestPayload, TestPayload, DefaultProtocolContext>
+        for CountingSubscription
+    {
+        fn on_update(&mut self, _total: usize, _added: usize) -> SubscriptionUpdate {
+            self.updates.fetch_add(1, Ordering::SeqCst);
+            SubscriptionUpdate::Skip
+        }
+
+        fn on_packet(&mut self, _packet: PacketDef<TestBlock, TestPayload, TestPayload>) {}
+
+        fn on_error(&mut self, _err: &ObserverError) -> SubscriptionErrorAction {
+            SubscriptionErrorAction::Continue
+        }
+
+        fn on_stopped(&mut self, _reason: Option<ObserverError>) {
+            self.stopped.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_aborted(&mut self) {
+            self.aborted.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn counting_subscription_callbacks_update_counters_and_actions() {
+        let updates = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let aborted = Arc::new(AtomicUsize::new(0));
+
+        let mut subscription = CountingSubscription {
+            updates: updates.clone(),
+            stopped: stopped.clone(),
+            aborted: aborted.clone(),
+        };
+
+        assert!(matches!(
+            subscription.on_update(10, 3),
+            SubscriptionUpdate::Skip
+        ));
+        assert_eq!(updates.load(Ordering::SeqCst), 1);
+
+        let action = subscription.on_error(&Error::Test.into());
+        assert!(matches!(action, SubscriptionErrorAction::Continue));
+
+        subscription.on_packet(PacketDef::default());
+        subscription.on_stopped(None);
+        subscription.on_aborted();
+
+        assert_eq!(stopped.load(Ordering::SeqCst), 1);
+        assert_eq!(aborted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn observer_with_opt_returns_no_subscription_error() {
+        let file = NamedTempFile::new().expect("temp file");
+        let options = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            CountingSubscription,
+            DefaultProtocolContext,
+        >::new(file.path());
+
+        let result = FileObserverDef::with_opt(options, ());
+        assert!(matches!(result, Err(Error::NoSubscription)));
+    }
+
+    #[test]
+    fn observer_with_opt_returns_io_error_for_missing_file() {
+        let updates = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let aborted = Arc::new(AtomicUsize::new(0));
+
+        let subscription = CountingSubscription {
+            updates,
+            stopped,
+            aborted,
+        };
+
+        let missing = "/tmp/brec-observer-missing-file-for-tests.bin";
+        let options = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            CountingSubscription,
+            DefaultProtocolContext,
+        >::new(missing)
+        .subscribe(subscription);
+
+        let result = FileObserverDef::with_opt(options, ());
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn observer_new_shutdown_is_idempotent_and_emits_lifecycle_callbacks() {
+        let file = NamedTempFile::new().expect("temp file");
+        let updates = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let aborted = Arc::new(AtomicUsize::new(0));
+
+        let subscription = CountingSubscription {
+            updates: updates.clone(),
+            stopped: stopped.clone(),
+            aborted: aborted.clone(),
+        };
+
+        let options = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            CountingSubscription,
+            DefaultProtocolContext,
+        >::new(file.path())
+        .subscribe(subscription);
+
+        let mut observer =
+            FileObserverDef::new(options).expect("observer must be created for existing file");
+
+        observer.shutdown().await;
+        observer.shutdown().await;
+
+        assert!(
+            updates.load(Ordering::SeqCst) >= 1,
+            "at least one on_update call is expected"
+        );
+        assert!(
+            stopped.load(Ordering::SeqCst) + aborted.load(Ordering::SeqCst) >= 1,
+            "observer should report terminal lifecycle callback"
+        );
+    }
+}
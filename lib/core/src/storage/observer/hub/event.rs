@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crate::*;
+
+use super::super::stream::QueueMessage;
+
+/// Packet delivered by [`ObserverHubDef`].
+///
+/// The hub decodes each packet once and shares it between all subscribers which accepted it.
+pub type SharedPacketDef<B, P, Inner> = Arc<PacketDef<B, P, Inner>>;
+
+/// Identifies a subscriber attached to [`ObserverHubDef`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HubSubscriberId(pub(super) usize);
+
+/// Events delivered to subscribers of [`ObserverHubDef`].
+///
+/// Mirrors [`FileObserverEvent`]; packets are shared between subscribers instead of being
+/// owned by each of them.
+pub enum HubEvent<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> {
+    /// New packets became available for the subscriber.
+    ///
+    /// `total` is the total number of packets currently visible in storage, `added` is the
+    /// number of packets the subscriber hasn't checked yet.
+    Update { total: usize, added: usize },
+
+    /// A packet accepted by the subscriber's rules.
+    Packet(SharedPacketDef<B, P, Inner>),
+
+    /// The given number of packets was dropped because the subscriber didn't keep up.
+    ///
+    /// Emitted only for a subscriber with a bounded channel and [`OverflowPolicy::DropOldest`];
+    /// reported right before the next event.
+    Lagged(usize),
+
+    /// The observed file became shorter than already processed data.
+    Truncated,
+
+    /// The observed path points to another file (the file was deleted and created again,
+    /// or rotated).
+    Replaced,
+
+    /// A non-terminal error, e.g. a packet which cannot be parsed.
+    Error(ObserverError),
+
+    /// The subscriber was stopped.
+    ///
+    /// `None` means a normal stop of the hub. `Some(error)` means the hub could not continue
+    /// because of a terminal failure (the same error is shared by all subscribers) or the
+    /// subscriber was detached because its channel overflowed.
+    Stopped(Option<ObserverError>),
+
+    /// The hub was explicitly aborted by shutdown.
+    Aborted,
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> Clone for HubEvent<B, P, Inner>
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Update { total, added } => Self::Update {
+                total: *total,
+                added: *added,
+            },
+            Self::Packet(packet) => Self::Packet(packet.clone()),
+            Self::Lagged(dropped) => Self::Lagged(*dropped),
+            Self::Truncated => Self::Truncated,
+            Self::Replaced => Self::Replaced,
+            Self::Error(err) => Self::Error(err.clone()),
+            Self::Stopped(reason) => Self::Stopped(reason.clone()),
+            Self::Aborted => Self::Aborted,
+        }
+    }
+}
+
+/// Message sent from the hub task to a subscriber.
+///
+/// Cursor updates travel through the same channel as events, so the subscriber's cursor
+/// stays in sync with the events already taken.
+pub(super) enum HubMessage<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> {
+    Event(HubEvent<B, P, Inner>),
+    Cursor(usize),
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> QueueMessage for HubMessage<B, P, Inner>
+{
+    fn is_packet(&self) -> bool {
+        matches!(self, HubMessage::Event(HubEvent::Packet(..)))
+    }
+
+    fn lagged(dropped: usize) -> Self {
+        HubMessage::Event(HubEvent::Lagged(dropped))
+    }
+}
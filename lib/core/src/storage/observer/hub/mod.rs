@@ -0,0 +1,904 @@
+mod event;
+mod options;
+mod stream;
+mod subscription;
+
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    marker::PhantomData,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+use tokio::{
+    select,
+    sync::Notify,
+    task::{self, JoinHandle},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use crate::*;
+
+use self::event::HubMessage;
+use super::stream::Queue;
+
+pub use event::{HubEvent, HubSubscriberId, SharedPacketDef};
+pub use options::*;
+pub use stream::HubStreamDef;
+pub use subscription::HubSubscriptionDef;
+
+type SubscriberQueue<B, P, Inner> = Arc<Queue<HubMessage<B, P, Inner>>>;
+
+/// Observer which watches a storage file once and fans packets out to many subscribers.
+///
+/// Every [`FileObserverDef`] opens its own reader and sensor and decodes every packet for
+/// itself. The hub owns a single reader and a single sensor instead: each packet is read once,
+/// checked against the rules of each subscriber and, if at least one subscriber accepts it,
+/// decoded once and shared between them (see [`SharedPacketDef`]).
+///
+/// Subscribers can be attached and detached at any time:
+/// - [`ObserverHubDef::stream`] returns a [`HubStreamDef`] of [`HubEvent`] values
+/// - [`ObserverHubDef::subscribe`] drives a [`HubSubscriptionDef`] on its own thread
+///
+/// Each subscriber has its own rules, start position and channel capacity (see
+/// [`HubSubscriberOptions`]), so a lagging subscriber drops its packets or is detached without
+/// affecting the others (unless it's configured to block the hub).
+///
+/// Like [`FileObserverDef`], the hub is driven by a Tokio task and reads the file with
+/// blocking I/O on the blocking thread pool.
+pub struct ObserverHubDef<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    O: Send + Sync + 'static,
+> {
+    shared: Arc<Shared<B, BR, P, Inner>>,
+    handler: Option<JoinHandle<()>>,
+    sd: CancellationToken,
+    _phantom: PhantomData<O>,
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    O: Send + Sync + 'static,
+> ObserverHubDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Creates a hub with explicit protocol context options.
+    ///
+    /// The hub starts without subscribers; packets are checked as soon as the first
+    /// subscriber is attached.
+    pub fn with_opt(options: ObserverHubOptions, opt: O) -> Result<Self, Error> {
+        let file = std::fs::File::open(&options.path)?;
+        let mut reader: ReaderDef<std::fs::File, B, BR, P, Inner> =
+            ReaderDef::new(file.try_clone()?)?;
+        let (sensor, mut wake_rx) = Sensor::with_mode(&options.path, options.sensor)?;
+        // Bind the sensor to the opened file and the packets already written to it
+        sensor.reset(&file, reader.written()?.1)?;
+        let shared = Arc::new(Shared {
+            registry: Mutex::new(Registry {
+                pending: Vec::new(),
+                queues: HashMap::new(),
+                next_id: 0,
+                stopped: false,
+            }),
+            changed: Notify::new(),
+        });
+        let mut core = HubCore {
+            reader,
+            subscribers: Vec::new(),
+            shared: shared.clone(),
+            opt,
+        };
+        let sd = CancellationToken::new();
+        let shutdown = sd.clone();
+        let path = options.path;
+        let reopen = options.reopen;
+
+        let handler = task::spawn(async move {
+            let shared = core.shared.clone();
+            let stop_reason = loop {
+                let Some((done, result)) = core.process_blocking().await else {
+                    shared.stop();
+                    return;
+                };
+                core = done;
+                if let Err(err) = result {
+                    break Some(err);
+                }
+                let wake = select! {
+                    biased;
+                    _ = shutdown.cancelled() => {
+                        debug!("Cancel signal has been gotten");
+                        core.finish(HubEvent::Aborted);
+                        return;
+                    }
+                    _ = shared.changed.notified() => {
+                        continue;
+                    }
+                    wake = wake_rx.recv() => {
+                        let Some(wake) = wake else {
+                            break None;
+                        };
+                        wake
+                    }
+                };
+                if wake.change != FileChange::Grown {
+                    core.broadcast(if wake.change == FileChange::Truncated {
+                        HubEvent::Truncated
+                    } else {
+                        HubEvent::Replaced
+                    });
+                    if !reopen {
+                        break None;
+                    }
+                    if let Err(err) = core.reopen(&path, &sensor) {
+                        break Some(err);
+                    }
+                    continue;
+                }
+                let processed = match core.reader.reload().and_then(|_| core.reader.written()) {
+                    Ok((_, processed)) => processed,
+                    Err(Error::NotEnoughData(_)) => {
+                        continue;
+                    }
+                    Err(err) => {
+                        break Some(err);
+                    }
+                };
+                if let Err(err) = sensor.processed(processed) {
+                    break Some(Error::from(err));
+                }
+            };
+            debug!("observer hub loop is closed");
+            core.finish(HubEvent::Stopped(stop_reason.map(ObserverError::from)));
+            drop(sensor);
+        });
+        Ok(Self {
+            shared,
+            handler: Some(handler),
+            sd,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Creates a hub using default protocol context options.
+    pub fn new(options: ObserverHubOptions) -> Result<Self, Error>
+    where
+        O: Default,
+    {
+        Self::with_opt(options, O::default())
+    }
+
+    /// Attaches a subscriber and returns the stream of its events.
+    ///
+    /// Dropping the stream detaches the subscriber. Fails with [`Error::HubStopped`] if the hub
+    /// has already stopped.
+    pub fn stream(
+        &self,
+        options: HubSubscriberOptions<B, BR, P, Inner>,
+    ) -> Result<HubStreamDef<B, P, Inner>, Error> {
+        let (id, queue) = self.attach(options)?;
+        Ok(HubStreamDef::new(id, queue))
+    }
+
+    /// Attaches a callback-based subscriber.
+    ///
+    /// Callbacks are called on a dedicated thread. The returned identifier can be passed to
+    /// [`ObserverHubDef::detach`]. Fails with [`Error::HubStopped`] if the hub has already
+    /// stopped.
+    pub fn subscribe<S>(
+        &self,
+        options: HubSubscriberOptions<B, BR, P, Inner>,
+        subscription: S,
+    ) -> Result<HubSubscriberId, Error>
+    where
+        S: HubSubscriptionDef<B, P, Inner>,
+    {
+        let (id, queue) = self.attach(options)?;
+        subscription::dispatch(queue, subscription);
+        Ok(id)
+    }
+
+    /// Detaches a subscriber.
+    ///
+    /// The subscriber gets no more events: pending events are dropped and its stream ends
+    /// (or its thread finishes). Returns `false` if there is no such subscriber.
+    pub fn detach(&self, id: HubSubscriberId) -> bool {
+        let mut registry = self.shared.lock();
+        let Some(queue) = registry.queues.remove(&id) else {
+            return false;
+        };
+        registry.pending.retain(|subscriber| subscriber.id != id);
+        drop(registry);
+        queue.disconnect();
+        queue.close();
+        self.shared.changed.notify_one();
+        true
+    }
+
+    /// Requests graceful shutdown and waits for the hub task completion.
+    ///
+    /// All subscribers receive [`HubEvent::Aborted`].
+    pub async fn shutdown(&mut self) {
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+        if !handler.is_finished() {
+            self.abort();
+        }
+        let _ = handler.await;
+    }
+
+    fn attach(
+        &self,
+        options: HubSubscriberOptions<B, BR, P, Inner>,
+    ) -> Result<(HubSubscriberId, SubscriberQueue<B, P, Inner>), Error> {
+        let mut rules = RulesDef::default();
+        for rule in options.rules {
+            rules.add_rule(rule)?;
+        }
+        let queue = Arc::new(Queue::new(options.capacity));
+        let mut registry = self.shared.lock();
+        if registry.stopped {
+            return Err(Error::HubStopped);
+        }
+        let id = HubSubscriberId(registry.next_id);
+        registry.next_id += 1;
+        registry.queues.insert(id, queue.clone());
+        registry.pending.push(Subscriber {
+            id,
+            rules,
+            start: options.start,
+            next: 0,
+            reported: 0,
+            queue: queue.clone(),
+            stopped: false,
+        });
+        drop(registry);
+        self.shared.changed.notify_one();
+        Ok((id, queue))
+    }
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    O: Send + Sync + 'static,
+> ObserverHubDef<B, BR, P, Inner, O>
+{
+    /// Cancels the hub task; a task blocked by a full channel is let through.
+    fn abort(&self) {
+        for queue in self.shared.lock().queues.values() {
+            queue.release();
+        }
+        self.sd.cancel();
+    }
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    O: Send + Sync + 'static,
+> Drop for ObserverHubDef<B, BR, P, Inner, O>
+{
+    /// Stops the hub task if it wasn't shut down explicitly; subscribers receive
+    /// [`HubEvent::Aborted`].
+    fn drop(&mut self) {
+        if self.handler.is_some() {
+            self.abort();
+        }
+    }
+}
+
+/// State shared between the hub handle and the hub task.
+struct Shared<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> {
+    registry: Mutex<Registry<B, BR, P, Inner>>,
+    /// Notifies the hub task about attached and detached subscribers.
+    changed: Notify,
+}
+
+struct Registry<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> {
+    /// Subscribers which haven't been picked up by the hub task yet.
+    pending: Vec<Subscriber<B, BR, P, Inner>>,
+    /// Queues of attached subscribers; used to detach them and to release them on shutdown.
+    queues: HashMap<HubSubscriberId, SubscriberQueue<B, P, Inner>>,
+    next_id: usize,
+    /// The hub task is finished; no subscribers can be attached anymore.
+    stopped: bool,
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> Shared<B, BR, P, Inner>
+{
+    fn lock(&self) -> MutexGuard<'_, Registry<B, BR, P, Inner>> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Marks the hub as stopped and closes the queues of all subscribers.
+    fn stop(&self) {
+        let mut registry = self.lock();
+        registry.stopped = true;
+        registry.pending.clear();
+        for (_, queue) in registry.queues.drain() {
+            queue.close();
+        }
+    }
+}
+
+/// Subscriber state owned by the hub task.
+struct Subscriber<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> {
+    id: HubSubscriberId,
+    rules: RulesDef<B, BR, P, Inner>,
+    start: StartPosition,
+    /// Index of the next packet to check.
+    next: usize,
+    /// Cursor last sent to the subscriber.
+    reported: usize,
+    queue: SubscriberQueue<B, P, Inner>,
+    /// The subscriber overflowed its channel and has to be dropped.
+    stopped: bool,
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> Subscriber<B, BR, P, Inner>
+{
+    fn send(&self, event: HubEvent<B, P, Inner>) {
+        self.queue.send(HubMessage::Event(event));
+    }
+
+    /// Sends the cursor if it has changed since the last report.
+    fn report(&mut self, cursor: usize) {
+        if self.reported != cursor {
+            self.reported = cursor;
+            self.queue.send(HubMessage::Cursor(cursor));
+        }
+    }
+
+    /// Sends a packet, applying the subscriber's overflow policy.
+    ///
+    /// A subscriber which overflowed with [`OverflowPolicy::StopWithError`] gets
+    /// [`HubEvent::Stopped`] and is dropped by the hub.
+    fn send_packet(&mut self, nth: usize, packet: SharedPacketDef<B, P, Inner>) {
+        if let Err(err) = self
+            .queue
+            .send_packet(HubMessage::Event(HubEvent::Packet(packet)))
+        {
+            self.send(HubEvent::Stopped(Some(
+                ObserverError::new(err).at_packet(nth),
+            )));
+            self.stopped = true;
+        }
+    }
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> Drop for Subscriber<B, BR, P, Inner>
+{
+    /// Closes the queue, so the subscriber's stream ends once all pending events are taken.
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+/// Hub state owned by the hub task.
+struct HubCore<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    O: Send + Sync + 'static,
+> {
+    reader: ReaderDef<std::fs::File, B, BR, P, Inner>,
+    subscribers: Vec<Subscriber<B, BR, P, Inner>>,
+    shared: Arc<Shared<B, BR, P, Inner>>,
+    opt: O,
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    O: Send + Sync + 'static,
+> HubCore<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Picks up attached subscribers and drops stopped and detached ones.
+    ///
+    /// `count` is the number of packets completely written to the file; it resolves the start
+    /// position of new subscribers.
+    fn sync_subscribers(&mut self, count: usize) {
+        let mut registry = self.shared.lock();
+        for mut subscriber in registry.pending.drain(..) {
+            subscriber.next = subscriber.start.resolve(count);
+            subscriber.reported = subscriber.next;
+            subscriber.queue.send(HubMessage::Cursor(subscriber.next));
+            self.subscribers.push(subscriber);
+        }
+        self.subscribers.retain(|subscriber| {
+            let keep = !subscriber.stopped && !subscriber.queue.is_disconnected();
+            if !keep {
+                registry.queues.remove(&subscriber.id);
+            }
+            keep
+        });
+    }
+
+    /// Delivers packets available in storage to the subscribers which haven't checked them yet.
+    ///
+    /// Returns `Err` only on a failure of the storage access; errors related to a single
+    /// packet are reported to the subscribers as [`HubEvent::Error`].
+    fn process(&mut self) -> Result<(), Error> {
+        let (count, _) = self.reader.written()?;
+        self.sync_subscribers(count);
+        for subscriber in self.subscribers.iter().filter(|sub| sub.next < count) {
+            subscriber.send(HubEvent::Update {
+                total: count,
+                added: count - subscriber.next,
+            });
+        }
+        let from = self
+            .subscribers
+            .iter()
+            .map(|subscriber| subscriber.next)
+            .min()
+            .unwrap_or(count);
+        for nth in from..count {
+            self.fan_out(nth)?;
+        }
+        for subscriber in self.subscribers.iter_mut() {
+            let next = subscriber.next;
+            subscriber.report(next);
+        }
+        Ok(())
+    }
+
+    /// Checks the `nth` packet against the rules of the subscribers which haven't checked it
+    /// yet; if any of them accepts it, decodes the packet once and sends it to them.
+    ///
+    /// Subscribers with different `Verify` rules may get different signature statuses of the
+    /// same packet; the packet is decoded once per distinct status, so each subscriber gets
+    /// the status produced by its own rules.
+    fn fan_out(&mut self, nth: usize) -> Result<(), Error> {
+        let mut candidates = Vec::new();
+        for (index, subscriber) in self.subscribers.iter_mut().enumerate() {
+            if !subscriber.stopped && subscriber.next <= nth {
+                subscriber.next = nth + 1;
+                candidates.push(index);
+            }
+        }
+        if candidates.is_empty() {
+            return Ok(());
+        }
+        let Some(raw) = self.reader.nth_raw(nth)? else {
+            return Ok(());
+        };
+        let offset = self.reader.nth_offset(nth);
+        // Prefilter and payload filter don't need a decoded packet
+        let mut accepted: Vec<(SignatureStatus, Vec<usize>)> = Vec::new();
+        for index in candidates {
+            let subscriber = &self.subscribers[index];
+            match PacketDef::<B, P, Inner>::prematches(&mut Cursor::new(&raw), &subscriber.rules) {
+                Ok(LookInStatus::Accepted(_, signature)) => {
+                    match accepted.iter_mut().find(|(status, _)| *status == signature) {
+                        Some((_, indices)) => indices.push(index),
+                        None => accepted.push((signature, vec![index])),
+                    }
+                }
+                Ok(LookInStatus::Denied(..)) => {}
+                Ok(LookInStatus::NotEnoughData(needed)) => subscriber.send(HubEvent::Error(
+                    located(Error::NotEnoughData(needed), nth, offset),
+                )),
+                Err(err) => subscriber.send(HubEvent::Error(located(err, nth, offset))),
+            }
+        }
+        for (signature, indices) in accepted {
+            self.deliver(nth, offset, &raw, signature, indices)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes the `nth` packet and sends it to the given subscribers, which accepted it with
+    /// the same signature status.
+    fn deliver(
+        &mut self,
+        nth: usize,
+        offset: Option<u64>,
+        raw: &[u8],
+        signature: SignatureStatus,
+        accepted: Vec<usize>,
+    ) -> Result<(), Error> {
+        // An undecryptable packet is kept if one of the subscribers wants it; the policy of
+        // each subscriber is applied to the decoded packet
+        let mut rules = RulesDef::<B, BR, P, Inner>::default();
+        if accepted.iter().any(|index| {
+            self.subscribers[*index].rules.undecryptable() != UndecryptablePolicy::Fail
+        }) {
+            rules.add_rule(RuleDef::Undecryptable(UndecryptablePolicy::Keep))?;
+        }
+        let (err, failed) = match PacketDef::<B, P, Inner>::filtered(
+            &mut Cursor::new(raw),
+            &rules,
+            &mut self.opt,
+        ) {
+            Ok(LookInStatus::Accepted(_, mut packet)) => {
+                // The packet is decoded without a `Verify` rule; the status comes from the
+                // rules of the subscribers
+                packet.set_signature(signature);
+                let packet = Arc::new(packet);
+                let mut failed = Vec::new();
+                for index in accepted {
+                    let subscriber = &mut self.subscribers[index];
+                    if packet.undecryptable().is_some() {
+                        match subscriber.rules.undecryptable() {
+                            UndecryptablePolicy::Fail => {
+                                failed.push(index);
+                                continue;
+                            }
+                            UndecryptablePolicy::Skip => continue,
+                            UndecryptablePolicy::Keep => {}
+                        }
+                    }
+                    if subscriber.rules.filter_packet(&packet) {
+                        subscriber.report(nth + 1);
+                        subscriber.send_packet(nth, packet.clone());
+                    }
+                }
+                if failed.is_empty() {
+                    return Ok(());
+                }
+                // The decryption error isn't kept with the shared packet; decoding the packet
+                // without the policy reproduces it
+                match PacketDef::<B, P, Inner>::filtered(
+                    &mut Cursor::new(raw),
+                    &RulesDef::<B, BR, P, Inner>::default(),
+                    &mut self.opt,
+                ) {
+                    Err(err) => (err, failed),
+                    Ok(..) => return Ok(()),
+                }
+            }
+            Ok(LookInStatus::Denied(..)) => {
+                return Ok(());
+            }
+            Ok(LookInStatus::NotEnoughData(needed)) => (Error::NotEnoughData(needed), accepted),
+            Err(err) => (err, accepted),
+        };
+        let err = located(err, nth, offset);
+        for index in failed {
+            self.subscribers[index].send(HubEvent::Error(err.clone()));
+        }
+        Ok(())
+    }
+
+    /// Runs [`HubCore::process`] on the blocking thread pool.
+    ///
+    /// Returns `None` if processing panicked.
+    async fn process_blocking(mut self) -> Option<(Self, Result<(), Error>)> {
+        match task::spawn_blocking(move || {
+            let result = self.process();
+            (self, result)
+        })
+        .await
+        {
+            Ok(done) => Some(done),
+            Err(err) => {
+                error!("Observer hub processing has failed: {err}");
+                None
+            }
+        }
+    }
+
+    /// Reopens the file at `path`; all subscribers restart from its first packet.
+    fn reopen(&mut self, path: &Path, sensor: &Sensor) -> Result<(), Error> {
+        let file = std::fs::File::open(path)?;
+        self.reader.reopen(file.try_clone()?)?;
+        sensor.reset(&file, self.reader.written()?.1)?;
+        for subscriber in self.subscribers.iter_mut() {
+            subscriber.next = 0;
+            subscriber.report(0);
+        }
+        Ok(())
+    }
+
+    /// Sends an event to all active subscribers.
+    fn broadcast(&self, event: HubEvent<B, P, Inner>) {
+        for subscriber in self.subscribers.iter().filter(|sub| !sub.stopped) {
+            subscriber.send(event.clone());
+        }
+    }
+
+    /// Sends the final event to all subscribers (including the ones which haven't been picked
+    /// up yet) and closes their queues; no subscribers can be attached afterwards.
+    fn finish(mut self, event: HubEvent<B, P, Inner>) {
+        self.broadcast(event.clone());
+        for subscriber in self.shared.lock().pending.iter() {
+            subscriber.send(event.clone());
+        }
+        self.subscribers.clear();
+        self.shared.stop();
+    }
+}
+
+/// Binds an error of reading the `nth` packet to its location in the file.
+fn located(err: Error, nth: usize, offset: Option<u64>) -> ObserverError {
+    let mut err = ObserverError::new(err).at_packet(nth);
+    err.offset = offset;
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DefaultProtocolContext, Error, HubEvent, HubStreamDef, HubSubscriberOptions,
+        HubSubscriptionDef, ObserverError, ObserverErrorKind, ObserverHubDef, ObserverHubOptions,
+        OverflowPolicy, PacketDef, RuleDef, RuleFnDef, SharedPacketDef, StartPosition, WriterDef,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::{
+        fs::File,
+        sync::{Arc, mpsc},
+        time::Duration,
+    };
+    use tempfile::NamedTempFile;
+    use tokio::time::timeout;
+    use tokio_stream::StreamExt;
+
+    type Packet = PacketDef<TestBlock, TestPayload, TestPayload>;
+    type Hub =
+        ObserverHubDef<TestBlock, TestBlock, TestPayload, TestPayload, DefaultProtocolContext>;
+    type Stream = HubStreamDef<TestBlock, TestPayload, TestPayload>;
+    type Options = HubSubscriberOptions<TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    fn write_packets(file: &mut File, count: usize) {
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(file).expect("writer");
+        for _ in 0..count {
+            writer
+                .insert(Packet::default(), &mut ())
+                .expect("packet written");
+        }
+    }
+
+    async fn next_event(
+        stream: &mut Stream,
+    ) -> Option<HubEvent<TestBlock, TestPayload, TestPayload>> {
+        timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("event in time")
+    }
+
+    async fn read_packets(
+        stream: &mut Stream,
+        count: usize,
+    ) -> Vec<SharedPacketDef<TestBlock, TestPayload, TestPayload>> {
+        let mut packets = Vec::new();
+        while packets.len() < count {
+            match next_event(stream).await.expect("stream must not end") {
+                HubEvent::Packet(packet) => packets.push(packet),
+                HubEvent::Update { .. } => {}
+                _ => panic!("unexpected event"),
+            }
+        }
+        packets
+    }
+
+    #[tokio::test]
+    async fn hub_shares_decoded_packets_between_subscribers() {
+        let file = NamedTempFile::new().expect("temp file");
+        write_packets(&mut file.reopen().expect("reopen"), 3);
+
+        let mut hub = Hub::new(ObserverHubOptions::new(file.path())).expect("hub created");
+        let mut all = hub.stream(Options::new()).expect("subscriber attached");
+        let mut none = hub
+            .stream(Options::new().with_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| false))))
+            .expect("subscriber attached");
+        let mut decoded = hub
+            .stream(Options::new().with_rule(RuleDef::FilterPacket(RuleFnDef::Static(|_| true))))
+            .expect("subscriber attached");
+
+        let first = read_packets(&mut all, 3).await;
+        let second = read_packets(&mut decoded, 3).await;
+        for (left, right) in first.iter().zip(second.iter()) {
+            assert!(Arc::ptr_eq(left, right));
+        }
+        assert_eq!(all.cursor(), 3);
+        assert_eq!(decoded.cursor(), 3);
+
+        hub.shutdown().await;
+        let mut rest = Vec::new();
+        while let Some(event) = next_event(&mut none).await {
+            rest.push(event);
+        }
+        assert!(
+            !rest
+                .iter()
+                .any(|event| matches!(event, HubEvent::Packet(..)))
+        );
+        assert!(matches!(rest.last(), Some(HubEvent::Aborted)));
+        assert_eq!(none.cursor(), 3);
+        assert!(matches!(hub.stream(Options::new()), Err(Error::HubStopped)));
+    }
+
+    #[tokio::test]
+    async fn hub_attaches_and_detaches_subscribers_at_runtime() {
+        let file = NamedTempFile::new().expect("temp file");
+        let mut wfile = file.reopen().expect("reopen");
+        write_packets(&mut wfile, 2);
+
+        let mut hub = Hub::new(ObserverHubOptions::new(file.path())).expect("hub created");
+        let mut first = hub.stream(Options::new()).expect("subscriber attached");
+        read_packets(&mut first, 2).await;
+
+        let mut late = hub
+            .stream(Options::new().start_at(StartPosition::End))
+            .expect("subscriber attached");
+        // Let the hub pick the subscriber up before new packets are written
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        write_packets(&mut wfile, 1);
+        assert_eq!(read_packets(&mut late, 1).await.len(), 1);
+        assert_eq!(late.cursor(), 3);
+        read_packets(&mut first, 1).await;
+
+        assert!(hub.detach(late.id()));
+        assert!(!hub.detach(late.id()));
+        assert!(next_event(&mut late).await.is_none());
+
+        write_packets(&mut wfile, 1);
+        read_packets(&mut first, 1).await;
+        assert_eq!(first.cursor(), 4);
+        hub.shutdown().await;
+    }
+
+    #[cfg(feature = "sign")]
+    #[tokio::test]
+    async fn hub_keeps_signature_status_of_each_subscriber() {
+        use crate::{
+            SignatureStatus,
+            sign::{PacketSigner, PacketVerifier},
+        };
+
+        let signer = PacketSigner::new("writer", [7u8; 32]).expect("signer");
+        let other = PacketSigner::new("other", [9u8; 32]).expect("other signer");
+        let file = NamedTempFile::new().expect("temp file");
+        WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(file.reopen().expect("reopen"))
+            .expect("writer")
+            .with_signer(signer.clone())
+            .insert(Packet::default(), &mut ())
+            .expect("packet written");
+        let verify = |key: [u8; 32]| {
+            RuleDef::Verify(Box::new(
+                PacketVerifier::new()
+                    .with_key("writer", key)
+                    .expect("verifier"),
+            ))
+        };
+        // Sees the status of its own verifier
+        let verified_only = RuleDef::FilterPacket(RuleFnDef::Static(|packet: &Packet| {
+            matches!(packet.signature(), SignatureStatus::Verified(_))
+        }));
+
+        let mut hub = Hub::new(ObserverHubOptions::new(file.path())).expect("hub created");
+        let mut good = hub
+            .stream(
+                Options::new()
+                    .with_rule(verify(signer.public_key()))
+                    .with_rule(verified_only),
+            )
+            .expect("subscriber attached");
+        let mut bad = hub
+            .stream(Options::new().with_rule(verify(other.public_key())))
+            .expect("subscriber attached");
+        let mut unchecked = hub.stream(Options::new()).expect("subscriber attached");
+
+        let writer = b"writer".to_vec();
+        assert_eq!(
+            read_packets(&mut good, 1).await[0].signature(),
+            &SignatureStatus::Verified(writer.clone())
+        );
+        assert_eq!(
+            read_packets(&mut bad, 1).await[0].signature(),
+            &SignatureStatus::Invalid(writer.clone())
+        );
+        assert_eq!(
+            read_packets(&mut unchecked, 1).await[0].signature(),
+            &SignatureStatus::Unchecked(writer)
+        );
+        hub.shutdown().await;
+    }
+
+    struct ChannelSubscription(mpsc::Sender<&'static str>);
+
+    impl HubSubscriptionDef<TestBlock, TestPayload, TestPayload> for ChannelSubscription {
+        fn on_packet(&mut self, _packet: SharedPacketDef<TestBlock, TestPayload, TestPayload>) {
+            let _ = self.0.send("packet");
+        }
+
+        fn on_stopped(&mut self, reason: Option<ObserverError>) {
+            let _ = self.0.send(if reason.is_some() {
+                "stopped with error"
+            } else {
+                "stopped"
+            });
+        }
+
+        fn on_aborted(&mut self) {
+            let _ = self.0.send("aborted");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn hub_detaches_only_overflowed_subscriber() {
+        let file = NamedTempFile::new().expect("temp file");
+        write_packets(&mut file.reopen().expect("reopen"), 4);
+
+        let mut hub = Hub::new(ObserverHubOptions::new(file.path())).expect("hub created");
+        // The stream isn't read until all packets are sent, so its channel overflows
+        let mut overflowed = hub
+            .stream(Options::new().bounded(1, OverflowPolicy::StopWithError))
+            .expect("subscriber attached");
+        let (tx, rx) = mpsc::channel();
+        hub.subscribe(Options::new(), ChannelSubscription(tx))
+            .expect("subscriber attached");
+
+        for _ in 0..4 {
+            let event = rx.recv_timeout(Duration::from_secs(5)).expect("callback");
+            assert_eq!(event, "packet");
+        }
+        let mut events = Vec::new();
+        while let Some(event) = next_event(&mut overflowed).await {
+            events.push(event);
+        }
+        assert!(matches!(
+            events.last(),
+            Some(HubEvent::Stopped(Some(err)))
+                if err.kind == ObserverErrorKind::Overflow
+                    && matches!(*err.error, Error::ObserverOverflow(_))
+        ));
+
+        hub.shutdown().await;
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).expect("callback"),
+            "aborted"
+        );
+    }
+}
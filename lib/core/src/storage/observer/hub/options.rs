@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+/// Builder options for creating [`ObserverHubDef`].
+#[derive(Debug, Clone)]
+pub struct ObserverHubOptions {
+    /// Path to the observed storage file.
+    pub path: PathBuf,
+    /// Reopen the file and restart from its beginning when it's truncated or replaced.
+    pub reopen: bool,
+    /// Defines how the hub detects changes of the file.
+    pub sensor: SensorMode,
+}
+
+impl ObserverHubOptions {
+    /// Creates hub options for a target file path.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            reopen: false,
+            sensor: SensorMode::default(),
+        }
+    }
+
+    /// Sets whether the hub reopens the file when it's truncated or replaced.
+    ///
+    /// If enabled, all subscribers restart from the beginning of the new file. See
+    /// [`FileObserverOptions::reopen`].
+    pub fn reopen(mut self, reopen: bool) -> Self {
+        self.reopen = reopen;
+        self
+    }
+
+    /// Sets how the hub detects changes of the file.
+    ///
+    /// See [`FileObserverOptions::with_sensor`].
+    pub fn with_sensor(mut self, mode: SensorMode) -> Self {
+        self.sensor = mode;
+        self
+    }
+}
+
+/// Options of a single subscriber attached to [`ObserverHubDef`].
+pub struct HubSubscriberOptions<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> {
+    /// Rules of this subscriber; other subscribers are not affected by them.
+    pub rules: Vec<RuleDef<B, BR, P, Inner>>,
+    /// Packet from which the subscriber starts receiving packets.
+    pub start: StartPosition,
+    /// Capacity of the subscriber's channel; defines how the subscriber's lag is handled.
+    pub capacity: ChannelCapacity,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> HubSubscriberOptions<B, BR, P, Inner>
+{
+    /// Creates options of a subscriber which receives all packets from the beginning of the
+    /// file through an unbounded channel.
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            start: StartPosition::Beginning,
+            capacity: ChannelCapacity::Unbounded,
+        }
+    }
+
+    /// Adds a filtering rule (`Prefilter`, `FilterPayload`, `FilterPacket`).
+    ///
+    /// Adding the same kind of rule twice makes attaching fail with [`Error::RuleDuplicate`].
+    pub fn with_rule(mut self, rule: RuleDef<B, BR, P, Inner>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Sets the packet from which the subscriber starts receiving packets.
+    ///
+    /// The position is resolved against the number of packets at the moment the hub picks
+    /// the subscriber up, so [`StartPosition::End`] means "only packets written from now on".
+    pub fn start_at(mut self, position: StartPosition) -> Self {
+        self.start = position;
+        self
+    }
+
+    /// Limits the number of pending packets of the subscriber. `0` is treated as `1`.
+    ///
+    /// With [`OverflowPolicy::Block`] a slow subscriber holds up the whole hub, with
+    /// [`OverflowPolicy::DropOldest`] it loses packets (reported via [`HubEvent::Lagged`]) and
+    /// with [`OverflowPolicy::StopWithError`] it's detached. Other subscribers aren't affected
+    /// by the last two policies.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.capacity = ChannelCapacity::Bounded(capacity.max(1), policy);
+        self
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> Default for HubSubscriberOptions<B, BR, P, Inner>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HubSubscriberOptions, ObserverHubOptions};
+    use crate::{
+        ChannelCapacity, OverflowPolicy, RuleDef, RuleFnDef, SensorMode, StartPosition,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::time::Duration;
+
+    type Options = HubSubscriberOptions<TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    #[test]
+    fn hub_options_builder_sets_fields() {
+        let opts = ObserverHubOptions::new("abc/storage.bin");
+        assert!(opts.path.ends_with("abc/storage.bin"));
+        assert!(!opts.reopen);
+        assert_eq!(opts.sensor, SensorMode::default());
+
+        let opts = opts
+            .reopen(true)
+            .with_sensor(SensorMode::Poll(Duration::from_millis(100)));
+        assert!(opts.reopen);
+        assert_eq!(opts.sensor, SensorMode::Poll(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn subscriber_options_builder_sets_fields() {
+        let opts = Options::default();
+        assert!(opts.rules.is_empty());
+        assert_eq!(opts.start, StartPosition::Beginning);
+        assert_eq!(opts.capacity, ChannelCapacity::Unbounded);
+
+        let opts = opts
+            .with_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| true)))
+            .start_at(StartPosition::End)
+            .bounded(0, OverflowPolicy::DropOldest);
+        assert_eq!(opts.rules.len(), 1);
+        assert_eq!(opts.start, StartPosition::End);
+        assert_eq!(
+            opts.capacity,
+            ChannelCapacity::Bounded(1, OverflowPolicy::DropOldest)
+        );
+    }
+}
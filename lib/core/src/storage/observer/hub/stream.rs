@@ -0,0 +1,91 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use crate::*;
+
+use super::super::stream::Queue;
+use super::event::HubMessage;
+
+/// Stream of [`HubEvent`] values of a single subscriber attached to [`ObserverHubDef`].
+///
+/// Created by [`ObserverHubDef::stream`]. The stream ends after [`HubEvent::Stopped`] or
+/// [`HubEvent::Aborted`], or right away once the subscriber is detached. Dropping the stream
+/// detaches the subscriber.
+pub struct HubStreamDef<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> {
+    id: HubSubscriberId,
+    queue: Arc<Queue<HubMessage<B, P, Inner>>>,
+    cursor: usize,
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> HubStreamDef<B, P, Inner>
+{
+    pub(super) fn new(id: HubSubscriberId, queue: Arc<Queue<HubMessage<B, P, Inner>>>) -> Self {
+        Self {
+            id,
+            queue,
+            cursor: 0,
+        }
+    }
+
+    /// Returns the identifier of the subscriber (see [`ObserverHubDef::detach`]).
+    pub fn id(&self) -> HubSubscriberId {
+        self.id
+    }
+
+    /// Returns the global index of the next packet to be checked for this subscriber.
+    ///
+    /// The cursor is in sync with events already yielded by the stream (see
+    /// [`FileObserverStreamDef::cursor`]).
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> tokio_stream::Stream for HubStreamDef<B, P, Inner>
+{
+    type Item = HubEvent<B, P, Inner>;
+
+    /// Polls the next event of the subscriber.
+    ///
+    /// Cursor updates are consumed internally and never yielded.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.queue.poll_recv(cx) {
+                Poll::Ready(Some(HubMessage::Event(event))) => return Poll::Ready(Some(event)),
+                Poll::Ready(Some(HubMessage::Cursor(cursor))) => {
+                    this.cursor = cursor;
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+> Drop for HubStreamDef<B, P, Inner>
+{
+    /// Disconnects the queue; the hub drops the subscriber on its next update.
+    fn drop(&mut self) {
+        self.queue.disconnect();
+    }
+}
@@ -0,0 +1,111 @@
+use std::{sync::Arc, thread};
+
+use crate::*;
+
+use super::super::stream::Queue;
+use super::event::HubMessage;
+
+/// Defines the callback interface of a subscriber attached to [`ObserverHubDef`] with
+/// [`ObserverHubDef::subscribe`].
+///
+/// Callbacks mirror [`SubscriptionDef`], but packets are shared between subscribers (see
+/// [`SharedPacketDef`]). Each subscription gets its own thread, so a slow subscription holds
+/// up neither the hub nor other subscribers (unless its channel is bounded with
+/// [`OverflowPolicy::Block`]).
+pub trait HubSubscriptionDef<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+>: Send + 'static
+{
+    /// Called when new packets became available for the subscriber.
+    ///
+    /// - `total` is the total number of records currently available in storage.
+    /// - `added` is the number of packets the subscriber hasn't checked yet.
+    fn on_update(&mut self, total: usize, added: usize) {
+        // default implementation
+        let _ = (total, added);
+    }
+
+    /// Called for each packet accepted by the subscriber's rules.
+    fn on_packet(&mut self, packet: SharedPacketDef<B, P, Inner>);
+
+    /// Called when the subscriber's cursor moves (see [`SubscriptionDef::on_cursor`]).
+    fn on_cursor(&mut self, cursor: usize) {
+        // default implementation
+        let _ = cursor;
+    }
+
+    /// Called when packets were dropped because the subscription didn't keep up.
+    fn on_lagged(&mut self, dropped: usize) {
+        // default implementation
+        let _ = dropped;
+    }
+
+    /// Called when the observed file became shorter than already processed data.
+    fn on_truncated(&mut self) {
+        // default implementation
+    }
+
+    /// Called when the observed path points to another file.
+    fn on_replaced(&mut self) {
+        // default implementation
+    }
+
+    /// Called on a non-terminal error (e.g. a packet which cannot be parsed).
+    ///
+    /// Returning [`SubscriptionErrorAction::Stop`] detaches the subscription; no more
+    /// callbacks are called in this case.
+    fn on_error(&mut self, err: &ObserverError) -> SubscriptionErrorAction {
+        // default implementation
+        let _ = err;
+        SubscriptionErrorAction::Continue
+    }
+
+    /// Called when the subscriber is stopped (see [`HubEvent::Stopped`]).
+    fn on_stopped(&mut self, reason: Option<ObserverError>) {
+        // default implementation
+        let _ = reason;
+    }
+
+    /// Called when the hub is aborted by an explicit shutdown signal.
+    fn on_aborted(&mut self) {
+        // default implementation
+    }
+}
+
+/// Starts the thread which takes messages of the subscriber and calls its callbacks.
+///
+/// The thread finishes once the queue is closed (the subscriber is stopped or detached).
+pub(super) fn dispatch<
+    B: BlockDef + Send + Sync + 'static,
+    P: PayloadDef<Inner> + Send + Sync + 'static,
+    Inner: PayloadInnerDef + Send + Sync + 'static,
+    S: HubSubscriptionDef<B, P, Inner>,
+>(
+    queue: Arc<Queue<HubMessage<B, P, Inner>>>,
+    mut subscription: S,
+) {
+    thread::spawn(move || {
+        while let Some(message) = queue.recv() {
+            match message {
+                HubMessage::Cursor(cursor) => subscription.on_cursor(cursor),
+                HubMessage::Event(HubEvent::Update { total, added }) => {
+                    subscription.on_update(total, added);
+                }
+                HubMessage::Event(HubEvent::Packet(packet)) => subscription.on_packet(packet),
+                HubMessage::Event(HubEvent::Lagged(dropped)) => subscription.on_lagged(dropped),
+                HubMessage::Event(HubEvent::Truncated) => subscription.on_truncated(),
+                HubMessage::Event(HubEvent::Replaced) => subscription.on_replaced(),
+                HubMessage::Event(HubEvent::Error(err)) => {
+                    if matches!(subscription.on_error(&err), SubscriptionErrorAction::Stop) {
+                        queue.disconnect();
+                        break;
+                    }
+                }
+                HubMessage::Event(HubEvent::Stopped(reason)) => subscription.on_stopped(reason),
+                HubMessage::Event(HubEvent::Aborted) => subscription.on_aborted(),
+            }
+        }
+    });
+}
@@ -1,3 +1,4 @@
+mod start;
 mod subscription;
 
 use crate::*;
@@ -6,6 +7,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
+pub use start::*;
 pub use subscription::*;
 
 /// Builder options for creating [`FileObserverDef`](crate::storage::FileObserverDef).
@@ -21,6 +23,17 @@ pub struct FileObserverOptions<
     pub path: PathBuf,
     /// Subscriber that receives observer callbacks.
     pub subscription: Option<S>,
+    /// Rules applied by the observer's reader; packets denied by rules are never
+    /// decoded or delivered to the subscription.
+    pub rules: Vec<RuleDef<B, BR, P, Inner>>,
+    /// Packet from which the observer starts delivering packets.
+    pub start: StartPosition,
+    /// Maximum number of packets passed to a single `SubscriptionDef::on_packets` call.
+    pub max_batch: usize,
+    /// Reopen the file and restart from its beginning when it's truncated or replaced.
+    pub reopen: bool,
+    /// Defines how the observer detects changes of the file.
+    pub sensor: SensorMode,
     _phantom: PhantomData<(B, BR, P, Inner, O)>,
 }
 
@@ -38,6 +51,11 @@ impl<
         Self {
             path: path.as_ref().to_path_buf(),
             subscription: None,
+            rules: Vec::new(),
+            start: StartPosition::Beginning,
+            max_batch: 1,
+            reopen: false,
+            sensor: SensorMode::default(),
             _phantom: PhantomData,
         }
     }
@@ -47,6 +65,54 @@ impl<
         self.subscription = Some(subscription);
         self
     }
+
+    /// Adds a filtering rule (`Prefilter`, `FilterPayload`, `FilterPacket`, etc.).
+    ///
+    /// Rules are registered in the observer's reader (see [`ReaderDef::add_rule`]), so
+    /// adding the same kind of rule twice makes observer creation fail with
+    /// [`Error::RuleDuplicate`].
+    pub fn with_rule(mut self, rule: RuleDef<B, BR, P, Inner>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Sets the packet from which the observer starts delivering packets.
+    ///
+    /// By default all existing packets are delivered ([`StartPosition::Beginning`]).
+    pub fn start_at(mut self, position: StartPosition) -> Self {
+        self.start = position;
+        self
+    }
+
+    /// Sets the maximum number of packets delivered with a single
+    /// [`SubscriptionDef::on_packets`] call.
+    ///
+    /// By default packets are delivered one by one. `0` is treated as `1`.
+    pub fn with_max_batch(mut self, max_batch: usize) -> Self {
+        self.max_batch = max_batch.max(1);
+        self
+    }
+
+    /// Sets whether the observer reopens the file when it's truncated or replaced (deleted and
+    /// created again, or rotated).
+    ///
+    /// If enabled, the observer restarts from the beginning of the new file. By default the
+    /// observer stops after reporting the change via [`SubscriptionDef::on_truncated`] or
+    /// [`SubscriptionDef::on_replaced`].
+    pub fn reopen(mut self, reopen: bool) -> Self {
+        self.reopen = reopen;
+        self
+    }
+
+    /// Sets how the observer detects changes of the file.
+    ///
+    /// By default the platform file watcher is used, falling back to polling if the watcher
+    /// cannot be started. Use [`SensorMode::Poll`] for filesystems where the watcher doesn't
+    /// report changes (e.g. network mounts).
+    pub fn with_sensor(mut self, mode: SensorMode) -> Self {
+        self.sensor = mode;
+        self
+    }
 }
 
 impl<
@@ -65,12 +131,13 @@ impl<
 
 #[cfg(test)]
 mod tests {
-    use super::FileObserverOptions;
+    use super::{FileObserverOptions, StartPosition};
     use crate::{
-        DefaultProtocolContext,
+        DefaultProtocolContext, RuleDef, RuleFnDef, SensorMode,
         storage::observer::{SubscriptionDef, SubscriptionUpdate},
         tests::{TestBlock, TestPayload},
     };
+    use std::time::Duration;
 
     struct DummySubscription;
 
@@ -94,6 +161,83 @@ mod tests {
         >::new("abc/storage.bin");
         assert!(opts.path.ends_with("abc/storage.bin"));
         assert!(opts.subscription.is_none());
+        assert!(opts.rules.is_empty());
+        assert_eq!(opts.start, StartPosition::Beginning);
+        assert_eq!(opts.max_batch, 1);
+        assert!(!opts.reopen);
+        assert_eq!(opts.sensor, SensorMode::default());
+    }
+
+    #[test]
+    fn file_observer_options_with_max_batch_sets_size() {
+        let opts = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DummySubscription,
+            DefaultProtocolContext,
+        >::new("x.bin")
+        .with_max_batch(64);
+        assert_eq!(opts.max_batch, 64);
+        assert_eq!(opts.with_max_batch(0).max_batch, 1);
+    }
+
+    #[test]
+    fn file_observer_options_reopen_sets_flag() {
+        let opts = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DummySubscription,
+            DefaultProtocolContext,
+        >::new("x.bin")
+        .reopen(true);
+        assert!(opts.reopen);
+    }
+
+    #[test]
+    fn file_observer_options_with_sensor_sets_mode() {
+        let opts = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DummySubscription,
+            DefaultProtocolContext,
+        >::new("x.bin")
+        .with_sensor(SensorMode::Poll(Duration::from_secs(1)));
+        assert_eq!(opts.sensor, SensorMode::Poll(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn file_observer_options_start_at_sets_position() {
+        let opts = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DummySubscription,
+            DefaultProtocolContext,
+        >::new("x.bin")
+        .start_at(StartPosition::Index(42));
+        assert_eq!(opts.start, StartPosition::Index(42));
+    }
+
+    #[test]
+    fn file_observer_options_with_rule_collects_rules() {
+        let opts = FileObserverOptions::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DummySubscription,
+            DefaultProtocolContext,
+        >::new("x.bin")
+        .with_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| true)))
+        .with_rule(RuleDef::FilterPayload(RuleFnDef::Static(|_| true)));
+        assert_eq!(opts.rules.len(), 2);
     }
 
     #[test]
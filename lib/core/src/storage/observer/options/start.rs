@@ -0,0 +1,41 @@
+/// Defines the packet from which [`FileObserverDef`](crate::storage::FileObserverDef)
+/// starts delivering packets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StartPosition {
+    /// Deliver all packets which already exist in storage.
+    #[default]
+    Beginning,
+    /// Skip existing packets and deliver only packets written after the observer has started.
+    End,
+    /// Start from the packet with the given global index.
+    ///
+    /// Typically this is a cursor saved by a previous observer (see `on_cursor`), which allows
+    /// to resume observation without duplicates or gaps. If storage has fewer packets, the
+    /// observer waits until the packet with this index is written.
+    Index(usize),
+}
+
+impl StartPosition {
+    /// Returns the index of the first packet to deliver for storage with `count` packets.
+    pub fn resolve(&self, count: usize) -> usize {
+        match self {
+            Self::Beginning => 0,
+            Self::End => count,
+            Self::Index(index) => *index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StartPosition;
+
+    #[test]
+    fn start_position_resolves_first_index() {
+        assert_eq!(StartPosition::default(), StartPosition::Beginning);
+        assert_eq!(StartPosition::Beginning.resolve(10), 0);
+        assert_eq!(StartPosition::End.resolve(10), 10);
+        assert_eq!(StartPosition::Index(3).resolve(10), 3);
+        assert_eq!(StartPosition::Index(30).resolve(10), 30);
+    }
+}
@@ -1,5 +1,3 @@
-use tracing::debug;
-
 use crate::*;
 
 /// Controls how the observer should handle newly detected packets after `on_update`.
@@ -7,7 +5,7 @@ use crate::*;
 pub enum SubscriptionUpdate {
     /// Skip reading newly available packets for this update cycle.
     Skip,
-    /// Read newly available packets and deliver them via `on_packet`.
+    /// Read newly available packets and deliver them via `on_packets`.
     Read,
 }
 
@@ -38,10 +36,10 @@ pub trait SubscriptionDef<
     /// - `added` is the number of packets that became available since the last check.
     ///
     /// If this method returns [`SubscriptionUpdate::Skip`], newly available
-    /// packets will not be read and `on_packet()` will not be called.
+    /// packets will not be read and `on_packets()` will not be called.
     ///
-    /// If it returns [`SubscriptionUpdate::Read`], `on_packet()` will be called
-    /// once for each available packet.
+    /// If it returns [`SubscriptionUpdate::Read`], available packets will be delivered
+    /// via `on_packets()` (by default, `on_packet()` is called once for each packet).
     fn on_update(&mut self, total: usize, added: usize) -> SubscriptionUpdate;
 
     /// Called when a packet has been successfully read and parsed.
@@ -54,6 +52,54 @@ pub trait SubscriptionDef<
         let _ = packet;
     }
 
+    /// Called with a batch of successfully read and parsed packets.
+    ///
+    /// The batch holds at most `max_batch` packets (see
+    /// [`FileObserverOptions::with_max_batch`]); packets are in storage order. The default
+    /// implementation calls `on_packet()` for each packet of the batch.
+    ///
+    /// Returning `Err` stops the observer; the error is then reported via
+    /// `on_stopped(Some(...))` and the cursor is moved back to the first packet of the batch.
+    fn on_packets(&mut self, packets: Vec<PacketDef<B, P, Inner>>) -> Result<(), Error> {
+        // default implementation
+        for packet in packets {
+            self.on_packet(packet);
+        }
+        Ok(())
+    }
+
+    /// Called when the observer's cursor moves.
+    ///
+    /// `cursor` is the global index of the next packet the observer will check. It's
+    /// reported once when the observer starts, right before `on_packets()` (so it already
+    /// points past the delivered batch) and after packets denied by rules have been skipped.
+    ///
+    /// Persisting this value and passing it back via
+    /// [`StartPosition::Index`] allows a restarted observer to resume without
+    /// duplicates or gaps.
+    fn on_cursor(&mut self, cursor: usize) {
+        // default implementation
+        let _ = cursor;
+    }
+
+    /// Called when the observed file became shorter than already processed data.
+    ///
+    /// If the observer is configured to reopen the file (see [`FileObserverOptions::reopen`]),
+    /// it restarts from the beginning of the file; `on_cursor()` and `on_update()` follow.
+    /// Otherwise the observer stops and `on_stopped(None)` is called.
+    fn on_truncated(&mut self) {
+        // default implementation
+    }
+
+    /// Called when the observed path points to another file (the file was deleted and
+    /// created again, or rotated).
+    ///
+    /// The reaction is the same as for `on_truncated()`: the observer either restarts from
+    /// the beginning of the new file or stops.
+    fn on_replaced(&mut self) {
+        // default implementation
+    }
+
     /// Called whenever the observer encounters an error.
     ///
     /// Some errors, such as failures related to source navigation or reading
@@ -64,16 +110,20 @@ pub trait SubscriptionDef<
     /// [`SubscriptionErrorAction::Continue`] allows the observer to ignore such
     /// an error and continue processing subsequent data.
     ///
-    /// The error is passed by reference so the same terminal error can later be
-    /// reported again via `on_stopped(Some(...))` when the observer cannot
-    /// continue.
+    /// The error carries its category, the index of the failed packet and its byte
+    /// offset (see [`ObserverError`]). It's passed by reference so the same terminal
+    /// error can later be reported again via `on_stopped(Some(...))` when the observer
+    /// cannot continue.
     ///
     /// # Returns
     /// * [`SubscriptionErrorAction::Stop`] to stop the observer.
     /// * [`SubscriptionErrorAction::Continue`] to continue observing.
-    fn on_error(&mut self, err: &Error) -> SubscriptionErrorAction {
+    fn on_error(&mut self, err: &ObserverError) -> SubscriptionErrorAction {
         // default implementation
-        debug!("Error on reading data with observer: {err}");
+        #[cfg(feature = "observer")]
+        tracing::debug!("Error on reading data with observer: {err}");
+        #[cfg(not(feature = "observer"))]
+        let _ = err;
         SubscriptionErrorAction::Continue
     }
 
@@ -81,7 +131,7 @@ pub trait SubscriptionDef<
     ///
     /// `reason` is `None` for a normal stop and `Some(error)` when the observer
     /// cannot continue due to a terminal failure.
-    fn on_stopped(&mut self, reason: Option<Error>) {
+    fn on_stopped(&mut self, reason: Option<ObserverError>) {
         // default implementation
         let _ = reason;
     }
@@ -123,13 +173,42 @@ mod tests {
         assert_eq!(sub.updates, 1);
 
         let packet = PacketDef::<TestBlock, TestPayload, TestPayload>::default();
+        sub.on_cursor(1);
         sub.on_packet(packet);
 
-        let action = sub.on_error(&Error::Test);
+        let action = sub.on_error(&Error::Test.into());
         assert_eq!(action, SubscriptionErrorAction::Continue);
 
+        sub.on_truncated();
+        sub.on_replaced();
         sub.on_stopped(None);
-        sub.on_stopped(Some(Error::Test));
+        sub.on_stopped(Some(Error::Test.into()));
         sub.on_aborted();
     }
+
+    struct PacketCounter {
+        packets: usize,
+    }
+
+    impl SubscriptionDef<TestBlock, TestBlock, TestPayload, TestPayload, DefaultProtocolContext>
+        for PacketCounter
+    {
+        fn on_update(&mut self, _total: usize, _added: usize) -> SubscriptionUpdate {
+            SubscriptionUpdate::Read
+        }
+
+        fn on_packet(&mut self, _packet: PacketDef<TestBlock, TestPayload, TestPayload>) {
+            self.packets += 1;
+        }
+    }
+
+    #[test]
+    fn default_on_packets_forwards_each_packet_to_on_packet() {
+        let mut sub = PacketCounter { packets: 0 };
+        let batch = (0..3)
+            .map(|_| PacketDef::<TestBlock, TestPayload, TestPayload>::default())
+            .collect();
+        assert!(sub.on_packets(batch).is_ok());
+        assert_eq!(sub.packets, 3);
+    }
 }
@@ -14,6 +14,7 @@ pub enum SensorError {
     Disconnected,
 }
 
+#[cfg(feature = "observer")]
 impl<T> From<tokio::sync::mpsc::error::SendError<T>> for SensorError {
     fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
         SensorError::SendError
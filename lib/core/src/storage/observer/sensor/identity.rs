@@ -0,0 +1,35 @@
+use std::fs::Metadata;
+
+/// Identity of a file, used to detect that the observed path points to another file
+/// (e.g. the file was deleted and created again or rotated).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct FileId {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(not(unix))]
+    created: Option<std::time::SystemTime>,
+}
+
+impl FileId {
+    /// Returns the identity of the file described by `meta`.
+    ///
+    /// On Unix it's the device and inode numbers; on other platforms the creation time is used.
+    pub(crate) fn of(meta: &Metadata) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Self {
+                dev: meta.dev(),
+                ino: meta.ino(),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            Self {
+                created: meta.created().ok(),
+            }
+        }
+    }
+}
@@ -1,122 +1,321 @@
 mod error;
+mod identity;
+mod mode;
 
 use notify::{Event, RecursiveMode, Result as NotifyResult, Watcher, recommended_watcher};
 use std::{
+    ffi::OsString,
     fs,
     path::{Path, PathBuf},
     sync::{
-        Arc,
+        Arc, Mutex, PoisonError,
         atomic::{AtomicBool, AtomicU64, Ordering},
     },
+    time::Duration,
 };
-use tokio::sync::mpsc::{Receiver, Sender, channel, error::TrySendError};
-use tracing::error;
 
 pub use error::SensorError;
+pub(crate) use identity::FileId;
+use mode::Poller;
+pub use mode::{DEFAULT_POLL_INTERVAL, SensorMode};
+
+/// Kind of change detected by a file sensor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileChange {
+    /// The file has grown beyond already processed data.
+    #[default]
+    Grown,
+    /// The file became shorter than already processed data.
+    Truncated,
+    /// The observed path points to another file now (the file was deleted and created
+    /// again, or rotated).
+    Replaced,
+}
 
-/// Notification emitted by a file sensor when source length grows.
+/// Notification emitted by a file sensor when the observed file changes.
 #[derive(Clone, Copy, Debug)]
 pub struct Wake {
     /// Current observed file size in bytes.
     pub size: u64,
+    /// Kind of the detected change.
+    pub change: FileChange,
 }
 
 impl Wake {
-    /// Creates a wake event with the provided file size.
+    /// Creates a wake event for a grown file with the provided file size.
     pub fn new(size: u64) -> Self {
-        Self { size }
+        Self::with_change(size, FileChange::Grown)
+    }
+
+    /// Creates a wake event with the provided file size and kind of change.
+    pub fn with_change(size: u64, change: FileChange) -> Self {
+        Self { size, change }
     }
 }
 
-/// File watcher that emits wake signals when new data appears.
+/// File watcher that emits wake signals when new data appears, or when the file is
+/// truncated or replaced.
+///
+/// The parent directory is watched, so the sensor also notices a file which has been
+/// created again at the observed path. Where the platform file watcher isn't available,
+/// the file is polled instead (see [`SensorMode`]); wake signals are the same in both cases.
 pub struct Sensor {
+    probe: Probe,
+    backend: Backend,
+}
+
+/// Source of file change notifications keeping the sensor alive.
+enum Backend {
+    Watcher {
+        _watcher: notify::RecommendedWatcher,
+    },
+    Poller {
+        _poller: Poller,
+    },
+}
+
+/// State shared between the sensor and its notification source.
+#[derive(Clone)]
+struct Probe {
     target: PathBuf,
     locked: Arc<AtomicBool>,
     processed_len: Arc<AtomicU64>,
-    tx: Sender<Wake>,
-    _watcher: notify::RecommendedWatcher,
+    identity: Arc<Mutex<FileId>>,
+    tx: WakeTx,
+}
+
+/// Sending side of the wake channel.
+#[derive(Clone)]
+enum WakeTx {
+    #[cfg(feature = "observer")]
+    Tokio(tokio::sync::mpsc::Sender<Wake>),
+    #[cfg(feature = "observer-sync")]
+    Std(std::sync::mpsc::SyncSender<Wake>),
 }
 
 impl Sensor {
     /// Starts observing a file and returns the sensor plus wake receiver channel.
-    pub fn new(target: impl AsRef<Path>) -> Result<(Self, Receiver<Wake>), SensorError> {
-        let target = target.as_ref().to_path_buf();
-        if !target.is_file() {
-            return Err(SensorError::NotFile(target.to_string_lossy().to_string()));
-        }
-
-        let (tx, rx): (Sender<Wake>, Receiver<Wake>) = channel(1);
-
-        let locked = Arc::new(AtomicBool::new(false));
-        let processed_len = Arc::new(AtomicU64::new(0));
+    ///
+    /// Uses [`SensorMode::default`]: the platform file watcher with a polling fallback.
+    #[cfg(feature = "observer")]
+    pub fn new(
+        target: impl AsRef<Path>,
+    ) -> Result<(Self, tokio::sync::mpsc::Receiver<Wake>), SensorError> {
+        Self::with_mode(target, SensorMode::default())
+    }
 
-        let inner_locked = locked.clone();
-        let inner_processed_len = processed_len.clone();
-        let inner_target = target.clone();
-        let inner_tx = tx.clone();
+    /// Starts observing a file in the given mode and returns the sensor plus wake receiver
+    /// channel.
+    #[cfg(feature = "observer")]
+    pub fn with_mode(
+        target: impl AsRef<Path>,
+        mode: SensorMode,
+    ) -> Result<(Self, tokio::sync::mpsc::Receiver<Wake>), SensorError> {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        Ok((Self::start(target.as_ref(), mode, WakeTx::Tokio(tx))?, rx))
+    }
 
-        let mut watcher = recommended_watcher(move |res: NotifyResult<Event>| {
-            if res.is_err() {
-                return;
-            }
-            if let Err(err) = Sensor::emit(
-                &inner_target,
-                &inner_tx,
-                inner_locked.as_ref(),
-                inner_processed_len.as_ref(),
-            ) {
-                error!("Error emitting wake: {}", err);
-            }
-        })?;
+    /// Starts observing a file in the given mode and returns the sensor plus a std wake
+    /// receiver channel, for owners which don't run an async runtime.
+    #[cfg(feature = "observer-sync")]
+    pub fn with_mode_sync(
+        target: impl AsRef<Path>,
+        mode: SensorMode,
+    ) -> Result<(Self, std::sync::mpsc::Receiver<Wake>), SensorError> {
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        Ok((Self::start(target.as_ref(), mode, WakeTx::Std(tx))?, rx))
+    }
 
-        watcher.watch(&target, RecursiveMode::NonRecursive)?;
+    fn start(target: &Path, mode: SensorMode, tx: WakeTx) -> Result<Self, SensorError> {
+        let target = target.to_path_buf();
+        if !target.is_file() {
+            return Err(SensorError::NotFile(target.to_string_lossy().to_string()));
+        }
+        let Some(name) = target.file_name().map(|name| name.to_os_string()) else {
+            return Err(SensorError::NotFile(target.to_string_lossy().to_string()));
+        };
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
 
-        let sensor = Self {
+        let probe = Probe {
+            identity: Arc::new(Mutex::new(FileId::of(&fs::metadata(&target)?))),
             target,
-            locked,
-            processed_len,
+            locked: Arc::new(AtomicBool::new(false)),
+            processed_len: Arc::new(AtomicU64::new(0)),
             tx,
-            _watcher: watcher,
         };
 
-        Ok((sensor, rx))
+        let backend = match mode {
+            SensorMode::Watch => Backend::Watcher {
+                _watcher: Self::watch(&dir, name, probe.clone())?,
+            },
+            SensorMode::Poll(interval) => Backend::Poller {
+                _poller: Self::poll(interval, probe.clone())?,
+            },
+            SensorMode::Auto(interval) => match Self::watch(&dir, name, probe.clone()) {
+                Ok(watcher) => Backend::Watcher { _watcher: watcher },
+                Err(err) => {
+                    log::fallback(&probe.target, &err);
+                    Backend::Poller {
+                        _poller: Self::poll(interval, probe.clone())?,
+                    }
+                }
+            },
+        };
+
+        Ok(Self { probe, backend })
+    }
+
+    /// Returns `true` if the file is polled instead of being watched by the platform
+    /// file watcher.
+    pub fn is_polling(&self) -> bool {
+        matches!(self.backend, Backend::Poller { .. })
     }
 
     /// Owner acknowledges the wake and reports it successfully read up to `until` byte offset.
     /// Sensor unlocks and, if there is already more data beyond `until`, emits Wake immediately.
     pub fn processed(&self, until: u64) -> Result<(), SensorError> {
-        fetch_max_u64(&self.processed_len, until);
-        self.locked.store(false, Ordering::Release);
-        Self::emit(&self.target, &self.tx, &self.locked, &self.processed_len)
+        fetch_max_u64(&self.probe.processed_len, until);
+        self.probe.locked.store(false, Ordering::Release);
+        self.probe.emit()
     }
 
-    fn emit(
-        target: &Path,
-        tx: &Sender<Wake>,
-        locked: &AtomicBool,
-        processed_len: &AtomicU64,
-    ) -> Result<(), SensorError> {
-        let size = fs::metadata(target)?.len();
+    /// Owner acknowledges a [`FileChange::Truncated`] or [`FileChange::Replaced`] wake: it has
+    /// reopened the observed path as `file` and read it up to `until` byte offset.
+    ///
+    /// The sensor starts tracking `file` and, if there is already more data beyond `until`,
+    /// emits Wake immediately.
+    pub fn reset(&self, file: &fs::File, until: u64) -> Result<(), SensorError> {
+        *self
+            .probe
+            .identity
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = FileId::of(&file.metadata()?);
+        self.probe.processed_len.store(until, Ordering::Release);
+        self.processed(until)
+    }
 
-        if size <= processed_len.load(Ordering::Acquire) {
-            return Ok(());
-        }
-        if locked
+    fn watch(
+        dir: &Path,
+        name: OsString,
+        probe: Probe,
+    ) -> Result<notify::RecommendedWatcher, SensorError> {
+        let mut watcher = recommended_watcher(move |res: NotifyResult<Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if !event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(name.as_os_str()))
+            {
+                // Change of another file in the same directory
+                return;
+            }
+            if let Err(err) = probe.emit() {
+                log::emit_error(&err);
+            }
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    }
+
+    fn poll(interval: Duration, probe: Probe) -> Result<Poller, SensorError> {
+        Ok(Poller::start(interval, move || match probe.emit() {
+            Ok(()) => true,
+            Err(SensorError::Disconnected) => false,
+            Err(err) => {
+                log::emit_error(&err);
+                true
+            }
+        })?)
+    }
+}
+
+impl Probe {
+    fn emit(&self) -> Result<(), SensorError> {
+        let meta = match fs::metadata(&self.target) {
+            Ok(meta) => meta,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                // The file has been removed (or rotated); wait until it's created again
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let size = meta.len();
+        let processed_len = self.processed_len.load(Ordering::Acquire);
+        let change =
+            if *self.identity.lock().unwrap_or_else(PoisonError::into_inner) != FileId::of(&meta) {
+                FileChange::Replaced
+            } else if size < processed_len {
+                FileChange::Truncated
+            } else if size > processed_len {
+                FileChange::Grown
+            } else {
+                return Ok(());
+            };
+        if self
+            .locked
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
             .is_err()
         {
             return Ok(());
         }
-        match tx.try_send(Wake::new(size)) {
-            Ok(_) | Err(TrySendError::Full(_)) => {
-                // Prevent blocking in notify callback.
-                Ok(())
+        // Never block in the notify callback: a full channel already has a pending wake
+        self.tx.try_send(Wake::with_change(size, change))
+    }
+}
+
+impl WakeTx {
+    fn try_send(&self, wake: Wake) -> Result<(), SensorError> {
+        match self {
+            #[cfg(feature = "observer")]
+            Self::Tokio(tx) => {
+                use tokio::sync::mpsc::error::TrySendError;
+                match tx.try_send(wake) {
+                    Ok(_) | Err(TrySendError::Full(_)) => Ok(()),
+                    Err(TrySendError::Closed(_)) => Err(SensorError::Disconnected),
+                }
+            }
+            #[cfg(feature = "observer-sync")]
+            Self::Std(tx) => {
+                use std::sync::mpsc::TrySendError;
+                match tx.try_send(wake) {
+                    Ok(_) | Err(TrySendError::Full(_)) => Ok(()),
+                    Err(TrySendError::Disconnected(_)) => Err(SensorError::Disconnected),
+                }
             }
-            Err(TrySendError::Closed(_)) => Err(SensorError::Disconnected),
         }
     }
 }
 
+/// Sensor diagnostics. Logged via `tracing` with the `observer` feature; the thread-based
+/// observer alone (`observer-sync`) doesn't depend on `tracing`, so messages are dropped.
+mod log {
+    use std::path::Path;
+
+    use super::SensorError;
+
+    pub(super) fn emit_error(err: &SensorError) {
+        #[cfg(feature = "observer")]
+        tracing::error!("Error emitting wake: {}", err);
+        #[cfg(not(feature = "observer"))]
+        let _ = err;
+    }
+
+    pub(super) fn fallback(target: &Path, err: &SensorError) {
+        #[cfg(feature = "observer")]
+        tracing::warn!(
+            "Cannot watch {}: {err}; falling back to polling",
+            target.display()
+        );
+        #[cfg(not(feature = "observer"))]
+        let _ = (target, err);
+    }
+}
+
 fn fetch_max_u64(store: &AtomicU64, checked: u64) {
     let mut current = store.load(Ordering::Relaxed);
     while checked > current {
@@ -127,7 +326,7 @@ fn fetch_max_u64(store: &AtomicU64, checked: u64) {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "observer"))]
 mod tests {
     use super::*;
     use std::{
@@ -211,5 +410,129 @@ mod tests {
             w1.size,
             w2.size
         );
+        assert_eq!(w2.change, FileChange::Grown);
+    }
+
+    #[tokio::test]
+    async fn emits_truncated_when_file_shrinks() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("store.bin");
+        File::create(&path).expect("create file");
+
+        let (sensor, mut rx) = Sensor::new(&path).expect("sensor new");
+        append_and_sync(&path, b"1234567890");
+        let w1 = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected first wake")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(w1.change, FileChange::Grown);
+        sensor.processed(w1.size).expect("processed");
+
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .expect("open for truncation")
+            .set_len(3)
+            .expect("set_len");
+        let w2 = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected wake after truncation")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(w2.change, FileChange::Truncated);
+        assert_eq!(w2.size, 3);
+
+        // After reset, the sensor tracks the file from the new position
+        let file = File::open(&path).expect("reopen");
+        sensor.reset(&file, 3).expect("reset");
+        append_and_sync(&path, b"45");
+        let w3 = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected wake after reset")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(w3.change, FileChange::Grown);
+        assert_eq!(w3.size, 5);
+    }
+
+    #[tokio::test]
+    async fn emits_replaced_when_file_is_recreated() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("store.bin");
+        append_and_sync(&path, b"12345");
+
+        let (sensor, mut rx) = Sensor::new(&path).expect("sensor new");
+        sensor.processed(5).expect("processed");
+
+        // Rotate: move the file away and create a new one at the same path
+        std::fs::rename(&path, dir.path().join("store.bin.1")).expect("rename");
+        append_and_sync(&path, b"1234567890");
+        let wake = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected wake after replacement")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(wake.change, FileChange::Replaced);
+
+        // Changes of other files in the directory are ignored
+        let file = File::open(&path).expect("reopen");
+        sensor.reset(&file, 10).expect("reset");
+        append_and_sync(&dir.path().join("store.bin.1"), b"678");
+        let no_wake = timeout(Duration::from_millis(200), rx.recv()).await;
+        assert!(no_wake.is_err(), "should not emit wake for another file");
+    }
+
+    #[tokio::test]
+    async fn poll_mode_emits_same_wakes() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("store.bin");
+        File::create(&path).expect("create file");
+
+        let (sensor, mut rx) =
+            Sensor::with_mode(&path, SensorMode::Poll(Duration::from_millis(20)))
+                .expect("sensor new");
+        assert!(sensor.is_polling());
+
+        append_and_sync(&path, b"12345");
+        let w1 = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected wake after append")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(w1.change, FileChange::Grown);
+        assert_eq!(w1.size, 5);
+
+        // No new wake until the owner reports processed data
+        append_and_sync(&path, b"678");
+        let no_wake = timeout(Duration::from_millis(100), rx.recv()).await;
+        assert!(no_wake.is_err(), "should not emit wake while locked");
+        sensor.processed(5).expect("processed");
+        let w2 = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected wake after processed")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(w2.size, 8);
+        sensor.processed(8).expect("processed");
+
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .expect("open for truncation")
+            .set_len(0)
+            .expect("set_len");
+        let w3 = timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .expect("expected wake after truncation")
+            .expect("sensor channel closed unexpectedly");
+        assert_eq!(w3.change, FileChange::Truncated);
+    }
+
+    #[test]
+    fn default_mode_watches_with_poll_fallback() {
+        assert_eq!(
+            SensorMode::default(),
+            SensorMode::Auto(DEFAULT_POLL_INTERVAL)
+        );
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("store.bin");
+        File::create(&path).expect("create file");
+        let (sensor, _rx) = Sensor::with_mode(&path, SensorMode::Watch).expect("sensor new");
+        assert!(!sensor.is_polling());
     }
 }
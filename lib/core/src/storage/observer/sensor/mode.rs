@@ -0,0 +1,55 @@
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+/// Default interval between file checks in polling mode.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Defines how a [`Sensor`](super::Sensor) detects changes of the observed file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SensorMode {
+    /// Use the platform file watcher; if it cannot be started (for example, on network
+    /// mounts or when watcher limits are exhausted), fall back to polling with the given
+    /// interval.
+    Auto(Duration),
+    /// Use only the platform file watcher; fail if it cannot be started.
+    Watch,
+    /// Check the file with the given interval, without the platform file watcher.
+    ///
+    /// Works on any filesystem, at the cost of a delay of up to one interval.
+    Poll(Duration),
+}
+
+impl Default for SensorMode {
+    fn default() -> Self {
+        Self::Auto(DEFAULT_POLL_INTERVAL)
+    }
+}
+
+/// Background thread which periodically runs a check until the poller is dropped.
+pub(super) struct Poller {
+    _stop: mpsc::Sender<()>,
+}
+
+impl Poller {
+    /// Starts calling `check` every `interval`. `check` returns `false` to stop polling.
+    pub(super) fn start<F>(interval: Duration, mut check: F) -> std::io::Result<Self>
+    where
+        F: FnMut() -> bool + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel::<()>();
+        thread::Builder::new()
+            .name("brec-sensor-poll".to_owned())
+            .spawn(move || {
+                // Sender is never used to send; dropping it disconnects the channel
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if !check() {
+                        break;
+                    }
+                }
+            })?;
+        Ok(Self { _stop: stop })
+    }
+}
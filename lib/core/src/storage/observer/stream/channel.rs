@@ -1,26 +1,89 @@
-use std::{marker::PhantomData, path::Path};
-
-use tokio::sync::mpsc;
+use std::{marker::PhantomData, sync::Arc};
 
 use crate::*;
 
-use super::FileObserverEvent;
+use super::{FileObserverEvent, FileObserverStreamOptions, queue::Queue};
+
+/// Message sent from the observer task to the stream facade.
+///
+/// Cursor updates travel through the same channel as events, so the stream can
+/// report a cursor which is in sync with the events already consumed by the caller.
+pub(in crate::storage::observer) enum StreamMessage<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> {
+    /// Observer event which should be yielded by the stream.
+    Event(FileObserverEvent<B, P, Inner>),
+    /// New observer cursor (see `SubscriptionDef::on_cursor`).
+    Cursor(usize),
+    /// New byte offset of processed data (used by [`StreamFileObserverDef`]).
+    #[cfg(feature = "observer")]
+    Offset(u64),
+}
 
 /// Internal subscription adapter that translates observer callbacks into stream
-/// events sent through a Tokio channel.
+/// events sent through the stream [`Queue`].
 ///
 /// This type is intentionally kept private to avoid exposing callback-oriented
 /// details in the public stream API.
-pub(super) struct StreamSubscription<
+pub(in crate::storage::observer) struct StreamSubscription<
     B: BlockDef + Send + 'static,
     P: PayloadDef<Inner> + Send + 'static,
     Inner: PayloadInnerDef + Send + 'static,
     O: Send + Sync + 'static,
 > {
-    tx: mpsc::UnboundedSender<FileObserverEvent<B, P, Inner>>,
+    queue: Arc<Queue<StreamMessage<B, P, Inner>>>,
     _phantom: PhantomData<O>,
 }
 
+impl<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> StreamSubscription<B, P, Inner, O>
+{
+    /// Creates a subscription which sends observer events into `queue`.
+    pub(in crate::storage::observer) fn new(queue: Arc<Queue<StreamMessage<B, P, Inner>>>) -> Self {
+        Self {
+            queue,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Options of the observer which feeds a stream facade through its queue.
+pub(in crate::storage::observer) type StreamObserverOptions<B, BR, P, Inner, O> =
+    FileObserverOptions<B, BR, P, Inner, StreamSubscription<B, P, Inner, O>, O>;
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> FileObserverStreamOptions<B, BR, P, Inner>
+{
+    /// Creates options of the observer which feeds a stream facade through `queue`.
+    ///
+    /// Shared by the async stream and the sync iterator, which take the same options. The
+    /// queue is created by the caller from [`FileObserverStreamOptions::capacity`].
+    pub(in crate::storage::observer) fn into_observer_options<O: Send + Sync + 'static>(
+        self,
+        queue: &Arc<Queue<StreamMessage<B, P, Inner>>>,
+    ) -> StreamObserverOptions<B, BR, P, Inner, O> {
+        let subscription = StreamSubscription::new(queue.clone());
+        self.rules.into_iter().fold(
+            FileObserverOptions::new(self.path)
+                .subscribe(subscription)
+                .start_at(self.start)
+                .reopen(self.reopen)
+                .with_sensor(self.sensor),
+            |options, rule| options.with_rule(rule),
+        )
+    }
+}
+
 impl<
     B: BlockDef + Send + 'static,
     BR: BlockReferredDef<B> + 'static,
@@ -32,103 +95,111 @@ impl<
     /// Always requests packet delivery and forwards the update event to the
     /// stream consumer.
     fn on_update(&mut self, total: usize, added: usize) -> SubscriptionUpdate {
-        let _ = self.tx.send(FileObserverEvent::Update { total, added });
+        self.queue
+            .send(StreamMessage::Event(FileObserverEvent::Update {
+                total,
+                added,
+            }));
         SubscriptionUpdate::Read
     }
 
     /// Forwards parsed packets to the stream consumer.
     fn on_packet(&mut self, packet: PacketDef<B, P, Inner>) {
-        let _ = self.tx.send(FileObserverEvent::Packet(packet));
+        let _ = self
+            .queue
+            .send_packet(StreamMessage::Event(FileObserverEvent::Packet(packet)));
+    }
+
+    /// Forwards parsed packets to the stream consumer, applying the channel overflow policy.
+    ///
+    /// Fails with [`Error::ObserverOverflow`] if the channel is full and configured to stop.
+    fn on_packets(&mut self, packets: Vec<PacketDef<B, P, Inner>>) -> Result<(), Error> {
+        for packet in packets {
+            self.queue
+                .send_packet(StreamMessage::Event(FileObserverEvent::Packet(packet)))?;
+        }
+        Ok(())
     }
 
-    /// Forwards non-terminal observer errors as text.
-    fn on_error(&mut self, err: &Error) -> SubscriptionErrorAction {
-        let _ = self.tx.send(FileObserverEvent::Error(err.to_string()));
+    /// Forwards cursor updates to the stream facade.
+    fn on_cursor(&mut self, cursor: usize) {
+        self.queue.send(StreamMessage::Cursor(cursor));
+    }
+
+    /// Forwards file truncation.
+    fn on_truncated(&mut self) {
+        self.queue
+            .send(StreamMessage::Event(FileObserverEvent::Truncated));
+    }
+
+    /// Forwards file replacement.
+    fn on_replaced(&mut self) {
+        self.queue
+            .send(StreamMessage::Event(FileObserverEvent::Replaced));
+    }
+
+    /// Forwards non-terminal observer errors.
+    fn on_error(&mut self, err: &ObserverError) -> SubscriptionErrorAction {
+        self.queue
+            .send(StreamMessage::Event(FileObserverEvent::Error(err.clone())));
         SubscriptionErrorAction::Continue
     }
 
     /// Forwards terminal stop information.
-    fn on_stopped(&mut self, reason: Option<Error>) {
-        let _ = self.tx.send(FileObserverEvent::Stopped(reason));
+    fn on_stopped(&mut self, reason: Option<ObserverError>) {
+        self.queue
+            .send(StreamMessage::Event(FileObserverEvent::Stopped(reason)));
     }
 
     /// Forwards explicit observer abort.
     fn on_aborted(&mut self) {
-        let _ = self.tx.send(FileObserverEvent::Aborted);
+        self.queue
+            .send(StreamMessage::Event(FileObserverEvent::Aborted));
     }
 }
 
-/// Shared internal state used by the public stream facade.
-///
-/// This type owns:
-/// - the underlying [`FileObserverDef`]
-/// - the receiving side of the Tokio channel used for event delivery
-///
-/// The observer remains Tokio-backed, but the actual storage reads performed by
-/// the observer are still synchronous and blocking at the file I/O layer.
-pub(super) struct ObserverStreamState<
-    B: BlockDef + Send + 'static,
-    BR: BlockReferredDef<B> + 'static,
-    P: PayloadDef<Inner> + Send + 'static,
-    Inner: PayloadInnerDef + Send + 'static,
-    O: Send + Sync + 'static,
-> {
-    observer: FileObserverDef<B, BR, P, Inner, O>,
-    pub(super) rx: mpsc::UnboundedReceiver<FileObserverEvent<B, P, Inner>>,
-    _phantom: PhantomData<BR>,
-}
-
 impl<
     B: BlockDef + Send + 'static,
-    BR: BlockReferredDef<B> + 'static,
     P: PayloadDef<Inner> + Send + 'static,
     Inner: PayloadInnerDef + Send + 'static,
     O: Send + Sync + 'static,
-> ObserverStreamState<B, BR, P, Inner, O>
-where
-    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+> Drop for StreamSubscription<B, P, Inner, O>
 {
-    /// Creates the internal observer and wires it to the channel-backed stream
-    /// adapter.
-    pub(super) fn new(path: impl AsRef<Path>, opt: O) -> Result<Self, Error> {
-        let (tx, rx) = mpsc::unbounded_channel();
-        let subscription = StreamSubscription::<B, P, Inner, O> {
-            tx,
-            _phantom: PhantomData,
-        };
-        let observer =
-            FileObserverDef::with_opt(FileObserverOptions::new(path).subscribe(subscription), opt)?;
-        Ok(Self {
-            observer,
-            rx,
-            _phantom: PhantomData,
-        })
-    }
-
-    /// Shuts down the underlying observer task.
-    pub(super) async fn shutdown(&mut self) {
-        self.observer.shutdown().await;
+    /// Closes the queue, so the stream ends once all pending events are taken.
+    fn drop(&mut self) {
+        self.queue.close();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ObserverStreamState, StreamSubscription};
+    use super::{Queue, StreamMessage, StreamSubscription};
     use crate::{
-        DefaultProtocolContext, Error, FileObserverEvent, PacketDef, SubscriptionDef,
-        SubscriptionErrorAction, SubscriptionUpdate,
+        ChannelCapacity, DefaultProtocolContext, Error, FileObserverEvent, OverflowPolicy,
+        PacketDef, SubscriptionDef, SubscriptionErrorAction, SubscriptionUpdate,
         tests::{TestBlock, TestPayload},
     };
-    use std::marker::PhantomData;
-    use tempfile::NamedTempFile;
-    use tokio::sync::mpsc;
+    use std::{
+        marker::PhantomData,
+        sync::Arc,
+        task::{Context, Poll, Waker},
+    };
+
+    fn try_recv(
+        queue: &Queue<StreamMessage<TestBlock, TestPayload, TestPayload>>,
+    ) -> Option<StreamMessage<TestBlock, TestPayload, TestPayload>> {
+        match queue.poll_recv(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(message) => message,
+            Poll::Pending => None,
+        }
+    }
 
     #[test]
     fn stream_subscription_maps_callbacks_to_events() {
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        let queue = Arc::new(Queue::new(ChannelCapacity::Unbounded));
         let mut sub =
             StreamSubscription::<TestBlock, TestPayload, TestPayload, DefaultProtocolContext> {
-                tx,
+                queue: queue.clone(),
                 _phantom: PhantomData,
             };
 
@@ -147,8 +218,8 @@ mod tests {
         assert_eq!(update, SubscriptionUpdate::Read);
         assert!(
             matches!(
-                rx.try_recv().expect("expected update event"),
-                FileObserverEvent::Update { total: 7, added: 3 }
+                try_recv(&queue).expect("expected update event"),
+                StreamMessage::Event(FileObserverEvent::Update { total: 7, added: 3 })
             ),
             "first event must be Update(total=7, added=3)"
         );
@@ -168,12 +239,32 @@ mod tests {
         >>::on_packet(&mut sub, packet);
         assert!(
             matches!(
-                rx.try_recv().expect("expected packet event"),
-                FileObserverEvent::Packet(_)
+                try_recv(&queue).expect("expected packet event"),
+                StreamMessage::Event(FileObserverEvent::Packet(_))
             ),
             "second event must be Packet"
         );
 
+        <StreamSubscription<
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DefaultProtocolContext,
+        > as SubscriptionDef<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DefaultProtocolContext,
+        >>::on_cursor(&mut sub, 5);
+        assert!(
+            matches!(
+                try_recv(&queue).expect("expected cursor message"),
+                StreamMessage::Cursor(5)
+            ),
+            "cursor must be forwarded as a message, not as an event"
+        );
+
         let action = <StreamSubscription<
             TestBlock,
             TestPayload,
@@ -185,12 +276,12 @@ mod tests {
             TestPayload,
             TestPayload,
             DefaultProtocolContext,
-        >>::on_error(&mut sub, &Error::Test);
+        >>::on_error(&mut sub, &Error::Test.into());
         assert_eq!(action, SubscriptionErrorAction::Continue);
-        match rx.try_recv().expect("expected error event") {
-            FileObserverEvent::Error(message) => assert!(
-                !message.is_empty(),
-                "error message forwarded to stream must not be empty"
+        match try_recv(&queue).expect("expected error event") {
+            StreamMessage::Event(FileObserverEvent::Error(err)) => assert!(
+                matches!(*err.error, Error::Test),
+                "original error must be forwarded to stream"
             ),
             _ => panic!("third event must be Error"),
         }
@@ -206,11 +297,12 @@ mod tests {
             TestPayload,
             TestPayload,
             DefaultProtocolContext,
-        >>::on_stopped(&mut sub, Some(Error::Test));
+        >>::on_stopped(&mut sub, Some(Error::Test.into()));
         assert!(
             matches!(
-                rx.try_recv().expect("expected stopped event"),
-                FileObserverEvent::Stopped(Some(Error::Test))
+                try_recv(&queue).expect("expected stopped event"),
+                StreamMessage::Event(FileObserverEvent::Stopped(Some(err)))
+                    if matches!(*err.error, Error::Test)
             ),
             "fourth event must be Stopped(Some(Error::Test))"
         );
@@ -229,8 +321,8 @@ mod tests {
         >>::on_aborted(&mut sub);
         assert!(
             matches!(
-                rx.try_recv().expect("expected aborted event"),
-                FileObserverEvent::Aborted
+                try_recv(&queue).expect("expected aborted event"),
+                StreamMessage::Event(FileObserverEvent::Aborted)
             ),
             "fifth event must be Aborted"
         );
@@ -238,13 +330,12 @@ mod tests {
 
     #[test]
     fn stream_subscription_swallows_send_errors() {
-        let (tx, rx) =
-            mpsc::unbounded_channel::<FileObserverEvent<TestBlock, TestPayload, TestPayload>>();
-        drop(rx);
+        let queue = Arc::new(Queue::new(ChannelCapacity::Unbounded));
+        queue.disconnect();
 
         let mut sub =
             StreamSubscription::<TestBlock, TestPayload, TestPayload, DefaultProtocolContext> {
-                tx,
+                queue: queue.clone(),
                 _phantom: PhantomData,
             };
 
@@ -287,7 +378,7 @@ mod tests {
                 TestPayload,
                 TestPayload,
                 DefaultProtocolContext,
-            >>::on_error(&mut sub, &Error::Test),
+            >>::on_error(&mut sub, &Error::Test.into()),
             SubscriptionErrorAction::Continue
         );
         <StreamSubscription<
@@ -314,33 +405,47 @@ mod tests {
             TestPayload,
             DefaultProtocolContext,
         >>::on_aborted(&mut sub);
+        assert!(try_recv(&queue).is_none());
     }
 
-    #[tokio::test]
-    async fn observer_stream_state_new_rejects_missing_file() {
-        let missing = std::path::Path::new("/tmp/brec_missing_stream_observer_state_file.bin");
-        let state = ObserverStreamState::<
-            TestBlock,
+    #[test]
+    fn stream_subscription_stops_on_overflow_and_closes_queue_on_drop() {
+        let queue = Arc::new(Queue::new(ChannelCapacity::Bounded(
+            2,
+            OverflowPolicy::StopWithError,
+        )));
+        let mut sub =
+            StreamSubscription::<TestBlock, TestPayload, TestPayload, DefaultProtocolContext> {
+                queue: queue.clone(),
+                _phantom: PhantomData,
+            };
+        let batch = (0..3)
+            .map(|_| PacketDef::<TestBlock, TestPayload, TestPayload>::default())
+            .collect();
+        let result = <StreamSubscription<
             TestBlock,
             TestPayload,
             TestPayload,
             DefaultProtocolContext,
-        >::new(missing, ());
-        assert!(state.is_err());
-    }
-
-    #[tokio::test]
-    async fn observer_stream_state_new_and_shutdown_on_existing_file() {
-        let file = NamedTempFile::new().expect("temp file must be created");
-        let mut state = ObserverStreamState::<
+        > as SubscriptionDef<
             TestBlock,
             TestBlock,
             TestPayload,
             TestPayload,
             DefaultProtocolContext,
-        >::new(file.path(), ())
-        .expect("state must be created for existing file");
+        >>::on_packets(&mut sub, batch);
+        assert!(matches!(result, Err(Error::ObserverOverflow(2))));
+        drop(sub);
 
-        state.shutdown().await;
+        for _ in 0..2 {
+            assert!(matches!(
+                try_recv(&queue),
+                Some(StreamMessage::Event(FileObserverEvent::Packet(_)))
+            ));
+        }
+        assert!(matches!(
+            queue.poll_recv(&mut Context::from_waker(Waker::noop())),
+            Poll::Ready(None)
+        ));
     }
 }
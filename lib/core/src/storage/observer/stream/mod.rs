@@ -1,5 +1,10 @@
 mod channel;
+mod options;
+mod queue;
+#[cfg(feature = "observer")]
+mod state;
 
+#[cfg(feature = "observer")]
 use std::{
     pin::Pin,
     task::{Context, Poll},
@@ -7,13 +12,22 @@ use std::{
 
 use crate::*;
 
-use self::channel::ObserverStreamState;
+pub(super) use self::channel::StreamMessage;
+pub use self::options::*;
+pub(super) use self::queue::Queue;
+#[cfg(feature = "observer")]
+pub(super) use self::queue::QueueMessage;
+#[cfg(feature = "observer")]
+use self::state::ObserverStreamState;
 
 /// Events emitted by [`FileObserverStreamDef`].
 ///
 /// The stream mirrors the observer lifecycle:
 /// - storage growth is reported via [`FileObserverEvent::Update`]
 /// - successfully parsed packets are emitted via [`FileObserverEvent::Packet`]
+/// - packets dropped by a bounded channel are reported via [`FileObserverEvent::Lagged`]
+/// - truncation or replacement of the file is reported via [`FileObserverEvent::Truncated`]
+///   and [`FileObserverEvent::Replaced`]
 /// - non-terminal observer errors are forwarded as [`FileObserverEvent::Error`]
 /// - terminal completion is reported via [`FileObserverEvent::Stopped`] or
 ///   [`FileObserverEvent::Aborted`]
@@ -35,19 +49,43 @@ pub enum FileObserverEvent<
     /// A packet was successfully read and parsed.
     Packet(PacketDef<B, P, Inner>),
 
+    /// Bytes which don't belong to any `brec` packet (for example, text log lines
+    /// written into the same file).
+    ///
+    /// Emitted only by [`StreamFileObserverDef`], which tails raw packet streams.
+    Ignored(Vec<u8>),
+
+    /// The given number of packets was dropped because the stream consumer didn't keep up.
+    ///
+    /// Emitted only by a stream with a bounded channel and [`OverflowPolicy::DropOldest`];
+    /// reported right before the next event.
+    Lagged(usize),
+
+    /// The observed file became shorter than already processed data.
+    ///
+    /// If the observer reopens the file, observation restarts from the beginning of the file;
+    /// otherwise the observer stops.
+    Truncated,
+
+    /// The observed path points to another file (the file was deleted and created again,
+    /// or rotated).
+    ///
+    /// If the observer reopens the file, observation restarts from the beginning of the new
+    /// file; otherwise the observer stops.
+    Replaced,
+
     /// A non-terminal observer error.
     ///
-    /// At this layer the error is exposed as text because `SubscriptionDef`
-    /// reports it by shared reference, so ownership of the original [`Error`]
-    /// is not available.
-    Error(String),
+    /// The error carries its category, the index of the failed packet and its byte offset
+    /// (see [`ObserverError`]), so a consumer can decide whether to continue.
+    Error(ObserverError),
 
     /// The observer stopped.
     ///
     /// `None` means a normal stop.
     /// `Some(error)` means the observer could not continue because of a
     /// terminal failure.
-    Stopped(Option<Error>),
+    Stopped(Option<ObserverError>),
 
     /// The observer was explicitly aborted by shutdown.
     Aborted,
@@ -65,6 +103,9 @@ pub enum FileObserverEvent<
 /// - observer lifecycle is driven by Tokio tasks
 /// - events are forwarded through an async-aware channel
 ///
+/// The channel is unbounded by default. Use [`FileObserverStreamOptions::bounded`] to limit
+/// the number of pending packets and choose an [`OverflowPolicy`].
+///
 /// However, this does **not** mean the storage access itself is non-blocking.
 /// At the low level the observer still relies on synchronous, blocking file I/O
 /// (`std::fs::File`, `Read`, `Seek`) through the storage reader. In other words:
@@ -75,6 +116,7 @@ pub enum FileObserverEvent<
 /// This is acceptable for the current observer design because the async layer is
 /// mainly about coordination and integration with Tokio-based applications, not
 /// about turning regular file I/O into true non-blocking disk access.
+#[cfg(feature = "observer")]
 pub struct FileObserverStreamDef<
     B: BlockDef + Send + 'static,
     BR: BlockReferredDef<B> + 'static,
@@ -83,8 +125,10 @@ pub struct FileObserverStreamDef<
     O: Send + Sync + 'static,
 > {
     inner: ObserverStreamState<B, BR, P, Inner, O>,
+    cursor: usize,
 }
 
+#[cfg(feature = "observer")]
 impl<
     B: BlockDef + Send + 'static,
     BR: BlockReferredDef<B> + 'static,
@@ -97,6 +141,7 @@ where
 {
 }
 
+#[cfg(feature = "observer")]
 impl<
     B: BlockDef + Send + 'static,
     BR: BlockReferredDef<B> + 'static,
@@ -112,8 +157,47 @@ where
     /// The observer starts immediately and begins emitting lifecycle events as
     /// soon as storage changes are detected.
     pub fn with_opt(path: impl AsRef<std::path::Path>, opt: O) -> Result<Self, Error> {
+        Self::with_rules(path, Vec::new(), opt)
+    }
+
+    /// Creates a new observer stream which emits only packets passing the given rules.
+    ///
+    /// Rules are applied by the observer's reader, so packets denied by `Prefilter` or
+    /// `FilterPayload` are never fully decoded and never cross the channel.
+    pub fn with_rules(
+        path: impl AsRef<std::path::Path>,
+        rules: Vec<RuleDef<B, BR, P, Inner>>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        Self::with_start_at(path, StartPosition::Beginning, rules, opt)
+    }
+
+    /// Creates a new observer stream which starts from the given position and emits only
+    /// packets passing the given rules.
+    ///
+    /// Use [`FileObserverStreamDef::cursor`] to get a position to resume from later.
+    pub fn with_start_at(
+        path: impl AsRef<std::path::Path>,
+        start: StartPosition,
+        rules: Vec<RuleDef<B, BR, P, Inner>>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let options = rules.into_iter().fold(
+            FileObserverStreamOptions::new(path).start_at(start),
+            |options, rule| options.with_rule(rule),
+        );
+        Self::with_options(options, opt)
+    }
+
+    /// Creates a new observer stream from options (rules, start position, channel capacity).
+    pub fn with_options(
+        options: FileObserverStreamOptions<B, BR, P, Inner>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let cursor = options.start.resolve(0);
         Ok(Self {
-            inner: ObserverStreamState::new(path, opt)?,
+            inner: ObserverStreamState::new(options, opt)?,
+            cursor,
         })
     }
 
@@ -124,6 +208,15 @@ where
         Self::with_opt(path, O::default())
     }
 
+    /// Returns the global index of the next packet to be checked.
+    ///
+    /// The cursor is in sync with events already yielded by the stream: all packets
+    /// before it have been yielded (or skipped by rules). Passing it back via
+    /// [`StartPosition::Index`] resumes observation without duplicates or gaps.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
     /// Stops the underlying observer task and waits for it to finish.
     ///
     /// After shutdown, no more events will be produced.
@@ -132,6 +225,7 @@ where
     }
 }
 
+#[cfg(feature = "observer")]
 impl<
     B: BlockDef + Send + 'static,
     BR: BlockReferredDef<B> + 'static,
@@ -144,8 +238,21 @@ where
 {
     type Item = FileObserverEvent<B, P, Inner>;
 
-    /// Polls the next observer event from the internal Tokio channel.
+    /// Polls the next observer event from the internal channel.
+    ///
+    /// Cursor updates are consumed internally and never yielded.
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.get_mut().inner.rx).poll_recv(cx)
+        let this = self.get_mut();
+        loop {
+            match this.inner.queue.poll_recv(cx) {
+                Poll::Ready(Some(StreamMessage::Event(event))) => return Poll::Ready(Some(event)),
+                Poll::Ready(Some(StreamMessage::Cursor(cursor))) => {
+                    this.cursor = cursor;
+                }
+                Poll::Ready(Some(StreamMessage::Offset(..))) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
     }
 }
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+/// Defines how a bounded observer stream reacts when its channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Block the observer's reader until the consumer takes packets from the stream.
+    #[default]
+    Block,
+    /// Drop the oldest pending packet to make room for the new one.
+    ///
+    /// The number of dropped packets is reported via [`FileObserverEvent::Lagged`].
+    DropOldest,
+    /// Stop the observer with [`Error::ObserverOverflow`].
+    StopWithError,
+}
+
+/// Capacity of the channel between the observer and [`FileObserverStreamDef`].
+///
+/// Only packets count against the capacity; lifecycle events (updates, errors, stop)
+/// are always delivered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelCapacity {
+    /// No limit: the observer never waits for the consumer.
+    #[default]
+    Unbounded,
+    /// At most the given number of packets can be pending; the policy defines what happens
+    /// when the limit is reached.
+    Bounded(usize, OverflowPolicy),
+}
+
+/// Builder options for creating [`FileObserverStreamDef`].
+pub struct FileObserverStreamOptions<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> {
+    /// Path to the observed storage file.
+    pub path: PathBuf,
+    /// Rules applied by the observer's reader; packets denied by rules never cross
+    /// the channel.
+    pub rules: Vec<RuleDef<B, BR, P, Inner>>,
+    /// Packet from which the observer starts delivering packets.
+    pub start: StartPosition,
+    /// Capacity of the channel between the observer and the stream.
+    pub capacity: ChannelCapacity,
+    /// Reopen the file and restart from its beginning when it's truncated or replaced.
+    pub reopen: bool,
+    /// Defines how the observer detects changes of the file.
+    pub sensor: SensorMode,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> FileObserverStreamOptions<B, BR, P, Inner>
+{
+    /// Creates stream options for a target file path.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            rules: Vec::new(),
+            start: StartPosition::Beginning,
+            capacity: ChannelCapacity::Unbounded,
+            reopen: false,
+            sensor: SensorMode::default(),
+        }
+    }
+
+    /// Adds a filtering rule (`Prefilter`, `FilterPayload`, `FilterPacket`, etc.).
+    pub fn with_rule(mut self, rule: RuleDef<B, BR, P, Inner>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Sets the packet from which the observer starts delivering packets.
+    pub fn start_at(mut self, position: StartPosition) -> Self {
+        self.start = position;
+        self
+    }
+
+    /// Limits the number of pending packets. `0` is treated as `1`.
+    ///
+    /// By default the channel is unbounded.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.capacity = ChannelCapacity::Bounded(capacity.max(1), policy);
+        self
+    }
+
+    /// Sets whether the observer reopens the file when it's truncated or replaced.
+    ///
+    /// See [`FileObserverOptions::reopen`].
+    pub fn reopen(mut self, reopen: bool) -> Self {
+        self.reopen = reopen;
+        self
+    }
+
+    /// Sets how the observer detects changes of the file.
+    ///
+    /// See [`FileObserverOptions::with_sensor`].
+    pub fn with_sensor(mut self, mode: SensorMode) -> Self {
+        self.sensor = mode;
+        self
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> std::fmt::Debug for FileObserverStreamOptions<B, BR, P, Inner>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FileObserverStreamOptions: {}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelCapacity, FileObserverStreamOptions, OverflowPolicy};
+    use crate::{
+        RuleDef, RuleFnDef, SensorMode, StartPosition,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::time::Duration;
+
+    type Options = FileObserverStreamOptions<TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    #[test]
+    fn stream_options_defaults_are_unbounded_from_beginning() {
+        let opts = Options::new("abc/storage.bin");
+        assert!(opts.path.ends_with("abc/storage.bin"));
+        assert!(opts.rules.is_empty());
+        assert_eq!(opts.start, StartPosition::Beginning);
+        assert_eq!(opts.capacity, ChannelCapacity::Unbounded);
+        assert!(!opts.reopen);
+        assert_eq!(opts.sensor, SensorMode::default());
+    }
+
+    #[test]
+    fn stream_options_builder_sets_fields() {
+        let opts = Options::new("x.bin")
+            .with_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| true)))
+            .start_at(StartPosition::End)
+            .bounded(0, OverflowPolicy::DropOldest)
+            .reopen(true)
+            .with_sensor(SensorMode::Poll(Duration::from_millis(100)));
+        assert_eq!(opts.rules.len(), 1);
+        assert!(opts.reopen);
+        assert_eq!(opts.sensor, SensorMode::Poll(Duration::from_millis(100)));
+        assert_eq!(opts.start, StartPosition::End);
+        assert_eq!(
+            opts.capacity,
+            ChannelCapacity::Bounded(1, OverflowPolicy::DropOldest)
+        );
+        assert!(format!("{opts:?}").contains("x.bin"));
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+    thread::{self, Thread},
+};
+
+use crate::*;
+
+use super::{ChannelCapacity, FileObserverEvent, OverflowPolicy, StreamMessage};
+
+/// Message which can be passed through the [`Queue`].
+pub(in crate::storage::observer) trait QueueMessage: Send {
+    /// Returns `true` if the message carries a packet and counts against the capacity.
+    fn is_packet(&self) -> bool;
+
+    /// Creates a message which reports the number of dropped packets.
+    fn lagged(dropped: usize) -> Self;
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> QueueMessage for StreamMessage<B, P, Inner>
+{
+    fn is_packet(&self) -> bool {
+        matches!(self, StreamMessage::Event(FileObserverEvent::Packet(..)))
+    }
+
+    fn lagged(dropped: usize) -> Self {
+        StreamMessage::Event(FileObserverEvent::Lagged(dropped))
+    }
+}
+
+/// Channel between the observer task and its consumer (e.g. [`FileObserverStreamDef`]).
+///
+/// Works as an unbounded channel, but can limit the number of pending packets (see
+/// [`ChannelCapacity`]). Only packets count against the capacity; other messages are
+/// always queued. With [`OverflowPolicy::Block`] the sending side waits on a condition
+/// variable, so packets have to be sent from a thread which is allowed to block.
+pub(in crate::storage::observer) struct Queue<M: QueueMessage> {
+    state: Mutex<QueueState<M>>,
+    space: Condvar,
+    capacity: ChannelCapacity,
+}
+
+struct QueueState<M: QueueMessage> {
+    messages: VecDeque<M>,
+    /// Number of packets in `messages`.
+    packets: usize,
+    /// Number of packets dropped since the last lag report.
+    lagged: usize,
+    waker: Option<Waker>,
+    /// The sending side is gone; no more messages will come.
+    closed: bool,
+    /// The sending side must not wait for free space anymore.
+    released: bool,
+    /// The receiving side is gone; messages are discarded.
+    disconnected: bool,
+}
+
+impl<M: QueueMessage> Queue<M> {
+    pub(in crate::storage::observer) fn new(capacity: ChannelCapacity) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::new(),
+                packets: 0,
+                lagged: 0,
+                waker: None,
+                closed: false,
+                released: false,
+                disconnected: false,
+            }),
+            space: Condvar::new(),
+            capacity,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState<M>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Queues a message which doesn't count against the capacity.
+    pub(in crate::storage::observer) fn send(&self, message: M) {
+        let mut state = self.lock();
+        if state.disconnected {
+            return;
+        }
+        state.messages.push_back(message);
+        state.wake();
+    }
+
+    /// Queues a packet message, applying the overflow policy if the channel is full.
+    ///
+    /// Returns [`Error::ObserverOverflow`] if the channel is full and the policy is
+    /// [`OverflowPolicy::StopWithError`]; the packet is not queued in this case.
+    pub(in crate::storage::observer) fn send_packet(&self, packet: M) -> Result<(), Error> {
+        let mut state = self.lock();
+        if let ChannelCapacity::Bounded(capacity, policy) = self.capacity {
+            while state.packets >= capacity && !state.released && !state.disconnected {
+                match policy {
+                    OverflowPolicy::Block => {
+                        state = self
+                            .space
+                            .wait(state)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                    OverflowPolicy::DropOldest => {
+                        let Some(oldest) = state.messages.iter().position(M::is_packet) else {
+                            break;
+                        };
+                        state.messages.remove(oldest);
+                        state.packets -= 1;
+                        state.lagged += 1;
+                    }
+                    OverflowPolicy::StopWithError => {
+                        return Err(Error::ObserverOverflow(capacity));
+                    }
+                }
+            }
+        }
+        if state.disconnected {
+            return Ok(());
+        }
+        state.messages.push_back(packet);
+        state.packets += 1;
+        state.wake();
+        Ok(())
+    }
+
+    /// Takes the next message.
+    ///
+    /// Dropped packets are reported with [`QueueMessage::lagged`] before the next message.
+    /// Returns `Poll::Ready(None)` once the sending side is gone and all messages have been
+    /// taken.
+    pub(in crate::storage::observer) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<M>> {
+        let mut state = self.lock();
+        if state.lagged > 0 {
+            let lagged = std::mem::take(&mut state.lagged);
+            return Poll::Ready(Some(M::lagged(lagged)));
+        }
+        if let Some(message) = state.messages.pop_front() {
+            if message.is_packet() {
+                state.packets -= 1;
+                self.space.notify_all();
+            }
+            return Poll::Ready(Some(message));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Blocks the current thread until the next message is available; `None` once the
+    /// sending side is gone and all messages have been taken.
+    pub(in crate::storage::observer) fn recv(&self) -> Option<M> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match self.poll_recv(&mut cx) {
+                Poll::Ready(message) => return message,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// Marks the sending side as closed.
+    pub(in crate::storage::observer) fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        state.wake();
+    }
+
+    /// Lets a sender blocked by a full channel continue; used on shutdown.
+    ///
+    /// After release the capacity is no longer enforced.
+    pub(in crate::storage::observer) fn release(&self) {
+        self.lock().released = true;
+        self.space.notify_all();
+    }
+
+    /// Marks the receiving side as gone: pending messages are dropped and new ones discarded.
+    pub(in crate::storage::observer) fn disconnect(&self) {
+        let mut state = self.lock();
+        state.disconnected = true;
+        state.messages.clear();
+        state.packets = 0;
+        drop(state);
+        self.space.notify_all();
+    }
+
+    /// Returns `true` if the receiving side is gone.
+    #[cfg(feature = "observer")]
+    pub(in crate::storage::observer) fn is_disconnected(&self) -> bool {
+        self.lock().disconnected
+    }
+}
+
+impl<M: QueueMessage> QueueState<M> {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Wakes the thread waiting for the next message.
+struct ThreadWaker(Thread);
+
+impl std::task::Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Queue;
+    use crate::{
+        ChannelCapacity, Error, FileObserverEvent, OverflowPolicy, PacketDef,
+        storage::observer::stream::StreamMessage,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::{
+        sync::Arc,
+        task::{Context, Poll, Waker},
+    };
+
+    type Message = StreamMessage<TestBlock, TestPayload, TestPayload>;
+    type TestQueue = Queue<Message>;
+
+    fn recv(queue: &TestQueue) -> Poll<Option<Message>> {
+        queue.poll_recv(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn packet() -> Message {
+        StreamMessage::Event(FileObserverEvent::Packet(PacketDef::default()))
+    }
+
+    #[test]
+    fn unbounded_queue_keeps_order_and_closes() {
+        let queue = TestQueue::new(ChannelCapacity::Unbounded);
+        assert!(recv(&queue).is_pending());
+        queue.send(StreamMessage::Cursor(1));
+        for _ in 0..10 {
+            queue
+                .send_packet(packet())
+                .expect("unbounded queue accepts packets");
+        }
+        queue.close();
+        assert!(matches!(
+            recv(&queue),
+            Poll::Ready(Some(StreamMessage::Cursor(1)))
+        ));
+        for _ in 0..10 {
+            assert!(matches!(
+                recv(&queue),
+                Poll::Ready(Some(StreamMessage::Event(FileObserverEvent::Packet(..))))
+            ));
+        }
+        assert!(matches!(recv(&queue), Poll::Ready(None)));
+    }
+
+    #[test]
+    fn drop_oldest_reports_lag_before_next_message() {
+        let queue = TestQueue::new(ChannelCapacity::Bounded(2, OverflowPolicy::DropOldest));
+        for cursor in 0..5 {
+            queue.send(StreamMessage::Cursor(cursor));
+            queue.send_packet(packet()).expect("packet is queued");
+        }
+        assert!(matches!(
+            recv(&queue),
+            Poll::Ready(Some(StreamMessage::Event(FileObserverEvent::Lagged(3))))
+        ));
+        // Cursor messages of dropped packets are kept
+        for cursor in 0..4 {
+            assert!(
+                matches!(recv(&queue), Poll::Ready(Some(StreamMessage::Cursor(c))) if c == cursor)
+            );
+        }
+        assert!(matches!(
+            recv(&queue),
+            Poll::Ready(Some(StreamMessage::Event(FileObserverEvent::Packet(..))))
+        ));
+    }
+
+    #[test]
+    fn stop_with_error_rejects_packets_over_capacity() {
+        let queue = TestQueue::new(ChannelCapacity::Bounded(1, OverflowPolicy::StopWithError));
+        queue.send_packet(packet()).expect("first packet fits");
+        assert!(matches!(
+            queue.send_packet(packet()),
+            Err(Error::ObserverOverflow(1))
+        ));
+        // Control messages are not limited
+        queue.send(StreamMessage::Event(FileObserverEvent::Stopped(None)));
+        assert!(matches!(
+            recv(&queue),
+            Poll::Ready(Some(StreamMessage::Event(FileObserverEvent::Packet(..))))
+        ));
+        assert!(matches!(
+            recv(&queue),
+            Poll::Ready(Some(StreamMessage::Event(FileObserverEvent::Stopped(None))))
+        ));
+    }
+
+    #[test]
+    fn block_waits_for_consumer_and_release() {
+        let queue = Arc::new(TestQueue::new(ChannelCapacity::Bounded(
+            1,
+            OverflowPolicy::Block,
+        )));
+        queue.send_packet(packet()).expect("first packet fits");
+        let sender = {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                queue.send_packet(packet()).expect("packet is queued");
+                queue.send_packet(packet()).expect("packet is queued");
+            })
+        };
+        // The first blocked packet goes through as soon as there is free space
+        assert!(recv(&queue).is_ready());
+        // The second one is let through by release
+        queue.release();
+        sender.join().expect("sender must finish");
+        assert!(recv(&queue).is_ready());
+        assert!(recv(&queue).is_ready());
+        assert!(recv(&queue).is_pending());
+    }
+
+    #[test]
+    fn recv_blocks_until_message_or_close() {
+        let queue = Arc::new(TestQueue::new(ChannelCapacity::Unbounded));
+        let receiver = {
+            let queue = queue.clone();
+            std::thread::spawn(move || {
+                let first = queue.recv();
+                let second = queue.recv();
+                (first, second)
+            })
+        };
+        queue.send(StreamMessage::Cursor(7));
+        queue.close();
+        let (first, second) = receiver.join().expect("receiver must finish");
+        assert!(matches!(first, Some(StreamMessage::Cursor(7))));
+        assert!(second.is_none());
+    }
+
+    #[test]
+    fn disconnected_queue_discards_messages() {
+        let queue = TestQueue::new(ChannelCapacity::Bounded(1, OverflowPolicy::Block));
+        queue.send_packet(packet()).expect("first packet fits");
+        queue.disconnect();
+        queue
+            .send_packet(packet())
+            .expect("discarded without blocking");
+        queue.send(StreamMessage::Cursor(1));
+        assert!(recv(&queue).is_pending());
+    }
+}
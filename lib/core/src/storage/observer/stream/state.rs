@@ -0,0 +1,113 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::*;
+
+use super::{FileObserverStreamOptions, channel::StreamMessage, queue::Queue};
+
+/// Shared internal state used by the public stream facade.
+///
+/// This type owns:
+/// - the underlying [`FileObserverDef`]
+/// - the receiving side of the queue used for event delivery
+///
+/// The observer remains Tokio-backed, but the actual storage reads performed by
+/// the observer are still synchronous and blocking at the file I/O layer.
+pub(super) struct ObserverStreamState<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    observer: FileObserverDef<B, BR, P, Inner, O>,
+    pub(super) queue: Arc<Queue<StreamMessage<B, P, Inner>>>,
+    _phantom: PhantomData<BR>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> ObserverStreamState<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Creates the internal observer and wires it to the queue-backed stream
+    /// adapter.
+    pub(super) fn new(
+        options: FileObserverStreamOptions<B, BR, P, Inner>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let queue = Arc::new(Queue::new(options.capacity));
+        let observer_options = options.into_observer_options(&queue);
+        let observer = FileObserverDef::with_opt(observer_options, opt)?;
+        Ok(Self {
+            observer,
+            queue,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Shuts down the underlying observer task.
+    ///
+    /// The queue is released first, so an observer blocked by a full channel can finish.
+    pub(super) async fn shutdown(&mut self) {
+        self.queue.release();
+        self.observer.shutdown().await;
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Drop for ObserverStreamState<B, BR, P, Inner, O>
+{
+    /// Disconnects the queue, so an observer blocked by a full channel doesn't wait for a
+    /// consumer which is gone.
+    fn drop(&mut self) {
+        self.queue.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ObserverStreamState;
+    use crate::{
+        DefaultProtocolContext, FileObserverStreamOptions,
+        tests::{TestBlock, TestPayload},
+    };
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn observer_stream_state_new_rejects_missing_file() {
+        let missing = std::path::Path::new("/tmp/brec_missing_stream_observer_state_file.bin");
+        let state = ObserverStreamState::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DefaultProtocolContext,
+        >::new(FileObserverStreamOptions::new(missing), ());
+        assert!(state.is_err());
+    }
+
+    #[tokio::test]
+    async fn observer_stream_state_new_and_shutdown_on_existing_file() {
+        let file = NamedTempFile::new().expect("temp file must be created");
+        let mut state = ObserverStreamState::<
+            TestBlock,
+            TestBlock,
+            TestPayload,
+            TestPayload,
+            DefaultProtocolContext,
+        >::new(FileObserverStreamOptions::new(file.path()), ())
+        .expect("state must be created for existing file");
+
+        state.shutdown().await;
+    }
+}
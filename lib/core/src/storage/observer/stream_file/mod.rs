@@ -0,0 +1,504 @@
+mod options;
+mod source;
+
+use std::{
+    fs::File,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+use tokio::{
+    select,
+    sync::mpsc,
+    task::{self, JoinHandle},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use crate::*;
+
+pub use self::options::*;
+use self::source::TailSource;
+use super::stream::{Queue, StreamMessage};
+
+/// Tokio-backed observer of raw packet-stream files.
+///
+/// Unlike [`FileObserverStreamDef`], which tails slot-based storage, this observer tails
+/// a plain stream of packets, possibly mixed with other data (for example, text log lines),
+/// as written by [`PacketDef::write_all`] into a file. New bytes are fed through
+/// [`PacketBufReaderDef`], so the same rules can be used to filter packets.
+///
+/// The observer yields [`FileObserverEvent`] values:
+/// - [`FileObserverEvent::Packet`] for each packet passing the rules
+/// - [`FileObserverEvent::Ignored`] for bytes which don't belong to any packet
+/// - [`FileObserverEvent::Update`] after each processing pass; `total` is the number of
+///   packets found so far (including packets denied by rules), `added` is the number of
+///   packets found during the pass
+/// - [`FileObserverEvent::Error`] for non-terminal errors (e.g. a damaged packet)
+/// - [`FileObserverEvent::Truncated`] / [`FileObserverEvent::Replaced`] when the file is
+///   truncated or replaced (e.g. rotated); observation then restarts from the beginning of
+///   the file
+/// - [`FileObserverEvent::Stopped`] / [`FileObserverEvent::Aborted`] on completion
+///
+/// Observation can start from a stored byte offset (see [`StreamFileObserverDef::offset`]).
+///
+/// The channel is unbounded by default. Use [`StreamFileObserverOptions::bounded`] to limit
+/// the number of pending packets and choose an [`OverflowPolicy`].
+///
+/// As with other observers, the orchestration is async, but the file is read with
+/// synchronous, blocking I/O under the hood.
+pub struct StreamFileObserverDef<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    handler: Option<JoinHandle<()>>,
+    sd: CancellationToken,
+    queue: Arc<Queue<StreamMessage<B, P, Inner>>>,
+    offset: u64,
+    _phantom: PhantomData<(BR, O)>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Unpin for StreamFileObserverDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> StreamFileObserverDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Starts observing the file from options (rules, start offset, channel capacity).
+    ///
+    /// Rules are applied by the underlying [`PacketBufReaderDef`]. `RuleDef::Ignored` is used
+    /// by the observer itself to emit [`FileObserverEvent::Ignored`], so passing such a rule
+    /// fails with [`Error::RuleDuplicate`]; use `RuleDef::IgnoredControl` to reject ignored data.
+    pub fn with_options(
+        options: StreamFileObserverOptions<B, BR, P, Inner>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let StreamFileObserverOptions {
+            path,
+            rules,
+            offset,
+            capacity,
+            sensor,
+        } = options;
+        let queue = Arc::new(Queue::new(capacity));
+        let mut reader_rules = RulesDef::default();
+        let ignored = queue.clone();
+        reader_rules.add_rule(RuleDef::Ignored(RuleFnDef::Dynamic(Box::new(
+            move |bytes: &[u8]| {
+                ignored.send(StreamMessage::Event(FileObserverEvent::Ignored(
+                    bytes.to_vec(),
+                )));
+            },
+        ))))?;
+        for rule in rules {
+            reader_rules.add_rule(rule)?;
+        }
+        let file = File::open(&path)?;
+        let probe = file.try_clone()?;
+        let source = TailSource::new(file, offset)?;
+        let (sensor, wake_rx) = Sensor::with_mode(&path, sensor)?;
+        sensor.reset(&probe, offset)?;
+        let tail = Tail {
+            source,
+            probe,
+            rules: reader_rules,
+            queue: queue.clone(),
+            total: 0,
+            opt,
+        };
+        let sd = CancellationToken::new();
+        let handler = task::spawn(observe(tail, sensor, wake_rx, path, sd.clone()));
+        Ok(Self {
+            handler: Some(handler),
+            sd,
+            queue,
+            offset,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Starts observing the file from the given byte offset, applying the given rules.
+    ///
+    /// `offset` is typically a value previously returned by [`StreamFileObserverDef::offset`];
+    /// use `0` to read the file from the beginning. See [`StreamFileObserverDef::with_options`]
+    /// for rules reserved by the observer.
+    pub fn with_offset(
+        path: impl AsRef<Path>,
+        offset: u64,
+        rules: Vec<RuleDef<B, BR, P, Inner>>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let options = rules.into_iter().fold(
+            StreamFileObserverOptions::new(path).from_offset(offset),
+            |options, rule| options.with_rule(rule),
+        );
+        Self::with_options(options, opt)
+    }
+
+    /// Starts observing the file from the beginning, applying the given rules.
+    pub fn with_rules(
+        path: impl AsRef<Path>,
+        rules: Vec<RuleDef<B, BR, P, Inner>>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        Self::with_offset(path, 0, rules, opt)
+    }
+
+    /// Starts observing the file from the beginning with explicit protocol context options.
+    pub fn with_opt(path: impl AsRef<Path>, opt: O) -> Result<Self, Error> {
+        Self::with_rules(path, Vec::new(), opt)
+    }
+
+    /// Starts observing the file from the beginning using default protocol context options.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error>
+    where
+        O: Default,
+    {
+        Self::with_opt(path, O::default())
+    }
+
+    /// Returns the byte offset up to which the file has been processed.
+    ///
+    /// The offset is in sync with events already yielded by the stream: all packets before it
+    /// have been yielded (or skipped by rules) and all bytes which don't belong to packets have
+    /// been reported. A partially written packet is never counted, so passing the offset to
+    /// [`StreamFileObserverOptions::from_offset`] resumes observation without duplicates or gaps.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Stops the underlying observer task and waits for it to finish.
+    pub async fn shutdown(&mut self) {
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+        // Let the reader blocked by a full channel finish
+        self.queue.release();
+        self.sd.cancel();
+        let _ = handler.await;
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> tokio_stream::Stream for StreamFileObserverDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    type Item = FileObserverEvent<B, P, Inner>;
+
+    /// Polls the next observer event from the internal channel.
+    ///
+    /// Offset updates are consumed internally and never yielded.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.queue.poll_recv(cx) {
+                Poll::Ready(Some(StreamMessage::Event(event))) => return Poll::Ready(Some(event)),
+                Poll::Ready(Some(StreamMessage::Offset(offset))) => {
+                    this.offset = offset;
+                }
+                Poll::Ready(Some(StreamMessage::Cursor(..))) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Drop for StreamFileObserverDef<B, BR, P, Inner, O>
+{
+    /// Disconnects the queue, so a reader blocked by a full channel doesn't wait for a
+    /// consumer which is gone, and stops the observer task.
+    fn drop(&mut self) {
+        self.queue.disconnect();
+        self.sd.cancel();
+    }
+}
+
+/// Reading side of the observer.
+///
+/// Each processing pass is moved to the blocking thread pool: reading is blocking I/O and
+/// sending a packet to a bounded channel may wait for the consumer.
+struct Tail<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    source: TailSource,
+    /// Handle of the observed file used to check its size.
+    probe: File,
+    rules: RulesDef<B, BR, P, Inner>,
+    queue: Arc<Queue<StreamMessage<B, P, Inner>>>,
+    /// Number of packets found so far.
+    total: usize,
+    opt: O,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Tail<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Processes all currently available data (see [`drain`]).
+    ///
+    /// Returns the byte offset up to which the file has been read. Data taken by the reader
+    /// but not processed yet (e.g. a partially written packet) is read again by the next pass.
+    fn drain(&mut self) -> Result<u64, Error> {
+        let position = self.source.position();
+        let mut reader = PacketBufReaderDef::<TailSource, B, BR, P, Inner>::new(&mut self.source);
+        reader.set_rules(std::mem::take(&mut self.rules));
+        let result = drain(
+            &mut reader,
+            &self.probe,
+            &position,
+            &self.queue,
+            &mut self.total,
+            &mut self.opt,
+        );
+        let processed = processed(&reader, &position);
+        self.rules = reader.take_rules();
+        result?;
+        let read = position.load(Ordering::SeqCst);
+        self.source.rewind(processed)?;
+        Ok(read)
+    }
+
+    /// Runs [`Tail::drain`] on the blocking thread pool. Returns `None` if processing panicked.
+    async fn drain_blocking(mut self) -> Option<(Self, Result<u64, Error>)> {
+        match task::spawn_blocking(move || {
+            let result = self.drain();
+            (self, result)
+        })
+        .await
+        {
+            Ok(done) => Some(done),
+            Err(err) => {
+                error!("Stream file processing has failed: {err}");
+                None
+            }
+        }
+    }
+
+    /// Switches to the file at `path`, starting from its beginning.
+    fn reopen(&mut self, path: &Path) -> Result<(), Error> {
+        let file = File::open(path)?;
+        self.probe = file.try_clone()?;
+        self.source.reopen(file);
+        Ok(())
+    }
+}
+
+/// Observer task: processes the file on each wake until it's stopped, then closes the queue.
+async fn observe<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner,
+    O: Send + Sync + 'static,
+>(
+    tail: Tail<B, BR, P, Inner, O>,
+    sensor: Sensor,
+    wake_rx: mpsc::Receiver<Wake>,
+    path: PathBuf,
+    shutdown: CancellationToken,
+) where
+    Inner: PayloadInnerDef + Send + 'static,
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    let queue = tail.queue.clone();
+    if let Some(event) = process(tail, &sensor, wake_rx, &path, &shutdown).await {
+        queue.send(StreamMessage::Event(event));
+    }
+    drop(sensor);
+    debug!("sensor loop is closed");
+    queue.close();
+}
+
+/// Processes the file on each wake; observation restarts from the beginning of the file
+/// when it's truncated or replaced.
+///
+/// Returns the final event, or `None` if processing panicked.
+async fn process<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner,
+    O: Send + Sync + 'static,
+>(
+    mut tail: Tail<B, BR, P, Inner, O>,
+    sensor: &Sensor,
+    mut wake_rx: mpsc::Receiver<Wake>,
+    path: &Path,
+    shutdown: &CancellationToken,
+) -> Option<FileObserverEvent<B, P, Inner>>
+where
+    Inner: PayloadInnerDef + Send + 'static,
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    let stop_reason = loop {
+        let (done, result) = tail.drain_blocking().await?;
+        tail = done;
+        let read = match result {
+            Ok(read) => read,
+            Err(err) => break Some(err),
+        };
+        if let Err(err) = sensor.processed(read) {
+            break Some(Error::from(err));
+        }
+        let wake = select! {
+            biased;
+            _ = shutdown.cancelled() => {
+                debug!("Cancel signal has been gotten");
+                return Some(FileObserverEvent::Aborted);
+            }
+            wake = wake_rx.recv() => wake,
+        };
+        let Some(wake) = wake else {
+            break None;
+        };
+        let event = match wake.change {
+            FileChange::Grown => continue,
+            FileChange::Truncated => FileObserverEvent::Truncated,
+            FileChange::Replaced => FileObserverEvent::Replaced,
+        };
+        tail.queue.send(StreamMessage::Event(event));
+        // Restart from the beginning of the (new) file
+        if let Err(err) = tail.reopen(path) {
+            break Some(err);
+        }
+        if let Err(err) = sensor.reset(&tail.probe, 0) {
+            break Some(Error::from(err));
+        }
+    };
+    Some(FileObserverEvent::Stopped(
+        stop_reason.map(ObserverError::from),
+    ))
+}
+
+/// Returns the byte offset up to which the source has been processed by the reader.
+fn processed<B: BlockDef, BR: BlockReferredDef<B>, P: PayloadDef<Inner>, Inner: PayloadInnerDef>(
+    reader: &PacketBufReaderDef<'_, TailSource, B, BR, P, Inner>,
+    position: &AtomicU64,
+) -> u64 {
+    position
+        .load(Ordering::SeqCst)
+        .saturating_sub(reader.pending() as u64)
+}
+
+/// Reads all currently available data and sends found packets to the stream.
+///
+/// Reading stops once the file has no more data. Partially written packets stay in the
+/// reader until the rest of the packet is written.
+///
+/// Returns `Err` on a terminal error: an I/O error, ignored data rejected by
+/// `RuleDef::IgnoredControl`, an overflow of a bounded channel with
+/// [`OverflowPolicy::StopWithError`], or an error which doesn't let the reader move forward.
+fn drain<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner,
+    O: Send + Sync + 'static,
+>(
+    reader: &mut PacketBufReaderDef<'_, TailSource, B, BR, P, Inner>,
+    probe: &File,
+    position: &AtomicU64,
+    queue: &Queue<StreamMessage<B, P, Inner>>,
+    total: &mut usize,
+    opt: &mut O,
+) -> Result<(), Error>
+where
+    Inner: PayloadInnerDef + Send + 'static,
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    let mut added = 0;
+    loop {
+        let before = processed(reader, position);
+        match reader.read(opt) {
+            Ok(NextPacket::Found(packet)) => {
+                added += 1;
+                queue.send(StreamMessage::Offset(processed(reader, position)));
+                queue.send_packet(StreamMessage::Event(FileObserverEvent::Packet(packet)))?;
+            }
+            Ok(NextPacket::Skipped) => {
+                added += 1;
+            }
+            Ok(NextPacket::NotEnoughData(0)) => {
+                // Header has been found; the rest of buffered data is still to be processed
+            }
+            Ok(NextPacket::NotEnoughData(_)) | Ok(NextPacket::NotFound) => {
+                // All buffered data has been taken by the reader. Reading from an exhausted
+                // source would make the reader treat a partially written packet as ignored data.
+                if probe.metadata()?.len() <= position.load(Ordering::SeqCst) {
+                    break;
+                }
+            }
+            Ok(NextPacket::NoData) => break,
+            Err(err @ Error::Io(..)) | Err(err @ Error::IgnoredDataRejected) => {
+                return Err(err);
+            }
+            Err(err) => {
+                if processed(reader, position) == before {
+                    // The reader cannot skip the data, so the same error would repeat forever
+                    return Err(err);
+                }
+                queue.send(StreamMessage::Event(FileObserverEvent::Error(
+                    ObserverError::new(err)
+                        .at_packet(*total + added)
+                        .at_offset(before),
+                )));
+            }
+        }
+    }
+    if added > 0 {
+        *total += added;
+        queue.send(StreamMessage::Event(FileObserverEvent::Update {
+            total: *total,
+            added,
+        }));
+    }
+    queue.send(StreamMessage::Offset(processed(reader, position)));
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use crate::*;
+
+/// Builder options for creating [`StreamFileObserverDef`].
+pub struct StreamFileObserverOptions<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> {
+    /// Path to the observed stream file.
+    pub path: PathBuf,
+    /// Rules applied by the observer's reader; packets denied by rules never cross
+    /// the channel.
+    pub rules: Vec<RuleDef<B, BR, P, Inner>>,
+    /// Byte offset from which the observer starts reading the file.
+    pub offset: u64,
+    /// Capacity of the channel between the observer and the stream.
+    pub capacity: ChannelCapacity,
+    /// Defines how the observer detects changes of the file.
+    pub sensor: SensorMode,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> StreamFileObserverOptions<B, BR, P, Inner>
+{
+    /// Creates options for a target file path.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            rules: Vec::new(),
+            offset: 0,
+            capacity: ChannelCapacity::Unbounded,
+            sensor: SensorMode::default(),
+        }
+    }
+
+    /// Adds a filtering rule (`Prefilter`, `FilterPayload`, `FilterPacket`, etc.).
+    ///
+    /// See [`StreamFileObserverDef::with_offset`] for rules reserved by the observer.
+    pub fn with_rule(mut self, rule: RuleDef<B, BR, P, Inner>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Sets the byte offset from which the observer starts reading the file.
+    ///
+    /// The offset is typically a value previously returned by [`StreamFileObserverDef::offset`].
+    pub fn from_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Limits the number of pending packets. `0` is treated as `1`.
+    ///
+    /// By default the channel is unbounded.
+    pub fn bounded(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.capacity = ChannelCapacity::Bounded(capacity.max(1), policy);
+        self
+    }
+
+    /// Sets how the observer detects changes of the file.
+    ///
+    /// See [`FileObserverOptions::with_sensor`].
+    pub fn with_sensor(mut self, mode: SensorMode) -> Self {
+        self.sensor = mode;
+        self
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+> std::fmt::Debug for StreamFileObserverOptions<B, BR, P, Inner>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StreamFileObserverOptions: {}", self.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamFileObserverOptions;
+    use crate::{
+        ChannelCapacity, OverflowPolicy, RuleDef, RuleFnDef, SensorMode,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::time::Duration;
+
+    type Options = StreamFileObserverOptions<TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    #[test]
+    fn stream_file_options_defaults_are_unbounded_from_beginning() {
+        let opts = Options::new("abc/stream.bin");
+        assert!(opts.path.ends_with("abc/stream.bin"));
+        assert!(opts.rules.is_empty());
+        assert_eq!(opts.offset, 0);
+        assert_eq!(opts.capacity, ChannelCapacity::Unbounded);
+        assert_eq!(opts.sensor, SensorMode::default());
+    }
+
+    #[test]
+    fn stream_file_options_builder_sets_fields() {
+        let opts = Options::new("x.bin")
+            .with_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| true)))
+            .from_offset(42)
+            .bounded(0, OverflowPolicy::Block)
+            .with_sensor(SensorMode::Poll(Duration::from_millis(100)));
+        assert_eq!(opts.rules.len(), 1);
+        assert_eq!(opts.offset, 42);
+        assert_eq!(
+            opts.capacity,
+            ChannelCapacity::Bounded(1, OverflowPolicy::Block)
+        );
+        assert_eq!(opts.sensor, SensorMode::Poll(Duration::from_millis(100)));
+        assert!(format!("{opts:?}").contains("x.bin"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// File source which tracks the position of the last byte taken from the file.
+///
+/// The position is shared, so the observer can read it while the source is borrowed
+/// by the packet reader.
+pub(super) struct TailSource {
+    file: File,
+    position: Arc<AtomicU64>,
+}
+
+impl TailSource {
+    /// Opens a source positioned at `offset`.
+    pub(super) fn new(mut file: File, offset: u64) -> std::io::Result<Self> {
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Self {
+            file,
+            position: Arc::new(AtomicU64::new(offset)),
+        })
+    }
+
+    /// Switches the source to another file, positioned at its beginning.
+    ///
+    /// Used when the observed file has been truncated or replaced.
+    pub(super) fn reopen(&mut self, file: File) {
+        self.file = file;
+        self.position.store(0, Ordering::SeqCst);
+    }
+
+    /// Moves the source back to `offset`, so the data after it is read again.
+    ///
+    /// Used when the reader which has taken the data is dropped before processing all of it.
+    pub(super) fn rewind(&mut self, offset: u64) -> std::io::Result<()> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.position.store(offset, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Returns a handle to the position of the last byte taken from the file.
+    pub(super) fn position(&self) -> Arc<AtomicU64> {
+        self.position.clone()
+    }
+}
+
+impl Read for TailSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.file.read(buf)?;
+        self.position.fetch_add(read as u64, Ordering::SeqCst);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TailSource;
+    use std::{
+        io::{Read, Write},
+        sync::atomic::Ordering,
+    };
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn tail_source_tracks_position_from_offset() {
+        let mut file = NamedTempFile::new().expect("temp file");
+        file.write_all(b"0123456789").expect("write");
+        let mut source =
+            TailSource::new(file.reopen().expect("reopen"), 4).expect("source created");
+        let position = source.position();
+        assert_eq!(position.load(Ordering::SeqCst), 4);
+        let mut buf = [0u8; 3];
+        source.read_exact(&mut buf).expect("read");
+        assert_eq!(&buf, b"456");
+        assert_eq!(position.load(Ordering::SeqCst), 7);
+        file.write_all(b"ab").expect("write more");
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest).expect("read rest");
+        assert_eq!(rest, b"789ab");
+        assert_eq!(position.load(Ordering::SeqCst), 12);
+    }
+
+    #[test]
+    fn tail_source_reopen_starts_from_beginning() {
+        let mut first = NamedTempFile::new().expect("temp file");
+        first.write_all(b"0123456789").expect("write");
+        let mut second = NamedTempFile::new().expect("temp file");
+        second.write_all(b"abc").expect("write");
+        let mut source =
+            TailSource::new(first.reopen().expect("reopen"), 8).expect("source created");
+        let position = source.position();
+        source.reopen(second.reopen().expect("reopen"));
+        assert_eq!(position.load(Ordering::SeqCst), 0);
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest).expect("read rest");
+        assert_eq!(rest, b"abc");
+        assert_eq!(position.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn tail_source_rewind_reads_data_again() {
+        let mut file = NamedTempFile::new().expect("temp file");
+        file.write_all(b"0123456789").expect("write");
+        let mut source =
+            TailSource::new(file.reopen().expect("reopen"), 0).expect("source created");
+        let position = source.position();
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest).expect("read");
+        assert_eq!(position.load(Ordering::SeqCst), 10);
+        source.rewind(6).expect("rewind");
+        assert_eq!(position.load(Ordering::SeqCst), 6);
+        rest.clear();
+        source.read_to_end(&mut rest).expect("read rest");
+        assert_eq!(rest, b"6789");
+        assert_eq!(position.load(Ordering::SeqCst), 10);
+    }
+}
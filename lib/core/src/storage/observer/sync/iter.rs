@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use crate::*;
+
+use super::super::stream::{Queue, StreamMessage};
+
+/// Thread-based observer exposed as an iterator of [`FileObserverEvent`] values.
+///
+/// A counterpart of [`FileObserverStreamDef`] for applications without an async runtime:
+/// it takes the same [`FileObserverStreamOptions`] (including the channel capacity and
+/// [`OverflowPolicy`]) and yields the same events. [`Iterator::next`] blocks the calling
+/// thread until the next event is available and returns `None` once the observer has
+/// stopped and all events have been taken.
+pub struct SyncFileObserverIterDef<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    observer: SyncFileObserverDef<B, BR, P, Inner, O>,
+    queue: Arc<Queue<StreamMessage<B, P, Inner>>>,
+    cursor: usize,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> SyncFileObserverIterDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Creates an observer iterator from options (rules, start position, channel capacity).
+    pub fn with_options(
+        options: FileObserverStreamOptions<B, BR, P, Inner>,
+        opt: O,
+    ) -> Result<Self, Error> {
+        let cursor = options.start.resolve(0);
+        let queue = Arc::new(Queue::new(options.capacity));
+        let observer_options = options.into_observer_options(&queue);
+        Ok(Self {
+            observer: SyncFileObserverDef::with_opt(observer_options, opt)?,
+            queue,
+            cursor,
+        })
+    }
+
+    /// Creates an observer iterator with explicit protocol context options.
+    pub fn with_opt(path: impl AsRef<std::path::Path>, opt: O) -> Result<Self, Error> {
+        Self::with_options(FileObserverStreamOptions::new(path), opt)
+    }
+
+    /// Creates an observer iterator using default protocol context options.
+    pub fn new(path: impl AsRef<std::path::Path>) -> Result<Self, Error>
+    where
+        O: Default,
+    {
+        Self::with_opt(path, O::default())
+    }
+
+    /// Returns the global index of the next packet to be checked.
+    ///
+    /// The cursor is in sync with events already taken from the iterator (see
+    /// [`FileObserverStreamDef::cursor`]).
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Stops the observer thread and waits for it to finish.
+    ///
+    /// Events produced before shutdown (including [`FileObserverEvent::Aborted`]) can still
+    /// be taken from the iterator.
+    pub fn shutdown(&mut self) {
+        // Let an observer blocked by a full channel finish
+        self.queue.release();
+        self.observer.shutdown();
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Iterator for SyncFileObserverIterDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    type Item = FileObserverEvent<B, P, Inner>;
+
+    /// Blocks until the next observer event is available.
+    ///
+    /// Cursor updates are consumed internally and never yielded.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.queue.recv()? {
+                StreamMessage::Event(event) => return Some(event),
+                StreamMessage::Cursor(cursor) => {
+                    self.cursor = cursor;
+                }
+                #[cfg(feature = "observer")]
+                StreamMessage::Offset(..) => {}
+            }
+        }
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Drop for SyncFileObserverIterDef<B, BR, P, Inner, O>
+{
+    /// Disconnects the queue, so the observer thread doesn't wait for a consumer which is gone.
+    fn drop(&mut self) {
+        self.queue.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DefaultProtocolContext, FileObserverEvent, FileObserverStreamOptions, OverflowPolicy,
+        PacketDef, SyncFileObserverIterDef, WriterDef,
+        tests::{TestBlock, TestPayload},
+    };
+    use tempfile::NamedTempFile;
+
+    type Packet = PacketDef<TestBlock, TestPayload, TestPayload>;
+    type Iter = SyncFileObserverIterDef<
+        TestBlock,
+        TestBlock,
+        TestPayload,
+        TestPayload,
+        DefaultProtocolContext,
+    >;
+
+    #[test]
+    fn sync_iterator_yields_packets_and_ends_after_shutdown() {
+        let file = NamedTempFile::new().expect("temp file");
+        let mut wfile = file.reopen().expect("reopen");
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(&mut wfile).expect("writer");
+        for _ in 0..4 {
+            writer
+                .insert(Packet::default(), &mut ())
+                .expect("packet written");
+        }
+
+        let mut events = Iter::with_options(
+            FileObserverStreamOptions::new(file.path()).bounded(1, OverflowPolicy::Block),
+            (),
+        )
+        .expect("observer created");
+        let mut packets = 0;
+        while packets < 4 {
+            match events.next().expect("iterator must not end") {
+                FileObserverEvent::Packet(..) => packets += 1,
+                FileObserverEvent::Update { .. } => {}
+                _ => panic!("unexpected event"),
+            }
+        }
+        assert_eq!(events.cursor(), 4);
+
+        events.shutdown();
+        let rest = events.by_ref().collect::<Vec<_>>();
+        assert!(matches!(rest.last(), Some(FileObserverEvent::Aborted)));
+        assert!(events.next().is_none());
+    }
+}
@@ -0,0 +1,402 @@
+mod iter;
+
+use std::{
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::*;
+
+use super::delivery::Delivery;
+
+pub use self::iter::*;
+
+/// How often the observer thread checks for a shutdown request while waiting for changes.
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Thread-based observer that tails a storage file and forwards packets to a subscription.
+///
+/// A counterpart of [`FileObserverDef`] for applications without an async runtime: the
+/// observer runs on a dedicated std thread and gets wake signals through `std::sync::mpsc`.
+/// It accepts the same [`FileObserverOptions`] and calls the same [`SubscriptionDef`]
+/// callbacks; the callbacks are invoked on the observer thread.
+///
+/// Dropping the observer shuts it down and waits for the thread to finish.
+pub struct SyncFileObserverDef<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> {
+    handler: Option<JoinHandle<()>>,
+    sd: Arc<AtomicBool>,
+    cursor: Arc<AtomicUsize>,
+    _phantom: PhantomData<(B, BR, P, Inner, O)>,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> SyncFileObserverDef<B, BR, P, Inner, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Creates observer with explicit protocol context options.
+    pub fn with_opt<S>(
+        mut options: FileObserverOptions<B, BR, P, Inner, S, O>,
+        opt: O,
+    ) -> Result<Self, Error>
+    where
+        S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    {
+        let Some(subscription) = options.subscription.take() else {
+            return Err(Error::NoSubscription);
+        };
+        let sd = Arc::new(AtomicBool::new(false));
+        let cursor = Arc::new(AtomicUsize::new(0));
+        let file = std::fs::File::open(&options.path)?;
+        let mut reader: ReaderDef<std::fs::File, B, BR, P, Inner> =
+            ReaderDef::new(file.try_clone()?)?;
+        for rule in options.rules.drain(..) {
+            reader.add_rule(rule)?;
+        }
+        let mut delivery = Delivery {
+            reader,
+            subscription,
+            last: 0,
+            cursor: cursor.clone(),
+            max_batch: options.max_batch.max(1),
+            opt,
+        };
+
+        let (sensor, wake_rx) = Sensor::with_mode_sync(&options.path, options.sensor)?;
+        // Bind the sensor to the opened file and the packets already written to it
+        let (written, processed) = delivery.reader.written()?;
+        sensor.reset(&file, processed)?;
+        let worker = Worker {
+            delivery,
+            written,
+            sensor,
+            wake_rx,
+            shutdown: sd.clone(),
+            start: options.start,
+            path: options.path.clone(),
+            reopen: options.reopen,
+        };
+        let handler = thread::Builder::new()
+            .name("brec-observer".to_owned())
+            .spawn(move || worker.run())?;
+        Ok(Self {
+            handler: Some(handler),
+            sd,
+            cursor,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Creates observer using default protocol context options.
+    pub fn new<S>(options: FileObserverOptions<B, BR, P, Inner, S, O>) -> Result<Self, Error>
+    where
+        S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+        O: Default,
+    {
+        Self::with_opt(options, O::default())
+    }
+
+    /// Returns the global index of the next packet the observer will check.
+    ///
+    /// See [`FileObserverDef::cursor`].
+    pub fn cursor(&self) -> usize {
+        self.cursor.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the observer thread has finished (the observer stopped or was
+    /// shut down).
+    pub fn is_finished(&self) -> bool {
+        self.handler
+            .as_ref()
+            .is_none_or(|handler| handler.is_finished())
+    }
+
+    /// Requests shutdown and waits for the observer thread to finish.
+    ///
+    /// The subscription gets `on_aborted()` unless the observer has already stopped. A
+    /// subscription blocked in a callback delays shutdown until the callback returns.
+    pub fn shutdown(&mut self) {
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+        self.sd.store(true, Ordering::SeqCst);
+        let _ = handler.join();
+    }
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    O: Send + Sync + 'static,
+> Drop for SyncFileObserverDef<B, BR, P, Inner, O>
+{
+    /// Stops the observer thread, so it never outlives the observer handle.
+    fn drop(&mut self) {
+        let Some(handler) = self.handler.take() else {
+            return;
+        };
+        self.sd.store(true, Ordering::SeqCst);
+        let _ = handler.join();
+    }
+}
+
+/// State owned by the observer thread.
+struct Worker<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    O: Send + Sync + 'static,
+> {
+    delivery: Delivery<B, BR, P, Inner, S, O>,
+    /// Number of packets completely written to the file when the observer started.
+    written: usize,
+    sensor: Sensor,
+    wake_rx: Receiver<Wake>,
+    shutdown: Arc<AtomicBool>,
+    start: StartPosition,
+    path: PathBuf,
+    reopen: bool,
+}
+
+impl<
+    B: BlockDef + Send + 'static,
+    BR: BlockReferredDef<B> + 'static,
+    P: PayloadDef<Inner> + Send + 'static,
+    Inner: PayloadInnerDef + Send + 'static,
+    S: SubscriptionDef<B, BR, P, Inner, O> + 'static,
+    O: Send + Sync + 'static,
+> Worker<B, BR, P, Inner, S, O>
+where
+    for<'a> Inner: ProtocolSchema<Context<'a> = O>,
+{
+    /// Observer loop; mirrors the loop of [`FileObserverDef`].
+    fn run(mut self) {
+        let delivery = &mut self.delivery;
+        let mut count = self.written;
+        delivery.last = self.start.resolve(count);
+        delivery.cursor.store(delivery.last, Ordering::SeqCst);
+        delivery.subscription.on_cursor(delivery.last);
+        if matches!(
+            delivery
+                .subscription
+                .on_update(count, count.saturating_sub(delivery.last)),
+            SubscriptionUpdate::Read
+        ) {
+            // Load first existed
+            if let Err(err) = delivery.deliver(count) {
+                delivery.subscription.on_stopped(Some(err));
+                return;
+            }
+        }
+        let stop_reason = loop {
+            if self.shutdown.load(Ordering::SeqCst) {
+                delivery.subscription.on_aborted();
+                return;
+            }
+            let wake = match self.wake_rx.recv_timeout(SHUTDOWN_CHECK_INTERVAL) {
+                Ok(wake) => wake,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break None,
+            };
+            if wake.change != FileChange::Grown {
+                if wake.change == FileChange::Truncated {
+                    delivery.subscription.on_truncated();
+                } else {
+                    delivery.subscription.on_replaced();
+                }
+                if !self.reopen {
+                    break None;
+                }
+                count = match delivery.reopen(&self.path, &self.sensor) {
+                    Ok(count) => count,
+                    Err(err) => {
+                        let err = ObserverError::new(err);
+                        let _ = delivery.subscription.on_error(&err);
+                        break Some(err);
+                    }
+                };
+                if !matches!(
+                    delivery.subscription.on_update(count, count),
+                    SubscriptionUpdate::Read
+                ) {
+                    continue;
+                }
+                if let Err(err) = delivery.deliver(count) {
+                    break Some(err);
+                }
+                continue;
+            }
+            let (written, processed) = match delivery.reload() {
+                Ok(written) => written,
+                Err(Error::NotEnoughData(_)) => {
+                    continue;
+                }
+                Err(err) => {
+                    let err = ObserverError::new(err).at_offset(delivery.reader.get_offset());
+                    let _ = delivery.subscription.on_error(&err);
+                    break Some(err);
+                }
+            };
+            if let Err(err) = self.sensor.processed(processed) {
+                let err = ObserverError::new(err.into());
+                let _ = delivery.subscription.on_error(&err);
+                break Some(err);
+            }
+            if written <= count {
+                continue;
+            }
+            let added = written - count;
+            count = written;
+            if !matches!(
+                delivery.subscription.on_update(count, added),
+                SubscriptionUpdate::Read
+            ) {
+                continue;
+            }
+            if let Err(err) = delivery.deliver(count) {
+                break Some(err);
+            }
+        };
+        delivery.subscription.on_stopped(stop_reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DefaultProtocolContext, Error, FileObserverOptions, ObserverError, PacketDef,
+        SubscriptionDef, SubscriptionUpdate, SyncFileObserverDef, WriterDef,
+        tests::{TestBlock, TestPayload},
+    };
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+    use tempfile::NamedTempFile;
+
+    type Packet = PacketDef<TestBlock, TestPayload, TestPayload>;
+
+    #[derive(Default)]
+    struct Log {
+        packets: usize,
+        cursor: usize,
+        stopped: usize,
+        aborted: usize,
+    }
+
+    struct LogSubscription(Arc<Mutex<Log>>);
+
+    impl SubscriptionDef<TestBlock, TestBlock, TestPayload, TestPayload, DefaultProtocolContext>
+        for LogSubscription
+    {
+        fn on_update(&mut self, _total: usize, _added: usize) -> SubscriptionUpdate {
+            SubscriptionUpdate::Read
+        }
+
+        fn on_cursor(&mut self, cursor: usize) {
+            self.0.lock().unwrap().cursor = cursor;
+        }
+
+        fn on_packet(&mut self, _packet: Packet) {
+            self.0.lock().unwrap().packets += 1;
+        }
+
+        fn on_stopped(&mut self, _reason: Option<ObserverError>) {
+            self.0.lock().unwrap().stopped += 1;
+        }
+
+        fn on_aborted(&mut self) {
+            self.0.lock().unwrap().aborted += 1;
+        }
+    }
+
+    type Options = FileObserverOptions<
+        TestBlock,
+        TestBlock,
+        TestPayload,
+        TestPayload,
+        LogSubscription,
+        DefaultProtocolContext,
+    >;
+
+    fn wait_for(log: &Mutex<Log>, packets: usize) {
+        let started = Instant::now();
+        while log.lock().unwrap().packets < packets {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "observer didn't deliver {packets} packets in time"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn sync_observer_requires_subscription_and_file() {
+        let file = NamedTempFile::new().expect("temp file");
+        let result = SyncFileObserverDef::with_opt(Options::new(file.path()), ());
+        assert!(matches!(result, Err(Error::NoSubscription)));
+
+        let log = Arc::new(Mutex::new(Log::default()));
+        let options = Options::new("/tmp/brec-sync-observer-missing-file-for-tests.bin")
+            .subscribe(LogSubscription(log));
+        let result = SyncFileObserverDef::with_opt(options, ());
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn sync_observer_delivers_existing_and_new_packets() {
+        let file = NamedTempFile::new().expect("temp file");
+        let mut wfile = file.reopen().expect("reopen");
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(&mut wfile).expect("writer");
+        for _ in 0..3 {
+            writer
+                .insert(Packet::default(), &mut ())
+                .expect("packet written");
+        }
+
+        let log = Arc::new(Mutex::new(Log::default()));
+        let options = Options::new(file.path()).subscribe(LogSubscription(log.clone()));
+        let mut observer = SyncFileObserverDef::new(options).expect("observer created");
+        wait_for(&log, 3);
+        assert_eq!(observer.cursor(), 3);
+
+        for _ in 0..2 {
+            writer
+                .insert(Packet::default(), &mut ())
+                .expect("packet written");
+        }
+        wait_for(&log, 5);
+        assert_eq!(log.lock().unwrap().cursor, 5);
+
+        observer.shutdown();
+        observer.shutdown();
+        assert!(observer.is_finished());
+        let log = log.lock().unwrap();
+        assert_eq!(log.packets, 5);
+        assert_eq!(log.aborted, 1);
+        assert_eq!(log.stopped, 0);
+    }
+}
@@ -0,0 +1,282 @@
+use std::{fs::File, path::Path};
+
+use crate::*;
+
+/// `FileReaderDef` provides a wrapper around `ReaderDef<File, ...>` that cooperates with
+/// [`FileWriterDef`] through the `.access` companion lock file.
+///
+/// Slots are loaded (on creation and on each [`FileReaderDef::reload`]) under a short-lived
+/// shared lock. Writers take the exclusive lock only while a slot and a packet are written,
+/// so a reader never sees a slot header which is being rewritten or a packet which isn't
+/// completely written yet. Any number of readers can work with the same file at the same time,
+/// including readers in other processes.
+///
+/// Packets are read without taking the lock: only already indexed (completely written) packets
+/// are accessible, and writers never rewrite them.
+///
+/// The reader opens an existing `.access` file read-only, so it also works on read-only files,
+/// directories and mounts. If the `.access` file doesn't exist and can't be created, the reader
+/// works without the lock; nobody can write to such storage anyway.
+///
+/// Note: this is an *advisory lock*; see [`FileWriterDef`] for details.
+pub struct FileReaderDef<
+    B: BlockDef,
+    BR: BlockReferredDef<B>,
+    P: PayloadDef<Inner>,
+    Inner: PayloadInnerDef,
+> {
+    access: Option<File>,
+    inner: ReaderDef<File, B, BR, P, Inner>,
+}
+
+impl<B: BlockDef, BR: BlockReferredDef<B>, P: PayloadDef<Inner>, Inner: PayloadInnerDef>
+    FileReaderDef<B, BR, P, Inner>
+{
+    /// Opens the storage file for reading and loads its slots under a shared access lock.
+    ///
+    /// If a writer is currently writing a packet, this method waits until the write is complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - Path to the storage file.
+    ///
+    /// # Errors
+    ///
+    /// This function returns:
+    ///
+    /// * [`Error::PathIsNotFile`] - if the specified path exists but is not a regular file.
+    /// * [`Error::FailToLockFile`] - if the existing `.access` file cannot be opened or locked.
+    /// * [`Error::Io`] - if the storage file cannot be opened for reading.
+    /// * Any error returned by [`ReaderDef::new`] if slots cannot be loaded.
+    pub fn new<F: AsRef<Path>>(filename: F) -> Result<Self, Error> {
+        let filename = filename.as_ref();
+        if filename.exists() && !filename.is_file() {
+            return Err(Error::PathIsNotFile(filename.to_string_lossy().to_string()));
+        }
+        let access = AccessLock::open_read(filename)?;
+        let inner = {
+            let _access = access.as_ref().map(AccessLock::shared).transpose()?;
+            ReaderDef::new(File::open(filename)?)?
+        };
+        Ok(Self { access, inner })
+    }
+
+    /// Re-reads storage metadata under a shared access lock and returns the number of newly
+    /// discovered packets (see [`ReaderDef::reload`]).
+    pub fn reload(&mut self) -> Result<usize, Error> {
+        let _access = self.access.as_ref().map(AccessLock::shared).transpose()?;
+        self.inner.reload()
+    }
+
+    /// Returns the number of records currently known to the reader.
+    pub fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Returns the absolute end offset of the currently known storage contents.
+    pub fn get_offset(&self) -> u64 {
+        self.inner.get_offset()
+    }
+
+    /// Collects storage statistics (see [`ReaderDef::stats`]).
+    pub fn stats(&mut self) -> Result<StorageStats, Error> {
+        self.inner.stats()
+    }
+
+    /// Adds a packet filter or processing rule (see [`ReaderDef::add_rule`]).
+    pub fn add_rule(&mut self, rule: RuleDef<B, BR, P, Inner>) -> Result<(), Error> {
+        self.inner.add_rule(rule)
+    }
+
+    /// Removes a previously added rule by its identifier.
+    pub fn remove_rule(&mut self, rule: RuleDefId) {
+        self.inner.remove_rule(rule);
+    }
+
+    /// Returns an iterator over all packets in the storage (no filtering).
+    pub fn iter<'a>(
+        &'a mut self,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> ReaderIterator<'a, impl Iterator<Item = &'a Slot>, File, B, P, Inner> {
+        self.inner.iter(ctx)
+    }
+
+    /// Returns an iterator positioned at the given packet index.
+    pub fn seek<'a>(
+        &'a mut self,
+        packet: usize,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> Result<ReaderIterator<'a, impl Iterator<Item = &'a Slot>, File, B, P, Inner>, Error> {
+        self.inner.seek(packet, ctx)
+    }
+
+    /// Returns a filtered iterator over packets using configured rules.
+    pub fn filtered<'a>(
+        &'a mut self,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> ReaderFilteredIterator<'a, impl Iterator<Item = &'a Slot>, File, B, BR, P, Inner> {
+        self.inner.filtered(ctx)
+    }
+
+    /// Retrieves the `nth` packet by global index (see [`ReaderDef::nth`]).
+    pub fn nth(
+        &mut self,
+        nth: usize,
+        ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
+    ) -> Result<Option<PacketDef<B, P, Inner>>, Error> {
+        self.inner.nth(nth, ctx)
+    }
+
+    /// Returns an iterator over a specific range of packets by global index.
+    pub fn range<'a>(
+        &'a mut self,
+        from: usize,
+        len: usize,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> ReaderRangeIterator<'a, File, B, BR, P, Inner> {
+        self.inner.range(from, len, ctx)
+    }
+
+    /// Returns a filtered range iterator applying rules to each packet.
+    pub fn range_filtered<'a>(
+        &'a mut self,
+        from: usize,
+        len: usize,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> ReaderRangeFilteredIterator<'a, File, B, BR, P, Inner> {
+        self.inner.range_filtered(from, len, ctx)
+    }
+
+    /// Returns the number of packets which pass the configured rules
+    /// (see [`ReaderDef::count_filtered`]).
+    pub fn count_filtered(
+        &mut self,
+        ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
+    ) -> Result<usize, Error> {
+        self.inner.count_filtered(ctx)
+    }
+
+    /// Returns an iterator over global indices of matching packets
+    /// (see [`ReaderDef::matching_indices`]).
+    pub fn matching_indices<'a>(
+        &'a mut self,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> impl Iterator<Item = Result<usize, Error>> + 'a {
+        self.inner.matching_indices(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::{storage::writer::AccessLock, tests::*};
+    use std::{
+        env::temp_dir,
+        fs,
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+        time::{Duration, Instant},
+    };
+
+    type TestWriter = FileWriterDef<TestBlock, TestPayload, TestPayload, DefaultProtocolContext>;
+    type TestReader = FileReaderDef<TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    fn packet() -> PacketDef<TestBlock, TestPayload, TestPayload> {
+        PacketDef::new(Vec::new(), None)
+    }
+
+    #[test]
+    fn readers_share_file_with_writer() {
+        let filename = temp_dir().join("test_brec_filereader_shared.bin");
+        if filename.exists() {
+            fs::remove_file(&filename).expect("Test file has been removed");
+        }
+        let mut writer =
+            TestWriter::with_opt(&filename, None, None, ()).expect("Writer has been created");
+        for _ in 0..10 {
+            writer.insert(packet()).expect("Packet has been written");
+        }
+        let mut a = TestReader::new(&filename).expect("Reader A has been created");
+        let mut b = TestReader::new(&filename).expect("Reader B has been created");
+        assert_eq!(a.count(), 10);
+        assert_eq!(b.count(), 10);
+
+        for _ in 10..20 {
+            writer.insert(packet()).expect("Packet has been written");
+        }
+        assert_eq!(a.reload().expect("Reader A has been reloaded"), 10);
+        assert_eq!(b.reload().expect("Reader B has been reloaded"), 10);
+        let packets = a
+            .iter(&mut ())
+            .collect::<Result<Vec<_>, Error>>()
+            .expect("Packets have been read");
+        assert_eq!(packets.len(), 20);
+        assert!(b.nth(19, &mut ()).expect("Packet has been read").is_some());
+    }
+
+    #[test]
+    fn reload_waits_for_write() {
+        let filename = temp_dir().join("test_brec_filereader_wait.bin");
+        if filename.exists() {
+            fs::remove_file(&filename).expect("Test file has been removed");
+        }
+        let mut writer =
+            TestWriter::with_opt(&filename, None, None, ()).expect("Writer has been created");
+        writer.insert(packet()).expect("Packet has been written");
+        let mut reader = TestReader::new(&filename).expect("Reader has been created");
+
+        let (tx, rx) = channel();
+        let filename_writer = filename.clone();
+        let holder = spawn(move || {
+            let access = AccessLock::open(&filename_writer).expect("Access file has been opened");
+            let lock = AccessLock::exclusive(&access).expect("Access has been locked");
+            tx.send(()).expect("Signal has been send");
+            sleep(Duration::from_millis(200));
+            drop(lock);
+            writer.insert(packet()).expect("Packet has been written");
+        });
+        rx.recv().expect("Signal has been gotten");
+        let started = Instant::now();
+        reader.reload().expect("Reader has been reloaded");
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        holder.join().expect("Holder has been finished");
+        reader.reload().expect("Reader has been reloaded");
+        assert_eq!(reader.count(), 2);
+    }
+
+    #[test]
+    fn reader_opens_read_only_storage() {
+        let filename = temp_dir().join("test_brec_filereader_read_only.bin");
+        let access = filename.with_extension("access");
+        for path in [&filename, &access] {
+            if path.exists() {
+                fs::remove_file(path).expect("Test file has been removed");
+            }
+        }
+        {
+            let mut writer =
+                TestWriter::with_opt(&filename, None, None, ()).expect("Writer has been created");
+            writer.insert(packet()).expect("Packet has been written");
+        }
+        let original = [&filename, &access].map(|path| {
+            let permissions = fs::metadata(path).expect("Metadata").permissions();
+            let mut readonly = permissions.clone();
+            readonly.set_readonly(true);
+            fs::set_permissions(path, readonly).expect("Permissions have been set");
+            (path, permissions)
+        });
+        let result = TestReader::new(&filename).map(|mut reader| {
+            reader.reload().expect("Reader has been reloaded");
+            reader.count()
+        });
+        for (path, permissions) in original {
+            fs::set_permissions(path, permissions).expect("Permissions have been restored");
+        }
+        assert_eq!(result.expect("Reader has been created"), 1);
+
+        // Without the access file the reader creates it
+        fs::remove_file(&access).expect("Access file has been removed");
+        TestReader::new(&filename).expect("Reader has been created");
+        assert!(access.exists());
+    }
+}
@@ -1,7 +1,13 @@
 mod iters;
+#[cfg(feature = "locked_storage")]
+mod locker;
+mod stats;
 
 use crate::*;
 pub(crate) use iters::*;
+#[cfg(feature = "locked_storage")]
+pub use locker::FileReaderDef;
+pub use stats::StorageStats;
 
 /// Result of `ReaderDef::nth_filtered`, containing either a filtered packet outcome or no packet.
 pub type NthFilteredPacket<B, P, Inner> = Option<LookInStatus<PacketDef<B, P, Inner>>>;
@@ -164,6 +170,23 @@ impl<
         Ok(read)
     }
 
+    /// Replaces the storage backend (e.g. a reopened file) and loads it from scratch.
+    ///
+    /// Rules are kept. On failure the reader stays unchanged.
+    #[cfg(any(feature = "observer", feature = "observer-sync"))]
+    pub(crate) fn reopen(&mut self, inner: S) -> Result<(), Error> {
+        let mut reopened = Self {
+            slots: Vec::new(),
+            inner,
+            locator: FreeSlotLocator::default(),
+            rules: RulesDef::default(),
+        }
+        .load()?;
+        reopened.rules = std::mem::take(&mut self.rules);
+        *self = reopened;
+        Ok(())
+    }
+
     /// Adds a packet filter or processing rule.
     ///
     /// # Arguments
@@ -324,6 +347,78 @@ impl<
         ReaderRangeFilteredIterator::new(self, from, len, ctx)
     }
 
+    /// Returns the number of packets which pass the configured rules.
+    ///
+    /// Unlike [`ReaderDef::filtered`], packets aren't materialized: only blocks are parsed
+    /// for `Prefilter` and the raw payload bytes are read for `FilterPayload`. Payloads are
+    /// decoded only if a `FilterPacket` rule is configured.
+    ///
+    /// # Returns
+    /// * `Ok(usize)` - Number of matching packets
+    /// * `Err(Error)` - On I/O or parse failure
+    pub fn count_filtered(
+        &mut self,
+        ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
+    ) -> Result<usize, Error> {
+        let mut count = 0;
+        for nth in 0..self.count() {
+            if self.nth_matches(nth, ctx)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns an iterator over global indices of packets which pass the configured rules.
+    ///
+    /// Packets are checked the same way as in [`ReaderDef::count_filtered`], so the indices
+    /// can be used with [`ReaderDef::nth`] or [`ReaderDef::range`] to load only the packets
+    /// which are really needed (e.g. a page of results).
+    ///
+    /// # Returns
+    /// * Iterator yielding `Ok(index)` for each matching packet or `Err(Error)` on failure
+    pub fn matching_indices<'a>(
+        &'a mut self,
+        ctx: &'a mut <Inner as ProtocolSchema>::Context<'a>,
+    ) -> impl Iterator<Item = Result<usize, Error>> + 'a {
+        (0..self.count()).filter_map(move |nth| match self.nth_matches(nth, ctx) {
+            Ok(true) => Some(Ok(nth)),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        })
+    }
+
+    /// Checks whether the `nth` packet passes the configured rules without building it.
+    ///
+    /// Returns `Ok(false)` if there is no packet at the given index.
+    fn nth_matches(
+        &mut self,
+        nth: usize,
+        ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
+    ) -> Result<bool, Error> {
+        let slot_index = nth / DEFAULT_SLOT_CAPACITY;
+        let index_in_slot = nth % DEFAULT_SLOT_CAPACITY;
+        let Some(slot) = self.slots.get(slot_index) else {
+            return Ok(false);
+        };
+        if slot.is_empty(index_in_slot)? {
+            return Ok(false);
+        }
+        let Some(mut offset) = slot.get_slot_offset(index_in_slot) else {
+            return Ok(false);
+        };
+        offset += self.slots[..slot_index]
+            .iter()
+            .map(|slot| slot.width() + slot.size())
+            .sum::<u64>();
+        self.inner.seek(std::io::SeekFrom::Start(offset))?;
+        match PacketDef::<B, P, Inner>::matches(&mut self.inner, &self.rules, ctx)? {
+            LookInStatus::Accepted(..) => Ok(true),
+            LookInStatus::Denied(..) => Ok(false),
+            LookInStatus::NotEnoughData(needed) => Err(Error::NotEnoughData(needed)),
+        }
+    }
+
     /// Returns the filtered result of the `nth` packet.
     ///
     /// This method applies all configured rules (block, payload, full packet).
@@ -364,6 +459,76 @@ impl<
             LookInStatus::NotEnoughData(needed) => Err(Error::NotEnoughData(needed)),
         }
     }
+
+    /// Returns the raw bytes of the `nth` packet without parsing them.
+    ///
+    /// # Returns
+    /// * `Ok(Some(bytes))` - Packet bytes (header included)
+    /// * `Ok(None)` - No packet at index
+    /// * `Err(Error)` - On I/O failure
+    #[cfg(feature = "observer")]
+    pub(crate) fn nth_raw(&mut self, nth: usize) -> Result<Option<Vec<u8>>, Error> {
+        let slot_index = nth / DEFAULT_SLOT_CAPACITY;
+        let index_in_slot = nth % DEFAULT_SLOT_CAPACITY;
+        let Some(slot) = self.slots.get(slot_index) else {
+            return Ok(None);
+        };
+        if slot.is_empty(index_in_slot)? {
+            return Ok(None);
+        }
+        let Some(mut offset) = slot.get_slot_offset(index_in_slot) else {
+            return Ok(None);
+        };
+        let length = slot.inner.len_of(index_in_slot);
+        offset += self.slots[..slot_index]
+            .iter()
+            .map(|slot| slot.width() + slot.size())
+            .sum::<u64>();
+        self.inner.seek(std::io::SeekFrom::Start(offset))?;
+        let mut buffer = vec![0u8; length as usize];
+        self.inner.read_exact(&mut buffer)?;
+        Ok(Some(buffer))
+    }
+
+    /// Returns the byte offset of a packet by its global index.
+    ///
+    /// Used by observers to find where completely written packets end and to report the
+    /// location of a packet which failed to be read.
+    ///
+    /// # Returns
+    /// * `Some(offset)` - Offset of the packet in the source
+    /// * `None` - No packet at index
+    #[cfg(any(feature = "observer", feature = "observer-sync"))]
+    pub(crate) fn nth_offset(&self, nth: usize) -> Option<u64> {
+        let slot_index = nth / DEFAULT_SLOT_CAPACITY;
+        let index_in_slot = nth % DEFAULT_SLOT_CAPACITY;
+        let offset = self.slots.get(slot_index)?.get_slot_offset(index_in_slot)?;
+        Some(
+            offset
+                + self.slots[..slot_index]
+                    .iter()
+                    .map(|slot| slot.width() + slot.size())
+                    .sum::<u64>(),
+        )
+    }
+
+    /// Returns the number of packets which are completely written to the source and the
+    /// offset where these packets end.
+    ///
+    /// The writer updates the slot before it writes the packet itself, so a storage which is
+    /// being written may already declare packets which aren't (completely) written yet.
+    /// Observers use it to not read such packets and to not consider the file truncated.
+    #[cfg(any(feature = "observer", feature = "observer-sync"))]
+    pub(crate) fn written(&mut self) -> Result<(usize, u64), Error> {
+        let len = self.inner.seek(std::io::SeekFrom::End(0))?;
+        let mut count = self.count();
+        let mut end = self.get_offset();
+        while end > len && count > 0 {
+            count -= 1;
+            end = self.nth_offset(count).unwrap_or(0);
+        }
+        Ok((count, end.min(len)))
+    }
 }
 
 #[cfg(test)]
@@ -419,4 +584,91 @@ mod tests {
             .add_rule(RuleDef::Prefilter(RuleFnDef::Static(|_| true)))
             .expect("prefilter should be addable again after remove");
     }
+
+    #[test]
+    fn reader_counts_matching_packets_without_materializing() {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(&mut buffer).expect("writer");
+        for _ in 0..5 {
+            writer
+                .insert(PacketDef::new(Vec::new(), None), &mut ())
+                .expect("insert packet");
+        }
+        let mut reader = ReaderUnderTest::new(Cursor::new(buffer.into_inner())).expect("reader");
+
+        assert_eq!(reader.count_filtered(&mut ()).expect("count"), 5);
+        let indices = reader
+            .matching_indices(&mut ())
+            .collect::<Result<Vec<_>, Error>>()
+            .expect("indices");
+        assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+
+        reader
+            .add_rule(RuleDef::FilterPacket(RuleFnDef::Static(|packet| {
+                packet.payload.is_none()
+            })))
+            .expect("packet filter rule");
+        assert_eq!(reader.count_filtered(&mut ()).expect("count"), 5);
+
+        reader
+            .add_rule(RuleDef::Prefilter(RuleFnDef::Static(|blocks| {
+                !blocks.is_empty()
+            })))
+            .expect("prefilter rule");
+        assert_eq!(reader.count_filtered(&mut ()).expect("count"), 0);
+        assert!(reader.matching_indices(&mut ()).next().is_none());
+    }
+
+    #[cfg(feature = "observer")]
+    #[test]
+    fn reader_counts_only_completely_written_packets() {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(&mut buffer).expect("writer");
+        for _ in 0..3 {
+            writer
+                .insert(PacketDef::new(Vec::new(), None), &mut ())
+                .expect("insert packet");
+        }
+        let mut bytes = buffer.into_inner();
+        let mut reader = ReaderUnderTest::new(Cursor::new(bytes.clone())).expect("reader");
+        assert_eq!(reader.written().expect("written"), (3, bytes.len() as u64));
+
+        // The slot already declares the last packet, but the packet is written partially
+        let last = reader.nth_offset(2).expect("offset");
+        bytes.truncate(bytes.len() - 1);
+        let mut reader = ReaderUnderTest::new(Cursor::new(bytes)).expect("reader");
+        assert_eq!(reader.count(), 3);
+        assert_eq!(reader.written().expect("written"), (2, last));
+    }
+
+    #[cfg(feature = "observer")]
+    #[test]
+    fn reader_returns_raw_packet_bytes() {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer =
+            WriterDef::<_, TestBlock, TestPayload, TestPayload>::new(&mut buffer).expect("writer");
+        for _ in 0..2 {
+            writer
+                .insert(PacketDef::new(Vec::new(), None), &mut ())
+                .expect("insert packet");
+        }
+        let mut reader = ReaderUnderTest::new(Cursor::new(buffer.into_inner())).expect("reader");
+
+        let raw = reader.nth_raw(1).expect("read").expect("packet exists");
+        let mut source = Cursor::new(&raw);
+        assert!(matches!(
+            PacketDef::<TestBlock, TestPayload, TestPayload>::filtered(
+                &mut source,
+                &RulesDef::<TestBlock, TestBlock, TestPayload, TestPayload>::default(),
+                &mut (),
+            )
+            .expect("packet parsed"),
+            LookInStatus::Accepted(..)
+        ));
+        // The whole packet is returned, nothing more
+        assert_eq!(source.position() as usize, raw.len());
+        assert!(reader.nth_raw(2).expect("read").is_none());
+    }
 }
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+
+use crate::*;
+
+/// Summary of a storage content collected by [`ReaderDef::stats`].
+///
+/// Statistics are collected from packet and block headers only; payloads are never decoded,
+/// so no protocol context (e.g. decryption keys) is required.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Number of storage slots.
+    pub slots: usize,
+    /// Number of packets (used slot entries), including damaged ones.
+    pub packets: usize,
+    /// Total size of all packets in bytes, as stored (i.e. compressed size for compressed packets).
+    /// Packets of damaged slot entries aren't included.
+    pub total_size: u64,
+    /// Packet size histogram. The key is the upper bound of a bucket (a power of two), the value
+    /// is the number of packets with a size in `(key / 2, key]`.
+    pub histogram: BTreeMap<u64, usize>,
+    /// Number of blocks per block type (see [`BlockReferredDef::block_name`]).
+    pub blocks: BTreeMap<&'static str, usize>,
+    /// Number of payloads per payload signature.
+    pub payloads: BTreeMap<Vec<u8>, usize>,
+    /// Number of packets without payload.
+    pub no_payload: usize,
+    /// Number of compressed packets.
+    pub compressed: usize,
+    /// Number of signed packets.
+    pub signed: usize,
+    /// Number of free entries in the loaded slots.
+    pub empty: usize,
+    /// Number of packets which cannot be inspected (damaged slot entry, header, blocks or
+    /// payload header).
+    pub damaged: usize,
+    /// Number of slot entries which declare an impossible packet length (longer than a packet
+    /// can be or going past the end of the source). Such packets are counted in `damaged` as
+    /// well. Slots with a broken CRC aren't counted here: the reader rejects them on loading.
+    pub damaged_slots: usize,
+}
+
+impl StorageStats {
+    /// Returns the average packet size in bytes (`0` if there are no packets).
+    ///
+    /// Packets of damaged slot entries aren't taken into account.
+    pub fn average_size(&self) -> u64 {
+        let packets = self.packets.saturating_sub(self.damaged_slots);
+        if packets == 0 {
+            0
+        } else {
+            self.total_size / packets as u64
+        }
+    }
+
+    /// Returns the histogram bucket (upper bound) for the given packet size.
+    pub fn bucket(size: u64) -> u64 {
+        size.max(1).next_power_of_two()
+    }
+}
+
+/// Block names, payload signature, compression and signing flags of an inspected packet.
+type InspectedPacket = (Vec<&'static str>, Option<Vec<u8>>, bool, bool);
+
+/// Reads packet and block headers (and the payload header) from the packet bytes.
+fn inspect<B: BlockDef, BR: BlockReferredDef<B>, Inner: PayloadInnerDef>(
+    bytes: &[u8],
+) -> Result<InspectedPacket, Error> {
+    let header = <PacketHeader as ReadBlockFromSlice>::read_from_slice(bytes, false)?;
+    let body = &bytes[PacketHeader::ssize() as usize..];
+    if (body.len() as u64) < header.size {
+        return Err(Error::NotEnoughData(
+            (header.size - body.len() as u64) as usize,
+        ));
+    }
+    let body = &body[..header.size as usize];
+    if header.compressed {
+        let (_, packet) = header.unpack::<Inner>(body)?;
+        let (blocks, payload, _, signed) = inspect::<B, BR, Inner>(&packet)?;
+        return Ok((blocks, payload, true, signed));
+    }
+    if header.signed {
+        let signed = header.unsign::<Inner>(body)?;
+        let (blocks, payload, _, _) = inspect::<B, BR, Inner>(signed.packet())?;
+        return Ok((blocks, payload, false, true));
+    }
+    let blocks_len = header.blocks_len as usize;
+    let mut read = 0usize;
+    let mut blocks = Vec::new();
+    while read < blocks_len {
+        let blk = <BR as ReadBlockFromSlice>::read_from_slice(&body[read..blocks_len], false)?;
+        let size = blk.size() as usize;
+        if size == 0 {
+            return Err(Error::InvalidLength);
+        }
+        read += size;
+        blocks.push(blk.block_name());
+    }
+    let payload = if header.payload {
+        let payload_header = <PayloadHeader as ReadFrom>::read::<_, Inner>(
+            &mut std::io::Cursor::new(&body[blocks_len..]),
+        )?;
+        header.validate_payload(&payload_header)?;
+        Some(payload_header.sig.as_slice().to_vec())
+    } else {
+        None
+    };
+    Ok((blocks, payload, false, false))
+}
+
+impl<
+    S: std::io::Read + std::io::Seek,
+    B: BlockDef,
+    BR: BlockReferredDef<B>,
+    P: PayloadDef<Inner>,
+    Inner: PayloadInnerDef,
+> ReaderDef<S, B, BR, P, Inner>
+{
+    /// Walks all loaded slots and collects storage statistics.
+    ///
+    /// Only packet headers, block headers and payload headers are read; payloads aren't decoded.
+    /// Compressed packets are decompressed and signed packets are unwrapped (signatures aren't
+    /// checked) to inspect their blocks. Packets which cannot be inspected are counted as
+    /// damaged and don't stop the walk. A packet is never read if its slot entry declares an
+    /// impossible length, so a corrupted entry can't cause a huge allocation.
+    ///
+    /// Unlike reading methods, it takes no protocol context: nothing which requires one (e.g.
+    /// decryption keys) is ever decoded.
+    ///
+    /// # Errors
+    /// Returns an I/O error if the source cannot be repositioned.
+    pub fn stats(&mut self) -> Result<StorageStats, Error> {
+        let mut stats = StorageStats {
+            slots: self.slots.len(),
+            ..Default::default()
+        };
+        let end = self.inner.seek(std::io::SeekFrom::End(0))?;
+        let max_len = PacketHeader::ssize() + Inner::MAX_PACKET_LEN;
+        let mut buffer = Vec::new();
+        for anchored in self.slots.iter() {
+            let slot = &anchored.inner;
+            stats.empty += slot.lenghts.iter().filter(|ln| **ln == 0).count();
+            let mut offset = anchored.offset + slot.size();
+            for len in (0..slot.lenghts.len())
+                .map(|nth| slot.len_of(nth))
+                .filter(|ln| *ln > 0)
+            {
+                stats.packets += 1;
+                let start = offset;
+                offset = offset.saturating_add(len);
+                if len > max_len || offset > end {
+                    stats.damaged += 1;
+                    stats.damaged_slots += 1;
+                    continue;
+                }
+                stats.total_size += len;
+                *stats
+                    .histogram
+                    .entry(StorageStats::bucket(len))
+                    .or_default() += 1;
+                self.inner.seek(std::io::SeekFrom::Start(start))?;
+                buffer.resize(len as usize, 0);
+                if self.inner.read_exact(&mut buffer).is_err() {
+                    stats.damaged += 1;
+                    continue;
+                }
+                match inspect::<B, BR, Inner>(&buffer) {
+                    Ok((blocks, payload, compressed, signed)) => {
+                        for name in blocks {
+                            *stats.blocks.entry(name).or_default() += 1;
+                        }
+                        match payload {
+                            Some(sig) => *stats.payloads.entry(sig).or_default() += 1,
+                            None => stats.no_payload += 1,
+                        }
+                        if compressed {
+                            stats.compressed += 1;
+                        }
+                        if signed {
+                            stats.signed += 1;
+                        }
+                    }
+                    Err(_) => {
+                        stats.damaged += 1;
+                    }
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::*;
+    use std::io::Cursor;
+
+    type TestWriter<'a> = WriterDef<&'a mut Cursor<Vec<u8>>, TestBlock, TestPayload, TestPayload>;
+    type TestReader = ReaderDef<Cursor<Vec<u8>>, TestBlock, TestBlock, TestPayload, TestPayload>;
+
+    fn storage(count: usize) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer = TestWriter::new(&mut buffer).expect("writer");
+        for _ in 0..count {
+            writer
+                .insert(PacketDef::new(Vec::new(), None), &mut ())
+                .expect("insert packet");
+        }
+        buffer.into_inner()
+    }
+
+    #[test]
+    fn stats_of_empty_storage() {
+        let mut reader = TestReader::new(Cursor::new(Vec::new())).expect("reader");
+        let stats = reader.stats().expect("stats");
+        assert_eq!(stats, StorageStats::default());
+        assert_eq!(stats.average_size(), 0);
+    }
+
+    #[test]
+    fn stats_count_packets_and_free_entries() {
+        let count = DEFAULT_SLOT_CAPACITY + 10;
+        let mut reader = TestReader::new(Cursor::new(storage(count))).expect("reader");
+        let stats = reader.stats().expect("stats");
+        assert_eq!(stats.slots, 2);
+        assert_eq!(stats.packets, count);
+        assert_eq!(stats.empty, DEFAULT_SLOT_CAPACITY - 10);
+        assert_eq!(stats.no_payload, count);
+        assert_eq!(stats.damaged, 0);
+        assert!(stats.blocks.is_empty());
+        assert!(stats.payloads.is_empty());
+        assert_eq!(stats.total_size, count as u64 * PacketHeader::ssize());
+        assert_eq!(stats.average_size(), PacketHeader::ssize());
+        assert_eq!(
+            stats
+                .histogram
+                .get(&StorageStats::bucket(PacketHeader::ssize())),
+            Some(&count)
+        );
+    }
+
+    #[test]
+    fn stats_count_damaged_packets() {
+        let mut bytes = storage(3);
+        let slot_size = Slot::default().size() as usize;
+        // Break the signature of the second packet
+        bytes[slot_size + PacketHeader::ssize() as usize] ^= 0xFF;
+        let mut reader = TestReader::new(Cursor::new(bytes)).expect("reader");
+        let stats = reader.stats().expect("stats");
+        assert_eq!(stats.packets, 3);
+        assert_eq!(stats.damaged, 1);
+        assert_eq!(stats.no_payload, 2);
+    }
+
+    #[test]
+    fn stats_count_damaged_slot_entries() {
+        let bytes = storage(3);
+        let mut reader = TestReader::new(Cursor::new(bytes.clone())).expect("reader");
+        // The second entry declares a packet longer than allowed, the third one goes past the
+        // end of the source
+        reader.slots[0].inner.lenghts[1] = u64::MAX;
+        reader.slots[0].inner.lenghts[2] = bytes.len() as u64;
+        let stats = reader.stats().expect("stats");
+        assert_eq!(stats.packets, 3);
+        assert_eq!(stats.damaged, 2);
+        assert_eq!(stats.damaged_slots, 2);
+        assert_eq!(stats.no_payload, 1);
+        assert_eq!(stats.average_size(), PacketHeader::ssize());
+    }
+}
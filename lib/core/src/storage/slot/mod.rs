@@ -13,6 +13,21 @@ pub(crate) use header::*;
 pub static DEFAULT_SLOT_CAPACITY: usize = 500;
 /// Signature prefix used to identify serialized slot headers.
 pub static STORAGE_SLOT_SIG: [u8; 8] = [166u8, 177u8, 188u8, 199u8, 199u8, 188u8, 177u8, 166u8];
+/// Bit of a slot entry which marks a compressed packet; the other bits hold the entry length.
+///
+/// A marked entry holds a compressed packet (see `PACKET_FLAG_COMPRESSED`), which readers
+/// restore transparently. Storages without compressed packets don't use this bit at all.
+pub const SLOT_ENTRY_COMPRESSED: u64 = 1 << 63;
+
+/// Returns the length of a slot entry without the compression marker.
+fn entry_len(entry: u64) -> u64 {
+    entry & !SLOT_ENTRY_COMPRESSED
+}
+
+/// Returns the total length of the given slot entries.
+fn entries_len(entries: &[u64]) -> u64 {
+    entries.iter().copied().map(entry_len).sum()
+}
 
 /// Fixed-size data structure representing a storage slot containing multiple data regions.
 ///
@@ -21,6 +36,7 @@ pub static STORAGE_SLOT_SIG: [u8; 8] = [166u8, 177u8, 188u8, 199u8, 199u8, 188u8
 ///
 /// Each slot contains:
 /// - a vector of `lenghts` (`Vec<u64>`) where each non-zero value represents a data chunk
+///   (the highest bit marks a compressed packet, see [`SLOT_ENTRY_COMPRESSED`])
 /// - a declared `capacity` (maximum number of chunks)
 /// - a CRC checksum for validation
 ///
@@ -29,7 +45,7 @@ pub static STORAGE_SLOT_SIG: [u8; 8] = [166u8, 177u8, 188u8, 199u8, 199u8, 188u8
 /// The constant signature `STORAGE_SLOT_SIG` is used when persisting this structure to disk.
 #[derive(Debug)]
 pub struct Slot {
-    /// List of chunk lengths (zero = unused).
+    /// List of chunk lengths (zero = unused), possibly marked with [`SLOT_ENTRY_COMPRESSED`].
     pub lenghts: Vec<u64>,
 
     /// Maximum number of entries this slot can hold.
@@ -61,12 +77,12 @@ impl Slot {
     /// Returns the total used width (sum of all non-zero chunk lengths).
     pub fn width(&self) -> u64 {
         if self.is_full() {
-            return self.lenghts.iter().sum();
+            return entries_len(&self.lenghts);
         }
         let Some(free_pos) = self.lenghts.iter().position(|ln| ln == &0) else {
-            return self.lenghts.iter().sum();
+            return entries_len(&self.lenghts);
         };
-        self.lenghts[..free_pos].iter().sum()
+        entries_len(&self.lenghts[..free_pos])
     }
 
     /// Returns an iterator over active ranges in the slot.
@@ -81,7 +97,7 @@ impl Slot {
         if nth >= self.lenghts.len() {
             return None;
         }
-        Some(self.lenghts[..nth].iter().sum::<u64>() + self.size())
+        Some(entries_len(&self.lenghts[..nth]) + self.size())
     }
 
     /// Returns whether the `nth` chunk is unused (i.e., zero-length).
@@ -95,6 +111,19 @@ impl Slot {
             .ok_or(Error::OutOfBounds(self.lenghts.len(), nth))
     }
 
+    /// Returns the length of the `nth` chunk without the compression marker; zero if the
+    /// chunk is unused or the index is out of bounds.
+    pub fn len_of(&self, nth: usize) -> u64 {
+        self.lenghts.get(nth).copied().map(entry_len).unwrap_or(0)
+    }
+
+    /// Checks if the `nth` chunk is marked as a compressed packet.
+    pub fn is_compressed(&self, nth: usize) -> bool {
+        self.lenghts
+            .get(nth)
+            .is_some_and(|ln| ln & SLOT_ENTRY_COMPRESSED != 0)
+    }
+
     /// Counts the number of used (non-zero) chunks in the slot.
     pub fn count(&self) -> usize {
         self.lenghts.iter().filter(|&&ln| ln > 0).count()
@@ -123,7 +152,7 @@ impl Slot {
         if idx >= self.lenghts.len() || !self.is_used(idx) {
             return None;
         }
-        Some(self.lenghts[..idx].iter().sum::<u64>())
+        Some(entries_len(&self.lenghts[..idx]))
     }
 
     /// Checks if slot has space
@@ -143,7 +172,7 @@ impl Slot {
             return None;
         }
         let free_pos = self.lenghts.iter().position(|ln| ln == &0)?;
-        Some(self.lenghts[..free_pos].iter().sum::<u64>() + self.size())
+        Some(entries_len(&self.lenghts[..free_pos]) + self.size())
     }
 
     /// Returns the indoex of the first available free slot, or `None` if the slot is full.
@@ -161,7 +190,8 @@ impl Slot {
 
     /// Inserts a new chunk into the first available slot.
     ///
-    /// Updates the CRC accordingly.
+    /// Updates the CRC accordingly. A compressed packet is inserted with its length marked
+    /// with [`SLOT_ENTRY_COMPRESSED`].
     ///
     /// # Errors
     /// Returns `Error::CannotInsertIntoSlot` if no space is available.
@@ -239,16 +269,16 @@ impl Iterator for SlotIterator<'_> {
     type Item = RangeInclusive<u64>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let ln = self.slot.lenghts.get(self.next)?;
-        if ln == &0 {
+        let ln = entry_len(*self.slot.lenghts.get(self.next)?);
+        if ln == 0 {
             return None;
         }
         let range = RangeInclusive::new(
             self.offset + self.slot.size(),
-            self.offset + *ln + self.slot.size(),
+            self.offset + ln + self.slot.size(),
         );
         self.next += 1;
-        self.offset += *ln;
+        self.offset += ln;
         Some(range)
     }
 }
@@ -256,7 +286,7 @@ impl Iterator for SlotIterator<'_> {
 #[cfg(test)]
 mod tests {
     use super::Slot;
-    use crate::{CrcU32, Error, STORAGE_SLOT_SIG, Size, StaticSize};
+    use crate::{CrcU32, Error, SLOT_ENTRY_COMPRESSED, STORAGE_SLOT_SIG, Size, StaticSize};
 
     #[test]
     fn slot_default_shape_and_crc_are_valid() {
@@ -317,6 +347,34 @@ mod tests {
         assert!(matches!(slot.insert(1), Err(Error::CannotInsertIntoSlot)));
     }
 
+    #[test]
+    fn slot_skips_compression_marker_in_lengths() {
+        let mut slot = Slot::new(vec![0, 0, 0], 3, [0; 4]);
+        slot.insert(10).expect("insert plain chunk");
+        slot.insert(20 | SLOT_ENTRY_COMPRESSED)
+            .expect("insert compressed chunk");
+
+        assert!(!slot.is_compressed(0));
+        assert!(slot.is_compressed(1));
+        assert!(!slot.is_compressed(2));
+        assert_eq!(slot.len_of(1), 20);
+        assert_eq!(slot.len_of(3), 0);
+        assert_eq!(slot.count(), 2);
+        assert_eq!(slot.width(), 30);
+        assert_eq!(slot.offset_of(1), Some(10));
+        assert_eq!(slot.get_free_slot_offset(), Some(slot.size() + 30));
+
+        slot.insert(5).expect("insert third chunk");
+        assert_eq!(slot.width(), 35);
+        assert_eq!(slot.offset_of(2), Some(30));
+        assert_eq!(slot.get_slot_offset(2), Some(slot.size() + 30));
+        let ends: Vec<_> = slot
+            .iter()
+            .map(|range| *range.end() - slot.size())
+            .collect();
+        assert_eq!(ends, vec![10, 30, 35]);
+    }
+
     #[test]
     fn slot_empty_and_bounds_checks() {
         let slot = Slot::new(vec![11, 0], 2, [0; 4]);
@@ -56,7 +56,9 @@ impl TryReadFrom for Slot {
         buf: &mut T,
     ) -> Result<ReadStatus<Self>, Error> {
         let start_pos = buf.stream_position()?;
-        let len = buf.seek(std::io::SeekFrom::End(0))? - start_pos;
+        let len = buf
+            .seek(std::io::SeekFrom::End(0))?
+            .saturating_sub(start_pos);
         buf.seek(std::io::SeekFrom::Start(start_pos))?;
 
         let header = match SlotHeader::try_read::<_, S>(buf)? {
@@ -14,6 +14,13 @@ use crate::*;
 /// For example, if the target is `data.brec`, the lock file will be `data.lock`.
 pub const LOCK_EXT: &str = "lock";
 
+/// The extension of the companion file used to coordinate readers and writers.
+///
+/// A writer holds an exclusive lock on this file only while a slot and a packet are written;
+/// readers ([`FileReaderDef`]) hold a shared lock while (re)loading slots. For example, if
+/// the target is `data.brec`, the access lock file will be `data.access`.
+pub const ACCESS_LOCK_EXT: &str = "access";
+
 /// The default polling interval in milliseconds between attempts to acquire the file lock.
 ///
 /// Used when no custom interval is specified via [`FileStorageOptions::interval`].
@@ -128,6 +135,62 @@ impl FileStorageOptions {
     }
 }
 
+/// Short-lived advisory lock on the access lock file (see [`ACCESS_LOCK_EXT`]).
+///
+/// The lock is acquired on creation (waiting until it becomes available) and released on drop.
+pub(crate) struct AccessLock<'a> {
+    file: &'a File,
+}
+
+impl<'a> AccessLock<'a> {
+    /// Opens (or creates) the access lock file for the given storage file.
+    pub(crate) fn open<P: AsRef<Path>>(filename: P) -> Result<File, Error> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(filename.as_ref().with_extension(ACCESS_LOCK_EXT))
+            .map_err(Error::FailToLockFile)
+    }
+
+    /// Opens the access lock file for a reader.
+    ///
+    /// An existing file is opened read-only, so readers work with storage on read-only
+    /// files, directories and mounts. A missing file is created; if it can't be created,
+    /// `None` is returned and the reader works without the lock.
+    pub(crate) fn open_read<P: AsRef<Path>>(filename: P) -> Result<Option<File>, Error> {
+        let path = filename.as_ref().with_extension(ACCESS_LOCK_EXT);
+        match File::open(&path) {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .ok()),
+            Err(err) => Err(Error::FailToLockFile(err)),
+        }
+    }
+
+    /// Takes a shared lock; any number of readers can hold it at the same time.
+    pub(crate) fn shared(file: &'a File) -> Result<Self, Error> {
+        FileExt::lock_shared(file).map_err(Error::FailToLockFile)?;
+        Ok(Self { file })
+    }
+
+    /// Takes an exclusive lock; waits until all readers have released their shared locks.
+    pub(crate) fn exclusive(file: &'a File) -> Result<Self, Error> {
+        FileExt::lock(file).map_err(Error::FailToLockFile)?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for AccessLock<'_> {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(self.file);
+    }
+}
+
 /// `FileWriterDef` provides a wrapper around `WriterDef<File, ...>` that attempts to prevent
 /// concurrent access to the target storage file by using a filesystem-based locking mechanism.
 ///
@@ -142,8 +205,13 @@ impl FileStorageOptions {
 ///
 /// `FileWriterDef` also supports an optional timeout while waiting for the lock, enabling
 /// coordinated access patterns in multi-process environments.
+///
+/// Besides the writer lock, each insert takes an exclusive lock on the `.access` companion
+/// file for the time a slot and a packet are written. [`FileReaderDef`] takes a shared lock on
+/// the same file while loading slots, so readers never observe a half-written slot or packet.
 pub struct FileWriterDef<B: BlockDef, PL: PayloadDef<Inner>, Inner: PayloadInnerDef, O> {
     _filelock: File,
+    access: File,
     inner: WriterDef<File, B, PL, Inner>,
     opt: O,
 }
@@ -229,6 +297,7 @@ where
                 }
             };
         };
+        let access = AccessLock::open(&filename)?;
         let storage_file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -237,6 +306,7 @@ where
             .open(filename)?;
         Ok(Self {
             _filelock: filelock,
+            access,
             inner: WriterDef::new(storage_file)?,
             opt,
         })
@@ -255,6 +325,9 @@ where
 
     /// Inserts a new packet into storage at the next available slot.
     ///
+    /// The slot and the packet are written under an exclusive access lock, so readers
+    /// using [`FileReaderDef`] wait until the write is complete.
+    ///
     /// # Arguments
     /// * `packet` - The `PacketDef` to be written
     ///
@@ -262,8 +335,23 @@ where
     /// * `Ok(())` - Packet successfully written
     /// * `Err(Error)` - If no space is found or write fails
     pub fn insert(&mut self, packet: PacketDef<B, PL, Inner>) -> Result<(), Error> {
+        let _access = AccessLock::exclusive(&self.access)?;
         self.inner.insert(packet, &mut self.opt)
     }
+
+    /// Enables compression of written packets (see [`WriterDef::with_compression`]).
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    pub fn with_compression(mut self, compression: crate::compress::Compression) -> Self {
+        self.inner.set_compression(Some(compression));
+        self
+    }
+
+    /// Enables signing of written packets (see [`WriterDef::with_signer`]).
+    #[cfg(feature = "sign")]
+    pub fn with_signer(mut self, signer: crate::sign::PacketSigner) -> Self {
+        self.inner.set_signer(Some(signer));
+        self
+    }
 }
 
 #[cfg(test)]
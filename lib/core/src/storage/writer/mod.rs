@@ -1,6 +1,8 @@
 #[cfg(feature = "locked_storage")]
 mod locker;
 #[cfg(feature = "locked_storage")]
+pub(crate) use locker::AccessLock;
+#[cfg(feature = "locked_storage")]
 pub use locker::{FileStorageOptions, FileWriterDef};
 
 use crate::*;
@@ -16,6 +18,10 @@ pub struct WriterDef<
     pub slots: Vec<Slot>,
     inner: S,
     locator: FreeSlotLocator,
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    compression: Option<crate::compress::Compression>,
+    #[cfg(feature = "sign")]
+    signer: Option<crate::sign::PacketSigner>,
     _phantom: std::marker::PhantomData<(B, P, Inner)>,
 }
 
@@ -39,11 +45,53 @@ impl<
             slots: Vec::new(),
             inner,
             locator: FreeSlotLocator::default(),
+            #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+            compression: None,
+            #[cfg(feature = "sign")]
+            signer: None,
             _phantom: std::marker::PhantomData,
         }
         .load()
     }
 
+    /// Enables compression of packets written by this writer.
+    ///
+    /// Each packet is stored as a compressed packet, and its slot entry is marked with
+    /// [`SLOT_ENTRY_COMPRESSED`], so readers restore it transparently. Packets which don't
+    /// become smaller are stored as is. Storage may freely mix compressed and plain packets.
+    ///
+    /// A compressed packet also carries `PACKET_FLAG_COMPRESSED` in its `PacketHeader`, so
+    /// the same bytes are readable by `PacketBufReader` when they are copied out of the
+    /// storage into a stream.
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    pub fn with_compression(mut self, compression: crate::compress::Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Changes compression of packets written after this call; `None` disables compression.
+    #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+    pub fn set_compression(&mut self, compression: Option<crate::compress::Compression>) {
+        self.compression = compression;
+    }
+
+    /// Enables signing of packets written by this writer.
+    ///
+    /// Each packet is stored as a signed packet; readers unwrap it transparently and check
+    /// the signature with a `RuleDef::Verify` rule. If compression is enabled too, packets
+    /// are signed first and the signed packet is compressed.
+    #[cfg(feature = "sign")]
+    pub fn with_signer(mut self, signer: crate::sign::PacketSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Changes the signer of packets written after this call; `None` disables signing.
+    #[cfg(feature = "sign")]
+    pub fn set_signer(&mut self, signer: Option<crate::sign::PacketSigner>) {
+        self.signer = signer;
+    }
+
     /// Loads storage data and initializes packet indexing.
     ///
     /// # Returns
@@ -87,35 +135,68 @@ impl<
         mut packet: PacketDef<B, P, Inner>,
         ctx: &mut <Inner as ProtocolSchema>::Context<'_>,
     ) -> Result<(), Error> {
-        let offset = match self.locator.next(&self.slots) {
-            Some(offset) => offset,
-            None => {
-                self.slots.push(Slot::default());
-                self.locator
-                    .next(&self.slots)
-                    .ok_or(Error::CannotFindFreeSlot)?
-            }
-        };
         // Convert the packet into bytes
         let mut buffer: Vec<u8> = Vec::new();
         packet.write_all(&mut buffer, ctx)?;
-        // Insert length of packet
-        self.locator.insert(&mut self.slots, buffer.len() as u64)?;
-        // Get updated slot data
-        let (slot_index, slot_offset) = self.locator.current();
-        self.inner.flush()?;
-        self.inner.seek(std::io::SeekFrom::Start(slot_offset))?;
-        let slot = self
-            .slots
-            .get(slot_index)
-            .ok_or(Error::CannotFindFreeSlot)?;
-        // Write/Rewrite slot
-        slot.write_all(&mut self.inner)?;
-        self.inner.seek(std::io::SeekFrom::Start(offset))?;
-        self.inner.flush()?;
-        self.inner.seek(std::io::SeekFrom::Start(offset))?;
-        self.inner.write_all(&buffer)?;
-        self.inner.flush()?;
-        Ok(())
+        #[cfg(feature = "sign")]
+        if let Some(signer) = self.signer.as_ref() {
+            buffer = signer.sign(&buffer).map_err(std::io::Error::from)?;
+        }
+        #[cfg(any(feature = "compress-zstd", feature = "compress-lz4"))]
+        let (buffer, compressed) = match self.compression.as_ref() {
+            Some(compression) => match PacketHeader::pack(&buffer, compression)? {
+                Some(packed) => (packed, true),
+                None => (buffer, false),
+            },
+            None => (buffer, false),
+        };
+        #[cfg(not(any(feature = "compress-zstd", feature = "compress-lz4")))]
+        let compressed = false;
+        write_to_slot(
+            &mut self.inner,
+            &mut self.slots,
+            &mut self.locator,
+            &buffer,
+            compressed,
+        )
+    }
+}
+
+/// Writes an already serialized packet into the next free slot entry.
+///
+/// A new slot is appended if all slots are full. The slot is written (or rewritten) before
+/// the packet itself. The entry of a compressed packet is marked with `SLOT_ENTRY_COMPRESSED`.
+pub(crate) fn write_to_slot<S: std::io::Write + std::io::Seek>(
+    inner: &mut S,
+    slots: &mut Vec<Slot>,
+    locator: &mut FreeSlotLocator,
+    buffer: &[u8],
+    compressed: bool,
+) -> Result<(), Error> {
+    let offset = match locator.next(slots) {
+        Some(offset) => offset,
+        None => {
+            slots.push(Slot::default());
+            locator.next(slots).ok_or(Error::CannotFindFreeSlot)?
+        }
+    };
+    // Insert length of packet
+    let mut length = buffer.len() as u64;
+    if compressed {
+        length |= SLOT_ENTRY_COMPRESSED;
     }
+    locator.insert(slots, length)?;
+    // Get updated slot data
+    let (slot_index, slot_offset) = locator.current();
+    inner.flush()?;
+    inner.seek(std::io::SeekFrom::Start(slot_offset))?;
+    let slot = slots.get(slot_index).ok_or(Error::CannotFindFreeSlot)?;
+    // Write/Rewrite slot
+    slot.write_all(inner)?;
+    inner.seek(std::io::SeekFrom::Start(offset))?;
+    inner.flush()?;
+    inner.seek(std::io::SeekFrom::Start(offset))?;
+    inner.write_all(buffer)?;
+    inner.flush()?;
+    Ok(())
 }
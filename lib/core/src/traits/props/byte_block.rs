@@ -5,7 +5,7 @@ use crate::*;
 /// `ByteBlock` is used for representing binary data chunks with known allowed sizes.
 /// It provides utility methods for accessing the internal data as a slice,
 /// validating capacity, and converting from `Vec<u8>` safely.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 pub enum ByteBlock {
     /// 4-byte buffer.
     Len4([u8; 4]),
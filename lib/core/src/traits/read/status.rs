@@ -64,4 +64,17 @@ impl<T> PacketReadStatus<T> {
     pub fn success(value: T) -> Self {
         Self::Success(value)
     }
+
+    /// Maps the read value, keeping the rest of the status.
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> PacketReadStatus<U> {
+        match self {
+            #[cfg(not(feature = "resilient"))]
+            Self::Success(value) => PacketReadStatus::Success(f(value)),
+            #[cfg(feature = "resilient")]
+            Self::Success((value, unrecognized)) => {
+                PacketReadStatus::Success((f(value), unrecognized))
+            }
+            Self::NotEnoughData(needed) => PacketReadStatus::NotEnoughData(needed),
+        }
+    }
 }
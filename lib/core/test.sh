@@ -4,7 +4,12 @@ set -e
 cargo test -- --nocapture
 cargo test --features locked_storage -- --nocapture
 cargo test --features observer -- --nocapture
+cargo test --features observer-sync -- --nocapture
 cargo test --features crypt -- --nocapture
+cargo test --features sign -- --nocapture
+cargo test --features compress-zstd -- --nocapture
+cargo test --features compress-lz4 -- --nocapture
+cargo test --features compress-zstd,compress-lz4 -- --nocapture
 cargo test --features bincode -- --nocapture
 cargo test --features napi -- --nocapture
 cargo test --features wasm -- --nocapture
@@ -0,0 +1,12 @@
+#[cfg(test)]
+mod protocol;
+#[cfg(test)]
+mod test;
+
+#[cfg(test)]
+pub(crate) use protocol::*;
+
+#[cfg(test)]
+brec::generate!();
+
+fn main() {}
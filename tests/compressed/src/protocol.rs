@@ -0,0 +1,27 @@
+use brec::prelude::*;
+
+#[block]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Metadata {
+    pub level: u8,
+    pub tm: u64,
+}
+
+#[payload(bincode)]
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
+pub struct Message {
+    pub text: String,
+}
+
+#[payload(bincode, compress = "zstd", level = 5)]
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
+pub struct Article {
+    pub title: String,
+    pub body: String,
+}
+
+#[payload(bincode, compress = "lz4")]
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
+pub struct Trace {
+    pub lines: Vec<String>,
+}
@@ -0,0 +1,232 @@
+use crate::*;
+use brec::prelude::*;
+use std::io::{BufReader, Cursor};
+
+const LITTER: &[u8] = b"some non-brec bytes between packets";
+
+fn packets(count: u64) -> Vec<Packet> {
+    (0..count)
+        .map(|n| {
+            Packet::new(
+                vec![Block::Metadata(Metadata {
+                    level: (n % 4) as u8,
+                    tm: 1_000_000 + n,
+                })],
+                Some(Payload::Message(Message {
+                    text: format!("[{n}] service started; listening on 0.0.0.0:8080\n").repeat(20),
+                })),
+            )
+        })
+        .collect()
+}
+
+fn compressions() -> Vec<Compression> {
+    vec![Compression::zstd(), Compression::Lz4]
+}
+
+fn write_storage(count: u64, compression: Option<Compression>) -> Vec<u8> {
+    let mut buffer = Cursor::new(Vec::new());
+    let mut writer = Writer::new(&mut buffer).expect("writer");
+    if let Some(compression) = compression {
+        writer = writer.with_compression(compression);
+    }
+    for packet in packets(count) {
+        writer.insert(packet, &mut ()).expect("insert packet");
+    }
+    buffer.into_inner()
+}
+
+fn assert_same(restored: &[Packet], source: &[Packet]) {
+    assert_eq!(restored.len(), source.len());
+    for (left, right) in restored.iter().zip(source.iter()) {
+        assert_eq!(left.blocks, right.blocks);
+        assert_eq!(left.payload, right.payload);
+    }
+}
+
+#[test]
+fn storage_with_compression() {
+    let source = packets(120);
+    let plain = write_storage(120, None);
+    for compression in compressions() {
+        let bytes = write_storage(120, Some(compression));
+        assert!(bytes.len() < plain.len());
+
+        let mut reader = Reader::new(Cursor::new(bytes)).expect("reader");
+        assert_eq!(reader.count(), source.len());
+        // Compressed packets are marked in slot metadata
+        for slot in reader.slots.iter() {
+            assert!((0..slot.inner.count()).all(|n| slot.inner.is_compressed(n)));
+        }
+        let restored = reader
+            .iter(&mut ())
+            .collect::<Result<Vec<Packet>, brec::Error>>()
+            .expect("read packets");
+        assert_same(&restored, &source);
+
+        let restored = reader
+            .filtered(&mut ())
+            .collect::<Result<Vec<Packet>, brec::Error>>()
+            .expect("read filtered packets");
+        assert_same(&restored, &source);
+
+        let packet = reader
+            .nth(42, &mut ())
+            .expect("nth packet")
+            .expect("exists");
+        assert_same(&[packet], &source[42..43]);
+    }
+}
+
+#[test]
+fn stream_with_compression() {
+    let source = packets(50);
+    for compression in compressions() {
+        let mut buffer = Vec::new();
+        for (n, mut packet) in packets(50).into_iter().enumerate() {
+            buffer.extend_from_slice(LITTER);
+            if n % 2 == 0 {
+                packet
+                    .write_compressed(&mut buffer, &compression, &mut ())
+                    .expect("write compressed packet");
+            } else {
+                packet
+                    .write_all(&mut buffer, &mut ())
+                    .expect("write packet");
+            }
+        }
+        let mut inner = BufReader::new(Cursor::new(buffer));
+        let mut reader: PacketBufReader<_> = PacketBufReader::new(&mut inner);
+        let mut restored = Vec::new();
+        loop {
+            match reader.read(&mut ()).expect("read packet") {
+                NextPacket::Found(packet) => restored.push(packet),
+                NextPacket::NoData => break,
+                _ => {}
+            }
+        }
+        assert_same(&restored, &source);
+    }
+}
+
+fn compressed_payloads() -> Vec<Payload> {
+    vec![
+        Payload::Article(Article {
+            title: "Release notes".to_owned(),
+            body: "Fixed reconnect handling in the storage writer. ".repeat(40),
+        }),
+        Payload::Trace(Trace {
+            lines: (0..40)
+                .map(|n| format!("at worker::process (src/worker.rs:{n})"))
+                .collect(),
+        }),
+        Payload::Message(Message {
+            text: "not compressed".to_owned(),
+        }),
+    ]
+}
+
+#[test]
+fn payloads_with_compress_attribute() {
+    let article = Article {
+        title: "Release notes".to_owned(),
+        body: "Fixed reconnect handling in the storage writer. ".repeat(40),
+    };
+    let plain = brec::bincode::serde::encode_to_vec(&article, brec::bincode::config::standard())
+        .expect("bincode");
+    let compressed = <Article as PayloadEncode>::encode(&article, &mut ()).expect("encode");
+    assert!(compressed.len() < plain.len());
+    let restored =
+        <Article as PayloadDecode<Article>>::decode(&compressed, &mut ()).expect("decode");
+    assert_eq!(restored, article);
+
+    let source = compressed_payloads();
+    let mut buffer = Cursor::new(Vec::new());
+    let mut writer = Writer::new(&mut buffer).expect("writer");
+    for payload in source.iter() {
+        writer
+            .insert(Packet::new(Vec::new(), Some(payload.clone())), &mut ())
+            .expect("insert packet");
+    }
+    let mut reader = Reader::new(Cursor::new(buffer.into_inner())).expect("reader");
+    let restored = reader
+        .iter(&mut ())
+        .map(|packet| packet.map(|packet| packet.payload.expect("payload")))
+        .collect::<Result<Vec<Payload>, brec::Error>>()
+        .expect("read packets");
+    assert_eq!(restored, source);
+}
+
+#[test]
+fn payload_decompression_is_limited_by_max_payload_len() {
+    let oversized = vec![b'x'; Article::MAX_PAYLOAD_LEN as usize + 1];
+    let frame = Compression::zstd().compress(&oversized).expect("compress");
+    assert!(<Article as PayloadDecode<Article>>::decode(&frame, &mut ()).is_err());
+
+    let frame = Compression::Lz4.compress(b"damaged").expect("compress");
+    assert!(<Trace as PayloadDecode<Trace>>::decode(&frame[..frame.len() - 1], &mut ()).is_err());
+}
+
+#[test]
+fn storage_stats() {
+    let source = packets(120);
+    for compression in [None, Some(Compression::zstd())] {
+        let mut reader = Reader::new(Cursor::new(write_storage(120, compression))).expect("reader");
+        let stats = reader.stats().expect("stats");
+        assert_eq!(stats.packets, source.len());
+        assert_eq!(stats.damaged, 0);
+        assert_eq!(stats.blocks.get("Metadata"), Some(&source.len()));
+        assert_eq!(
+            stats.payloads.get(Message::ssig().as_slice()),
+            Some(&source.len())
+        );
+        assert_eq!(stats.no_payload, 0);
+        assert_eq!(
+            stats.compressed,
+            if compression.is_some() {
+                source.len()
+            } else {
+                0
+            }
+        );
+        assert_eq!(stats.histogram.values().sum::<usize>(), source.len());
+        assert_eq!(stats.average_size(), stats.total_size / source.len() as u64);
+    }
+}
+
+#[test]
+fn count_filtered_and_matching_indices() {
+    let source = packets(120);
+    let expected = source
+        .iter()
+        .enumerate()
+        .filter_map(|(n, packet)| {
+            matches!(packet.blocks.first(), Some(Block::Metadata(meta)) if meta.level == 2)
+                .then_some(n)
+        })
+        .collect::<Vec<usize>>();
+    for compression in [None, Some(Compression::zstd())] {
+        let mut reader = Reader::new(Cursor::new(write_storage(120, compression))).expect("reader");
+        assert_eq!(reader.count_filtered(&mut ()).expect("count"), source.len());
+        reader
+            .add_rule(Rule::Prefilter(brec::RuleFnDef::Static(|blocks| {
+                blocks.iter().any(
+                    |block| matches!(&*block, BlockReferred::Metadata(meta) if meta.level == 2),
+                )
+            })))
+            .expect("prefilter rule");
+        assert_eq!(
+            reader.count_filtered(&mut ()).expect("count"),
+            expected.len()
+        );
+        let indices = reader
+            .matching_indices(&mut ())
+            .collect::<Result<Vec<usize>, brec::Error>>()
+            .expect("indices");
+        assert_eq!(indices, expected);
+        reader
+            .add_rule(Rule::FilterPayload(brec::RuleFnDef::Static(|_| false)))
+            .expect("payload rule");
+        assert_eq!(reader.count_filtered(&mut ()).expect("count"), 0);
+    }
+}
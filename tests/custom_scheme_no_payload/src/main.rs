@@ -29,6 +29,8 @@ fn packet_header(size: u64, blocks_len: u64, payload: bool) -> PacketHeader {
         size,
         blocks_len,
         payload,
+        compressed: false,
+        signed: false,
         crc: hasher.finalize(),
     }
 }
@@ -47,3 +47,52 @@ pub fn read_file(filename: &str) -> std::io::Result<()> {
     }
     Ok(())
 }
+
+pub fn write_and_read_concurrently(
+    packets: Vec<WrappedPacket>,
+    count: usize,
+    readers: usize,
+    filename: &str,
+) -> std::io::Result<()> {
+    let tmp = std::env::temp_dir().join(filename);
+    if tmp.exists() {
+        std::fs::remove_file(&tmp)?;
+    }
+    let mut storage = FileStorage::new(&tmp, Some(Duration::from_millis(400)), None)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let expected = packets.len() * count;
+    let readers = (0..readers)
+        .map(|_| {
+            let tmp = tmp.clone();
+            std::thread::spawn(move || -> Result<usize, brec::Error> {
+                let mut reader = FileReader::new(&tmp)?;
+                while reader.count() < expected {
+                    reader.reload()?;
+                    for packet in reader.iter(&mut ()) {
+                        packet?;
+                    }
+                }
+                Ok(reader.count())
+            })
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..count {
+        for packet in packets.iter() {
+            storage.insert(packet.into()).map_err(|err| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+            })?;
+        }
+    }
+    for reader in readers {
+        let read = reader
+            .join()
+            .map_err(|_| std::io::Error::other("Reader thread panicked"))?
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+        if read != expected {
+            return Err(std::io::Error::other(format!(
+                "Dismatch lengths: {read} vs {expected}"
+            )));
+        }
+    }
+    Ok(())
+}
@@ -43,6 +43,7 @@ impl Arbitrary for WrappedPacket {
 }
 
 const BIN_LOG_FILE: &str = "locked_storage_test_measurements.bin";
+const SHARED_BIN_LOG_FILE: &str = "locked_storage_test_shared.bin";
 
 proptest! {
     #![proptest_config(ProptestConfig {
@@ -57,4 +58,10 @@ proptest! {
         storage::read_file(BIN_LOG_FILE)?;
     }
 
+    #[test]
+    #[serial]
+    fn bin_logs_shared_readers(rows in proptest::collection::vec(any::<WrappedPacket>(), 10)) {
+        storage::write_and_read_concurrently(rows, 50, 4, SHARED_BIN_LOG_FILE)?;
+    }
+
 }
@@ -4,7 +4,7 @@ use proptest::prelude::*;
 use proptest::strategy::ValueTree;
 use std::sync::{
     Arc,
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Mutex,
 };
 use tokio::task::JoinHandle;
@@ -383,14 +383,14 @@ impl Subscription for VerifyingSubscription {
         }
     }
 
-    fn on_error(&mut self, err: &brec::Error) -> SubscriptionErrorAction {
+    fn on_error(&mut self, err: &brec::ObserverError) -> SubscriptionErrorAction {
         let mut state = self.state.lock().unwrap();
         state.failure = Some(format!("Observer returned an error: {err}"));
         self.token.cancel();
         SubscriptionErrorAction::Stop
     }
 
-    fn on_stopped(&mut self, reason: Option<brec::Error>) {
+    fn on_stopped(&mut self, reason: Option<brec::ObserverError>) {
         if let Some(reason) = reason {
             let mut state = self.state.lock().unwrap();
             if state.failure.is_none() {
@@ -621,6 +621,15 @@ async fn observer_stream_reads_packets_while_writing() -> std::io::Result<()> {
                 );
                 read += 1;
             }
+            brec::FileObserverEvent::Ignored(bytes) => {
+                panic!("Observer stream reported {} ignored bytes", bytes.len());
+            }
+            brec::FileObserverEvent::Lagged(dropped) => {
+                panic!("Observer stream dropped {dropped} packets");
+            }
+            brec::FileObserverEvent::Truncated | brec::FileObserverEvent::Replaced => {
+                panic!("Observer stream reported a file change");
+            }
             brec::FileObserverEvent::Error(err) => {
                 panic!("Observer stream returned an error event: {err}");
             }
@@ -650,6 +659,895 @@ async fn observer_stream_reads_packets_while_writing() -> std::io::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn observer_stream_applies_rules() -> std::io::Result<()> {
+    let count = brec::storage::DEFAULT_SLOT_CAPACITY.saturating_add(1);
+    let packets = gen_n::<WrappedPacket>(count);
+    let expected = packets
+        .iter()
+        .filter(|packet| packet.blocks.len() % 2 == 0)
+        .cloned()
+        .collect::<Vec<WrappedPacket>>();
+
+    let filename = format!("brec_test_observer_stream_rules_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let (existed, added) = packets.split_at(count / 2);
+    let mut wfile = write_storage(&tmp, existed)?;
+
+    let mut stream = FileObserverStream::with_rules(
+        &tmp,
+        vec![Rule::Prefilter(brec::RuleFnDef::Static(|blocks| {
+            blocks.len() % 2 == 0
+        }))],
+        (),
+    )
+    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    append_storage(&mut wfile, added)?;
+
+    let read = read_stream_packets(&mut stream, expected.len()).await?;
+    assert_eq!(read, expected);
+    stream.shutdown().await;
+    while let Some(event) = stream.next().await {
+        assert!(
+            !matches!(event, brec::FileObserverEvent::Packet(..)),
+            "Observer stream emitted a packet denied by rules"
+        );
+    }
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+/// Reads `count` packets from the stream; any event other than an update or a packet
+/// fails the test.
+async fn read_stream_packets(
+    stream: &mut FileObserverStream,
+    count: usize,
+) -> std::io::Result<Vec<WrappedPacket>> {
+    let (packets, dropped, stopped) = read_bounded_stream(stream, count).await?;
+    assert_eq!(dropped, 0, "Observer stream dropped {dropped} packets");
+    if let Some(reason) = stopped {
+        panic!("Observer stream stopped unexpectedly: {reason:?}");
+    }
+    Ok(packets)
+}
+
+#[tokio::test]
+async fn observer_stream_resumes_from_cursor() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(120);
+    let (existed, added) = packets.split_at(100);
+
+    let filename = format!("brec_test_observer_stream_cursor_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let mut wfile = write_storage(&tmp, existed)?;
+
+    // Resume from the saved cursor: only packets after it are delivered
+    let mut stream =
+        FileObserverStream::with_start_at(&tmp, brec::StartPosition::Index(40), Vec::new(), ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    assert_eq!(stream.cursor(), 40);
+    let read = read_stream_packets(&mut stream, 60).await?;
+    assert_eq!(read.as_slice(), &existed[40..]);
+    assert_eq!(stream.cursor(), existed.len());
+    stream.shutdown().await;
+
+    // Start from the end: existing packets are skipped
+    let mut stream =
+        FileObserverStream::with_start_at(&tmp, brec::StartPosition::End, Vec::new(), ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    append_storage(&mut wfile, added)?;
+    let read = read_stream_packets(&mut stream, added.len()).await?;
+    assert_eq!(read.as_slice(), added);
+    assert_eq!(stream.cursor(), packets.len());
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+struct CursorSubscription {
+    packets: Arc<Mutex<Vec<WrappedPacket>>>,
+    cursor: Arc<AtomicUsize>,
+    expected: usize,
+    token: CancellationToken,
+}
+
+impl Subscription for CursorSubscription {
+    fn on_update(&mut self, _total: usize, _added: usize) -> SubscriptionUpdate {
+        SubscriptionUpdate::Read
+    }
+
+    fn on_cursor(&mut self, cursor: usize) {
+        self.cursor.store(cursor, Ordering::SeqCst);
+    }
+
+    fn on_packet(&mut self, packet: Packet) {
+        let mut packets = self.packets.lock().unwrap();
+        packets.push(packet.into());
+        if packets.len() == self.expected {
+            self.token.cancel();
+        }
+    }
+}
+
+#[tokio::test]
+async fn observer_starts_at_index() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(50);
+
+    let filename = format!("brec_test_observer_start_at_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    write_storage(&tmp, &packets)?;
+
+    let token = CancellationToken::new();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let cursor = Arc::new(AtomicUsize::new(0));
+    let options = FileObserverOptions::new(&tmp)
+        .subscribe(CursorSubscription {
+            packets: received.clone(),
+            cursor: cursor.clone(),
+            expected: 30,
+            token: token.clone(),
+        })
+        .start_at(brec::StartPosition::Index(20));
+    let mut observer = FileObserver::new(options)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    tokio::time::timeout(tokio::time::Duration::from_secs(10), token.cancelled())
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer timeout"))?;
+    assert_eq!(received.lock().unwrap().as_slice(), &packets[20..]);
+    assert_eq!(cursor.load(Ordering::SeqCst), packets.len());
+    assert_eq!(observer.cursor(), packets.len());
+    observer.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+struct BatchSubscription {
+    packets: Arc<Mutex<Vec<WrappedPacket>>>,
+    batches: Arc<Mutex<Vec<usize>>>,
+    expected: usize,
+    token: CancellationToken,
+}
+
+impl Subscription for BatchSubscription {
+    fn on_update(&mut self, _total: usize, _added: usize) -> SubscriptionUpdate {
+        SubscriptionUpdate::Read
+    }
+
+    fn on_packets(&mut self, batch: Vec<Packet>) -> Result<(), Error> {
+        self.batches.lock().unwrap().push(batch.len());
+        let mut packets = self.packets.lock().unwrap();
+        packets.extend(batch.into_iter().map(WrappedPacket::from));
+        if packets.len() == self.expected {
+            self.token.cancel();
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn observer_delivers_packets_in_batches() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(50);
+
+    let filename = format!("brec_test_observer_batches_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    write_storage(&tmp, &packets)?;
+
+    let token = CancellationToken::new();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let options = FileObserverOptions::new(&tmp)
+        .subscribe(BatchSubscription {
+            packets: received.clone(),
+            batches: batches.clone(),
+            expected: packets.len(),
+            token: token.clone(),
+        })
+        .with_max_batch(16);
+    let mut observer = FileObserver::new(options)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    tokio::time::timeout(tokio::time::Duration::from_secs(10), token.cancelled())
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer timeout"))?;
+    observer.shutdown().await;
+    assert_eq!(received.lock().unwrap().as_slice(), packets.as_slice());
+    assert_eq!(batches.lock().unwrap().as_slice(), &[16, 16, 16, 2]);
+    assert_eq!(observer.cursor(), packets.len());
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+/// Reads stream events until the stream stops or `count` packets (received or dropped)
+/// are seen. Returns received packets, the number of dropped packets and the stop reason.
+async fn read_bounded_stream(
+    stream: &mut FileObserverStream,
+    count: usize,
+) -> std::io::Result<(Vec<WrappedPacket>, usize, Option<Option<brec::ObserverError>>)> {
+    let mut packets = Vec::new();
+    let mut dropped = 0;
+    while packets.len() + dropped < count {
+        let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), stream.next())
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer stream timeout"))?;
+        match event {
+            Some(brec::FileObserverEvent::Update { .. }) => {}
+            Some(brec::FileObserverEvent::Packet(packet)) => {
+                packets.push(packet.into());
+            }
+            Some(brec::FileObserverEvent::Lagged(lagged)) => {
+                dropped += lagged;
+            }
+            Some(brec::FileObserverEvent::Truncated | brec::FileObserverEvent::Replaced) => {
+                panic!("Observer stream reported a file change");
+            }
+            Some(brec::FileObserverEvent::Ignored(bytes)) => {
+                panic!("Observer stream reported {} ignored bytes", bytes.len());
+            }
+            Some(brec::FileObserverEvent::Error(err)) => {
+                panic!("Observer stream returned an error event: {err}");
+            }
+            Some(brec::FileObserverEvent::Stopped(reason)) => {
+                return Ok((packets, dropped, Some(reason)));
+            }
+            Some(brec::FileObserverEvent::Aborted) | None => {
+                panic!("Observer stream closed unexpectedly after reading {} packets", packets.len());
+            }
+        }
+    }
+    Ok((packets, dropped, None))
+}
+
+#[tokio::test]
+async fn observer_stream_applies_overflow_policy() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(50);
+
+    let filename = format!("brec_test_observer_stream_bounded_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    write_storage(&tmp, &packets)?;
+    let open = |policy: brec::OverflowPolicy| {
+        FileObserverStream::with_options(FileObserverStreamOptions::new(&tmp).bounded(4, policy), ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    };
+
+    // Block: the observer waits for the consumer, nothing is lost
+    let mut stream = open(brec::OverflowPolicy::Block)?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    let (read, dropped, stopped) = read_bounded_stream(&mut stream, packets.len()).await?;
+    assert_eq!(read, packets);
+    assert_eq!(dropped, 0);
+    assert!(stopped.is_none());
+    assert_eq!(stream.cursor(), packets.len());
+    stream.shutdown().await;
+
+    // DropOldest: the consumer gets the newest packets and a lag report
+    let mut stream = open(brec::OverflowPolicy::DropOldest)?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    let (read, dropped, stopped) = read_bounded_stream(&mut stream, packets.len()).await?;
+    assert!(stopped.is_none());
+    assert_eq!(read.len(), 4);
+    assert_eq!(dropped, packets.len() - 4);
+    assert_eq!(read.as_slice(), &packets[packets.len() - 4..]);
+    assert_eq!(stream.cursor(), packets.len());
+    stream.shutdown().await;
+
+    // StopWithError: the observer stops once the channel is full
+    let mut stream = open(brec::OverflowPolicy::StopWithError)?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    let (read, dropped, stopped) = read_bounded_stream(&mut stream, packets.len()).await?;
+    let reason = stopped.flatten().expect("observer stopped with error");
+    assert_eq!(reason.kind, brec::ObserverErrorKind::Overflow);
+    assert!(matches!(*reason.error, Error::ObserverOverflow(4)));
+    assert_eq!(read.as_slice(), &packets[..4]);
+    assert_eq!(dropped, 0);
+    assert_eq!(stream.cursor(), 4);
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn observer_stream_polls_file() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(30);
+    let (existed, added) = packets.split_at(20);
+
+    let filename = format!("brec_test_observer_stream_poll_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let mut wfile = write_storage(&tmp, existed)?;
+
+    let options = FileObserverStreamOptions::new(&tmp).with_sensor(brec::SensorMode::Poll(
+        std::time::Duration::from_millis(20),
+    ));
+    let mut stream = FileObserverStream::with_options(options, ())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let read = read_stream_packets(&mut stream, existed.len()).await?;
+    assert_eq!(read.as_slice(), existed);
+    append_storage(&mut wfile, added)?;
+    let read = read_stream_packets(&mut stream, added.len()).await?;
+    assert_eq!(read.as_slice(), added);
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+fn read_sync_packets(
+    events: &mut SyncFileObserverIter,
+    count: usize,
+) -> std::io::Result<Vec<WrappedPacket>> {
+    let mut packets = Vec::new();
+    while packets.len() < count {
+        match events.next() {
+            Some(brec::FileObserverEvent::Update { .. }) => {}
+            Some(brec::FileObserverEvent::Packet(packet)) => {
+                packets.push(packet.into());
+            }
+            Some(brec::FileObserverEvent::Ignored(bytes)) => {
+                panic!("Sync observer reported {} ignored bytes", bytes.len());
+            }
+            Some(brec::FileObserverEvent::Lagged(dropped)) => {
+                panic!("Sync observer dropped {dropped} packets");
+            }
+            Some(brec::FileObserverEvent::Truncated | brec::FileObserverEvent::Replaced) => {
+                panic!("Sync observer reported a file change");
+            }
+            Some(brec::FileObserverEvent::Error(err)) => {
+                panic!("Sync observer returned an error event: {err}");
+            }
+            Some(brec::FileObserverEvent::Stopped(reason)) => {
+                panic!("Sync observer stopped unexpectedly: {reason:?}");
+            }
+            Some(brec::FileObserverEvent::Aborted) | None => {
+                panic!("Sync observer closed unexpectedly after reading {} packets", packets.len());
+            }
+        }
+    }
+    Ok(packets)
+}
+
+#[test]
+fn sync_observer_iter_reads_packets_while_writing() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(30);
+    let (existed, added) = packets.split_at(20);
+
+    let filename = format!("brec_test_sync_observer_iter_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let mut wfile = write_storage(&tmp, existed)?;
+
+    let mut events = SyncFileObserverIter::with_options(
+        FileObserverStreamOptions::new(&tmp).bounded(4, brec::OverflowPolicy::Block),
+        (),
+    )
+    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let read = read_sync_packets(&mut events, existed.len())?;
+    assert_eq!(read.as_slice(), existed);
+    assert_eq!(events.cursor(), existed.len());
+
+    append_storage(&mut wfile, added)?;
+    let read = read_sync_packets(&mut events, added.len())?;
+    assert_eq!(read.as_slice(), added);
+
+    events.shutdown();
+    let rest = events.collect::<Vec<_>>();
+    assert!(matches!(rest.last(), Some(brec::FileObserverEvent::Aborted)));
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+async fn read_hub_packets(
+    stream: &mut HubStream,
+    count: usize,
+) -> std::io::Result<Vec<SharedPacket>> {
+    let mut packets = Vec::new();
+    while packets.len() < count {
+        let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), stream.next())
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "hub stream timeout"))?;
+        match event {
+            Some(brec::HubEvent::Update { .. }) => {}
+            Some(brec::HubEvent::Packet(packet)) => {
+                packets.push(packet);
+            }
+            Some(brec::HubEvent::Lagged(dropped)) => {
+                panic!("Hub stream dropped {dropped} packets");
+            }
+            Some(brec::HubEvent::Truncated | brec::HubEvent::Replaced) => {
+                panic!("Hub stream reported a file change");
+            }
+            Some(brec::HubEvent::Error(err)) => {
+                panic!("Hub stream returned an error event: {err}");
+            }
+            Some(brec::HubEvent::Stopped(reason)) => {
+                panic!("Hub stream stopped unexpectedly: {reason:?}");
+            }
+            Some(brec::HubEvent::Aborted) | None => {
+                panic!("Hub stream closed unexpectedly after reading {} packets", packets.len());
+            }
+        }
+    }
+    Ok(packets)
+}
+
+fn unwrap_shared(packets: &[SharedPacket]) -> Vec<WrappedPacket> {
+    packets
+        .iter()
+        .map(|packet| WrappedPacket {
+            blocks: packet.blocks.clone(),
+            payload: packet.payload.clone(),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn observer_hub_fans_out_packets_to_subscribers() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(60);
+    let (existed, added) = packets.split_at(40);
+    let even = |packets: &[WrappedPacket]| {
+        packets
+            .iter()
+            .filter(|packet| packet.blocks.len() % 2 == 0)
+            .cloned()
+            .collect::<Vec<WrappedPacket>>()
+    };
+
+    let filename = format!("brec_test_observer_hub_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let mut wfile = write_storage(&tmp, existed)?;
+
+    let mut hub = ObserverHub::new(brec::ObserverHubOptions::new(&tmp))
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let mut all = hub
+        .stream(HubSubscriberOptions::new())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let mut filtered = hub
+        .stream(
+            HubSubscriberOptions::new().with_rule(Rule::Prefilter(brec::RuleFnDef::Static(
+                |blocks| blocks.len() % 2 == 0,
+            ))),
+        )
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+
+    let read_all = read_hub_packets(&mut all, existed.len()).await?;
+    assert_eq!(unwrap_shared(&read_all).as_slice(), existed);
+    let expected = even(existed);
+    let read_filtered = read_hub_packets(&mut filtered, expected.len()).await?;
+    assert_eq!(unwrap_shared(&read_filtered), expected);
+    // Packets accepted by both subscribers are decoded once
+    for packet in read_filtered.iter() {
+        assert!(read_all.iter().any(|other| Arc::ptr_eq(packet, other)));
+    }
+
+    let mut live = hub
+        .stream(HubSubscriberOptions::new().start_at(brec::StartPosition::End))
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    // Let the hub pick the subscriber up before new packets are written
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    append_storage(&mut wfile, added)?;
+    let read = read_hub_packets(&mut live, added.len()).await?;
+    assert_eq!(unwrap_shared(&read).as_slice(), added);
+    assert_eq!(live.cursor(), packets.len());
+    let read = read_hub_packets(&mut all, added.len()).await?;
+    assert_eq!(unwrap_shared(&read).as_slice(), added);
+    let expected = even(added);
+    let read = read_hub_packets(&mut filtered, expected.len()).await?;
+    assert_eq!(unwrap_shared(&read), expected);
+
+    assert!(hub.detach(live.id()));
+    assert!(live.next().await.is_none());
+    hub.shutdown().await;
+    let rest = all.collect::<Vec<_>>().await;
+    assert!(matches!(rest.last(), Some(brec::HubEvent::Aborted)));
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn directory_observer_reads_session_files() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(50);
+    let (first, second) = packets.split_at(30);
+
+    let dir = std::env::temp_dir().join(format!("brec_test_directory_observer_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let first_path = dir.join("session-1.bin");
+    drop(write_storage(&first_path, first)?);
+    std::fs::write(dir.join("session.log"), b"not a storage file")?;
+
+    let mut observer = DirectoryObserver::new(
+        DirectoryObserverOptions::new(&dir).with_pattern("session-*.bin"),
+    )
+    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let second_path = dir.join("session-2.bin");
+    let mut expected = vec![(first_path.clone(), first.to_vec())];
+    let mut written = false;
+    let mut read: Vec<(std::path::PathBuf, Vec<WrappedPacket>)> = Vec::new();
+    let total = packets.len();
+    while read.iter().map(|(_, packets)| packets.len()).sum::<usize>() < total {
+        let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), observer.next())
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "directory observer timeout"))?;
+        match event {
+            Some(brec::DirectoryObserverEvent::Added(path)) => {
+                read.push((path.to_path_buf(), Vec::new()));
+            }
+            Some(brec::DirectoryObserverEvent::File(path, brec::FileObserverEvent::Packet(packet))) => {
+                let (_, packets) = read
+                    .iter_mut()
+                    .find(|(added, _)| added.as_path() == &*path)
+                    .expect("packets of an added file");
+                packets.push(packet.into());
+            }
+            Some(brec::DirectoryObserverEvent::File(_, brec::FileObserverEvent::Update { .. })) => {}
+            Some(event) => {
+                panic!("Unexpected directory observer event for {:?}", event.path());
+            }
+            None => {
+                panic!("Directory observer closed unexpectedly");
+            }
+        }
+        if !written && read.iter().any(|(_, packets)| packets.len() == first.len()) {
+            drop(write_storage(&second_path, second)?);
+            expected.push((second_path.clone(), second.to_vec()));
+            written = true;
+        }
+    }
+    assert_eq!(read, expected);
+
+    observer.shutdown().await;
+    let rest = observer.collect::<Vec<_>>().await;
+    assert!(matches!(rest.last(), Some(brec::DirectoryObserverEvent::Aborted)));
+
+    if let Err(err) = std::fs::remove_dir_all(&dir) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp dir:\ndir:{}\nerror: {err}",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+async fn read_raw_stream_packets(
+    stream: &mut StreamFileObserver,
+    count: usize,
+) -> std::io::Result<(Vec<WrappedPacket>, Vec<u8>)> {
+    let mut packets = Vec::new();
+    let mut ignored = Vec::new();
+    while packets.len() < count {
+        let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), stream.next())
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer stream timeout"))?;
+        match event {
+            Some(brec::FileObserverEvent::Update { .. }) => {}
+            Some(brec::FileObserverEvent::Packet(packet)) => {
+                packets.push(packet.into());
+            }
+            Some(brec::FileObserverEvent::Ignored(bytes)) => {
+                ignored.extend_from_slice(&bytes);
+            }
+            Some(brec::FileObserverEvent::Lagged(dropped)) => {
+                panic!("Observer stream dropped {dropped} packets");
+            }
+            Some(brec::FileObserverEvent::Truncated | brec::FileObserverEvent::Replaced) => {
+                panic!("Observer stream reported a file change");
+            }
+            Some(brec::FileObserverEvent::Error(err)) => {
+                panic!("Observer stream returned an error event: {err}");
+            }
+            Some(brec::FileObserverEvent::Stopped(reason)) => {
+                panic!("Observer stream stopped unexpectedly: {reason:?}");
+            }
+            Some(brec::FileObserverEvent::Aborted) | None => {
+                panic!("Observer stream closed unexpectedly after reading {} packets", packets.len());
+            }
+        }
+    }
+    Ok((packets, ignored))
+}
+
+fn write_raw_packets(
+    file: &mut std::fs::File,
+    packets: &[WrappedPacket],
+    litter: &[u8],
+) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut written = Vec::new();
+    for packet in packets {
+        file.write_all(litter)?;
+        written.extend_from_slice(litter);
+        let mut packet: Packet = packet.into();
+        packet
+            .write_all(file, &mut ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    }
+    file.flush()?;
+    Ok(written)
+}
+
+#[tokio::test]
+async fn stream_file_observer_tails_raw_stream() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(40);
+    let (existed, rest) = packets.split_at(20);
+    let (added, resumed) = rest.split_at(10);
+
+    let filename = format!("brec_test_stream_file_observer_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let mut wfile = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)?;
+    let litter = write_raw_packets(&mut wfile, existed, b"plain text log line\n")?;
+
+    let mut stream = StreamFileObserver::with_offset(&tmp, 0, Vec::new(), ())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let (read, ignored) = read_raw_stream_packets(&mut stream, existed.len()).await?;
+    assert_eq!(read.as_slice(), existed);
+    assert_eq!(ignored, litter);
+
+    // Appended data is picked up
+    let litter = write_raw_packets(&mut wfile, added, b"another line\n")?;
+    let (read, ignored) = read_raw_stream_packets(&mut stream, added.len()).await?;
+    assert_eq!(read.as_slice(), added);
+    assert_eq!(ignored, litter);
+    let offset = stream.offset();
+    assert_eq!(offset, wfile.metadata()?.len());
+    stream.shutdown().await;
+
+    // Resume from the saved offset: nothing is delivered twice
+    write_raw_packets(&mut wfile, resumed, b"")?;
+    let mut stream = StreamFileObserver::with_offset(&tmp, offset, Vec::new(), ())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let (read, ignored) = read_raw_stream_packets(&mut stream, resumed.len()).await?;
+    assert_eq!(read.as_slice(), resumed);
+    assert!(ignored.is_empty());
+    assert_eq!(stream.offset(), wfile.metadata()?.len());
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+/// Skips update events until the observer reports a file change.
+async fn wait_file_change(
+    stream: &mut (impl tokio_stream::Stream<Item = brec::FileObserverEvent<Block, Payload, Payload>>
+              + Unpin),
+) -> std::io::Result<brec::FileChange> {
+    loop {
+        let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), stream.next())
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer stream timeout"))?;
+        match event {
+            Some(brec::FileObserverEvent::Update { .. }) => {}
+            Some(brec::FileObserverEvent::Truncated) => return Ok(brec::FileChange::Truncated),
+            Some(brec::FileObserverEvent::Replaced) => return Ok(brec::FileChange::Replaced),
+            Some(brec::FileObserverEvent::Stopped(reason)) => {
+                panic!("Observer stream stopped unexpectedly: {reason:?}");
+            }
+            Some(brec::FileObserverEvent::Aborted) | None => {
+                panic!("Observer stream closed unexpectedly");
+            }
+            Some(_) => {
+                panic!("Observer stream emitted an unexpected event");
+            }
+        }
+    }
+}
+
+/// Creates a storage file with the given packets; the file is returned for appending.
+fn write_storage(path: &std::path::Path, packets: &[WrappedPacket]) -> std::io::Result<std::fs::File> {
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    append_storage(&mut file, packets)?;
+    Ok(file)
+}
+
+/// Appends packets to a storage file created by `write_storage`.
+fn append_storage(file: &mut std::fs::File, packets: &[WrappedPacket]) -> std::io::Result<()> {
+    let mut writer = Writer::new(file)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    for packet in packets {
+        writer
+            .insert(packet.into(), &mut ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn observer_stream_detects_replaced_and_truncated_file() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(40);
+    let (first, second) = packets.split_at(30);
+
+    let filename = format!("brec_test_observer_stream_rotation_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let rotated = std::env::temp_dir().join(format!("{filename}.next"));
+    write_storage(&tmp, first)?;
+
+    let mut stream =
+        FileObserverStream::with_options(FileObserverStreamOptions::new(&tmp).reopen(true), ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let read = read_stream_packets(&mut stream, first.len()).await?;
+    assert_eq!(read.as_slice(), first);
+    assert_eq!(stream.cursor(), first.len());
+
+    // Rotation: the path now points to another file, which is read from the beginning
+    write_storage(&rotated, second)?;
+    std::fs::rename(&rotated, &tmp)?;
+    assert_eq!(wait_file_change(&mut stream).await?, brec::FileChange::Replaced);
+    let read = read_stream_packets(&mut stream, second.len()).await?;
+    assert_eq!(read.as_slice(), second);
+    assert_eq!(stream.cursor(), second.len());
+    stream.shutdown().await;
+
+    // Without reopening, the observer stops after truncation
+    let mut stream = FileObserverStream::new(&tmp)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let read = read_stream_packets(&mut stream, second.len()).await?;
+    assert_eq!(read.as_slice(), second);
+    std::fs::OpenOptions::new().write(true).open(&tmp)?.set_len(0)?;
+    assert_eq!(wait_file_change(&mut stream).await?, brec::FileChange::Truncated);
+    let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), stream.next())
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer stream timeout"))?;
+    assert!(matches!(event, Some(brec::FileObserverEvent::Stopped(None))));
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn stream_file_observer_restarts_on_replaced_file() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(20);
+    let (first, second) = packets.split_at(10);
+
+    let filename = format!("brec_test_stream_file_observer_rotation_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let rotated = std::env::temp_dir().join(format!("{filename}.next"));
+    let mut wfile = std::fs::File::create(&tmp)?;
+    write_raw_packets(&mut wfile, first, b"")?;
+
+    let mut stream = StreamFileObserver::new(&tmp)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))?;
+    let (read, _) = read_raw_stream_packets(&mut stream, first.len()).await?;
+    assert_eq!(read.as_slice(), first);
+
+    let mut wfile = std::fs::File::create(&rotated)?;
+    write_raw_packets(&mut wfile, second, b"")?;
+    std::fs::rename(&rotated, &tmp)?;
+    assert_eq!(wait_file_change(&mut stream).await?, brec::FileChange::Replaced);
+    let (read, ignored) = read_raw_stream_packets(&mut stream, second.len()).await?;
+    assert_eq!(read.as_slice(), second);
+    assert!(ignored.is_empty());
+    assert_eq!(stream.offset(), wfile.metadata()?.len());
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn stream_file_observer_applies_overflow_policy() -> std::io::Result<()> {
+    let packets = gen_n::<WrappedPacket>(30);
+
+    let filename = format!("brec_test_stream_file_observer_bounded_{}.tmp", std::process::id());
+    let tmp = std::env::temp_dir().join(&filename);
+    let mut wfile = std::fs::File::create(&tmp)?;
+    let litter = write_raw_packets(&mut wfile, &packets, b"plain text log line\n")?;
+    let open = |policy: brec::OverflowPolicy| {
+        StreamFileObserver::with_options(StreamFileObserverOptions::new(&tmp).bounded(4, policy), ())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    };
+
+    // Block: the observer waits for the consumer, nothing is lost
+    let mut stream = open(brec::OverflowPolicy::Block)?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    let (read, ignored) = read_raw_stream_packets(&mut stream, packets.len()).await?;
+    assert_eq!(read, packets);
+    assert_eq!(ignored, litter);
+    assert_eq!(stream.offset(), wfile.metadata()?.len());
+    stream.shutdown().await;
+
+    // StopWithError: the observer stops once the channel is full
+    let mut stream = open(brec::OverflowPolicy::StopWithError)?;
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    let mut read = Vec::new();
+    let reason = loop {
+        let event = tokio::time::timeout(tokio::time::Duration::from_secs(10), stream.next())
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "observer stream timeout"))?;
+        match event {
+            Some(brec::FileObserverEvent::Packet(packet)) => {
+                read.push(WrappedPacket::from(packet));
+            }
+            Some(brec::FileObserverEvent::Stopped(reason)) => {
+                break reason.expect("observer stopped with error");
+            }
+            Some(brec::FileObserverEvent::Ignored(..) | brec::FileObserverEvent::Update { .. }) => {}
+            Some(_) | None => {
+                panic!("Observer stream closed unexpectedly after reading {} packets", read.len());
+            }
+        }
+    };
+    assert_eq!(reason.kind, brec::ObserverErrorKind::Overflow);
+    assert!(matches!(*reason.error, Error::ObserverOverflow(4)));
+    assert_eq!(read.as_slice(), &packets[..4]);
+    stream.shutdown().await;
+
+    if let Err(err) = std::fs::remove_file(&tmp) {
+        eprintln!(
+            "Test PASS, but cannot remove tmp file:\nfile:{}\nerror: {err}",
+            tmp.display()
+        );
+    }
+    Ok(())
+}
+
 fn gen_n<T: Arbitrary>(n: usize) -> Vec<T> {
     let mut runner = proptest::test_runner::TestRunner::default();
     let strat = any::<T>();
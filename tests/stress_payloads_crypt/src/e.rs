@@ -0,0 +1,30 @@
+use brec::prelude::*;
+use proptest::prelude::*;
+
+#[payload(bincode, crypt, compress = "zstd", level = 3)]
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, PartialOrd, Debug, Clone)]
+pub struct PayloadE {
+    pub field_u64: u64,
+    pub field_str: String,
+    pub vec_str: Vec<String>,
+}
+
+impl Arbitrary for PayloadE {
+    type Parameters = ();
+
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            any::<u64>(),
+            any::<String>(),
+            prop::collection::vec(any::<String>(), 0..10),
+        )
+            .prop_map(|(field_u64, field_str, vec_str)| PayloadE {
+                field_u64,
+                field_str,
+                vec_str,
+            })
+            .boxed()
+    }
+}
@@ -7,6 +7,8 @@ mod c;
 #[cfg(test)]
 mod d;
 #[cfg(test)]
+mod e;
+#[cfg(test)]
 mod test;
 
 #[cfg(test)]
@@ -18,6 +20,8 @@ pub(crate) use c::*;
 #[cfg(test)]
 pub(crate) use d::*;
 #[cfg(test)]
+pub(crate) use e::*;
+#[cfg(test)]
 pub(crate) use test::*;
 
 fn main() {}
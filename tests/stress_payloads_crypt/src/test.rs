@@ -1,9 +1,9 @@
 use brec::prelude::*;
 use proptest::prelude::*;
 use rsa::{
+    RsaPrivateKey,
     pkcs8::{EncodePrivateKey, EncodePublicKey},
     rand_core::OsRng,
-    RsaPrivateKey,
 };
 use std::sync::OnceLock;
 
@@ -22,6 +22,7 @@ impl Arbitrary for Payload {
             PayloadB::arbitrary().prop_map(Payload::PayloadB),
             PayloadC::arbitrary().prop_map(Payload::PayloadC),
             PayloadD::arbitrary().prop_map(Payload::PayloadD),
+            PayloadE::arbitrary().prop_map(Payload::PayloadE),
         ]
         .boxed()
     }
@@ -401,3 +402,29 @@ fn mixed_protocol_supports_open_and_encrypted_payloads() {
     let restored = read_payloads(&buf, &mut certs.decrypt).expect("read mixed protocol buffer");
     assert_eq!(restored, payloads);
 }
+
+#[test]
+fn compressed_payload_is_compressed_before_encryption() {
+    let mut certs = certificates();
+    let payload = PayloadE {
+        field_u64: 42,
+        field_str: "compressed-then-encrypted".to_owned(),
+        vec_str: vec!["repeated line of a large text payload".to_owned(); 64],
+    };
+    let plain = brec::bincode::serde::encode_to_vec(&payload, brec::bincode::config::standard())
+        .expect("bincode");
+    let mut encrypt_ctx = ProtocolContext::Encrypt(&mut certs.encrypt);
+    let encrypted =
+        <PayloadE as PayloadEncode>::encode(&payload, &mut encrypt_ctx).expect("encrypt payload");
+    assert!(encrypted.len() < plain.len());
+
+    let mut decrypt_ctx = ProtocolContext::Decrypt(&mut certs.decrypt);
+    let restored = <PayloadE as PayloadDecode<PayloadE>>::decode(&encrypted, &mut decrypt_ctx)
+        .expect("decrypt payload");
+    assert_eq!(restored, payload);
+
+    let mut wrong_decrypt_ctx = ProtocolContext::Decrypt(&mut certs.wrong_decrypt);
+    assert!(
+        <PayloadE as PayloadDecode<PayloadE>>::decode(&encrypted, &mut wrong_decrypt_ctx).is_err()
+    );
+}
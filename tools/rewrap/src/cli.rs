@@ -0,0 +1,390 @@
+use crate::Error;
+use brec::crypt::{
+    CryptError, DecryptKeyring, DecryptOptions, EncryptOptions, FileKeyProvider, RewrapMode,
+    RewrapReport, Rewrapper, SecretString,
+};
+use brec::sign::PacketSigner;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// Runs the tool from process arguments.
+///
+/// The source file is never modified: all packets are written into a new file, which must
+/// not exist yet, and a summary is printed once the whole source has been processed.
+pub fn run() -> Result<(), Error> {
+    let cli = match Cli::parse(env::args().skip(1))? {
+        Parsed::Help => {
+            print_usage();
+            return Ok(());
+        }
+        Parsed::Run(cli) => cli,
+    };
+    let report = cli.rewrap()?;
+    print_report(&report);
+    println!("written into {}", cli.output.display());
+    Ok(())
+}
+
+/// Key file given on the command line together with its key identifier.
+#[derive(Debug)]
+struct KeyArg {
+    path: PathBuf,
+    key_id: Vec<u8>,
+}
+
+/// Outcome of parsing the command line.
+#[derive(Debug)]
+enum Parsed {
+    /// Usage was requested with `-h` or `--help`.
+    Help,
+    /// Options of a rewrap run.
+    Run(Cli),
+}
+
+/// Parsed command line options.
+#[derive(Debug)]
+struct Cli {
+    input: PathBuf,
+    output: PathBuf,
+    stream: bool,
+    mode: RewrapMode,
+    keys: Vec<KeyArg>,
+    password_env: Option<String>,
+    recipients: Vec<KeyArg>,
+    signer: Option<KeyArg>,
+}
+
+impl Cli {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Parsed, Error> {
+        let mut input = None;
+        let mut output = None;
+        let mut stream = false;
+        let mut mode = RewrapMode::Reencrypt;
+        let mut keys: Vec<KeyArg> = Vec::new();
+        let mut password_env = None;
+        let mut recipients: Vec<KeyArg> = Vec::new();
+        let mut signer: Option<KeyArg> = None;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Parsed::Help),
+                "--input" => {
+                    let value = next_value(&mut args, "--input")?;
+                    input = Some(PathBuf::from(value));
+                }
+                "--output" => {
+                    let value = next_value(&mut args, "--output")?;
+                    output = Some(PathBuf::from(value));
+                }
+                "--stream" => stream = true,
+                "--session-keys-only" => mode = RewrapMode::SessionKeys,
+                "--key" => {
+                    let value = next_value(&mut args, "--key")?;
+                    keys.push(KeyArg::new(value));
+                }
+                "--key-id" => {
+                    let value = next_value(&mut args, "--key-id")?;
+                    set_key_id(keys.last_mut(), "--key-id", "--key", value)?;
+                }
+                "--password-env" => {
+                    password_env = Some(next_value(&mut args, "--password-env")?);
+                }
+                "--recipient" => {
+                    let value = next_value(&mut args, "--recipient")?;
+                    recipients.push(KeyArg::new(value));
+                }
+                "--recipient-id" => {
+                    let value = next_value(&mut args, "--recipient-id")?;
+                    set_key_id(
+                        recipients.last_mut(),
+                        "--recipient-id",
+                        "--recipient",
+                        value,
+                    )?;
+                }
+                "--signer" => {
+                    let value = next_value(&mut args, "--signer")?;
+                    signer = Some(KeyArg::new(value));
+                }
+                "--signer-id" => {
+                    let value = next_value(&mut args, "--signer-id")?;
+                    set_key_id(signer.as_mut(), "--signer-id", "--signer", value)?;
+                }
+                other => {
+                    return Err(Error::Cli(format!("unknown argument: {other}")));
+                }
+            }
+        }
+
+        if keys.is_empty() {
+            return Err(Error::Cli("at least one --key is required".to_owned()));
+        }
+        Ok(Parsed::Run(Self {
+            input: input.ok_or_else(|| Error::Cli("--input is required".to_owned()))?,
+            output: output.ok_or_else(|| Error::Cli("--output is required".to_owned()))?,
+            stream,
+            mode,
+            keys,
+            password_env,
+            recipients,
+            signer,
+        }))
+    }
+
+    /// Rewraps the input into the output file and returns the summary.
+    ///
+    /// Packets are written into a temporary file next to the output, which is renamed to the
+    /// output only once the whole source has been processed; on failure it's removed, so a
+    /// failed run can simply be repeated.
+    fn rewrap(&self) -> Result<RewrapReport, Error> {
+        if self.output.exists() {
+            return Err(Error::Cli(format!(
+                "{} already exists",
+                self.output.display()
+            )));
+        }
+        let mut rewrapper = self.rewrapper()?;
+        let partial = partial_path(&self.output);
+        match self.write(&mut rewrapper, &partial) {
+            Ok(()) => fs::rename(&partial, &self.output)?,
+            Err(err) => {
+                let _ = fs::remove_file(&partial);
+                return Err(err);
+            }
+        }
+        Ok(rewrapper.into_report())
+    }
+
+    fn rewrapper(&self) -> Result<Rewrapper, Error> {
+        let password = match self.password_env.as_deref() {
+            Some(name) => Some(SecretString::from(
+                env::var(name).map_err(|_| Error::MissingPassword(name.to_owned()))?,
+            )),
+            None => None,
+        };
+        let mut keyring = DecryptKeyring::new();
+        for key in self.keys.iter() {
+            let provider = match (FileKeyProvider::open(&key.path), password.as_ref()) {
+                (Err(CryptError::PasswordRequired), Some(password)) => {
+                    FileKeyProvider::open_encrypted(&key.path, password)?
+                }
+                (Err(CryptError::PasswordRequired), None) => return Err(Error::PasswordRequired),
+                (provider, _) => provider?,
+            };
+            keyring = keyring.with_provider(key.key_id.clone(), provider);
+        }
+        let mut recipients = self.recipients.iter();
+        let primary = recipients
+            .next()
+            .ok_or_else(|| Error::Cli("at least one --recipient is required".to_owned()))?;
+        let pem = fs::read_to_string(&primary.path)?;
+        let mut encrypt = match EncryptOptions::from_pem(&pem) {
+            Ok(options) => options,
+            Err(rsa_err) => EncryptOptions::from_x25519_public_pem(&pem).map_err(|_| rsa_err)?,
+        }
+        .with_key_id(primary.key_id.clone());
+        for recipient in recipients {
+            encrypt.add_recipient_pem(
+                recipient.key_id.clone(),
+                &fs::read_to_string(&recipient.path)?,
+            )?;
+        }
+
+        let mut rewrapper =
+            Rewrapper::new(DecryptOptions::from_keyring(keyring)?, encrypt).with_mode(self.mode);
+        if let Some(signer) = self.signer.as_ref() {
+            rewrapper = rewrapper.with_signer(PacketSigner::from_private_key_pem_file(
+                signer.key_id.clone(),
+                &signer.path,
+            )?);
+        }
+        Ok(rewrapper)
+    }
+
+    fn write(&self, rewrapper: &mut Rewrapper, partial: &Path) -> Result<(), Error> {
+        let mut src = BufReader::new(File::open(&self.input)?);
+        let dest = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(partial)?;
+        let mut dest = BufWriter::new(dest);
+        if self.stream {
+            rewrapper.rewrap_stream(&mut src, &mut dest)?;
+        } else {
+            rewrapper.rewrap_storage(&mut src, &mut dest)?;
+        }
+        dest.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        Ok(())
+    }
+}
+
+/// Returns the path of the temporary file the output is written into.
+fn partial_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".partial");
+    PathBuf::from(path)
+}
+
+impl KeyArg {
+    fn new(path: String) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            key_id: Vec::new(),
+        }
+    }
+}
+
+fn set_key_id(
+    key: Option<&mut KeyArg>,
+    flag: &str,
+    after: &str,
+    value: String,
+) -> Result<(), Error> {
+    let key = key.ok_or_else(|| Error::Cli(format!("{flag} must follow {after}")))?;
+    key.key_id = value.into_bytes();
+    Ok(())
+}
+
+fn print_report(report: &RewrapReport) {
+    println!("packets:     {}", report.packets);
+    println!("rewrapped:   {}", report.rewrapped);
+    println!("reencrypted: {}", report.reencrypted);
+    println!("untouched:   {}", report.untouched);
+    if report.kept_signed > 0 {
+        println!(
+            "kept signed: {} (encrypted for the old key; pass --signer to sign them again)",
+            report.kept_signed
+        );
+    }
+    for (key_id, count) in report.key_ids.iter() {
+        match key_id {
+            Some(key_id) => println!("key {}: {count}", String::from_utf8_lossy(key_id)),
+            None => println!("key without id: {count}"),
+        }
+    }
+}
+
+fn print_usage() {
+    println!(
+        "Usage: brec_rewrap --input <PATH> --output <PATH> --key <PEM> [--key-id <ID>]... --recipient <PEM> [--recipient-id <ID>]... [--password-env <VAR>] [--signer <PEM> --signer-id <ID>] [--stream] [--session-keys-only]
+
+Reads a storage file (or a stream of packets with --stream) and writes a new file
+with every encrypted payload moved to the new recipients. Plain payloads and blocks
+are copied as is; the source file isn't modified and --output must not exist.
+The output is written into <--output>.partial and renamed once the run succeeds.
+
+--key          private key (RSA or X25519, PKCS#8 PEM) which decrypts the source;
+               may be repeated. --key-id sets the identifier of the preceding key.
+--password-env name of the environment variable with the password of encrypted
+               (ENCRYPTED PRIVATE KEY) keys.
+--recipient    public key PEM (RSA, X509 certificate or X25519) of a new recipient;
+               may be repeated, the first one is the primary key. Additional
+               recipients must be RSA. --recipient-id sets the identifier of the
+               preceding recipient.
+--signer       Ed25519 private key PEM to sign changed signed packets again;
+               --signer-id sets its identifier.
+--session-keys-only
+               wrap the existing session keys for the new recipients instead of
+               decrypting and encrypting each payload with fresh session keys. Faster,
+               but the old key still decrypts the output: never use it to rotate a
+               compromised key."
+    );
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, Error> {
+    args.next()
+        .ok_or_else(|| Error::Cli(format!("missing value for {flag}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn parse(list: &[&str]) -> Result<Cli, Error> {
+        match Cli::parse(args(list))? {
+            Parsed::Run(cli) => Ok(cli),
+            Parsed::Help => panic!("help isn't expected"),
+        }
+    }
+
+    #[test]
+    fn parse_collects_keys_and_recipients() {
+        let cli = parse(&[
+            "--input",
+            "in.bin",
+            "--output",
+            "out.bin",
+            "--key",
+            "a.pem",
+            "--key-id",
+            "a",
+            "--key",
+            "b.pem",
+            "--recipient",
+            "new.pub.pem",
+            "--recipient-id",
+            "new",
+            "--stream",
+            "--session-keys-only",
+        ])
+        .expect("parsed");
+        assert_eq!(cli.input, PathBuf::from("in.bin"));
+        assert_eq!(cli.output, PathBuf::from("out.bin"));
+        assert!(cli.stream);
+        assert_eq!(cli.mode, RewrapMode::SessionKeys);
+        assert_eq!(cli.keys.len(), 2);
+        assert_eq!(cli.keys[0].key_id, b"a");
+        assert!(cli.keys[1].key_id.is_empty());
+        assert_eq!(cli.recipients.len(), 1);
+        assert_eq!(cli.recipients[0].key_id, b"new");
+        assert!(cli.signer.is_none());
+    }
+
+    #[test]
+    fn parse_reencrypts_by_default() {
+        let cli =
+            parse(&["--input", "in.bin", "--output", "out.bin", "--key", "a.pem"]).expect("parsed");
+        assert_eq!(cli.mode, RewrapMode::Reencrypt);
+        assert!(!cli.stream);
+    }
+
+    #[test]
+    fn parse_returns_help() {
+        for flag in ["-h", "--help"] {
+            assert!(matches!(
+                Cli::parse(args(&["--input", "in.bin", flag])),
+                Ok(Parsed::Help)
+            ));
+        }
+    }
+
+    #[test]
+    fn parse_rejects_invalid_arguments() {
+        for list in [
+            &["--input", "in.bin", "--output", "out.bin"][..],
+            &["--output", "out.bin", "--key", "a.pem"],
+            &["--input", "in.bin", "--key", "a.pem"],
+            &["--input", "in.bin", "--output", "out.bin", "--key"],
+            &["--key-id", "a", "--key", "a.pem"],
+            &["--recipient-id", "new"],
+            &["--signer-id", "signer"],
+            &["--unknown"],
+        ] {
+            assert!(matches!(parse(list), Err(Error::Cli(_))), "{list:?}");
+        }
+    }
+}
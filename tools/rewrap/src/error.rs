@@ -0,0 +1,23 @@
+use thiserror::Error;
+
+/// Error type for the rewrap tool.
+///
+/// Variants are user-facing: each error should tell which argument, key or file failed
+/// without requiring a backtrace.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    Cli(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("crypt error: {0}")]
+    Crypt(#[from] brec::crypt::CryptError),
+    #[error("sign error: {0}")]
+    Sign(#[from] brec::sign::SignError),
+    #[error("failed to rewrap: {0}")]
+    Rewrap(#[from] brec::Error),
+    #[error("password of an encrypted key is required; pass --password-env")]
+    PasswordRequired,
+    #[error("environment variable {0} is not set")]
+    MissingPassword(String),
+}
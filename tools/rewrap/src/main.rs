@@ -0,0 +1,11 @@
+mod cli;
+mod error;
+
+pub use error::*;
+
+fn main() {
+    if let Err(err) = cli::run() {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
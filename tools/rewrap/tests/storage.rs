@@ -0,0 +1,150 @@
+use brec::prelude::*;
+use rsa::{
+    RsaPrivateKey,
+    pkcs8::{EncodePrivateKey, EncodePublicKey},
+    rand_core::OsRng,
+};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+#[block]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    pub n: u32,
+}
+
+#[payload(bincode, crypt)]
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
+pub struct Secret {
+    pub text: String,
+}
+
+brec::generate!();
+
+/// Writes a PKCS#8 private key and its public key into `dir`; returns both paths.
+fn write_keys(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    let key = RsaPrivateKey::new(&mut OsRng, 2048).expect("private key");
+    let private = dir.join(format!("{name}.pem"));
+    let public = dir.join(format!("{name}.pub.pem"));
+    fs::write(
+        &private,
+        key.to_pkcs8_pem(Default::default())
+            .expect("private key pem")
+            .as_bytes(),
+    )
+    .expect("private key file");
+    fs::write(
+        &public,
+        key.to_public_key()
+            .to_public_key_pem(Default::default())
+            .expect("public key pem"),
+    )
+    .expect("public key file");
+    (private, public)
+}
+
+fn write_storage(path: &Path, public: &Path, texts: &[String]) {
+    let mut encrypt = EncryptOptions::from_pem_file(public)
+        .expect("encrypt options")
+        .with_key_id(b"old".to_vec());
+    let mut ctx = ProtocolContext::Encrypt(&mut encrypt);
+    let mut writer = Writer::new(File::create(path).expect("storage file")).expect("writer");
+    for (n, text) in texts.iter().enumerate() {
+        let block = Block::Sequence(Sequence { n: n as u32 });
+        let payload = Payload::Secret(Secret { text: text.clone() });
+        writer
+            .insert(Packet::new(vec![block], Some(payload)), &mut ctx)
+            .expect("packet has been written");
+    }
+}
+
+fn read_storage(path: &Path, private: &Path) -> Result<Vec<String>, brec::Error> {
+    let mut decrypt = DecryptOptions::from_private_key_pem_file(private).expect("decrypt options");
+    let mut ctx = ProtocolContext::Decrypt(&mut decrypt);
+    let mut reader = Reader::new(File::open(path).expect("storage file"))?;
+    reader
+        .iter(&mut ctx)
+        .enumerate()
+        .map(|(n, packet)| {
+            let packet = packet?;
+            assert_eq!(
+                packet.blocks,
+                vec![Block::Sequence(Sequence { n: n as u32 })]
+            );
+            match packet.payload {
+                Some(Payload::Secret(secret)) => Ok(secret.text),
+                payload => panic!("unexpected payload: {payload:?}"),
+            }
+        })
+        .collect()
+}
+
+fn rewrap(args: &[&Path], flags: &[&str]) -> Output {
+    let [input, output, key, recipient] = args else {
+        panic!("input, output, key and recipient are expected");
+    };
+    Command::new(env!("CARGO_BIN_EXE_brec_rewrap"))
+        .arg("--input")
+        .arg(input)
+        .arg("--output")
+        .arg(output)
+        .arg("--key")
+        .arg(key)
+        .args(["--key-id", "old"])
+        .arg("--recipient")
+        .arg(recipient)
+        .args(["--recipient-id", "new"])
+        .args(flags)
+        .output()
+        .expect("rewrap tool has been started")
+}
+
+#[test]
+fn rewrap_storage_for_new_key() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let (old_private, old_public) = write_keys(dir.path(), "old");
+    let (new_private, new_public) = write_keys(dir.path(), "new");
+    let input = dir.path().join("storage.bin");
+    let texts: Vec<String> = (0..10).map(|n| format!("secret {n}")).collect();
+    write_storage(&input, &old_public, &texts);
+
+    for (name, flags, counter) in [
+        ("reencrypted.bin", &[][..], "reencrypted: 10"),
+        ("rewrapped.bin", &["--session-keys-only"], "rewrapped:   10"),
+    ] {
+        let output = dir.path().join(name);
+        let result = rewrap(&[&input, &output, &old_private, &new_public], flags);
+        let stdout = String::from_utf8_lossy(&result.stdout);
+        assert!(result.status.success(), "{result:?}");
+        assert!(stdout.contains("packets:     10"), "{stdout}");
+        assert!(stdout.contains(counter), "{stdout}");
+        assert!(stdout.contains("key old: 10"), "{stdout}");
+        assert_eq!(read_storage(&output, &new_private).expect("read"), texts);
+        assert!(read_storage(&output, &old_private).is_err());
+        assert!(!dir.path().join(format!("{name}.partial")).exists());
+
+        // An existing output is never overwritten
+        let result = rewrap(&[&input, &output, &old_private, &new_public], flags);
+        assert!(!result.status.success());
+        assert_eq!(read_storage(&output, &new_private).expect("read"), texts);
+    }
+    // The source is left as is
+    assert_eq!(read_storage(&input, &old_private).expect("read"), texts);
+}
+
+#[test]
+fn failed_rewrap_leaves_no_output() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let (_, old_public) = write_keys(dir.path(), "old");
+    let (wrong_private, _) = write_keys(dir.path(), "wrong");
+    let (_, new_public) = write_keys(dir.path(), "new");
+    let input = dir.path().join("storage.bin");
+    let output = dir.path().join("rotated.bin");
+    write_storage(&input, &old_public, &["secret".to_owned()]);
+
+    let result = rewrap(&[&input, &output, &wrong_private, &new_public], &[]);
+    assert!(!result.status.success());
+    assert!(!output.exists());
+    assert!(!dir.path().join("rotated.bin.partial").exists());
+}